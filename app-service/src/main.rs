@@ -2,21 +2,34 @@ use std::env;
 
 use askama::Template;
 use axum::{
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse},
     routing::get,
     Json, Router,
 };
 use axum_extra::extract::CookieJar;
 use serde::Serialize;
-use tower_http::services::ServeDir;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    trace::TraceLayer,
+};
+
+mod telemetry;
 
 #[tokio::main]
 async fn main() {
+    let _tracer_provider = telemetry::init_tracing().expect("Failed to initialize tracing");
+
     let app = Router::new()
         .nest_service("/assets", ServeDir::new("assets"))
         .route("/", get(root))
-        .route("/protected", get(protected));
+        .route("/protected", get(protected))
+        .layer(TraceLayer::new_for_http().make_span_with(telemetry::make_span))
+        // Reuse the caller's X-Request-Id (or make one up), echo it back and
+        // pass it on to the auth service
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
 
@@ -24,6 +37,8 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
 #[derive(Template)]
 #[template(path = "index.html")]
 struct IndexTemplate {
@@ -46,7 +61,7 @@ async fn root() -> impl IntoResponse {
     Html(template.render().unwrap())
 }
 
-async fn protected(jar: CookieJar) -> impl IntoResponse {
+async fn protected(jar: CookieJar, headers: HeaderMap) -> impl IntoResponse {
     let jwt_cookie = match jar.get("jwt") {
         Some(cookie) => cookie,
         None => {
@@ -63,7 +78,25 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
     let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
     let url = format!("http://{}:3000/verify-token", auth_hostname);
 
-    let response = match api_client.post(&url).json(&verify_token_body).send().await {
+    // Let the auth service continue this request's trace and log it under
+    // the same request id
+    let mut forwarded_headers = reqwest::header::HeaderMap::new();
+    telemetry::inject_trace_context(&mut forwarded_headers);
+    if let Some(request_id) = headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| reqwest::header::HeaderValue::from_str(value).ok())
+    {
+        forwarded_headers.insert(REQUEST_ID_HEADER, request_id);
+    }
+
+    let response = match api_client
+        .post(&url)
+        .headers(forwarded_headers)
+        .json(&verify_token_body)
+        .send()
+        .await
+    {
         Ok(response) => response,
         Err(_) => {
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
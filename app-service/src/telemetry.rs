@@ -0,0 +1,89 @@
+use std::env;
+
+use axum::{body::Body, extract::Request, http::HeaderMap};
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::{TraceError, TracerProvider as _};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+use tracing::{Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+const SERVICE_NAME: &str = "app-service";
+
+// Set up logging and tracing. Traces are exported to the OTLP/HTTP collector
+// at OTEL_EXPORTER_OTLP_ENDPOINT (e.g. http://localhost:4318) when it's set;
+// either way the trace context is propagated to the auth service.
+pub fn init_tracing() -> Result<TracerProvider, TraceError> {
+    let mut builder = TracerProvider::builder()
+        .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)]));
+
+    if let Some(endpoint) = env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .ok()
+        .filter(|endpoint| !endpoint.is_empty())
+    {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()?;
+        builder = builder.with_batch_exporter(exporter, runtime::Tokio);
+    }
+    let provider = builder.build();
+
+    let filter_layer = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(tracing_subscriber::fmt::layer().compact())
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)))
+        .init();
+
+    Ok(provider)
+}
+
+// One span per incoming request, continuing the caller's trace if it sent a
+// W3C `traceparent` header
+pub fn make_span(request: &Request<Body>) -> Span {
+    let span = tracing::span!(
+        Level::INFO,
+        "[REQUEST]",
+        method = tracing::field::display(request.method()),
+        uri = tracing::field::display(request.uri()),
+        request_id = tracing::field::debug(request.headers().get("x-request-id")),
+    );
+    span.set_parent(TraceContextPropagator::new().extract(&HeaderExtractor(request.headers())));
+    span
+}
+
+// Add the current span's trace context to the headers of an outgoing request
+pub fn inject_trace_context(headers: &mut reqwest::header::HeaderMap) {
+    TraceContextPropagator::new()
+        .inject_context(&Span::current().context(), &mut HeaderInjector(headers));
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+// reqwest 0.11 still uses the `http` 0.2 header types, unlike axum
+struct HeaderInjector<'a>(&'a mut reqwest::header::HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(key.as_bytes()),
+            reqwest::header::HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+// The recovery token comes from the link in the new device alert email.
+// Nothing happens until the user presses the button, so link scanners
+// opening the page don't sign anyone out. Links in forced password reset
+// emails carry `step=reset` and go straight to choosing a new password.
+const params = new URLSearchParams(window.location.search);
+const token = params.get("token");
+
+const notMeSection = document.getElementById("not-me-section");
+const resetPasswordSection = document.getElementById("reset-password-section");
+
+if (params.get("step") === "reset") {
+    notMeSection.style.display = "none";
+    resetPasswordSection.style.display = "block";
+}
+
+function showError(alertElement, response) {
+    response.json().then(data => {
+        let error_msg = data.error;
+        if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+            alertElement.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+            alertElement.style.display = "block";
+        } else {
+            alertElement.style.display = "none";
+        }
+    });
+}
+
+const notMeButton = document.getElementById("not-me-submit");
+const notMeErrAlert = document.getElementById("not-me-err-alert");
+
+notMeButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    fetch('/not-me', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token }),
+    }).then(response => {
+        if (response.ok) {
+            notMeErrAlert.style.display = "none";
+            notMeSection.style.display = "none";
+            resetPasswordSection.style.display = "block";
+        } else {
+            showError(notMeErrAlert, response);
+        }
+    });
+});
+
+const resetPasswordForm = document.getElementById("reset-password-form");
+const resetPasswordButton = document.getElementById("reset-password-submit");
+const resetPasswordErrAlert = document.getElementById("reset-password-err-alert");
+
+resetPasswordButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const password = resetPasswordForm.password.value;
+
+    fetch('/reset-password', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token, password }),
+    }).then(response => {
+        if (response.ok) {
+            resetPasswordForm.password.value = "";
+            resetPasswordErrAlert.style.display = "none";
+            alert("Your password has been updated. You can now log in.");
+            window.location.href = "/";
+        } else {
+            showError(resetPasswordErrAlert, response);
+        }
+    });
+});
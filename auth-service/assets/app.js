@@ -66,6 +66,7 @@ loginButton.addEventListener("click", (e) => {
             loginForm.email.value = "";
             loginForm.password.value = "";
             loginErrAlter.style.display = "none";
+            acceptInvitation();
             alert("You have successfully logged in.");
         } else {
             response.json().then(data => {
@@ -97,13 +98,15 @@ signupButton.addEventListener("click", (e) => {
         headers: {
             'Content-Type': 'application/json',
         },
-        body: JSON.stringify({ email, password, requires2FA }),
+        body: JSON.stringify({ email, password, requires2FA, invitationToken }),
     }).then(response => {
         if (response.ok) {
             signupForm.email.value = "";
             signupForm.password.value = "";
             signupForm.twoFA.checked = false;
             signupErrAlter.style.display = "none";
+            // Signing up with the invitation already joined the organization
+            invitationToken = null;
             alert("You have successfully created a user.");
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
@@ -145,6 +148,7 @@ TwoFAButton.addEventListener("click", (e) => {
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
             TwoFAErrAlter.style.display = "none";
+            acceptInvitation();
             alert("You have successfully logged in.");
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
@@ -161,4 +165,55 @@ TwoFAButton.addEventListener("click", (e) => {
             });
         }
     });
-});
\ No newline at end of file
+});
+// -----------------------------------------------------
+
+// Opened from an invitation email: pre-fill the form the invitee needs, and
+// join the organization once they're logged in
+let invitationToken = new URLSearchParams(window.location.search).get("invitation");
+
+if (invitationToken) {
+    fetch('/invitation', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token: invitationToken }),
+    }).then(response => {
+        if (!response.ok) {
+            invitationToken = null;
+            return;
+        }
+        response.json().then(data => {
+            if (data.accountExists) {
+                loginForm.email.value = data.email;
+            } else {
+                signupForm.email.value = data.email;
+                loginSection.style.display = "none";
+                twoFASection.style.display = "none";
+                signupSection.style.display = "block";
+            }
+        });
+    });
+}
+
+function acceptInvitation() {
+    if (!invitationToken) {
+        return;
+    }
+
+    fetch('/accept-invitation', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token: invitationToken }),
+    }).then(response => {
+        if (response.ok) {
+            invitationToken = null;
+            response.json().then(data => {
+                alert(`You have joined ${data.organizationName}.`);
+            });
+        }
+    });
+}
@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS audit_events(
+   id BIGSERIAL PRIMARY KEY,
+   kind TEXT NOT NULL,
+   outcome TEXT NOT NULL,
+   reason TEXT,
+   actor TEXT,
+   ip TEXT,
+   user_agent TEXT,
+   request_id TEXT,
+   occurred_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_occurred_at_idx ON audit_events (occurred_at DESC);
+CREATE INDEX IF NOT EXISTS audit_events_actor_idx ON audit_events (actor, occurred_at DESC);
@@ -0,0 +1,8 @@
+-- Tokens issued to `email` before `revoked_at` are no longer accepted
+CREATE TABLE IF NOT EXISTS revoked_sessions(
+   email TEXT NOT NULL PRIMARY KEY,
+   revoked_at TIMESTAMPTZ NOT NULL,
+   expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS revoked_sessions_expires_at_idx ON revoked_sessions (expires_at);
@@ -0,0 +1,10 @@
+-- Devices each user has logged in from, to spot logins from new ones.
+-- A missing IP or user agent is stored as an empty string.
+CREATE TABLE IF NOT EXISTS known_devices(
+   email TEXT NOT NULL,
+   ip TEXT NOT NULL,
+   user_agent TEXT NOT NULL,
+   first_seen_at TIMESTAMPTZ NOT NULL,
+   last_seen_at TIMESTAMPTZ NOT NULL,
+   PRIMARY KEY (email, ip, user_agent)
+);
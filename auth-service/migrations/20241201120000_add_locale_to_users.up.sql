@@ -0,0 +1,2 @@
+-- Language the user's emails are written in, e.g. 'en' or 'de'
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locale TEXT NOT NULL DEFAULT 'en';
@@ -0,0 +1,17 @@
+-- Emails waiting to be delivered by the background worker. Delivered emails
+-- are deleted; ones that kept failing stay behind with dead_lettered_at set.
+CREATE TABLE IF NOT EXISTS email_outbox(
+   id BIGSERIAL PRIMARY KEY,
+   recipient TEXT NOT NULL,
+   subject TEXT NOT NULL,
+   html_body TEXT NOT NULL,
+   text_body TEXT NOT NULL,
+   attempts INTEGER NOT NULL DEFAULT 0,
+   next_attempt_at TIMESTAMPTZ NOT NULL,
+   last_error TEXT,
+   created_at TIMESTAMPTZ NOT NULL,
+   dead_lettered_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS email_outbox_due_idx ON email_outbox (next_attempt_at)
+   WHERE dead_lettered_at IS NULL;
@@ -0,0 +1,4 @@
+-- E.164 number login codes are texted to, e.g. '+4915112345678'
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_number TEXT;
+-- How login codes reach the user: 'email' or 'sms'
+ALTER TABLE users ADD COLUMN IF NOT EXISTS two_fa_channel TEXT NOT NULL DEFAULT 'email';
@@ -0,0 +1,32 @@
+-- Roles are named sets of permissions, granted to users
+CREATE TABLE IF NOT EXISTS roles(
+   name TEXT NOT NULL PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS permissions(
+   name TEXT NOT NULL PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions(
+   role TEXT NOT NULL REFERENCES roles (name) ON DELETE CASCADE,
+   permission TEXT NOT NULL REFERENCES permissions (name) ON DELETE CASCADE,
+   PRIMARY KEY (role, permission)
+);
+
+-- Like known_devices, keyed by email without referencing users, so roles
+-- can be looked up without touching the users table
+CREATE TABLE IF NOT EXISTS user_roles(
+   email TEXT NOT NULL,
+   role TEXT NOT NULL REFERENCES roles (name) ON DELETE CASCADE,
+   granted_at TIMESTAMPTZ NOT NULL,
+   PRIMARY KEY (email, role)
+);
+
+-- The built-in roles, see BUILT_IN_ROLES
+INSERT INTO roles (name) VALUES ('admin') ON CONFLICT DO NOTHING;
+INSERT INTO permissions (name)
+VALUES ('audit_events:read'), ('roles:read'), ('roles:write')
+ON CONFLICT DO NOTHING;
+INSERT INTO role_permissions (role, permission)
+VALUES ('admin', 'audit_events:read'), ('admin', 'roles:read'), ('admin', 'roles:write')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,31 @@
+-- Companies our users belong to, each user with a role per organization
+CREATE TABLE IF NOT EXISTS organizations(
+   id UUID NOT NULL PRIMARY KEY,
+   name TEXT NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL
+);
+
+-- Keyed by email without referencing users, like user_roles
+CREATE TABLE IF NOT EXISTS organization_members(
+   organization_id UUID NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+   email TEXT NOT NULL,
+   role TEXT NOT NULL,
+   joined_at TIMESTAMPTZ NOT NULL,
+   PRIMARY KEY (organization_id, email)
+);
+
+-- For looking up every organization a user belongs to
+CREATE INDEX IF NOT EXISTS organization_members_email_idx ON organization_members (email);
+
+-- Open invitations; deleted when accepted, and purged once expired
+CREATE TABLE IF NOT EXISTS organization_invitations(
+   id UUID NOT NULL PRIMARY KEY,
+   organization_id UUID NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+   email TEXT NOT NULL,
+   role TEXT NOT NULL,
+   invited_by TEXT NOT NULL,
+   expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS organization_invitations_organization_id_idx
+   ON organization_invitations (organization_id);
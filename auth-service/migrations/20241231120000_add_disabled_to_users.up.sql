@@ -0,0 +1,3 @@
+-- Set by operators to lock an account out. Its password keeps working
+-- once it is enabled again.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS disabled BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,7 @@
+-- Lets the built-in admin role manage users, see BUILT_IN_ROLES
+INSERT INTO permissions (name)
+VALUES ('users:read'), ('users:write')
+ON CONFLICT DO NOTHING;
+INSERT INTO role_permissions (role, permission)
+VALUES ('admin', 'users:read'), ('admin', 'users:write')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS audit_events_subject_idx;
+ALTER TABLE audit_events DROP COLUMN IF EXISTS subject;
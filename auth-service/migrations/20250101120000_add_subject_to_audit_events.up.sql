@@ -0,0 +1,4 @@
+-- The account an action was taken on, when that isn't the actor's own
+ALTER TABLE audit_events ADD COLUMN IF NOT EXISTS subject TEXT;
+
+CREATE INDEX IF NOT EXISTS audit_events_subject_idx ON audit_events (subject, occurred_at DESC);
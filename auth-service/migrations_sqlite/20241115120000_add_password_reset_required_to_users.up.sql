@@ -0,0 +1,3 @@
+-- Set when the owner reports a login that wasn't them. The current password
+-- stops working until it is reset.
+ALTER TABLE users ADD COLUMN password_reset_required BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Language the user's emails are written in, e.g. 'en' or 'de'
+ALTER TABLE users ADD COLUMN locale TEXT NOT NULL DEFAULT 'en';
@@ -0,0 +1,3 @@
+-- Whether emails still reach the user, as reported by the email provider:
+-- 'deliverable', 'soft_bounced', 'hard_bounced' or 'spam_complaint'
+ALTER TABLE users ADD COLUMN email_status TEXT NOT NULL DEFAULT 'deliverable';
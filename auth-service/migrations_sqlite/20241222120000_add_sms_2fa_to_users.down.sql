@@ -0,0 +1,2 @@
+ALTER TABLE users DROP COLUMN two_fa_channel;
+ALTER TABLE users DROP COLUMN phone_number;
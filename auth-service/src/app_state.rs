@@ -1,16 +1,41 @@
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
+use crate::domain::data_stores::audit_log::AuditLog;
 use crate::domain::data_stores::banned_token_store::BannedTokenStore;
+use crate::domain::data_stores::email_outbox::EmailOutbox;
+use crate::domain::data_stores::known_device_store::KnownDeviceStore;
+use crate::domain::data_stores::organization_store::OrganizationStore;
+use crate::domain::data_stores::role_store::RoleStore;
 use crate::domain::data_stores::TwoFACodeStore;
 use crate::domain::data_stores::UserStore;
-use crate::domain::EmailClient;
+use crate::domain::{EmailClient, SmsClient};
+use crate::services::data_stores::hashmap_email_outbox::HashMapEmailOutbox;
+use crate::services::data_stores::hashmap_organization_store::HashMapOrganizationStore;
+use crate::services::data_stores::hashmap_role_store::HashMapRoleStore;
+use crate::services::data_stores::instrumented::{
+    InstrumentedAuditLog, InstrumentedBannedTokenStore, InstrumentedEmailOutbox,
+    InstrumentedKnownDeviceStore, InstrumentedOrganizationStore, InstrumentedRoleStore,
+    InstrumentedTwoFACodeStore, InstrumentedUserStore,
+};
+use crate::services::dev_mailbox_email_client::DevMailbox;
+use crate::services::instrumented_email_client::InstrumentedEmailClient;
+use crate::services::suppressing_email_client::SuppressingEmailClient;
+use crate::settings::Settings;
+use crate::utils::metrics::Metrics;
 
 // Using a type alias to improve readability!
-pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
-pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
-pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
-pub type EmailClientType = Arc<RwLock<dyn EmailClient + Send + Sync>>;
+// Stores and clients handle their own concurrency (connection pools, internal
+// locks), so they're shared directly rather than behind an outer lock.
+pub type UserStoreType = Arc<dyn UserStore + Send + Sync>;
+pub type BannedTokenStoreType = Arc<dyn BannedTokenStore + Send + Sync>;
+pub type TwoFACodeStoreType = Arc<dyn TwoFACodeStore + Send + Sync>;
+pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
+pub type AuditLogType = Arc<dyn AuditLog + Send + Sync>;
+pub type KnownDeviceStoreType = Arc<dyn KnownDeviceStore + Send + Sync>;
+pub type EmailOutboxType = Arc<dyn EmailOutbox + Send + Sync>;
+pub type SmsClientType = Arc<dyn SmsClient + Send + Sync>;
+pub type RoleStoreType = Arc<dyn RoleStore + Send + Sync>;
+pub type OrganizationStoreType = Arc<dyn OrganizationStore + Send + Sync>;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -18,6 +43,21 @@ pub struct AppState {
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType,
+    pub audit_log: AuditLogType,
+    pub known_device_store: KnownDeviceStoreType,
+    // Where handlers put emails; they're delivered through `email_client` by
+    // the outbox worker
+    pub email_outbox: EmailOutboxType,
+    // Who has which roles, and so which permissions
+    pub role_store: RoleStoreType,
+    // Organizations, their members and open invitations
+    pub organization_store: OrganizationStoreType,
+    // Set when emails are captured locally, which also serves /dev/mailbox
+    pub dev_mailbox: Option<Arc<DevMailbox>>,
+    // Set when an SMS gateway is configured, so login codes can be texted
+    pub sms_client: Option<SmsClientType>,
+    pub settings: Arc<Settings>,
+    pub metrics: Arc<Metrics>,
 }
 
 impl AppState {
@@ -26,12 +66,90 @@ impl AppState {
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
         email_client: EmailClientType,
+        audit_log: AuditLogType,
+        known_device_store: KnownDeviceStoreType,
+        settings: Arc<Settings>,
     ) -> Self {
+        // Every store and the email client are wrapped so their latencies
+        // and outcomes show up on /metrics, whatever the backend
+        let metrics = Arc::new(Metrics::new());
+        let user_store: UserStoreType =
+            Arc::new(InstrumentedUserStore::new(user_store, metrics.clone()));
+        // Nothing is sent to addresses that bounced or complained
+        let email_client = Arc::new(SuppressingEmailClient::new(
+            Arc::new(InstrumentedEmailClient::new(email_client, metrics.clone())),
+            user_store.clone(),
+            metrics.clone(),
+        ));
         Self {
             user_store,
-            banned_token_store,
-            two_fa_code_store,
+            banned_token_store: Arc::new(InstrumentedBannedTokenStore::new(
+                banned_token_store,
+                metrics.clone(),
+            )),
+            two_fa_code_store: Arc::new(InstrumentedTwoFACodeStore::new(
+                two_fa_code_store,
+                metrics.clone(),
+            )),
             email_client,
+            audit_log: Arc::new(InstrumentedAuditLog::new(audit_log, metrics.clone())),
+            known_device_store: Arc::new(InstrumentedKnownDeviceStore::new(
+                known_device_store,
+                metrics.clone(),
+            )),
+            email_outbox: Arc::new(InstrumentedEmailOutbox::new(
+                Arc::new(HashMapEmailOutbox::default()),
+                metrics.clone(),
+            )),
+            role_store: Arc::new(InstrumentedRoleStore::new(
+                Arc::new(HashMapRoleStore::default()),
+                metrics.clone(),
+            )),
+            organization_store: Arc::new(InstrumentedOrganizationStore::new(
+                Arc::new(HashMapOrganizationStore::default()),
+                metrics.clone(),
+            )),
+            dev_mailbox: None,
+            sms_client: None,
+            settings,
+            metrics,
         }
     }
+
+    // Replaces the default in-memory outbox, e.g. with a durable one
+    pub fn with_email_outbox(mut self, email_outbox: EmailOutboxType) -> Self {
+        self.email_outbox = Arc::new(InstrumentedEmailOutbox::new(
+            email_outbox,
+            self.metrics.clone(),
+        ));
+        self
+    }
+
+    // Replaces the default in-memory role store, e.g. with a durable one
+    pub fn with_role_store(mut self, role_store: RoleStoreType) -> Self {
+        self.role_store = Arc::new(InstrumentedRoleStore::new(role_store, self.metrics.clone()));
+        self
+    }
+
+    // Replaces the default in-memory organization store, e.g. with a durable one
+    pub fn with_organization_store(mut self, organization_store: OrganizationStoreType) -> Self {
+        self.organization_store = Arc::new(InstrumentedOrganizationStore::new(
+            organization_store,
+            self.metrics.clone(),
+        ));
+        self
+    }
+
+    // Exposes the captured emails at /dev/mailbox. `dev_mailbox` should also
+    // be the email client, or there'll be nothing to see.
+    pub fn with_dev_mailbox(mut self, dev_mailbox: Arc<DevMailbox>) -> Self {
+        self.dev_mailbox = Some(dev_mailbox);
+        self
+    }
+
+    // Lets users choose to get their login codes by SMS
+    pub fn with_sms_client(mut self, sms_client: SmsClientType) -> Self {
+        self.sms_client = Some(sms_client);
+        self
+    }
 }
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+// Roles and permissions are plain names, e.g. "admin" and "roles:write",
+// defined in the role store. A role is a set of permissions, and a user's
+// roles add up to everything they're allowed to do.
+
+// A permission a route can require of its caller, see
+// `utils::authorization::RequirePermission`
+pub trait Permission {
+    const NAME: &'static str;
+}
+
+// A role a route can require of its caller, see
+// `utils::authorization::RequireRole`
+pub trait Role {
+    const NAME: &'static str;
+}
+
+// Search the audit log
+pub struct ReadAuditEvents;
+
+impl Permission for ReadAuditEvents {
+    const NAME: &'static str = "audit_events:read";
+}
+
+// See which roles exist and who has them
+pub struct ReadRoles;
+
+impl Permission for ReadRoles {
+    const NAME: &'static str = "roles:read";
+}
+
+// Grant and revoke roles
+pub struct ManageRoles;
+
+impl Permission for ManageRoles {
+    const NAME: &'static str = "roles:write";
+}
+
+// List and look up user accounts
+pub struct ReadUsers;
+
+impl Permission for ReadUsers {
+    const NAME: &'static str = "users:read";
+}
+
+// Disable accounts, force password resets, change their 2FA requirement
+// and sign them out
+pub struct ManageUsers;
+
+impl Permission for ManageUsers {
+    const NAME: &'static str = "users:write";
+}
+
+pub struct Admin;
+
+impl Role for Admin {
+    const NAME: &'static str = "admin";
+}
+
+// Roles every role store starts out with, and their permissions
+pub const BUILT_IN_ROLES: &[(&str, &[&str])] = &[(
+    Admin::NAME,
+    &[
+        ReadAuditEvents::NAME,
+        ReadRoles::NAME,
+        ManageRoles::NAME,
+        ReadUsers::NAME,
+        ManageUsers::NAME,
+    ],
+)];
+
+// The roles granted to a user and the permissions they add up to, as
+// carried in the user's auth tokens. Both are sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Grants {
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+impl Grants {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|granted| granted == role)
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|granted| granted == permission)
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AuditLogError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// What happened. Stored as the snake_case name, so existing rows keep their
+// meaning if variants are added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventKind {
+    Signup,
+    Login,
+    TwoFaCodeSent,
+    TwoFaVerification,
+    Logout,
+    TokenRejected,
+    NewDeviceAlert,
+    SessionsRevoked,
+    PasswordReset,
+    RoleGranted,
+    RoleRevoked,
+    InvitationSent,
+    InvitationAccepted,
+    MemberRemoved,
+    AccountDisabled,
+    AccountEnabled,
+    PasswordResetForced,
+    TwoFaRequirementChanged,
+}
+
+impl AuditEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEventKind::Signup => "signup",
+            AuditEventKind::Login => "login",
+            AuditEventKind::TwoFaCodeSent => "two_fa_code_sent",
+            AuditEventKind::TwoFaVerification => "two_fa_verification",
+            AuditEventKind::Logout => "logout",
+            AuditEventKind::TokenRejected => "token_rejected",
+            AuditEventKind::NewDeviceAlert => "new_device_alert",
+            AuditEventKind::SessionsRevoked => "sessions_revoked",
+            AuditEventKind::PasswordReset => "password_reset",
+            AuditEventKind::RoleGranted => "role_granted",
+            AuditEventKind::RoleRevoked => "role_revoked",
+            AuditEventKind::InvitationSent => "invitation_sent",
+            AuditEventKind::InvitationAccepted => "invitation_accepted",
+            AuditEventKind::MemberRemoved => "member_removed",
+            AuditEventKind::AccountDisabled => "account_disabled",
+            AuditEventKind::AccountEnabled => "account_enabled",
+            AuditEventKind::PasswordResetForced => "password_reset_forced",
+            AuditEventKind::TwoFaRequirementChanged => "two_fa_requirement_changed",
+        }
+    }
+}
+
+impl FromStr for AuditEventKind {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            AuditEventKind::Signup,
+            AuditEventKind::Login,
+            AuditEventKind::TwoFaCodeSent,
+            AuditEventKind::TwoFaVerification,
+            AuditEventKind::Logout,
+            AuditEventKind::TokenRejected,
+            AuditEventKind::NewDeviceAlert,
+            AuditEventKind::SessionsRevoked,
+            AuditEventKind::PasswordReset,
+            AuditEventKind::RoleGranted,
+            AuditEventKind::RoleRevoked,
+            AuditEventKind::InvitationSent,
+            AuditEventKind::InvitationAccepted,
+            AuditEventKind::MemberRemoved,
+            AuditEventKind::AccountDisabled,
+            AuditEventKind::AccountEnabled,
+            AuditEventKind::PasswordResetForced,
+            AuditEventKind::TwoFaRequirementChanged,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == s)
+        .ok_or_else(|| eyre!("unknown audit event kind \"{}\"", s))
+    }
+}
+
+impl fmt::Display for AuditEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+impl AuditOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditOutcome::Success => "success",
+            AuditOutcome::Failure => "failure",
+        }
+    }
+}
+
+impl FromStr for AuditOutcome {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "success" => Ok(AuditOutcome::Success),
+            "failure" => Ok(AuditOutcome::Failure),
+            _ => Err(eyre!("unknown audit outcome \"{}\"", s)),
+        }
+    }
+}
+
+// One security-relevant event. `actor` is who did it: the email as it was
+// submitted, so failed attempts against unknown or malformed addresses are
+// recorded too, or the admin API token. `subject` is the account an action
+// was taken on when that's someone else's, e.g. the user an admin disabled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    pub kind: AuditEventKind,
+    pub outcome: AuditOutcome,
+    // Why it failed (or e.g. that a login needs 2FA), as in the metrics labels
+    pub reason: Option<String>,
+    pub actor: Option<String>,
+    pub subject: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+// Which events to return. Events are returned newest first, at most `limit`
+// of them; empty `kinds` matches every kind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEventFilter {
+    pub actor: Option<String>,
+    pub subject: Option<String>,
+    pub kinds: Vec<AuditEventKind>,
+    pub outcome: Option<AuditOutcome>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+impl Default for AuditEventFilter {
+    fn default() -> Self {
+        Self {
+            actor: None,
+            subject: None,
+            kinds: Vec::new(),
+            outcome: None,
+            since: None,
+            until: None,
+            limit: 100,
+        }
+    }
+}
+
+impl AuditEventFilter {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        self.actor
+            .as_ref()
+            .is_none_or(|actor| event.actor.as_ref() == Some(actor))
+            && self
+                .subject
+                .as_ref()
+                .is_none_or(|subject| event.subject.as_ref() == Some(subject))
+            && (self.kinds.is_empty() || self.kinds.contains(&event.kind))
+            && self.outcome.is_none_or(|outcome| event.outcome == outcome)
+            && self.since.is_none_or(|since| event.occurred_at >= since)
+            && self.until.is_none_or(|until| event.occurred_at < until)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait AuditLog {
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError>;
+    async fn query(&self, filter: &AuditEventFilter) -> Result<Vec<AuditEvent>, AuditLogError>;
+    // Probed by the readiness endpoint
+    async fn health_check(&self) -> Result<(), AuditLogError> {
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::Report;
 use secrecy::Secret;
 use thiserror::Error;
+
+use crate::domain::Email;
 #[derive(Debug, Error)]
 pub enum BannedTokenStoreError {
     #[error("Unexpected error")]
@@ -9,6 +12,21 @@ pub enum BannedTokenStoreError {
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
+    async fn add_token(&self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
     async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError>;
+    // Ban every token issued to `email` before `revoked_at`. Like a banned
+    // token, this is only remembered for as long as a token can live.
+    async fn revoke_sessions(
+        &self,
+        email: &Email,
+        revoked_at: DateTime<Utc>,
+    ) -> Result<(), BannedTokenStoreError>;
+    async fn sessions_revoked_at(
+        &self,
+        email: &Email,
+    ) -> Result<Option<DateTime<Utc>>, BannedTokenStoreError>;
+    // Probed by the readiness endpoint
+    async fn health_check(&self) -> Result<(), BannedTokenStoreError> {
+        Ok(())
+    }
 }
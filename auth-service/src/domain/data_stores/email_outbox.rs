@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use color_eyre::eyre::Report;
+use thiserror::Error;
+
+use crate::domain::{Email, EmailMessage};
+
+#[derive(Debug, Error)]
+pub enum EmailOutboxError {
+    #[error("Email not found in outbox")]
+    EmailNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// An email waiting in the outbox, or one that was given up on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxEmail {
+    pub id: i64,
+    pub recipient: Email,
+    pub message: EmailMessage,
+    // Delivery attempts so far, counting the one it was just claimed for
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+// Emails are enqueued by request handlers and delivered in the background,
+// so a slow or failing provider doesn't fail the request that caused them.
+// This is not a transactional outbox. Enqueueing isn't part of the
+// handler's own writes, even when both live in Postgres: handlers store what
+// the email is about first and undo it if the enqueue fails. A crash in
+// between can still leave that state without its email (e.g. a 2FA code
+// that was never sent, until the user logs in again), but never an email
+// about something that wasn't stored.
+#[async_trait::async_trait]
+pub trait EmailOutbox {
+    async fn enqueue(
+        &self,
+        recipient: Email,
+        message: EmailMessage,
+    ) -> Result<(), EmailOutboxError>;
+    // Hand out up to `limit` emails that are due, oldest first. They are
+    // hidden from other claims for `lease`, after which they become due
+    // again in case whoever claimed them died before reporting back.
+    async fn claim_due(
+        &self,
+        limit: usize,
+        lease: Duration,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxError>;
+    async fn mark_sent(&self, id: i64) -> Result<(), EmailOutboxError>;
+    async fn retry_later(
+        &self,
+        id: i64,
+        delay: Duration,
+        error: &str,
+    ) -> Result<(), EmailOutboxError>;
+    // Stop trying to deliver the email, keeping it for inspection
+    async fn dead_letter(&self, id: i64, error: &str) -> Result<(), EmailOutboxError>;
+    async fn dead_letters(&self) -> Result<Vec<OutboxEmail>, EmailOutboxError>;
+    // Delete emails dead-lettered more than `retention` ago, returning how
+    // many. Their bodies can hold live links, so they aren't kept forever.
+    async fn purge_dead_letters(&self, retention: Duration) -> Result<u64, EmailOutboxError>;
+    // Emails still to be delivered, whether due yet or not
+    async fn pending_count(&self) -> Result<u64, EmailOutboxError>;
+    // Probed by the readiness endpoint
+    async fn health_check(&self) -> Result<(), EmailOutboxError> {
+        Ok(())
+    }
+}
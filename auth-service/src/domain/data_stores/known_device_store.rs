@@ -0,0 +1,42 @@
+use color_eyre::eyre::Report;
+use thiserror::Error;
+
+use crate::domain::Email;
+
+#[derive(Debug, Error)]
+pub enum KnownDeviceStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// Where a login came from. Either part may be missing, e.g. for a client
+// that doesn't send a User-Agent.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Device {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceStatus {
+    // The user had no known devices yet, i.e. this is their first login
+    First,
+    Known,
+    // Never used by this user before, while other devices have been
+    New,
+}
+
+#[async_trait::async_trait]
+pub trait KnownDeviceStore {
+    // Remember `device` as one `email` logs in from, telling whether it was
+    // already known
+    async fn remember_device(
+        &self,
+        email: &Email,
+        device: &Device,
+    ) -> Result<DeviceStatus, KnownDeviceStoreError>;
+    // Probed by the readiness endpoint
+    async fn health_check(&self) -> Result<(), KnownDeviceStoreError> {
+        Ok(())
+    }
+}
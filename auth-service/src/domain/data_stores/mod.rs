@@ -1,4 +1,9 @@
+pub mod audit_log;
 pub mod banned_token_store;
+pub mod email_outbox;
+pub mod known_device_store;
+pub mod organization_store;
+pub mod role_store;
 pub mod two_fa_code_store;
 pub mod user_store;
 pub use two_fa_code_store::*;
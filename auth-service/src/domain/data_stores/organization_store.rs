@@ -0,0 +1,74 @@
+use color_eyre::eyre::Report;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::domain::{Email, Invitation, Member, Membership, OrgRole, Organization, OrganizationId};
+
+#[derive(Debug, Error)]
+pub enum OrganizationStoreError {
+    #[error("Organization not found")]
+    OrganizationNotFound,
+    #[error("Not a member")]
+    NotMember,
+    #[error("Already a member")]
+    AlreadyMember,
+    // Removing the member would leave the organization without an owner
+    #[error("Last owner")]
+    LastOwner,
+    // Also for invitations that expired or were already accepted
+    #[error("Invitation not found")]
+    InvitationNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// Everything but creating organizations and listing a user's memberships is
+// scoped to one organization, so one tenant can never see another's members
+// or invitations.
+#[async_trait::async_trait]
+pub trait OrganizationStore {
+    // `owner` becomes its first member
+    async fn create_organization(
+        &self,
+        name: &str,
+        owner: &Email,
+    ) -> Result<Organization, OrganizationStoreError>;
+    async fn get_organization(
+        &self,
+        id: &OrganizationId,
+    ) -> Result<Organization, OrganizationStoreError>;
+    // Every organization `email` belongs to, the one they joined first first
+    async fn memberships(&self, email: &Email) -> Result<Vec<Membership>, OrganizationStoreError>;
+    async fn role(
+        &self,
+        id: &OrganizationId,
+        email: &Email,
+    ) -> Result<OrgRole, OrganizationStoreError>;
+    async fn members(&self, id: &OrganizationId) -> Result<Vec<Member>, OrganizationStoreError>;
+    // Refuses to remove the organization's last owner, checked atomically
+    // with the removal so concurrent removals can't both succeed
+    async fn remove_member(
+        &self,
+        id: &OrganizationId,
+        email: &Email,
+    ) -> Result<(), OrganizationStoreError>;
+    async fn add_invitation(&self, invitation: Invitation) -> Result<(), OrganizationStoreError>;
+    // Invitations of the organization that can still be accepted
+    async fn invitations(
+        &self,
+        id: &OrganizationId,
+    ) -> Result<Vec<Invitation>, OrganizationStoreError>;
+    async fn get_invitation(&self, id: &Uuid) -> Result<Invitation, OrganizationStoreError>;
+    async fn remove_invitation(&self, id: &Uuid) -> Result<(), OrganizationStoreError>;
+    // Uses up the invitation and makes `email`, the invitee's account, a
+    // member. Someone who already is keeps the role they have.
+    async fn accept_invitation(
+        &self,
+        id: &Uuid,
+        email: &Email,
+    ) -> Result<Membership, OrganizationStoreError>;
+    // Probed by the readiness endpoint
+    async fn health_check(&self) -> Result<(), OrganizationStoreError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use color_eyre::eyre::Report;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::domain::{Email, Grants};
+
+#[derive(Debug, Error)]
+pub enum RoleStoreError {
+    #[error("Unknown role")]
+    UnknownRole,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// A role and the permissions it grants, both sorted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleDefinition {
+    pub name: String,
+    pub permissions: Vec<String>,
+}
+
+#[async_trait::async_trait]
+pub trait RoleStore {
+    // Every role that can be granted, by name
+    async fn roles(&self) -> Result<Vec<RoleDefinition>, RoleStoreError>;
+    // Granting a role the user already has, or revoking one they don't, is
+    // not an error. Unknown roles are.
+    async fn grant_role(&self, email: &Email, role: &str) -> Result<(), RoleStoreError>;
+    async fn revoke_role(&self, email: &Email, role: &str) -> Result<(), RoleStoreError>;
+    // What `email` is allowed to do; nothing for users without roles
+    async fn grants(&self, email: &Email) -> Result<Grants, RoleStoreError>;
+    // Probed by the readiness endpoint
+    async fn health_check(&self) -> Result<(), RoleStoreError> {
+        Ok(())
+    }
+}
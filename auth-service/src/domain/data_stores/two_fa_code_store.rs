@@ -1,27 +1,34 @@
-use color_eyre::eyre::Report;
-use secrecy::{ExposeSecret, Secret};
-use thiserror::Error;
 use async_trait::async_trait;
+use color_eyre::eyre::Report;
 use rand::Rng;
+use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
+use thiserror::Error;
 use uuid::Uuid;
 
 use crate::domain::email::Email;
 
+// This value determines how long a 2FA code is valid for
+pub const TWO_FA_CODE_TTL_SECONDS: u64 = 600; // 10 minutes
+
 // This trait represents the interface all concrete 2FA code stores should implement
 #[async_trait]
 pub trait TwoFACodeStore {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError>;
     async fn get_code(
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    // Probed by the readiness endpoint
+    async fn health_check(&self) -> Result<(), TwoFACodeStoreError> {
+        Ok(())
+    }
 }
 
 #[derive(Debug, Error)]
@@ -59,7 +66,7 @@ impl LoginAttemptId {
         let uuid = Uuid::parse_str(&id);
         match uuid {
             Ok(uuid) => Ok(Self(Secret::new(uuid.to_string()))),
-            Err(_) => Err("could not parse login attempt id".to_string())
+            Err(_) => Err("could not parse login attempt id".to_string()),
         }
     }
 }
@@ -73,7 +80,7 @@ impl Default for LoginAttemptId {
 
 impl AsRef<str> for LoginAttemptId {
     fn as_ref(&self) -> &str {
-        &self.0.expose_secret()
+        self.0.expose_secret()
     }
 }
 
@@ -92,7 +99,7 @@ impl TwoFACode {
         if code.len() != 6 {
             return Err("could not parse FA Code".to_string());
         }
-        if !code.chars().all(|c| c.is_digit(10)) {
+        if !code.chars().all(|c| c.is_ascii_digit()) {
             return Err("could not parse FA Code because a non-digit was found".to_string());
         }
 
@@ -112,6 +119,6 @@ impl Default for TwoFACode {
 
 impl AsRef<str> for TwoFACode {
     fn as_ref(&self) -> &str {
-        &self.0.expose_secret()
+        self.0.expose_secret()
     }
 }
@@ -1,13 +1,45 @@
 use color_eyre::eyre::Report;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 // use color_eyre::eyre::{eyre, Context, Result};
-use crate::domain::{password::Password, user::User, Email};
+use crate::domain::{password::Password, user::User, Email, EmailStatus, Locale, TwoFAChannel};
 
 #[async_trait::async_trait]
 pub trait UserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
-    async fn validate_user(&self, email: &Email, password: &Password) -> Result<(), UserStoreError>;
+    // Fails with `AccountDisabled` or `PasswordResetRequired` for a correct
+    // password while the account is locked by `set_disabled` or
+    // `require_password_reset`
+    async fn validate_user(&self, email: &Email, password: &Password)
+        -> Result<(), UserStoreError>;
+    // Lock the account's current password until `reset_password` is called
+    async fn require_password_reset(&self, email: &Email) -> Result<(), UserStoreError>;
+    // Replace the password and lift the `require_password_reset` lock, in one
+    // step. Fails with `PasswordResetNotRequired` when the account isn't
+    // locked, so a recovery link can't set the password a second time.
+    async fn reset_password(&self, email: &Email, password: Password)
+        -> Result<(), UserStoreError>;
+    // Record what the email provider last reported about the user's address
+    async fn set_email_status(
+        &self,
+        email: &Email,
+        status: EmailStatus,
+    ) -> Result<(), UserStoreError>;
+    // One page of accounts, ordered by email
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError>;
+    async fn user_summary(&self, email: &Email) -> Result<UserSummary, UserStoreError>;
+    // A disabled account can't log in until it is enabled again
+    async fn set_disabled(&self, email: &Email, disabled: bool) -> Result<(), UserStoreError>;
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError>;
+    // Probed by the readiness endpoint
+    async fn health_check(&self) -> Result<(), UserStoreError> {
+        Ok(())
+    }
 }
 
 #[derive(Debug, Error)]
@@ -18,6 +50,12 @@ pub enum UserStoreError {
     UserNotFound,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("Password reset required")]
+    PasswordResetRequired,
+    #[error("Password reset not required")]
+    PasswordResetNotRequired,
+    #[error("Account disabled")]
+    AccountDisabled,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -29,7 +67,53 @@ impl PartialEq for UserStoreError {
             (Self::UserAlreadyExists, Self::UserAlreadyExists)
                 | (Self::UserNotFound, Self::UserNotFound)
                 | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::PasswordResetRequired, Self::PasswordResetRequired)
+                | (
+                    Self::PasswordResetNotRequired,
+                    Self::PasswordResetNotRequired
+                )
+                | (Self::AccountDisabled, Self::AccountDisabled)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
 }
+
+// Which accounts to list: those whose email contains `search`, ignoring
+// case, skipping the first `offset` and returning at most `limit`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserQuery {
+    pub search: Option<String>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Default for UserQuery {
+    fn default() -> Self {
+        Self {
+            search: None,
+            offset: 0,
+            limit: 50,
+        }
+    }
+}
+
+// `total` counts every account matching the query, not just this page
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserPage {
+    pub users: Vec<UserSummary>,
+    pub total: usize,
+}
+
+// What an admin gets to see of an account: everything but its password
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSummary {
+    pub email: String,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    pub locale: Locale,
+    pub email_status: EmailStatus,
+    pub two_fa_channel: TwoFAChannel,
+    pub disabled: bool,
+    pub password_reset_required: bool,
+}
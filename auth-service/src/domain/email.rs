@@ -31,10 +31,7 @@ impl Email {
         if s.validate_email() {
             Ok(Self(Secret::new(s)))
         } else {
-            Err(eyre!(format!(
-                "{} is not a valid email.",
-                s
-            )))
+            Err(eyre!(format!("{} is not a valid email.", s)))
         }
     }
 }
@@ -84,7 +81,7 @@ mod tests {
 
     #[test]
     fn can_convert_email_to_string() {
-        let email ="ken@cttm.io";
+        let email = "ken@cttm.io";
         let res = Email::parse(email.to_string()).unwrap();
         // not sure if this is valuable either
         assert_eq!(res.as_ref().expose_secret(), email);
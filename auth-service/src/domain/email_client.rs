@@ -1,13 +1,22 @@
 use super::Email;
 
 use color_eyre::Result;
+
+// A rendered email, ready to hand to a provider. Clients send both bodies so
+// mail programs that can't show HTML fall back to the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
 // This trait represents the interface all concrete email clients should implement
 #[async_trait::async_trait]
 pub trait EmailClient {
-    async fn send_email(
-        &self,
-        recipient: &Email,
-        subject: &str,
-        content: &str,
-    ) -> Result<()>;
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()>;
+    // Used by the readiness probe to check the email provider can be reached
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,88 @@
+use std::str::FromStr;
+
+use color_eyre::eyre::{eyre, Report};
+use serde::{Deserialize, Serialize};
+
+// Whether emails to a user's address are getting through, as last reported
+// by the email provider
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailStatus {
+    #[default]
+    Deliverable,
+    // Temporarily undeliverable, e.g. a full mailbox. Still worth trying.
+    SoftBounced,
+    // The address doesn't exist or refuses our mail
+    HardBounced,
+    // The recipient marked one of our emails as spam
+    SpamComplaint,
+}
+
+impl EmailStatus {
+    pub const ALL: [EmailStatus; 4] = [
+        EmailStatus::Deliverable,
+        EmailStatus::SoftBounced,
+        EmailStatus::HardBounced,
+        EmailStatus::SpamComplaint,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailStatus::Deliverable => "deliverable",
+            EmailStatus::SoftBounced => "soft_bounced",
+            EmailStatus::HardBounced => "hard_bounced",
+            EmailStatus::SpamComplaint => "spam_complaint",
+        }
+    }
+
+    // No more emails are sent to suppressed addresses. Sending anyway hurts
+    // our reputation with the provider and the mailbox providers.
+    pub fn is_suppressed(&self) -> bool {
+        matches!(self, EmailStatus::HardBounced | EmailStatus::SpamComplaint)
+    }
+
+    // Whether a successful delivery shows the address works again. A spam
+    // complaint stands however much mail still gets through.
+    pub fn cleared_by_delivery(&self) -> bool {
+        matches!(self, EmailStatus::SoftBounced | EmailStatus::HardBounced)
+    }
+}
+
+impl FromStr for EmailStatus {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EmailStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == s)
+            .ok_or_else(|| eyre!("unknown email status {:?}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_strings() {
+        for status in EmailStatus::ALL {
+            assert_eq!(status.as_str().parse::<EmailStatus>().unwrap(), status);
+        }
+        assert!("bounced".parse::<EmailStatus>().is_err());
+    }
+
+    #[test]
+    fn only_hard_bounces_and_complaints_are_suppressed() {
+        assert!(!EmailStatus::Deliverable.is_suppressed());
+        assert!(!EmailStatus::SoftBounced.is_suppressed());
+        assert!(EmailStatus::HardBounced.is_suppressed());
+        assert!(EmailStatus::SpamComplaint.is_suppressed());
+    }
+
+    #[test]
+    fn deliveries_clear_bounces_but_not_complaints() {
+        assert!(EmailStatus::SoftBounced.cleared_by_delivery());
+        assert!(EmailStatus::HardBounced.cleared_by_delivery());
+        assert!(!EmailStatus::SpamComplaint.cleared_by_delivery());
+    }
+}
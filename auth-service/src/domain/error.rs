@@ -13,6 +13,34 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Password reset required")]
+    PasswordResetRequired,
+    // Disabled by an admin, see the admin user API
+    #[error("Account disabled")]
+    AccountDisabled,
+    // 2FA codes can't be delivered to the user's address
+    #[error("Email address suppressed")]
+    EmailSuppressed,
+    // SMS 2FA was asked for, but no SMS gateway is configured
+    #[error("SMS unavailable")]
+    SmsUnavailable,
+    // The caller is authenticated but lacks the role or permission needed
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Unknown role")]
+    UnknownRole,
+    // Also for organizations the caller doesn't belong to, so outsiders
+    // can't tell which ones exist
+    #[error("Organization not found")]
+    OrganizationNotFound,
+    // Also for invitations that expired or were already accepted
+    #[error("Invitation not found")]
+    InvitationNotFound,
+    // Removing the member would leave their organization without an owner
+    #[error("Last owner")]
+    LastOwner,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
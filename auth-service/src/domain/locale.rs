@@ -0,0 +1,52 @@
+use std::str::FromStr;
+
+use color_eyre::eyre::{eyre, Report};
+use serde::{Deserialize, Serialize};
+
+// A language the service has messages for. Users get the default until they
+// pick one (at signup, or through their browser's Accept-Language).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::De];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = Report;
+
+    // Accepts a bare language ("de") or a language tag ("de-AT"), in any case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s.split('-').next().unwrap_or_default();
+        Locale::ALL
+            .into_iter()
+            .find(|locale| locale.as_str().eq_ignore_ascii_case(language))
+            .ok_or_else(|| eyre!("unsupported locale {:?}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_languages_and_tags() {
+        assert_eq!("en".parse::<Locale>().unwrap(), Locale::En);
+        assert_eq!("DE".parse::<Locale>().unwrap(), Locale::De);
+        assert_eq!("de-AT".parse::<Locale>().unwrap(), Locale::De);
+        assert!("fr".parse::<Locale>().is_err());
+        assert!("".parse::<Locale>().is_err());
+    }
+}
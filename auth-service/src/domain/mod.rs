@@ -1,8 +1,20 @@
 pub mod user;
 pub use user::*;
-pub mod error;
+pub mod access;
+pub use access::*;
 pub mod data_stores;
 pub mod email;
+pub mod email_status;
+pub mod error;
+pub use email_status::*;
+pub mod locale;
+pub use locale::*;
+pub mod organization;
+pub use organization::*;
+pub mod phone_number;
+pub use phone_number::*;
+pub mod two_fa_channel;
+pub use two_fa_channel::*;
 
 pub use email::*;
 pub mod password;
@@ -11,3 +23,6 @@ pub use password::*;
 
 pub mod email_client;
 pub use email_client::*;
+
+pub mod sms_client;
+pub use sms_client::*;
@@ -0,0 +1,188 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::Email;
+
+// Organizations are the companies our users work for. A user can belong to
+// several, with a role in each, and acts on behalf of one at a time: the one
+// named by the `org_id` claim of their auth token.
+
+// Random, so ids don't give away how many organizations there are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OrganizationId(Uuid);
+
+impl OrganizationId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    pub fn parse(id: &str) -> Result<Self, Report> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("invalid organization id {:?}", id))
+    }
+}
+
+impl Default for OrganizationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Uuid> for OrganizationId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for OrganizationId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for OrganizationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// What a member may do within their organization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrgRole {
+    // Created the organization, or was made an owner by another
+    Owner,
+    // Invites and removes members
+    Admin,
+    Member,
+}
+
+impl OrgRole {
+    pub const ALL: [OrgRole; 3] = [OrgRole::Owner, OrgRole::Admin, OrgRole::Member];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrgRole::Owner => "owner",
+            OrgRole::Admin => "admin",
+            OrgRole::Member => "member",
+        }
+    }
+
+    // Whether a member with this role may invite or remove members with
+    // `role`. Only owners can make or remove other owners.
+    pub fn can_manage(&self, role: OrgRole) -> bool {
+        match self {
+            OrgRole::Owner => true,
+            OrgRole::Admin => role != OrgRole::Owner,
+            OrgRole::Member => false,
+        }
+    }
+}
+
+impl FromStr for OrgRole {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OrgRole::ALL
+            .into_iter()
+            .find(|role| role.as_str() == s)
+            .ok_or_else(|| eyre!("unknown organization role {:?}", s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Organization {
+    pub id: OrganizationId,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// An organization a user belongs to, as they see it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Membership {
+    pub organization_id: OrganizationId,
+    pub organization_name: String,
+    pub role: OrgRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+// A user in an organization, as its members see them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Member {
+    pub email: String,
+    pub role: OrgRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+// An open invitation for `email` to join an organization with `role`. It is
+// used up when accepted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Invitation {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub email: String,
+    pub role: OrgRole,
+    pub invited_by: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Invitation {
+    // Whether `email` is the address the invitation was sent to. People
+    // don't always type their address the same way twice, so case is
+    // ignored.
+    pub fn is_for(&self, email: &Email) -> bool {
+        self.email
+            .eq_ignore_ascii_case(email.as_ref().expose_secret())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_round_trip_through_strings() {
+        for role in OrgRole::ALL {
+            assert_eq!(role.as_str().parse::<OrgRole>().unwrap(), role);
+        }
+        assert!("janitor".parse::<OrgRole>().is_err());
+    }
+
+    #[test]
+    fn invitations_match_their_address_in_any_case() {
+        let invitation = Invitation {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            email: "Jane.Doe@Example.com".to_owned(),
+            role: OrgRole::Member,
+            invited_by: "owner@example.com".to_owned(),
+            expires_at: Utc::now(),
+        };
+
+        let same = Email::parse("jane.doe@example.com".to_owned()).unwrap();
+        let other = Email::parse("john.doe@example.com".to_owned()).unwrap();
+        assert!(invitation.is_for(&same));
+        assert!(!invitation.is_for(&other));
+    }
+
+    #[test]
+    fn only_owners_manage_owners() {
+        assert!(OrgRole::Owner.can_manage(OrgRole::Owner));
+        assert!(!OrgRole::Admin.can_manage(OrgRole::Owner));
+        assert!(OrgRole::Admin.can_manage(OrgRole::Admin));
+        assert!(OrgRole::Admin.can_manage(OrgRole::Member));
+        assert!(!OrgRole::Member.can_manage(OrgRole::Member));
+    }
+}
@@ -0,0 +1,76 @@
+use std::hash::Hash;
+
+use color_eyre::eyre::{eyre, Result};
+use secrecy::{ExposeSecret, Secret};
+
+// A phone number in E.164 format, e.g. "+4915112345678": a plus, the country
+// code and the subscriber number, at most 15 digits and no separators
+#[derive(Debug, Clone)]
+pub struct PhoneNumber(pub Secret<String>);
+
+impl PartialEq for PhoneNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Eq for PhoneNumber {}
+
+impl Hash for PhoneNumber {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.expose_secret().hash(state);
+    }
+}
+
+impl PhoneNumber {
+    pub fn parse(s: String) -> Result<PhoneNumber> {
+        let valid = s.strip_prefix('+').is_some_and(|digits| {
+            (2..=15).contains(&digits.len())
+                && !digits.starts_with('0')
+                && digits.chars().all(|c| c.is_ascii_digit())
+        });
+        if valid {
+            Ok(Self(Secret::new(s)))
+        } else {
+            Err(eyre!("{} is not a valid E.164 phone number.", s))
+        }
+    }
+}
+
+impl AsRef<Secret<String>> for PhoneNumber {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn e164_numbers_can_be_parsed() {
+        for number in ["+4915112345678", "+14155552671", "+12"] {
+            let parsed = PhoneNumber::parse(number.to_owned()).unwrap();
+            assert_eq!(parsed.as_ref().expose_secret(), number);
+        }
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        for number in [
+            "4915112345678",
+            "+49 151 12345678",
+            "+49-151-12345678",
+            "+0015112345678",
+            "+1234567890123456",
+            "+1",
+            "",
+        ] {
+            assert!(
+                PhoneNumber::parse(number.to_owned()).is_err(),
+                "{:?} should be rejected",
+                number
+            );
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use super::PhoneNumber;
+
+use color_eyre::Result;
+
+// This trait represents the interface all concrete SMS clients should
+// implement, like `EmailClient` does for emails
+#[async_trait::async_trait]
+pub trait SmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> Result<()>;
+    // Used by the readiness probe to check the SMS gateway can be reached
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
+}
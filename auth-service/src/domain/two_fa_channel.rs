@@ -0,0 +1,49 @@
+use std::str::FromStr;
+
+use color_eyre::eyre::{eyre, Report};
+use serde::{Deserialize, Serialize};
+
+// How a user with 2FA gets their login codes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TwoFAChannel {
+    #[default]
+    Email,
+    // A text message to the user's phone number
+    Sms,
+}
+
+impl TwoFAChannel {
+    pub const ALL: [TwoFAChannel; 2] = [TwoFAChannel::Email, TwoFAChannel::Sms];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TwoFAChannel::Email => "email",
+            TwoFAChannel::Sms => "sms",
+        }
+    }
+}
+
+impl FromStr for TwoFAChannel {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TwoFAChannel::ALL
+            .into_iter()
+            .find(|channel| channel.as_str() == s)
+            .ok_or_else(|| eyre!("unknown 2FA channel {:?}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_strings() {
+        for channel in TwoFAChannel::ALL {
+            assert_eq!(channel.as_str().parse::<TwoFAChannel>().unwrap(), channel);
+        }
+        assert!("carrier_pigeon".parse::<TwoFAChannel>().is_err());
+    }
+}
@@ -1,16 +1,47 @@
-use super::{email::Email, password::Password};
+use super::{
+    email::Email, email_status::EmailStatus, locale::Locale, password::Password,
+    phone_number::PhoneNumber, two_fa_channel::TwoFAChannel,
+};
 
 // The User struct should contain 3 fields. email, which is a String;
 // password, which is also a String; and requires_2fa, which is a boolean.
+// `locale` is the language emails to the user are written in, and
+// `email_status` whether they still reach the user. Login codes go out by
+// `two_fa_channel`, which is only SMS with a `phone_number` to send them to.
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    pub locale: Locale,
+    pub email_status: EmailStatus,
+    pub phone_number: Option<PhoneNumber>,
+    pub two_fa_channel: TwoFAChannel,
 }
 
 impl User {
     pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
-        User {email, password, requires_2fa}
+        User {
+            email,
+            password,
+            requires_2fa,
+            locale: Locale::default(),
+            email_status: EmailStatus::default(),
+            phone_number: None,
+            two_fa_channel: TwoFAChannel::default(),
+        }
+    }
+
+    pub fn with_locale(self, locale: Locale) -> Self {
+        Self { locale, ..self }
+    }
+
+    // Login codes are texted to `phone_number` instead of emailed
+    pub fn with_sms_2fa(self, phone_number: PhoneNumber) -> Self {
+        Self {
+            phone_number: Some(phone_number),
+            two_fa_channel: TwoFAChannel::Sms,
+            ..self
+        }
     }
 }
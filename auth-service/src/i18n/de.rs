@@ -0,0 +1,197 @@
+use super::Catalog;
+use crate::domain::error::AuthAPIError;
+
+pub struct German;
+
+impl Catalog for German {
+    fn api_error(&self, error: &AuthAPIError) -> &'static str {
+        match error {
+            AuthAPIError::UserAlreadyExists => "Benutzer existiert bereits",
+            AuthAPIError::InvalidCredentials => "Ungültige Anmeldedaten",
+            AuthAPIError::IncorrectCredentials => "Falsche Anmeldedaten",
+            AuthAPIError::MissingToken => "Anmelde-Token fehlt",
+            AuthAPIError::InvalidToken => "Ungültiges Anmelde-Token",
+            AuthAPIError::PasswordResetRequired => "Passwort muss zurückgesetzt werden",
+            AuthAPIError::AccountDisabled => {
+                "Dieses Konto wurde deaktiviert. Bitte wenden Sie sich an den Support."
+            }
+            AuthAPIError::EmailSuppressed => {
+                "Wir können keine Anmeldecodes an Ihre E-Mail-Adresse senden, da E-Mails an sie nicht zugestellt werden konnten oder als Spam markiert wurden. Bitte wenden Sie sich an den Support."
+            }
+            AuthAPIError::SmsUnavailable => {
+                "Anmeldecodes können derzeit nicht per SMS gesendet werden. Bitte wählen Sie stattdessen E-Mail."
+            }
+            AuthAPIError::Forbidden => "Sie haben keine Berechtigung dafür",
+            AuthAPIError::UserNotFound => "Benutzer nicht gefunden",
+            AuthAPIError::UnknownRole => "Unbekannte Rolle",
+            AuthAPIError::OrganizationNotFound => "Organisation nicht gefunden",
+            AuthAPIError::InvitationNotFound => {
+                "Diese Einladung ist abgelaufen oder wurde bereits verwendet. Bitte fordern Sie eine neue an."
+            }
+            AuthAPIError::LastOwner => {
+                "Eine Organisation braucht mindestens einen Inhaber. Bitte laden Sie zuerst einen weiteren Inhaber ein."
+            }
+            AuthAPIError::UnexpectedError(_) => "Unerwarteter Fehler",
+        }
+    }
+
+    fn user_created(&self) -> &'static str {
+        "Benutzer erfolgreich angelegt!"
+    }
+
+    fn two_fa_required(&self) -> &'static str {
+        "2FA erforderlich"
+    }
+
+    fn sessions_revoked(&self) -> &'static str {
+        "Überall abgemeldet. Wählen Sie ein neues Passwort, um sich wieder anzumelden."
+    }
+
+    fn password_updated(&self) -> &'static str {
+        "Passwort aktualisiert"
+    }
+
+    fn email_footer(&self, product_name: &str) -> String {
+        format!(
+            "Sie erhalten diese E-Mail wegen Aktivitäten in Ihrem {}-Konto.",
+            product_name
+        )
+    }
+
+    fn email_questions(&self) -> &'static str {
+        "Fragen? Schreiben Sie uns an"
+    }
+
+    fn unknown(&self) -> &'static str {
+        "unbekannt"
+    }
+
+    fn two_fa_code_subject(&self, product_name: &str) -> String {
+        format!("Ihr Anmeldecode für {}", product_name)
+    }
+
+    fn two_fa_code_intro(&self, product_name: &str) -> String {
+        format!(
+            "Mit diesem Code schließen Sie die Anmeldung bei {} ab:",
+            product_name
+        )
+    }
+
+    fn two_fa_code_expiry(&self, minutes: u64) -> String {
+        format!("Er ist {} Minuten lang gültig.", minutes)
+    }
+
+    fn two_fa_code_sms(&self, product_name: &str, code: &str, minutes: u64) -> String {
+        format!(
+            "Ihr Anmeldecode für {} lautet {}. Er ist {} Minuten lang gültig.",
+            product_name, code, minutes
+        )
+    }
+
+    fn two_fa_code_not_you(&self) -> &'static str {
+        "Falls Sie sich nicht anmelden wollten, kennt möglicherweise jemand anderes Ihr Passwort. Ändern Sie es bitte so bald wie möglich."
+    }
+
+    fn email_verification_subject(&self, product_name: &str) -> String {
+        format!("Bestätigen Sie Ihre E-Mail-Adresse für {}", product_name)
+    }
+
+    fn email_verification_intro(&self, product_name: &str) -> String {
+        format!(
+            "Bestätigen Sie, dass dies Ihre E-Mail-Adresse ist, um die Einrichtung Ihres {}-Kontos abzuschließen.",
+            product_name
+        )
+    }
+
+    fn email_verification_button(&self) -> &'static str {
+        "E-Mail-Adresse bestätigen"
+    }
+
+    fn email_verification_ignore(&self) -> &'static str {
+        "Falls Sie sich nicht registriert haben, können Sie diese E-Mail ignorieren."
+    }
+
+    fn password_reset_subject(&self, product_name: &str) -> String {
+        format!("Setzen Sie Ihr {}-Passwort zurück", product_name)
+    }
+
+    fn password_reset_intro(&self, product_name: &str) -> String {
+        format!(
+            "Jemand möchte das Passwort Ihres {}-Kontos zurücksetzen.",
+            product_name
+        )
+    }
+
+    fn password_reset_button(&self) -> &'static str {
+        "Neues Passwort wählen"
+    }
+
+    fn password_reset_ignore(&self) -> &'static str {
+        "Falls Sie das nicht waren, können Sie diese E-Mail ignorieren. Ihr Passwort bleibt unverändert."
+    }
+
+    fn password_reset_required_subject(&self, product_name: &str) -> String {
+        format!("Wählen Sie ein neues {}-Passwort", product_name)
+    }
+
+    fn password_reset_required_intro(&self, product_name: &str) -> String {
+        format!(
+            "Ein Administrator hat Sie gebeten, ein neues Passwort für Ihr {}-Konto zu wählen. Mit Ihrem bisherigen Passwort können Sie sich nicht mehr anmelden.",
+            product_name
+        )
+    }
+
+    fn new_device_alert_subject(&self, product_name: &str) -> String {
+        format!("Neue Anmeldung bei Ihrem {}-Konto", product_name)
+    }
+
+    fn new_device_alert_intro(&self, product_name: &str) -> String {
+        format!(
+            "Bei Ihrem {}-Konto hat sich gerade ein Gerät angemeldet, das wir noch nicht kennen.",
+            product_name
+        )
+    }
+
+    fn new_device_alert_time(&self) -> &'static str {
+        "Zeit"
+    }
+
+    fn new_device_alert_ip(&self) -> &'static str {
+        "IP-Adresse"
+    }
+
+    fn new_device_alert_browser(&self) -> &'static str {
+        "Browser"
+    }
+
+    fn new_device_alert_was_you(&self) -> &'static str {
+        "Falls Sie das waren, müssen Sie nichts tun."
+    }
+
+    fn new_device_alert_wasnt_you(&self) -> &'static str {
+        "Falls nicht, melden Sie sich überall ab und wählen Sie ein neues Passwort:"
+    }
+
+    fn new_device_alert_button(&self) -> &'static str {
+        "Das war ich nicht"
+    }
+
+    fn invitation_subject(&self, organization: &str, product_name: &str) -> String {
+        format!("Treten Sie {} bei {} bei", organization, product_name)
+    }
+
+    fn invitation_intro(&self, inviter: &str, organization: &str, product_name: &str) -> String {
+        format!(
+            "{} hat Sie eingeladen, {} bei {} beizutreten.",
+            inviter, organization, product_name
+        )
+    }
+
+    fn invitation_button(&self) -> &'static str {
+        "Einladung annehmen"
+    }
+
+    fn invitation_expiry(&self, days: u64) -> String {
+        format!("Die Einladung ist {} Tage lang gültig.", days)
+    }
+}
@@ -0,0 +1,194 @@
+use super::Catalog;
+use crate::domain::error::AuthAPIError;
+
+pub struct English;
+
+impl Catalog for English {
+    fn api_error(&self, error: &AuthAPIError) -> &'static str {
+        match error {
+            AuthAPIError::UserAlreadyExists => "User already exists",
+            AuthAPIError::InvalidCredentials => "Invalid credentials",
+            AuthAPIError::IncorrectCredentials => "Incorrect credentials",
+            AuthAPIError::MissingToken => "Missing auth token",
+            AuthAPIError::InvalidToken => "Invalid auth token",
+            AuthAPIError::PasswordResetRequired => "Password reset required",
+            AuthAPIError::AccountDisabled => {
+                "This account has been disabled. Please contact support."
+            }
+            AuthAPIError::EmailSuppressed => {
+                "We can't deliver login codes to your email address because emails to it bounced or were marked as spam. Please contact support."
+            }
+            AuthAPIError::SmsUnavailable => {
+                "Login codes can't be sent by SMS at the moment. Please choose email instead."
+            }
+            AuthAPIError::Forbidden => "You don't have permission to do this",
+            AuthAPIError::UserNotFound => "User not found",
+            AuthAPIError::UnknownRole => "Unknown role",
+            AuthAPIError::OrganizationNotFound => "Organization not found",
+            AuthAPIError::InvitationNotFound => {
+                "This invitation has expired or was already used. Please ask for a new one."
+            }
+            AuthAPIError::LastOwner => {
+                "An organization needs at least one owner. Please invite another owner first."
+            }
+            AuthAPIError::UnexpectedError(_) => "Unexpected error",
+        }
+    }
+
+    fn user_created(&self) -> &'static str {
+        "User created successfully!"
+    }
+
+    fn two_fa_required(&self) -> &'static str {
+        "2FA required"
+    }
+
+    fn sessions_revoked(&self) -> &'static str {
+        "Signed out everywhere. Choose a new password to log in again."
+    }
+
+    fn password_updated(&self) -> &'static str {
+        "Password updated"
+    }
+
+    fn email_footer(&self, product_name: &str) -> String {
+        format!(
+            "You're receiving this email because of activity on your {} account.",
+            product_name
+        )
+    }
+
+    fn email_questions(&self) -> &'static str {
+        "Questions? Contact us at"
+    }
+
+    fn unknown(&self) -> &'static str {
+        "unknown"
+    }
+
+    fn two_fa_code_subject(&self, product_name: &str) -> String {
+        format!("Your {} login code", product_name)
+    }
+
+    fn two_fa_code_intro(&self, product_name: &str) -> String {
+        format!("Use this code to finish logging in to {}:", product_name)
+    }
+
+    fn two_fa_code_expiry(&self, minutes: u64) -> String {
+        format!("It expires in {} minutes.", minutes)
+    }
+
+    fn two_fa_code_sms(&self, product_name: &str, code: &str, minutes: u64) -> String {
+        format!(
+            "Your {} login code is {}. It expires in {} minutes.",
+            product_name, code, minutes
+        )
+    }
+
+    fn two_fa_code_not_you(&self) -> &'static str {
+        "If you didn't try to log in, someone else may know your password, so change it as soon as you can."
+    }
+
+    fn email_verification_subject(&self, product_name: &str) -> String {
+        format!("Confirm your email address for {}", product_name)
+    }
+
+    fn email_verification_intro(&self, product_name: &str) -> String {
+        format!(
+            "Confirm this is your email address to finish setting up your {} account.",
+            product_name
+        )
+    }
+
+    fn email_verification_button(&self) -> &'static str {
+        "Confirm email address"
+    }
+
+    fn email_verification_ignore(&self) -> &'static str {
+        "If you didn't sign up, you can ignore this email."
+    }
+
+    fn password_reset_subject(&self, product_name: &str) -> String {
+        format!("Reset your {} password", product_name)
+    }
+
+    fn password_reset_intro(&self, product_name: &str) -> String {
+        format!(
+            "Someone asked to reset the password of your {} account.",
+            product_name
+        )
+    }
+
+    fn password_reset_button(&self) -> &'static str {
+        "Choose a new password"
+    }
+
+    fn password_reset_ignore(&self) -> &'static str {
+        "If it wasn't you, you can ignore this email. Your password stays the same."
+    }
+
+    fn password_reset_required_subject(&self, product_name: &str) -> String {
+        format!("Choose a new {} password", product_name)
+    }
+
+    fn password_reset_required_intro(&self, product_name: &str) -> String {
+        format!(
+            "An administrator has asked you to choose a new password for your {} account. You can't log in with your current password any more.",
+            product_name
+        )
+    }
+
+    fn new_device_alert_subject(&self, product_name: &str) -> String {
+        format!("New sign-in to your {} account", product_name)
+    }
+
+    fn new_device_alert_intro(&self, product_name: &str) -> String {
+        format!(
+            "Your {} account was just signed in to from a device we haven't seen before.",
+            product_name
+        )
+    }
+
+    fn new_device_alert_time(&self) -> &'static str {
+        "Time"
+    }
+
+    fn new_device_alert_ip(&self) -> &'static str {
+        "IP address"
+    }
+
+    fn new_device_alert_browser(&self) -> &'static str {
+        "Browser"
+    }
+
+    fn new_device_alert_was_you(&self) -> &'static str {
+        "If this was you, there's nothing to do."
+    }
+
+    fn new_device_alert_wasnt_you(&self) -> &'static str {
+        "If it wasn't, sign out everywhere and choose a new password:"
+    }
+
+    fn new_device_alert_button(&self) -> &'static str {
+        "This wasn't me"
+    }
+
+    fn invitation_subject(&self, organization: &str, product_name: &str) -> String {
+        format!("Join {} on {}", organization, product_name)
+    }
+
+    fn invitation_intro(&self, inviter: &str, organization: &str, product_name: &str) -> String {
+        format!(
+            "{} invited you to join {} on {}.",
+            inviter, organization, product_name
+        )
+    }
+
+    fn invitation_button(&self) -> &'static str {
+        "Accept invitation"
+    }
+
+    fn invitation_expiry(&self, days: u64) -> String {
+        format!("The invitation is valid for {} days.", days)
+    }
+}
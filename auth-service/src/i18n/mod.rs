@@ -0,0 +1,155 @@
+// User-facing text in every supported language. Each locale implements
+// `Catalog`, so a message missing from any of them fails the build.
+//
+// API responses are written in the language the client asks for through
+// Accept-Language; emails in the language stored with the user.
+use axum::{extract::Request, http::header::ACCEPT_LANGUAGE, middleware::Next, response::Response};
+
+use crate::domain::error::AuthAPIError;
+use crate::domain::Locale;
+
+mod de;
+mod en;
+
+pub trait Catalog: Sync {
+    // API responses
+    fn api_error(&self, error: &AuthAPIError) -> &'static str;
+    fn user_created(&self) -> &'static str;
+    fn two_fa_required(&self) -> &'static str;
+    fn sessions_revoked(&self) -> &'static str;
+    fn password_updated(&self) -> &'static str;
+
+    // Shared by every email
+    fn email_footer(&self, product_name: &str) -> String;
+    fn email_questions(&self) -> &'static str;
+    fn unknown(&self) -> &'static str;
+
+    fn two_fa_code_subject(&self, product_name: &str) -> String;
+    fn two_fa_code_intro(&self, product_name: &str) -> String;
+    fn two_fa_code_expiry(&self, minutes: u64) -> String;
+    fn two_fa_code_not_you(&self) -> &'static str;
+    // The whole text message, for users who get their codes by SMS
+    fn two_fa_code_sms(&self, product_name: &str, code: &str, minutes: u64) -> String;
+
+    fn email_verification_subject(&self, product_name: &str) -> String;
+    fn email_verification_intro(&self, product_name: &str) -> String;
+    fn email_verification_button(&self) -> &'static str;
+    fn email_verification_ignore(&self) -> &'static str;
+
+    fn password_reset_subject(&self, product_name: &str) -> String;
+    fn password_reset_intro(&self, product_name: &str) -> String;
+    fn password_reset_button(&self) -> &'static str;
+    fn password_reset_ignore(&self) -> &'static str;
+
+    fn password_reset_required_subject(&self, product_name: &str) -> String;
+    fn password_reset_required_intro(&self, product_name: &str) -> String;
+
+    fn new_device_alert_subject(&self, product_name: &str) -> String;
+    fn new_device_alert_intro(&self, product_name: &str) -> String;
+    fn new_device_alert_time(&self) -> &'static str;
+    fn new_device_alert_ip(&self) -> &'static str;
+    fn new_device_alert_browser(&self) -> &'static str;
+    fn new_device_alert_was_you(&self) -> &'static str;
+    fn new_device_alert_wasnt_you(&self) -> &'static str;
+    fn new_device_alert_button(&self) -> &'static str;
+
+    fn invitation_subject(&self, organization: &str, product_name: &str) -> String;
+    fn invitation_intro(&self, inviter: &str, organization: &str, product_name: &str) -> String;
+    fn invitation_button(&self) -> &'static str;
+    fn invitation_expiry(&self, days: u64) -> String;
+}
+
+pub fn catalog(locale: Locale) -> &'static dyn Catalog {
+    match locale {
+        Locale::En => &en::English,
+        Locale::De => &de::German,
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_LOCALE: Locale;
+}
+
+// The language of the request being handled, or the default outside of one
+pub fn current_locale() -> Locale {
+    CURRENT_LOCALE
+        .try_with(|locale| *locale)
+        .unwrap_or_default()
+}
+
+// Middleware keeping the locale negotiated from Accept-Language in scope
+// while the request is handled, for handlers and error bodies alike
+pub async fn negotiate_locale(request: Request, next: Next) -> Response {
+    let locale = request
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(negotiate)
+        .unwrap_or_default();
+
+    CURRENT_LOCALE.scope(locale, next.run(request)).await
+}
+
+// The supported locale the client prefers most, going by the q-values of an
+// Accept-Language header such as "de-AT,de;q=0.9,en;q=0.8". `None` when it
+// names nothing we support.
+pub fn negotiate(accept_language: &str) -> Option<Locale> {
+    let mut ranges: Vec<(&str, f32)> = accept_language
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse().ok())?;
+            (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+    // Stable, so equally preferred ranges keep the client's order
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    ranges.into_iter().find_map(|(tag, _)| match tag {
+        "*" => Some(Locale::default()),
+        tag => tag.parse().ok(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_most_preferred_supported_locale() {
+        assert_eq!(negotiate("de"), Some(Locale::De));
+        assert_eq!(negotiate("de-AT,de;q=0.9,en;q=0.8"), Some(Locale::De));
+        assert_eq!(negotiate("en;q=0.5, de;q=0.9"), Some(Locale::De));
+        assert_eq!(
+            negotiate("fr-FR,fr;q=0.9,de;q=0.8,en;q=0.7"),
+            Some(Locale::De)
+        );
+        assert_eq!(negotiate("de,en"), Some(Locale::De));
+    }
+
+    #[test]
+    fn falls_back_when_nothing_is_supported() {
+        assert_eq!(negotiate("fr-FR,fr;q=0.9"), None);
+        assert_eq!(negotiate(""), None);
+        assert_eq!(negotiate("de;q=0"), None);
+        assert_eq!(negotiate("fr, *;q=0.1"), Some(Locale::default()));
+    }
+
+    #[test]
+    fn skips_malformed_ranges() {
+        assert_eq!(negotiate("de;q=abc, en;q=0.5"), Some(Locale::En));
+        assert_eq!(negotiate(",,;q=1, de"), Some(Locale::De));
+    }
+
+    #[tokio::test]
+    async fn current_locale_defaults_outside_a_request() {
+        assert_eq!(current_locale(), Locale::default());
+        let scoped = CURRENT_LOCALE
+            .scope(Locale::De, async { current_locale() })
+            .await;
+        assert_eq!(scoped, Locale::De);
+    }
+}
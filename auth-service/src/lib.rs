@@ -1,30 +1,53 @@
 pub mod app_state;
 use axum::http::StatusCode;
+use i18n::{catalog, current_locale, negotiate_locale};
 use secrecy::{ExposeSecret, Secret};
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
+use tower_http::{services::ServeDir, trace::TraceLayer};
+use utils::cors::cors_layer;
+use utils::metrics::track_requests;
+use utils::redact::redact;
+use utils::request_id::{propagate_request_id, RequestId};
 use utils::tracing::{make_span_with_request_id, on_request, on_response};
 pub mod domain;
+pub mod i18n;
 pub mod routes;
 pub mod services;
+pub mod settings;
 pub mod utils;
 use app_state::AppState;
-use axum::http::Method;
 use axum::{
+    extract::connect_info::{ConnectInfo, IntoMakeServiceWithConnectInfo},
+    middleware,
+    middleware::AddExtension,
     response::{Html, IntoResponse, Response},
-    routing::{get, post},
+    routing::{delete, get, post, put},
     serve::Serve,
     Json, Router,
 };
 use domain::error::AuthAPIError;
-use redis::{Client, RedisResult};
+use redis::{aio::ConnectionManager, Client, RedisResult};
 use routes::*;
 use serde::{Deserialize, Serialize};
+use services::email_outbox_worker::EmailOutboxWorker;
+#[cfg(feature = "postgres")]
 use sqlx::{postgres::PgPoolOptions, PgPool};
+#[cfg(feature = "sqlite")]
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
+};
 use std::error::Error;
+use std::net::SocketAddr;
+
+#[cfg(not(any(feature = "postgres", feature = "sqlite")))]
+compile_error!("auth-service needs at least one of the `postgres` or `sqlite` features enabled");
 
 // This struct encapsulates our application-related logic.
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
@@ -36,31 +59,91 @@ async fn hello_handler() -> Html<&'static str> {
 }
 
 impl Application {
-    pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
+    pub async fn build(app_state: AppState) -> Result<Self, Box<dyn Error>> {
         // Allow the app service(running on our local machine and in production) to call the auth service
-        let allowed_origins = [
-            "http://localhost:8000".parse()?,
-            // TODO: Replace [YOUR_DROPLET_IP] with your Droplet IP address
-            "http://[YOUR_DROPLET_IP]:8000".parse()?,
-        ];
+        let cors = cors_layer(&app_state.settings.cors)?;
+        let address = app_state.settings.application.address.clone();
+
+        // Emails queued by the handlers are delivered in the background
+        EmailOutboxWorker::new(
+            app_state.email_outbox.clone(),
+            app_state.email_client.clone(),
+            app_state.metrics.clone(),
+            &app_state.settings.email_outbox,
+        )
+        .spawn();
 
-        let cors = CorsLayer::new()
-            // Allow GET and POST requests
-            .allow_methods([Method::GET, Method::POST])
-            // Allow cookies to be included in requests
-            .allow_credentials(true)
-            .allow_origin(allowed_origins);
         // Move the Router definition from `main.rs` to here.
         // Also, remove the `hello` route.
         // We don't need it at this point!
-        let router = Router::new()
+        let mut routes = Router::new()
             .route("/hello", get(hello_handler))
+            .route("/healthz", get(healthz))
+            .route("/readyz", get(readyz))
+            .route("/metrics", get(metrics_handler))
             .route("/login", post(login_handler))
             .route("/signup", get(signup_handler))
             .route("/signup", post(signup_handler))
             .route("/logout", post(logout_handler))
             .route("/verify-2fa", post(verify_2fa_handler))
             .route("/verify-token", post(verify_token))
+            .route("/login-history", get(login_history_handler))
+            .route("/admin/audit-events", get(audit_events_handler))
+            .route("/admin/roles", get(list_roles_handler))
+            .route("/admin/users", get(list_users_handler))
+            .route("/admin/users/:email", get(user_details_handler))
+            .route("/admin/users/:email/disable", post(disable_user_handler))
+            .route("/admin/users/:email/enable", post(enable_user_handler))
+            .route(
+                "/admin/users/:email/password-reset",
+                post(force_password_reset_handler),
+            )
+            .route(
+                "/admin/users/:email/requires-2fa",
+                put(set_requires_2fa_handler),
+            )
+            .route(
+                "/admin/users/:email/revoke-sessions",
+                post(revoke_user_sessions_handler),
+            )
+            .route("/admin/users/:email/roles", get(user_roles_handler))
+            .route(
+                "/admin/users/:email/roles/:role",
+                put(grant_role_handler).delete(revoke_role_handler),
+            )
+            .route(
+                "/organizations",
+                get(list_organizations_handler).post(create_organization_handler),
+            )
+            .route("/organizations/:id/members", get(members_handler))
+            .route(
+                "/organizations/:id/members/:email",
+                delete(remove_member_handler),
+            )
+            .route(
+                "/organizations/:id/invitations",
+                get(invitations_handler).post(invite_handler),
+            )
+            .route("/invitation", post(invitation_handler))
+            .route("/accept-invitation", post(accept_invitation_handler))
+            .route("/switch-organization", post(switch_organization_handler))
+            .route("/not-me", post(not_me_handler))
+            .route("/reset-password", post(reset_password_handler))
+            .route("/webhooks/postmark", post(postmark_webhook_handler));
+        // Captured emails are only browsable when nothing is really sent
+        if app_state.dev_mailbox.is_some() {
+            tracing::warn!("Emails are captured, not delivered; browse them at /dev/mailbox");
+            routes = routes
+                .route("/dev/mailbox", get(dev_mailbox_handler))
+                .route("/dev/mailbox/:id", get(dev_mailbox_email_handler))
+                .route("/dev/mailbox/:id/text", get(dev_mailbox_text_handler));
+        }
+        let router = routes
+            .layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                track_requests,
+            ))
+            .layer(middleware::from_fn(negotiate_locale))
             .with_state(app_state)
             .layer(cors)
             .layer(
@@ -73,10 +156,17 @@ impl Application {
                     .on_request(on_request)
                     .on_response(on_response),
             )
-            .nest_service("/", ServeDir::new("assets"));
-        let listener = tokio::net::TcpListener::bind(address).await?;
+            .nest_service("/", ServeDir::new("assets"))
+            // Outermost, so every response carries the request id and the
+            // request span can pick it up
+            .layer(middleware::from_fn(propagate_request_id));
+        let listener = tokio::net::TcpListener::bind(&address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // Keep the client address around for the audit log
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
         // Create a new Application instance and return it
         Ok(Application { server, address })
     }
@@ -90,26 +180,37 @@ impl Application {
 #[derive(Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
+    // Quote this when reporting a problem, it's how we find the request in our logs
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
-        let (status, error_message) = match self {
-            AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
-            AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
-            AuthAPIError::IncorrectCredentials => {
-                (StatusCode::UNAUTHORIZED, "Incorrect credentials")
-            }
-            AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
-            AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
-            AuthAPIError::UnexpectedError(_) => {
-                // Updated!
-                (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
-            }
+        let status = match self {
+            AuthAPIError::UserAlreadyExists => StatusCode::CONFLICT,
+            AuthAPIError::InvalidCredentials => StatusCode::BAD_REQUEST,
+            AuthAPIError::IncorrectCredentials => StatusCode::UNAUTHORIZED,
+            AuthAPIError::MissingToken => StatusCode::BAD_REQUEST,
+            AuthAPIError::InvalidToken => StatusCode::UNAUTHORIZED,
+            AuthAPIError::PasswordResetRequired => StatusCode::FORBIDDEN,
+            AuthAPIError::AccountDisabled => StatusCode::FORBIDDEN,
+            AuthAPIError::EmailSuppressed => StatusCode::FORBIDDEN,
+            AuthAPIError::SmsUnavailable => StatusCode::BAD_REQUEST,
+            AuthAPIError::Forbidden => StatusCode::FORBIDDEN,
+            AuthAPIError::UserNotFound => StatusCode::NOT_FOUND,
+            AuthAPIError::UnknownRole => StatusCode::NOT_FOUND,
+            AuthAPIError::OrganizationNotFound => StatusCode::NOT_FOUND,
+            AuthAPIError::InvitationNotFound => StatusCode::NOT_FOUND,
+            AuthAPIError::LastOwner => StatusCode::CONFLICT,
+            AuthAPIError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
+        // In the language negotiated for this request
+        let error_message = catalog(current_locale()).api_error(&self);
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
+            request_id: RequestId::current().map(|id| id.to_string()),
         });
         (status, body).into_response()
     }
@@ -126,15 +227,45 @@ fn log_error_chain(e: &(dyn Error + 'static)) {
         current = cause.source();
     }
     report = format!("{}\n{}", report, separator);
-    tracing::error!("{}", report);
+    // Error messages can quote user input (e.g. a duplicate key's email), and
+    // the report is also exported with the trace, so redact it here as well
+    tracing::error!("{}", redact(&report));
 }
 
+#[cfg(feature = "postgres")]
 pub async fn get_postgres_pool(url: &Secret<String>) -> Result<PgPool, sqlx::Error> {
     // Create a new PostgreSQL connection pool
-    PgPoolOptions::new().max_connections(5).connect(url.expose_secret()).await
+    PgPoolOptions::new()
+        .max_connections(5)
+        .connect(url.expose_secret())
+        .await
+}
+
+#[cfg(feature = "sqlite")]
+pub async fn get_sqlite_pool(url: &Secret<String>) -> Result<SqlitePool, sqlx::Error> {
+    // Create the database file on first start instead of failing
+    let options = url
+        .expose_secret()
+        .parse::<SqliteConnectOptions>()?
+        .create_if_missing(true);
+    SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await
 }
 
 pub fn get_redis_client(redis_hostname: String) -> RedisResult<Client> {
     let redis_url = format!("redis://{}/", redis_hostname);
     redis::Client::open(redis_url)
 }
+
+// Connects to Redis through a `ConnectionManager`, which is cheap to clone,
+// lets concurrent requests share one multiplexed connection and transparently
+// reconnects if Redis restarts.
+pub async fn get_redis_connection_manager(
+    redis_hostname: String,
+) -> RedisResult<ConnectionManager> {
+    get_redis_client(redis_hostname)?
+        .get_connection_manager()
+        .await
+}
@@ -1,50 +1,164 @@
 use auth_service::app_state::AppState;
+use auth_service::app_state::AuditLogType;
 use auth_service::app_state::BannedTokenStoreType;
+use auth_service::app_state::EmailClientType;
+use auth_service::app_state::EmailOutboxType;
+use auth_service::app_state::KnownDeviceStoreType;
+use auth_service::app_state::OrganizationStoreType;
+use auth_service::app_state::RoleStoreType;
+use auth_service::app_state::SmsClientType;
 use auth_service::app_state::TwoFACodeStoreType;
 use auth_service::app_state::UserStoreType;
 use auth_service::domain::Email;
+#[cfg(feature = "postgres")]
 use auth_service::get_postgres_pool;
-use auth_service::get_redis_client;
+use auth_service::get_redis_connection_manager;
+#[cfg(feature = "sqlite")]
+use auth_service::get_sqlite_pool;
+use auth_service::services::data_stores::hashmap_email_outbox::HashMapEmailOutbox;
+use auth_service::services::data_stores::hashmap_known_device_store::HashMapKnownDeviceStore;
+use auth_service::services::data_stores::hashmap_organization_store::HashMapOrganizationStore;
+use auth_service::services::data_stores::hashmap_role_store::HashMapRoleStore;
+use auth_service::services::data_stores::hashmap_two_fa_code_store::HashmapTwoFACodeStore;
+use auth_service::services::data_stores::hashset_banned_token_store::HashsetBannedTokenStore;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_audit_log::PostgresAuditLog;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_banned_token_store::PostgresBannedTokenStore;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_email_outbox::PostgresEmailOutbox;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_known_device_store::PostgresKnownDeviceStore;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_organization_store::PostgresOrganizationStore;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_role_store::PostgresRoleStore;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_two_fa_code_store::PostgresTwoFACodeStore;
+#[cfg(feature = "postgres")]
 use auth_service::services::data_stores::postgres_user_store::PostgresUserStore;
 use auth_service::services::data_stores::redis_banned_token_store::RedisBannedTokenStore;
 use auth_service::services::data_stores::redis_two_fa_code_store::RedisTwoFACodeStore;
+#[cfg(feature = "sqlite")]
+use auth_service::services::data_stores::sqlite_user_store::SqliteUserStore;
+use auth_service::services::data_stores::vec_audit_log::VecAuditLog;
+use auth_service::services::dev_mailbox_email_client::DevMailbox;
+use auth_service::services::failover_email_client::FailoverEmailClient;
+use auth_service::services::http_sms_client::HttpSmsClient;
 use auth_service::services::postmark_email_client::PostmarkEmailClient;
-use auth_service::utils::constants::prod;
-use auth_service::utils::constants::DATABASE_URL;
-use auth_service::utils::constants::POSTMARK_AUTH_TOKEN;
-use auth_service::utils::constants::REDIS_HOST_NAME;
+use auth_service::services::sendgrid_email_client::SendGridEmailClient;
+use auth_service::services::smtp_email_client::SmtpEmailClient;
+use auth_service::settings::DatabaseSettings;
+use auth_service::settings::EmailClientSettings;
+use auth_service::settings::EmailProvider;
+use auth_service::settings::PersistentStoreBackend;
+use auth_service::settings::Settings;
+use auth_service::settings::SmsClientSettings;
+use auth_service::settings::StoreBackend;
 use auth_service::utils::tracing::init_tracing;
 use auth_service::Application;
+use color_eyre::eyre::{Context, Result};
+use redis::aio::ConnectionManager;
 use reqwest::Client;
+#[cfg(feature = "sqlite")]
+use secrecy::ExposeSecret;
+#[cfg(feature = "postgres")]
 use sqlx::PgPool;
+#[cfg(feature = "sqlite")]
+use sqlx::SqlitePool;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+
+// The database `database.url` points at, which also decides the user store
+enum Database {
+    #[cfg(feature = "postgres")]
+    Postgres(PgPool),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqlitePool),
+}
 
 #[tokio::main]
-async fn main() {
+async fn main() -> Result<()> {
     color_eyre::install().expect("Failed to install color_eyre");
-    init_tracing().expect("Failed to initialize tracing");
-    // We will use this PostgreSQL pool in the next task!
-    let pg_pool = configure_postgresql().await;
-    let user_store: UserStoreType = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-    let redis_connection = Arc::new(RwLock::new(configure_redis()));
-    let banned_token_store: BannedTokenStoreType = Arc::new(RwLock::new(
-        RedisBannedTokenStore::new(redis_connection.clone()),
-    ));
-    let two_fa_store: TwoFACodeStoreType =
-        Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
-    let email_client = Arc::new(RwLock::new(configure_postmark_email_client()));
-    let app_state = AppState::new(user_store, banned_token_store, two_fa_store, email_client);
-    let app = Application::build(app_state, prod::APP_ADDRESS)
+    let settings = Arc::new(Settings::load().wrap_err("Failed to load configuration")?);
+    let _tracing =
+        init_tracing(&settings.logging, &settings.telemetry).expect("Failed to initialize tracing");
+    let database = configure_database(&settings.database).await;
+    let hashing_params = settings.auth.password_hashing.argon2_params()?;
+    let user_store: UserStoreType = match &database {
+        #[cfg(feature = "postgres")]
+        Database::Postgres(pg_pool) => {
+            Arc::new(PostgresUserStore::new(pg_pool.clone()).with_hashing_params(hashing_params))
+        }
+        #[cfg(feature = "sqlite")]
+        Database::Sqlite(sqlite_pool) => {
+            Arc::new(SqliteUserStore::new(sqlite_pool.clone()).with_hashing_params(hashing_params))
+        }
+    };
+
+    // Only connect to Redis if at least one store actually lives there,
+    // so a Postgres-only deployment doesn't need Redis running at all
+    let redis_connection = if [settings.stores.banned_tokens, settings.stores.two_fa_codes]
+        .contains(&StoreBackend::Redis)
+    {
+        Some(configure_redis(settings.redis.host_name.clone()).await)
+    } else {
+        None
+    };
+
+    let banned_token_store =
+        configure_banned_token_store(&settings, &database, redis_connection.clone());
+    let two_fa_store = configure_two_fa_code_store(&settings, &database, redis_connection);
+    let audit_log = configure_audit_log(&settings, &database);
+    let known_device_store = configure_known_device_store(&settings, &database);
+    let email_outbox = configure_email_outbox(&settings, &database);
+    let role_store = configure_role_store(&settings, &database);
+    let organization_store = configure_organization_store(&settings, &database);
+    let dev_mailbox = configure_dev_mailbox(&settings.email_client);
+    let email_client = configure_email_client(&settings.email_client, dev_mailbox.clone());
+    let sms_client = configure_sms_client(&settings.sms_client);
+    let mut app_state = AppState::new(
+        user_store,
+        banned_token_store,
+        two_fa_store,
+        email_client,
+        audit_log,
+        known_device_store,
+        settings,
+    )
+    .with_email_outbox(email_outbox)
+    .with_role_store(role_store)
+    .with_organization_store(organization_store);
+    if let Some(dev_mailbox) = dev_mailbox {
+        app_state = app_state.with_dev_mailbox(dev_mailbox);
+    }
+    if let Some(sms_client) = sms_client {
+        app_state = app_state.with_sms_client(sms_client);
+    }
+    let app = Application::build(app_state)
         .await
         .expect("Failed to build app");
 
     app.run().await.expect("Failed to run app");
+    Ok(())
 }
 
-async fn configure_postgresql() -> PgPool {
+async fn configure_database(settings: &DatabaseSettings) -> Database {
+    #[cfg(feature = "sqlite")]
+    if settings.url.expose_secret().starts_with("sqlite:") {
+        return Database::Sqlite(configure_sqlite(settings).await);
+    }
+
+    #[cfg(feature = "postgres")]
+    return Database::Postgres(configure_postgresql(settings).await);
+
+    #[cfg(not(feature = "postgres"))]
+    unreachable!("database.url is checked against the build when the settings are loaded");
+}
+
+#[cfg(feature = "postgres")]
+async fn configure_postgresql(settings: &DatabaseSettings) -> PgPool {
     // Create a new database connection pool
-    let pg_pool = get_postgres_pool(&DATABASE_URL)
+    let pg_pool = get_postgres_pool(&settings.url)
         .await
         .expect("Failed to create Postgres connection pool!");
 
@@ -57,23 +171,251 @@ async fn configure_postgresql() -> PgPool {
     pg_pool
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
+#[cfg(feature = "sqlite")]
+async fn configure_sqlite(settings: &DatabaseSettings) -> SqlitePool {
+    let sqlite_pool = get_sqlite_pool(&settings.url)
+        .await
+        .expect("Failed to create SQLite connection pool!");
+
+    sqlx::migrate!("./migrations_sqlite")
+        .run(&sqlite_pool)
+        .await
+        .expect("Failed to run migrations");
+
+    sqlite_pool
+}
+
+async fn configure_redis(redis_hostname: String) -> ConnectionManager {
+    get_redis_connection_manager(redis_hostname)
+        .await
         .expect("Failed to get Redis connection")
 }
 
-fn configure_postmark_email_client() -> PostmarkEmailClient {
+#[cfg(feature = "postgres")]
+fn expect_postgres(database: &Database) -> &PgPool {
+    match database {
+        Database::Postgres(pg_pool) => pg_pool,
+        #[cfg(feature = "sqlite")]
+        Database::Sqlite(_) => {
+            unreachable!("stores can't be on Postgres with a SQLite database, as checked on load")
+        }
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+fn postgres_compiled_out() -> ! {
+    unreachable!("stores can't be \"postgres\" without the postgres feature, as checked on load")
+}
+
+fn configure_banned_token_store(
+    settings: &Settings,
+    #[allow(unused_variables)] database: &Database,
+    redis_connection: Option<ConnectionManager>,
+) -> BannedTokenStoreType {
+    let ttl = settings.auth.token_ttl();
+    match settings.stores.banned_tokens {
+        StoreBackend::Redis => Arc::new(
+            RedisBannedTokenStore::new(
+                redis_connection.expect("Redis connection should be configured"),
+            )
+            .with_ttl(ttl),
+        ),
+        #[cfg(feature = "postgres")]
+        StoreBackend::Postgres => {
+            let store =
+                PostgresBannedTokenStore::new(expect_postgres(database).clone()).with_ttl(ttl);
+            store.spawn_cleanup_task(settings.stores.cleanup_interval());
+            Arc::new(store)
+        }
+        #[cfg(not(feature = "postgres"))]
+        StoreBackend::Postgres => postgres_compiled_out(),
+        StoreBackend::Memory => Arc::new(HashsetBannedTokenStore::default().with_ttl(ttl)),
+    }
+}
+
+fn configure_two_fa_code_store(
+    settings: &Settings,
+    #[allow(unused_variables)] database: &Database,
+    redis_connection: Option<ConnectionManager>,
+) -> TwoFACodeStoreType {
+    let ttl = settings.auth.two_fa_code_ttl();
+    match settings.stores.two_fa_codes {
+        StoreBackend::Redis => Arc::new(
+            RedisTwoFACodeStore::new(
+                redis_connection.expect("Redis connection should be configured"),
+            )
+            .with_ttl(ttl),
+        ),
+        #[cfg(feature = "postgres")]
+        StoreBackend::Postgres => {
+            let store =
+                PostgresTwoFACodeStore::new(expect_postgres(database).clone()).with_ttl(ttl);
+            store.spawn_cleanup_task(settings.stores.cleanup_interval());
+            Arc::new(store)
+        }
+        #[cfg(not(feature = "postgres"))]
+        StoreBackend::Postgres => postgres_compiled_out(),
+        StoreBackend::Memory => Arc::new(HashmapTwoFACodeStore::default().with_ttl(ttl)),
+    }
+}
+
+fn configure_audit_log(
+    settings: &Settings,
+    #[allow(unused_variables)] database: &Database,
+) -> AuditLogType {
+    match settings.stores.audit_log {
+        #[cfg(feature = "postgres")]
+        PersistentStoreBackend::Postgres => {
+            Arc::new(PostgresAuditLog::new(expect_postgres(database).clone()))
+        }
+        #[cfg(not(feature = "postgres"))]
+        PersistentStoreBackend::Postgres => postgres_compiled_out(),
+        PersistentStoreBackend::Memory => Arc::new(VecAuditLog::default()),
+    }
+}
+
+fn configure_known_device_store(
+    settings: &Settings,
+    #[allow(unused_variables)] database: &Database,
+) -> KnownDeviceStoreType {
+    match settings.stores.known_devices {
+        #[cfg(feature = "postgres")]
+        PersistentStoreBackend::Postgres => Arc::new(PostgresKnownDeviceStore::new(
+            expect_postgres(database).clone(),
+        )),
+        #[cfg(not(feature = "postgres"))]
+        PersistentStoreBackend::Postgres => postgres_compiled_out(),
+        PersistentStoreBackend::Memory => Arc::new(HashMapKnownDeviceStore::default()),
+    }
+}
+
+fn configure_email_outbox(
+    settings: &Settings,
+    #[allow(unused_variables)] database: &Database,
+) -> EmailOutboxType {
+    match settings.stores.email_outbox {
+        #[cfg(feature = "postgres")]
+        PersistentStoreBackend::Postgres => {
+            Arc::new(PostgresEmailOutbox::new(expect_postgres(database).clone()))
+        }
+        #[cfg(not(feature = "postgres"))]
+        PersistentStoreBackend::Postgres => postgres_compiled_out(),
+        PersistentStoreBackend::Memory => Arc::new(HashMapEmailOutbox::default()),
+    }
+}
+
+fn configure_role_store(
+    settings: &Settings,
+    #[allow(unused_variables)] database: &Database,
+) -> RoleStoreType {
+    match settings.stores.roles {
+        #[cfg(feature = "postgres")]
+        PersistentStoreBackend::Postgres => {
+            Arc::new(PostgresRoleStore::new(expect_postgres(database).clone()))
+        }
+        #[cfg(not(feature = "postgres"))]
+        PersistentStoreBackend::Postgres => postgres_compiled_out(),
+        PersistentStoreBackend::Memory => Arc::new(HashMapRoleStore::default()),
+    }
+}
+
+fn configure_organization_store(
+    settings: &Settings,
+    #[allow(unused_variables)] database: &Database,
+) -> OrganizationStoreType {
+    match settings.stores.organizations {
+        #[cfg(feature = "postgres")]
+        PersistentStoreBackend::Postgres => {
+            let store = PostgresOrganizationStore::new(expect_postgres(database).clone());
+            store.spawn_cleanup_task(settings.stores.cleanup_interval());
+            Arc::new(store)
+        }
+        #[cfg(not(feature = "postgres"))]
+        PersistentStoreBackend::Postgres => postgres_compiled_out(),
+        PersistentStoreBackend::Memory => Arc::new(HashMapOrganizationStore::default()),
+    }
+}
+
+// Only when emails are captured locally instead of sent
+fn configure_dev_mailbox(settings: &EmailClientSettings) -> Option<Arc<DevMailbox>> {
+    if settings.provider != EmailProvider::DevMailbox {
+        return None;
+    }
+    // Already validated when the settings were loaded
+    let sender = Email::parse(settings.sender.clone()).unwrap();
+    Some(Arc::new(
+        DevMailbox::new(&settings.dev_mailbox, sender).expect("Failed to set up dev mailbox"),
+    ))
+}
+
+fn configure_sms_client(settings: &SmsClientSettings) -> Option<SmsClientType> {
+    let url = settings.url.clone()?;
     let http_client = Client::builder()
-        .timeout(prod::email_client::TIMEOUT)
+        .timeout(settings.timeout())
         .build()
         .expect("Failed to build HTTP client");
 
-    PostmarkEmailClient::new(
-        prod::email_client::BASE_URL.to_owned(),
-        Email::parse(prod::email_client::SENDER.to_owned()).unwrap(),
-        POSTMARK_AUTH_TOKEN.to_owned(),
+    Some(Arc::new(HttpSmsClient::new(
+        url,
+        settings.sender.clone(),
+        // Already validated when the settings were loaded
+        settings.auth_token.clone().unwrap(),
         http_client,
-    )
+    )))
+}
+
+fn configure_email_client(
+    settings: &EmailClientSettings,
+    dev_mailbox: Option<Arc<DevMailbox>>,
+) -> EmailClientType {
+    let primary = configure_email_provider(settings, settings.provider, dev_mailbox);
+    if settings.fallback_providers.is_empty() {
+        return primary;
+    }
+
+    let mut providers = vec![(settings.provider.as_str().to_owned(), primary)];
+    for provider in &settings.fallback_providers {
+        providers.push((
+            provider.as_str().to_owned(),
+            configure_email_provider(settings, *provider, None),
+        ));
+    }
+    Arc::new(FailoverEmailClient::new(
+        providers,
+        &settings.circuit_breaker,
+    ))
+}
+
+fn configure_email_provider(
+    settings: &EmailClientSettings,
+    provider: EmailProvider,
+    dev_mailbox: Option<Arc<DevMailbox>>,
+) -> EmailClientType {
+    // Already validated when the settings were loaded
+    let sender = Email::parse(settings.sender.clone()).unwrap();
+    let http_client = || {
+        Client::builder()
+            .timeout(settings.timeout())
+            .build()
+            .expect("Failed to build HTTP client")
+    };
+    match provider {
+        EmailProvider::Postmark => Arc::new(PostmarkEmailClient::new(
+            settings.base_url.clone(),
+            sender,
+            settings.auth_token.clone().unwrap(),
+            http_client(),
+        )),
+        EmailProvider::SendGrid => Arc::new(SendGridEmailClient::new(
+            settings.sendgrid.base_url.clone(),
+            sender,
+            settings.sendgrid.api_key.clone().unwrap(),
+            http_client(),
+        )),
+        EmailProvider::Smtp => Arc::new(
+            SmtpEmailClient::new(&settings.smtp, sender, settings.timeout())
+                .expect("Failed to configure SMTP email client"),
+        ),
+        EmailProvider::DevMailbox => dev_mailbox.expect("Dev mailbox should be configured"),
+    }
 }
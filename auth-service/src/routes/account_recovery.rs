@@ -0,0 +1,137 @@
+use axum::extract::State;
+use axum::Json;
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::audit_log::AuditEventKind;
+use crate::domain::data_stores::UserStoreError;
+use crate::domain::error::AuthAPIError;
+use crate::domain::{Email, Password};
+use crate::i18n::{catalog, current_locale};
+use crate::utils::audit::AuditContext;
+use crate::utils::auth::validate_recovery_token;
+use crate::utils::metrics::outcome;
+
+#[derive(Deserialize, Debug)]
+pub struct NotMeRequest {
+    pub token: Secret<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ResetPasswordRequest {
+    pub token: Secret<String>,
+    pub password: Secret<String>,
+}
+
+#[derive(Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AccountRecoveryResponse {
+    pub message: String,
+}
+
+// Followed from a new device alert: sign the account out everywhere, drop
+// any pending 2FA code and lock the password until it is reset with the same
+// recovery token
+#[tracing::instrument(name = "Not Me", skip_all)]
+pub async fn not_me_handler(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Json(request): Json<NotMeRequest>,
+) -> Result<Json<AccountRecoveryResponse>, AuthAPIError> {
+    let email = recovery_email(&state, &request.token)?;
+
+    let result = revoke_sessions(&state, &email).await;
+    let result_label = if result.is_ok() {
+        outcome::SUCCESS
+    } else {
+        outcome::ERROR
+    };
+    audit
+        .record(
+            &state.audit_log,
+            AuditEventKind::SessionsRevoked,
+            Some(email.as_ref().expose_secret()),
+            result_label,
+        )
+        .await;
+    result?;
+
+    Ok(Json(AccountRecoveryResponse {
+        message: catalog(current_locale()).sessions_revoked().to_string(),
+    }))
+}
+
+#[tracing::instrument(name = "Reset Password", skip_all)]
+pub async fn reset_password_handler(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<Json<AccountRecoveryResponse>, AuthAPIError> {
+    let email = recovery_email(&state, &request.token)?;
+
+    let result = reset_password(&state, &email, request.password).await;
+    let result_label = match &result {
+        Ok(()) => outcome::SUCCESS,
+        Err(AuthAPIError::InvalidCredentials) => outcome::INVALID_INPUT,
+        Err(AuthAPIError::InvalidToken) => outcome::FAILURE,
+        Err(_) => outcome::ERROR,
+    };
+    audit
+        .record(
+            &state.audit_log,
+            AuditEventKind::PasswordReset,
+            Some(email.as_ref().expose_secret()),
+            result_label,
+        )
+        .await;
+    result?;
+
+    Ok(Json(AccountRecoveryResponse {
+        message: catalog(current_locale()).password_updated().to_string(),
+    }))
+}
+
+fn recovery_email(state: &AppState, token: &Secret<String>) -> Result<Email, AuthAPIError> {
+    validate_recovery_token(token.expose_secret(), &state.settings.auth)
+        .map_err(|_| AuthAPIError::InvalidToken)
+}
+
+async fn revoke_sessions(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    state
+        .banned_token_store
+        .revoke_sessions(email, Utc::now())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .user_store
+        .require_password_reset(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Whoever is partway through logging in can't finish with their 2FA code
+    state
+        .two_fa_code_store
+        .remove_code(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+async fn reset_password(
+    state: &AppState,
+    email: &Email,
+    password: Secret<String>,
+) -> Result<(), AuthAPIError> {
+    let password = Password::parse(password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    match state.user_store.reset_password(email, password).await {
+        Ok(()) => Ok(()),
+        // The account was deleted after the token was issued, or the link
+        // was already used to reset the password
+        Err(UserStoreError::UserNotFound | UserStoreError::PasswordResetNotRequired) => {
+            Err(AuthAPIError::InvalidToken)
+        }
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
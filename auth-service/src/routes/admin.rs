@@ -0,0 +1,52 @@
+// Helpers shared by the routes that manage other users' accounts
+use secrecy::ExposeSecret;
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::audit_log::AuditEventKind;
+use crate::domain::data_stores::UserStoreError;
+use crate::domain::error::AuthAPIError;
+use crate::domain::Email;
+use crate::utils::audit::AuditContext;
+use crate::utils::authorization::Caller;
+use crate::utils::metrics::outcome;
+
+// The user named in the path, who has to have an account
+pub(crate) async fn existing_user(state: &AppState, email: String) -> Result<Email, AuthAPIError> {
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    match state.user_store.get_user(&email).await {
+        Ok(_) => Ok(email),
+        Err(e) => Err(user_store_error(e)),
+    }
+}
+
+pub(crate) fn user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+// Records what `caller` did to `subject`'s account
+pub(crate) async fn record(
+    state: &AppState,
+    audit: &AuditContext,
+    caller: &Caller,
+    kind: AuditEventKind,
+    subject: &Email,
+    result: &Result<(), AuthAPIError>,
+) {
+    let result_label = match result {
+        Ok(()) => outcome::SUCCESS,
+        Err(AuthAPIError::UnknownRole) => outcome::INVALID_INPUT,
+        Err(_) => outcome::ERROR,
+    };
+    audit
+        .record_with_subject(
+            &state.audit_log,
+            kind,
+            Some(caller.actor()),
+            Some(subject.as_ref().expose_secret()),
+            result_label,
+        )
+        .await;
+}
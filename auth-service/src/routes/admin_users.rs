@@ -0,0 +1,294 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::audit_log::AuditEventKind;
+use crate::domain::data_stores::{UserQuery, UserSummary};
+use crate::domain::error::AuthAPIError;
+use crate::domain::{Email, ManageUsers, Membership, ReadUsers};
+use crate::routes::admin::{existing_user, record, user_store_error};
+use crate::services::email_templates;
+use crate::utils::audit::AuditContext;
+use crate::utils::auth::generate_recovery_token;
+use crate::utils::authorization::RequirePermission;
+
+// Cap on how many users one request can return
+const MAX_USERS: usize = 200;
+
+#[derive(Deserialize, Debug)]
+pub struct UsersQuery {
+    // Part of the email, in any case
+    pub search: Option<String>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UsersResponse {
+    pub users: Vec<UserSummary>,
+    // How many users match, across all pages
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserDetails {
+    #[serde(flatten)]
+    pub user: UserSummary,
+    pub roles: Vec<String>,
+    pub organizations: Vec<Membership>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RequiresTwoFARequest {
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+}
+
+// Admin only: page through the users, ordered by email
+#[tracing::instrument(name = "List Users", skip_all)]
+pub async fn list_users_handler(
+    State(state): State<AppState>,
+    _: RequirePermission<ReadUsers>,
+    Query(query): Query<UsersQuery>,
+) -> Result<Json<UsersResponse>, AuthAPIError> {
+    let defaults = UserQuery::default();
+    let query = UserQuery {
+        search: query.search.filter(|search| !search.is_empty()),
+        offset: query.offset.unwrap_or(defaults.offset),
+        limit: query.limit.unwrap_or(defaults.limit).min(MAX_USERS),
+    };
+    let page = state
+        .user_store
+        .list_users(&query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(UsersResponse {
+        users: page.users,
+        total: page.total,
+    }))
+}
+
+// One user's account, roles and organizations
+#[tracing::instrument(name = "User Details", skip_all)]
+pub async fn user_details_handler(
+    State(state): State<AppState>,
+    _: RequirePermission<ReadUsers>,
+    Path(email): Path<String>,
+) -> Result<Json<UserDetails>, AuthAPIError> {
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let user = state
+        .user_store
+        .user_summary(&email)
+        .await
+        .map_err(user_store_error)?;
+    let grants = state
+        .role_store
+        .grants(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let organizations = state
+        .organization_store
+        .memberships(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(UserDetails {
+        user,
+        roles: grants.roles,
+        organizations,
+    }))
+}
+
+// Blocks logins and ends the user's sessions until they are enabled again
+#[tracing::instrument(name = "Disable User", skip_all)]
+pub async fn disable_user_handler(
+    State(state): State<AppState>,
+    RequirePermission { caller, .. }: RequirePermission<ManageUsers>,
+    audit: AuditContext,
+    Path(email): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let email = existing_user(&state, email).await?;
+
+    let result = disable_user(&state, &email).await;
+    record(
+        &state,
+        &audit,
+        &caller,
+        AuditEventKind::AccountDisabled,
+        &email,
+        &result,
+    )
+    .await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Enable User", skip_all)]
+pub async fn enable_user_handler(
+    State(state): State<AppState>,
+    RequirePermission { caller, .. }: RequirePermission<ManageUsers>,
+    audit: AuditContext,
+    Path(email): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let email = existing_user(&state, email).await?;
+
+    let result = state
+        .user_store
+        .set_disabled(&email, false)
+        .await
+        .map_err(user_store_error);
+    record(
+        &state,
+        &audit,
+        &caller,
+        AuditEventKind::AccountEnabled,
+        &email,
+        &result,
+    )
+    .await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Locks the user's password, ends their sessions and emails them a link to
+// choose a new one, as if they had reported a login that wasn't them
+#[tracing::instrument(name = "Force Password Reset", skip_all)]
+pub async fn force_password_reset_handler(
+    State(state): State<AppState>,
+    RequirePermission { caller, .. }: RequirePermission<ManageUsers>,
+    audit: AuditContext,
+    Path(email): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let email = existing_user(&state, email).await?;
+
+    let result = force_password_reset(&state, &email).await;
+    record(
+        &state,
+        &audit,
+        &caller,
+        AuditEventKind::PasswordResetForced,
+        &email,
+        &result,
+    )
+    .await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Takes effect from the user's next login
+#[tracing::instrument(name = "Set Requires 2FA", skip_all)]
+pub async fn set_requires_2fa_handler(
+    State(state): State<AppState>,
+    RequirePermission { caller, .. }: RequirePermission<ManageUsers>,
+    audit: AuditContext,
+    Path(email): Path<String>,
+    Json(request): Json<RequiresTwoFARequest>,
+) -> Result<StatusCode, AuthAPIError> {
+    let email = existing_user(&state, email).await?;
+
+    let result = state
+        .user_store
+        .set_requires_2fa(&email, request.requires_2fa)
+        .await
+        .map_err(user_store_error);
+    record(
+        &state,
+        &audit,
+        &caller,
+        AuditEventKind::TwoFaRequirementChanged,
+        &email,
+        &result,
+    )
+    .await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Signs the user out everywhere. Unlike disabling, they can log in again.
+#[tracing::instrument(name = "Revoke User Sessions", skip_all)]
+pub async fn revoke_user_sessions_handler(
+    State(state): State<AppState>,
+    RequirePermission { caller, .. }: RequirePermission<ManageUsers>,
+    audit: AuditContext,
+    Path(email): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let email = existing_user(&state, email).await?;
+
+    let result = revoke_sessions(&state, &email).await;
+    record(
+        &state,
+        &audit,
+        &caller,
+        AuditEventKind::SessionsRevoked,
+        &email,
+        &result,
+    )
+    .await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn disable_user(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    state
+        .user_store
+        .set_disabled(email, true)
+        .await
+        .map_err(user_store_error)?;
+    revoke_sessions(state, email).await?;
+
+    // A login already waiting on its 2FA code can't be completed either
+    state
+        .two_fa_code_store
+        .remove_code(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+async fn force_password_reset(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    let token = generate_recovery_token(email, &state.settings.auth)
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let link = format!(
+        "{}/account-recovery.html?token={}&step=reset",
+        state.settings.application.public_url.trim_end_matches('/'),
+        token
+    );
+    let locale = state
+        .user_store
+        .get_user(email)
+        .await
+        .map_err(user_store_error)?
+        .locale;
+    let message = email_templates::password_reset_required(&state.settings.branding, locale, &link)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .user_store
+        .require_password_reset(email)
+        .await
+        .map_err(user_store_error)?;
+    revoke_sessions(state, email).await?;
+    // The account stays locked if this fails, which is the safe side. Forcing
+    // the reset again sends a new link.
+    state
+        .email_outbox
+        .enqueue(email.clone(), message)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+async fn revoke_sessions(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    state
+        .banned_token_store
+        .revoke_sessions(email, Utc::now())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
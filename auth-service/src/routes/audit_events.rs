@@ -0,0 +1,93 @@
+use axum::extract::{Query, State};
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::audit_log::{
+    AuditEvent, AuditEventFilter, AuditEventKind, AuditOutcome,
+};
+use crate::domain::error::AuthAPIError;
+use crate::domain::ReadAuditEvents;
+use crate::utils::auth::validate_token;
+use crate::utils::authorization::RequirePermission;
+
+// Caps on how many events one request can return
+const MAX_AUDIT_EVENTS: usize = 1000;
+const LOGIN_HISTORY_LENGTH: usize = 20;
+
+#[derive(Deserialize, Debug)]
+pub struct AuditEventsQuery {
+    pub actor: Option<String>,
+    pub subject: Option<String>,
+    pub kind: Option<AuditEventKind>,
+    pub outcome: Option<AuditOutcome>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEventsResponse {
+    pub events: Vec<AuditEvent>,
+}
+
+// Admin only: search the audit log, newest events first
+#[tracing::instrument(name = "Audit Events", skip_all)]
+pub async fn audit_events_handler(
+    State(state): State<AppState>,
+    _: RequirePermission<ReadAuditEvents>,
+    Query(query): Query<AuditEventsQuery>,
+) -> Result<Json<AuditEventsResponse>, AuthAPIError> {
+    let filter = AuditEventFilter {
+        actor: query.actor,
+        subject: query.subject,
+        kinds: query.kind.into_iter().collect(),
+        outcome: query.outcome,
+        since: query.since,
+        until: query.until,
+        limit: query
+            .limit
+            .unwrap_or(AuditEventFilter::default().limit)
+            .min(MAX_AUDIT_EVENTS),
+    };
+    let events = state
+        .audit_log
+        .query(&filter)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(AuditEventsResponse { events }))
+}
+
+// The caller's own recent logins and 2FA verifications, successful or not,
+// so they can spot activity they don't recognise
+#[tracing::instrument(name = "Login History", skip_all)]
+pub async fn login_history_handler(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<Json<AuditEventsResponse>, AuthAPIError> {
+    let token = jar
+        .get(&state.settings.auth.cookie.name)
+        .ok_or(AuthAPIError::MissingToken)?
+        .value()
+        .to_owned();
+    let claims = validate_token(&token, &state.banned_token_store, &state.settings.auth)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let filter = AuditEventFilter {
+        actor: Some(claims.sub),
+        kinds: vec![AuditEventKind::Login, AuditEventKind::TwoFaVerification],
+        limit: LOGIN_HISTORY_LENGTH,
+        ..Default::default()
+    };
+    let events = state
+        .audit_log
+        .query(&filter)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(AuditEventsResponse { events }))
+}
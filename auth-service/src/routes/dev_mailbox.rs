@@ -0,0 +1,82 @@
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::{header::CONTENT_TYPE, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use secrecy::ExposeSecret;
+
+use crate::app_state::AppState;
+use crate::domain::error::AuthAPIError;
+use crate::services::dev_mailbox_email_client::CapturedEmail;
+
+struct MailboxRow {
+    id: u64,
+    received_at: String,
+    recipient: String,
+    subject: String,
+}
+
+#[derive(Template)]
+#[template(path = "dev/mailbox.html")]
+struct MailboxPage {
+    emails: Vec<MailboxRow>,
+}
+
+// Development only: lists the emails caught by the dev mailbox. Only routed
+// when email_client.provider = "dev_mailbox".
+#[tracing::instrument(name = "Dev mailbox", skip_all)]
+pub async fn dev_mailbox_handler(State(state): State<AppState>) -> Result<Response, AuthAPIError> {
+    let Some(dev_mailbox) = &state.dev_mailbox else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let emails = dev_mailbox
+        .emails()
+        .await
+        .into_iter()
+        .map(|email| MailboxRow {
+            id: email.id,
+            received_at: email
+                .received_at
+                .format("%Y-%m-%d %H:%M:%S UTC")
+                .to_string(),
+            recipient: email.recipient.as_ref().expose_secret().clone(),
+            subject: email.message.subject,
+        })
+        .collect();
+    let page = MailboxPage { emails }
+        .render()
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Html(page).into_response())
+}
+
+// The HTML version of a captured email, as the recipient would see it
+#[tracing::instrument(name = "Dev mailbox email", skip_all)]
+pub async fn dev_mailbox_email_handler(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Response {
+    match captured_email(&state, id).await {
+        Some(email) => Html(email.message.html_body).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[tracing::instrument(name = "Dev mailbox email text", skip_all)]
+pub async fn dev_mailbox_text_handler(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Response {
+    match captured_email(&state, id).await {
+        Some(email) => (
+            [(CONTENT_TYPE, "text/plain; charset=utf-8")],
+            email.message.text_body,
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn captured_email(state: &AppState, id: u64) -> Option<CapturedEmail> {
+    state.dev_mailbox.as_ref()?.email(id).await
+}
@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use color_eyre::eyre::{eyre, Report};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Unavailable,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub status: HealthStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    pub status: HealthStatus,
+    pub checks: BTreeMap<String, CheckResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Liveness: the process is up and serving requests. This deliberately checks
+// nothing else, so a flaky dependency never gets the service restarted.
+#[tracing::instrument(name = "Healthz", skip_all)]
+pub async fn healthz() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: HealthStatus::Ok,
+    })
+}
+
+// Readiness: every store (and optionally the email provider) can be reached.
+// Checks run concurrently, each with its own timeout, and the response
+// reports them individually. A store's `health_check` succeeds only if it
+// can serve requests; stores without external dependencies are always
+// healthy, so in-memory deployments are ready as soon as they start.
+#[tracing::instrument(name = "Readyz", skip_all)]
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let timeout = state.settings.health.timeout();
+
+    let (
+        user_store,
+        banned_token_store,
+        two_fa_code_store,
+        audit_log,
+        known_device_store,
+        email_outbox,
+        role_store,
+        organization_store,
+        email_client,
+    ) = tokio::join!(
+        run_check("user_store", timeout, async {
+            state.user_store.health_check().await.map_err(Report::from)
+        }),
+        run_check("banned_token_store", timeout, async {
+            state
+                .banned_token_store
+                .health_check()
+                .await
+                .map_err(Report::from)
+        }),
+        run_check("two_fa_code_store", timeout, async {
+            state
+                .two_fa_code_store
+                .health_check()
+                .await
+                .map_err(Report::from)
+        }),
+        run_check("audit_log", timeout, async {
+            state.audit_log.health_check().await.map_err(Report::from)
+        }),
+        run_check("known_device_store", timeout, async {
+            state
+                .known_device_store
+                .health_check()
+                .await
+                .map_err(Report::from)
+        }),
+        run_check("email_outbox", timeout, async {
+            state
+                .email_outbox
+                .health_check()
+                .await
+                .map_err(Report::from)
+        }),
+        run_check("role_store", timeout, async {
+            state.role_store.health_check().await.map_err(Report::from)
+        }),
+        run_check("organization_store", timeout, async {
+            state
+                .organization_store
+                .health_check()
+                .await
+                .map_err(Report::from)
+        }),
+        async {
+            if state.settings.health.check_email_provider {
+                Some(run_check("email_client", timeout, state.email_client.health_check()).await)
+            } else {
+                None
+            }
+        },
+    );
+
+    let mut checks = BTreeMap::new();
+    checks.insert("user_store".to_owned(), user_store);
+    checks.insert("banned_token_store".to_owned(), banned_token_store);
+    checks.insert("two_fa_code_store".to_owned(), two_fa_code_store);
+    checks.insert("audit_log".to_owned(), audit_log);
+    checks.insert("known_device_store".to_owned(), known_device_store);
+    checks.insert("email_outbox".to_owned(), email_outbox);
+    checks.insert("role_store".to_owned(), role_store);
+    checks.insert("organization_store".to_owned(), organization_store);
+    if let Some(email_client) = email_client {
+        checks.insert("email_client".to_owned(), email_client);
+    }
+
+    let (status_code, status) = if checks.values().all(|c| c.status == HealthStatus::Ok) {
+        (StatusCode::OK, HealthStatus::Ok)
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, HealthStatus::Unavailable)
+    };
+
+    (status_code, Json(ReadinessResponse { status, checks }))
+}
+
+async fn run_check(
+    name: &str,
+    timeout: Duration,
+    check: impl Future<Output = Result<(), Report>>,
+) -> CheckResult {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(timeout, check).await {
+        Ok(result) => result,
+        Err(_) => Err(eyre!("timed out after {}ms", timeout.as_millis())),
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(()) => CheckResult {
+            status: HealthStatus::Ok,
+            latency_ms,
+            error: None,
+        },
+        Err(e) => {
+            tracing::warn!(check = name, error = ?e, "Readiness check failed");
+            CheckResult {
+                status: HealthStatus::Unavailable,
+                latency_ms,
+                error: Some(e.root_cause().to_string()),
+            }
+        }
+    }
+}
@@ -6,12 +6,20 @@ use axum_extra::extract::CookieJar;
 use secrecy::Secret;
 use serde::{Deserialize, Serialize};
 
-use crate::app_state::AppState;
-use crate::domain::data_stores::{LoginAttemptId, TwoFACode};
+use crate::app_state::{AppState, SmsClientType};
+use crate::domain::data_stores::audit_log::AuditEventKind;
+use crate::domain::data_stores::{LoginAttemptId, TwoFACode, UserStoreError};
 use crate::domain::email::Email;
 use crate::domain::error::AuthAPIError;
+use crate::domain::locale::Locale;
 use crate::domain::password::Password;
-use crate::utils::auth::generate_auth_cookie;
+use crate::domain::{PhoneNumber, TwoFAChannel, User};
+use crate::i18n::{catalog, current_locale};
+use crate::services::email_templates;
+use crate::utils::audit::AuditContext;
+use crate::utils::metrics::outcome;
+use crate::utils::new_device_alert::alert_if_new_device;
+use crate::utils::session::issue_auth_cookie;
 
 #[derive(Deserialize, Debug)]
 pub struct LoginRequest {
@@ -22,9 +30,58 @@ pub struct LoginRequest {
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login_handler(
     State(state): State<AppState>,
+    audit: AuditContext,
     jar: CookieJar,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let actor = request.email.clone();
+    let (jar, result) = login(&state, jar, request).await;
+
+    let result_label = match &result {
+        Ok((StatusCode::PARTIAL_CONTENT, _)) => outcome::TWO_FA_REQUIRED,
+        Ok(_) => outcome::SUCCESS,
+        Err(AuthAPIError::InvalidCredentials) => outcome::INVALID_INPUT,
+        Err(AuthAPIError::IncorrectCredentials) => outcome::BAD_PASSWORD,
+        Err(AuthAPIError::PasswordResetRequired) => outcome::PASSWORD_RESET_REQUIRED,
+        Err(AuthAPIError::AccountDisabled) => outcome::ACCOUNT_DISABLED,
+        Err(AuthAPIError::EmailSuppressed) => outcome::EMAIL_SUPPRESSED,
+        Err(_) => outcome::ERROR,
+    };
+    state.metrics.record_login(result_label);
+    audit
+        .record(
+            &state.audit_log,
+            AuditEventKind::Login,
+            Some(&actor),
+            result_label,
+        )
+        .await;
+    if result_label == outcome::SUCCESS {
+        if let Ok(email) = Email::parse(actor.clone()) {
+            alert_if_new_device(&state, &audit, &email).await;
+        }
+    }
+    if result_label == outcome::TWO_FA_REQUIRED {
+        audit
+            .record(
+                &state.audit_log,
+                AuditEventKind::TwoFaCodeSent,
+                Some(&actor),
+                outcome::SUCCESS,
+            )
+            .await;
+    }
+    (jar, result)
+}
+
+async fn login(
+    state: &AppState,
+    jar: CookieJar,
+    request: LoginRequest,
+) -> (
+    CookieJar,
+    Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
+) {
     let email = Email::parse(request.email);
     let password = Password::parse(request.password);
 
@@ -34,10 +91,15 @@ pub async fn login_handler(
         return (jar, Err(AuthAPIError::InvalidCredentials));
     };
 
-    let user_store = state.user_store.read().await;
+    let user_store = &state.user_store;
 
-    if let Err(_) = user_store.validate_user(&email, &password).await {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    match user_store.validate_user(&email, &password).await {
+        Ok(()) => {}
+        Err(UserStoreError::PasswordResetRequired) => {
+            return (jar, Err(AuthAPIError::PasswordResetRequired))
+        }
+        Err(UserStoreError::AccountDisabled) => return (jar, Err(AuthAPIError::AccountDisabled)),
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     }
 
     let user = user_store.get_user(&email).await;
@@ -49,9 +111,34 @@ pub async fn login_handler(
     };
 
     if user.requires_2fa {
-        handle_2fa(jar, state.clone(), email).await
+        let sms = sms_recipient(state, &user);
+        // The code would never arrive, so say so instead of leaving them waiting
+        if sms.is_none() && user.email_status.is_suppressed() {
+            return (jar, Err(AuthAPIError::EmailSuppressed));
+        }
+        handle_2fa(jar, state.clone(), user, sms).await
     } else {
-        handle_no_2fa(&email, jar).await
+        handle_no_2fa(&email, jar, state).await
+    }
+}
+
+// The client and number to text the user's login code to, if they chose SMS.
+// Should the gateway have been unconfigured since, or the account have no
+// number, the code is emailed instead.
+fn sms_recipient(state: &AppState, user: &User) -> Option<(SmsClientType, PhoneNumber)> {
+    if user.two_fa_channel != TwoFAChannel::Sms {
+        return None;
+    }
+    match (&state.sms_client, &user.phone_number) {
+        (Some(sms_client), Some(phone_number)) => Some((sms_client.clone(), phone_number.clone())),
+        (None, _) => {
+            tracing::warn!("SMS isn't configured, emailing the login code instead");
+            None
+        }
+        (Some(_), None) => {
+            tracing::warn!("No phone number on file, emailing the login code instead");
+            None
+        }
     }
 }
 
@@ -59,7 +146,8 @@ pub async fn login_handler(
 async fn handle_2fa(
     jar: CookieJar,
     state: AppState,
-    email: Email,
+    user: User,
+    sms: Option<(SmsClientType, PhoneNumber)>,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
@@ -67,26 +155,41 @@ async fn handle_2fa(
     let login_attempt_id = LoginAttemptId::default();
     let two_fa_code = TwoFACode::default();
 
+    if let Err(e) = state
+        .two_fa_code_store
+        .add_code(
+            user.email.clone(),
+            login_attempt_id.clone(),
+            two_fa_code.clone(),
+        )
+        .await
     {
-        let mut two_fa_store = state.two_fa_code_store.write().await;
-        if let Err(e) = two_fa_store
-            .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
-            .await
-        {
-            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
-        }
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    let email_client = state.email_client.write().await;
-    if let Err(e) = email_client
-        .send_email(&email, "login now", two_fa_code.as_ref())
-        .await
-    {
+    let sent = match sms {
+        Some((sms_client, phone_number)) => {
+            text_code(
+                &state,
+                &sms_client,
+                &phone_number,
+                user.locale,
+                &two_fa_code,
+            )
+            .await
+        }
+        None => email_code(&state, user.email.clone(), user.locale, &two_fa_code).await,
+    };
+    if let Err(e) = sent {
+        // Don't leave a code behind that the user was never sent
+        if let Err(e) = state.two_fa_code_store.remove_code(&user.email).await {
+            tracing::warn!(error = %e, "failed to remove unsent 2FA code");
+        }
         return (jar, Err(AuthAPIError::UnexpectedError(e)));
     }
 
     let two_factor_response = TwoFactorAuthResponse {
-        message: "2FA required".to_string(),
+        message: catalog(current_locale()).two_fa_required().to_string(),
         login_attempt_id: login_attempt_id.as_ref().to_string(),
     };
     (
@@ -98,21 +201,60 @@ async fn handle_2fa(
     )
 }
 
+async fn email_code(
+    state: &AppState,
+    email: Email,
+    locale: Locale,
+    two_fa_code: &TwoFACode,
+) -> color_eyre::Result<()> {
+    let message = email_templates::two_fa_code(
+        &state.settings.branding,
+        locale,
+        two_fa_code,
+        state.settings.auth.two_fa_code_ttl(),
+    )?;
+    // Queued rather than sent, so a provider hiccup doesn't fail the login
+    // after the code was already stored. The outbox worker retries it.
+    state.email_outbox.enqueue(email, message).await?;
+    Ok(())
+}
+
+// Texts are sent right away, as there's no outbox for them. If the gateway
+// fails, so does the login, and logging in again sends a new code.
+async fn text_code(
+    state: &AppState,
+    sms_client: &SmsClientType,
+    phone_number: &PhoneNumber,
+    locale: Locale,
+    two_fa_code: &TwoFACode,
+) -> color_eyre::Result<()> {
+    let minutes = state.settings.auth.two_fa_code_ttl().as_secs().div_ceil(60);
+    let body = catalog(locale).two_fa_code_sms(
+        &state.settings.branding.product_name,
+        two_fa_code.as_ref(),
+        minutes,
+    );
+    let result = sms_client.send_sms(phone_number, &body).await;
+    state.metrics.record_sms(if result.is_ok() {
+        outcome::SUCCESS
+    } else {
+        outcome::FAILURE
+    });
+    result
+}
+
 #[tracing::instrument(name = "Handle No 2FA", skip_all)]
 async fn handle_no_2fa(
     email: &Email,
     jar: CookieJar,
+    state: &AppState,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = generate_auth_cookie(&email);
-
-    let auth_cookie = match auth_cookie {
+    let auth_cookie = match issue_auth_cookie(state, email, None).await {
         Ok(auth_cookie) => auth_cookie,
-        Err(e) => {
-            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
-        }
+        Err(e) => return (jar, Err(e)),
     };
 
     let updated_jar = jar.add(auth_cookie);
@@ -1,35 +1,65 @@
+use axum::http::StatusCode;
 use axum::{extract::State, response::IntoResponse};
 use axum_extra::extract::{cookie::Cookie, CookieJar};
-use axum::http::StatusCode;
 use secrecy::Secret;
 
-use crate::{app_state::AppState, domain::error::AuthAPIError, utils::{auth::validate_token, constants::JWT_COOKIE_NAME}};
+use crate::domain::data_stores::audit_log::AuditEventKind;
+use crate::utils::audit::AuditContext;
+use crate::utils::metrics::outcome;
+use crate::{app_state::AppState, domain::error::AuthAPIError, utils::auth::validate_token};
 
 #[tracing::instrument(name = "Verify 2FA", skip_all)]
 pub async fn logout_handler(
     State(state): State<AppState>,
+    audit: AuditContext,
     jar: CookieJar,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     // Retrieve JWT cookie from the `CookieJar`
     // Return AuthAPIError::MissingToken is the cookie is not found
-    let token = match jar.get(JWT_COOKIE_NAME) {
+    let cookie_name = state.settings.auth.cookie.name.clone();
+    let token = match jar.get(&cookie_name) {
         Some(cookie) => cookie.value().to_owned(),
         None => return (jar, Err(AuthAPIError::MissingToken)),
     };
 
-    let validation = validate_token(&token, &state.banned_token_store).await;
-    match validation {
-        Ok(_) => {},
-        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
-    }
+    let validation = validate_token(&token, &state.banned_token_store, &state.settings.auth).await;
+    state.metrics.record_token_validation(&validation);
+    let claims = match validation {
+        Ok(claims) => claims,
+        Err(e) => {
+            audit
+                .record(
+                    &state.audit_log,
+                    AuditEventKind::TokenRejected,
+                    None,
+                    e.as_str(),
+                )
+                .await;
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+    };
 
     //remove the cookie
-    let jar = jar.remove(Cookie::from(JWT_COOKIE_NAME));
+    let jar = jar.remove(Cookie::from(cookie_name));
 
     //add to the banned list
-    let mut banned_token_store = state.banned_token_store.write().await;
-    match banned_token_store.add_token(Secret::new(token)).await {
+    let result = state.banned_token_store.add_token(Secret::new(token)).await;
+    let result_label = if result.is_ok() {
+        outcome::SUCCESS
+    } else {
+        outcome::ERROR
+    };
+    audit
+        .record(
+            &state.audit_log,
+            AuditEventKind::Logout,
+            Some(&claims.sub),
+            result_label,
+        )
+        .await;
+
+    match result {
         Ok(()) => (jar, Ok(StatusCode::OK)),
-        Err(e) => (jar, Err(AuthAPIError::UnexpectedError(e.into())))
+        Err(e) => (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
     }
 }
@@ -1,11 +1,30 @@
+mod account_recovery;
+mod admin;
+mod admin_users;
+mod audit_events;
+mod dev_mailbox;
+mod health;
 mod login;
 mod logout;
+mod metrics;
+mod organizations;
+mod postmark_webhook;
+mod roles;
 mod signup;
 mod verify_2fa;
 mod verify_token;
 
+pub use account_recovery::*;
+pub use admin_users::*;
+pub use audit_events::*;
+pub use dev_mailbox::*;
+pub use health::*;
 pub use login::*;
 pub use logout::*;
+pub use metrics::*;
+pub use organizations::*;
+pub use postmark_webhook::*;
+pub use roles::*;
 pub use signup::*;
 pub use verify_2fa::*;
 pub use verify_token::*;
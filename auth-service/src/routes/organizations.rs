@@ -0,0 +1,457 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::audit_log::AuditEventKind;
+use crate::domain::data_stores::organization_store::OrganizationStoreError;
+use crate::domain::data_stores::UserStoreError;
+use crate::domain::error::AuthAPIError;
+use crate::domain::{Email, Invitation, Member, Membership, OrgRole, Organization, OrganizationId};
+use crate::i18n::current_locale;
+use crate::services::email_templates;
+use crate::utils::audit::AuditContext;
+use crate::utils::auth::{generate_invitation_token, validate_invitation_token};
+use crate::utils::authorization::SignedInUser;
+use crate::utils::metrics::outcome;
+use crate::utils::session::issue_auth_cookie;
+
+const MAX_NAME_LENGTH: usize = 100;
+
+#[derive(Deserialize, Debug)]
+pub struct CreateOrganizationRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrganizationsResponse {
+    pub organizations: Vec<Membership>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MembersResponse {
+    pub members: Vec<Member>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InviteRequest {
+    pub email: String,
+    // "owner", "admin" or "member" (the default)
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvitationsResponse {
+    pub invitations: Vec<Invitation>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InvitationTokenRequest {
+    pub token: Secret<String>,
+}
+
+// What the login page needs to pre-fill its forms from an invitation link
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvitationDetails {
+    pub organization_name: String,
+    pub email: String,
+    pub role: OrgRole,
+    // Whether the invitee should log in rather than sign up
+    pub account_exists: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SwitchOrganizationRequest {
+    #[serde(rename = "organizationId")]
+    pub organization_id: String,
+}
+
+// The caller becomes the owner of the new organization. It becomes their
+// active one once they switch to it.
+#[tracing::instrument(name = "Create Organization", skip_all)]
+pub async fn create_organization_handler(
+    State(state): State<AppState>,
+    user: SignedInUser,
+    Json(request): Json<CreateOrganizationRequest>,
+) -> Result<(StatusCode, Json<Organization>), AuthAPIError> {
+    let name = request.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let organization = state
+        .organization_store
+        .create_organization(name, &user.email)
+        .await
+        .map_err(organization_store_error)?;
+
+    Ok((StatusCode::CREATED, Json(organization)))
+}
+
+// The organizations the caller belongs to, with their role in each
+#[tracing::instrument(name = "List Organizations", skip_all)]
+pub async fn list_organizations_handler(
+    State(state): State<AppState>,
+    user: SignedInUser,
+) -> Result<Json<OrganizationsResponse>, AuthAPIError> {
+    let organizations = state
+        .organization_store
+        .memberships(&user.email)
+        .await
+        .map_err(organization_store_error)?;
+
+    Ok(Json(OrganizationsResponse { organizations }))
+}
+
+#[tracing::instrument(name = "List Members", skip_all)]
+pub async fn members_handler(
+    State(state): State<AppState>,
+    user: SignedInUser,
+    Path(id): Path<String>,
+) -> Result<Json<MembersResponse>, AuthAPIError> {
+    let id = organization_id(&id)?;
+    member_role(&state, &id, &user.email).await?;
+
+    let members = state
+        .organization_store
+        .members(&id)
+        .await
+        .map_err(organization_store_error)?;
+
+    Ok(Json(MembersResponse { members }))
+}
+
+// Members may leave; owners and admins remove those they could have
+// invited. Also ends the removed member's sessions, so tokens naming the
+// organization stop working right away.
+#[tracing::instrument(name = "Remove Member", skip_all)]
+pub async fn remove_member_handler(
+    State(state): State<AppState>,
+    user: SignedInUser,
+    audit: AuditContext,
+    Path((id, email)): Path<(String, String)>,
+) -> Result<StatusCode, AuthAPIError> {
+    let result = remove_member(&state, &user.email, &id, email.clone()).await;
+    audit
+        .record_with_subject(
+            &state.audit_log,
+            AuditEventKind::MemberRemoved,
+            Some(user.email.as_ref().expose_secret()),
+            Some(&email),
+            result_label(&result),
+        )
+        .await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn remove_member(
+    state: &AppState,
+    caller: &Email,
+    id: &str,
+    email: String,
+) -> Result<(), AuthAPIError> {
+    let id = organization_id(id)?;
+    let caller_role = member_role(state, &id, caller).await?;
+    let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let role = match state.organization_store.role(&id, &email).await {
+        Ok(role) => role,
+        Err(OrganizationStoreError::NotMember) => return Err(AuthAPIError::UserNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if &email != caller && !caller_role.can_manage(role) {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    // The store refuses to remove the last owner, so someone is always left
+    // to manage the organization
+    state
+        .organization_store
+        .remove_member(&id, &email)
+        .await
+        .map_err(organization_store_error)?;
+
+    state
+        .banned_token_store
+        .revoke_sessions(&email, Utc::now())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// Emails `email` a link to join the organization. It pre-fills the signup
+// form if they don't have an account yet, or joins their existing one once
+// they log in.
+#[tracing::instrument(name = "Invite Member", skip_all)]
+pub async fn invite_handler(
+    State(state): State<AppState>,
+    user: SignedInUser,
+    audit: AuditContext,
+    Path(id): Path<String>,
+    Json(request): Json<InviteRequest>,
+) -> Result<(StatusCode, Json<Invitation>), AuthAPIError> {
+    let invitee = request.email.clone();
+    let result = invite(&state, &user.email, &id, request).await;
+    audit
+        .record_with_subject(
+            &state.audit_log,
+            AuditEventKind::InvitationSent,
+            Some(user.email.as_ref().expose_secret()),
+            Some(&invitee),
+            result_label(&result),
+        )
+        .await;
+
+    Ok((StatusCode::CREATED, Json(result?)))
+}
+
+async fn invite(
+    state: &AppState,
+    inviter: &Email,
+    id: &str,
+    request: InviteRequest,
+) -> Result<Invitation, AuthAPIError> {
+    let id = organization_id(id)?;
+    let inviter_role = member_role(state, &id, inviter).await?;
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let role = match request.role {
+        Some(role) => role.parse().map_err(|_| AuthAPIError::InvalidCredentials)?,
+        None => OrgRole::Member,
+    };
+    if !inviter_role.can_manage(role) {
+        return Err(AuthAPIError::Forbidden);
+    }
+    let organization = state
+        .organization_store
+        .get_organization(&id)
+        .await
+        .map_err(organization_store_error)?;
+
+    let valid_for = state.settings.auth.invitation_ttl();
+    let invitation = Invitation {
+        id: Uuid::new_v4(),
+        organization_id: id,
+        email: email.as_ref().expose_secret().to_owned(),
+        role,
+        invited_by: inviter.as_ref().expose_secret().to_owned(),
+        expires_at: Utc::now()
+            + chrono::Duration::from_std(valid_for)
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?,
+    };
+    let token = generate_invitation_token(&invitation, &state.settings.auth)
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let link = format!(
+        "{}/?invitation={}",
+        state.settings.application.public_url.trim_end_matches('/'),
+        token
+    );
+    // In the invitee's language if they already have an account, otherwise
+    // in the inviter's
+    let locale = state
+        .user_store
+        .get_user(&email)
+        .await
+        .map(|user| user.locale)
+        .unwrap_or_else(|_| current_locale());
+    let message = email_templates::invitation(
+        &state.settings.branding,
+        locale,
+        &invitation.invited_by,
+        &organization.name,
+        &link,
+        valid_for,
+    )
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .organization_store
+        .add_invitation(invitation.clone())
+        .await
+        .map_err(organization_store_error)?;
+    if let Err(e) = state.email_outbox.enqueue(email, message).await {
+        // Nobody was sent the link, so nobody should be able to use it
+        if let Err(e) = state
+            .organization_store
+            .remove_invitation(&invitation.id)
+            .await
+        {
+            tracing::warn!(error = %e, "failed to remove unsent invitation");
+        }
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    Ok(invitation)
+}
+
+// Invitations that can still be accepted, for owners and admins
+#[tracing::instrument(name = "List Invitations", skip_all)]
+pub async fn invitations_handler(
+    State(state): State<AppState>,
+    user: SignedInUser,
+    Path(id): Path<String>,
+) -> Result<Json<InvitationsResponse>, AuthAPIError> {
+    let id = organization_id(&id)?;
+    if !member_role(&state, &id, &user.email)
+        .await?
+        .can_manage(OrgRole::Member)
+    {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    let invitations = state
+        .organization_store
+        .invitations(&id)
+        .await
+        .map_err(organization_store_error)?;
+
+    Ok(Json(InvitationsResponse { invitations }))
+}
+
+// Looks up the invitation behind a link. Anyone holding the link may, as it
+// was emailed to the invitee.
+#[tracing::instrument(name = "Invitation Details", skip_all)]
+pub async fn invitation_handler(
+    State(state): State<AppState>,
+    Json(request): Json<InvitationTokenRequest>,
+) -> Result<Json<InvitationDetails>, AuthAPIError> {
+    let invitation = pending_invitation(&state, &request.token).await?;
+    let organization = state
+        .organization_store
+        .get_organization(&invitation.organization_id)
+        .await
+        .map_err(organization_store_error)?;
+    let email = Email::parse(invitation.email.clone()).map_err(AuthAPIError::UnexpectedError)?;
+    let account_exists = match state.user_store.get_user(&email).await {
+        Ok(_) => true,
+        Err(UserStoreError::UserNotFound) => false,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    Ok(Json(InvitationDetails {
+        organization_name: organization.name,
+        email: invitation.email,
+        role: invitation.role,
+        account_exists,
+    }))
+}
+
+// Joins the signed in invitee to the organization and makes it their
+// active one
+#[tracing::instrument(name = "Accept Invitation", skip_all)]
+pub async fn accept_invitation_handler(
+    State(state): State<AppState>,
+    user: SignedInUser,
+    audit: AuditContext,
+    jar: CookieJar,
+    Json(request): Json<InvitationTokenRequest>,
+) -> Result<(CookieJar, Json<Membership>), AuthAPIError> {
+    let result = accept_invitation(&state, &user.email, &request.token).await;
+    audit
+        .record(
+            &state.audit_log,
+            AuditEventKind::InvitationAccepted,
+            Some(user.email.as_ref().expose_secret()),
+            result_label(&result),
+        )
+        .await;
+    let membership = result?;
+
+    let auth_cookie =
+        issue_auth_cookie(&state, &user.email, Some(&membership.organization_id)).await?;
+    Ok((jar.add(auth_cookie), Json(membership)))
+}
+
+async fn accept_invitation(
+    state: &AppState,
+    email: &Email,
+    token: &Secret<String>,
+) -> Result<Membership, AuthAPIError> {
+    let invitation = pending_invitation(state, token).await?;
+    // The link may have been forwarded
+    if !invitation.is_for(email) {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    state
+        .organization_store
+        .accept_invitation(&invitation.id, email)
+        .await
+        .map_err(organization_store_error)
+}
+
+// Reissues the caller's auth cookie to act for another of their
+// organizations
+#[tracing::instrument(name = "Switch Organization", skip_all)]
+pub async fn switch_organization_handler(
+    State(state): State<AppState>,
+    user: SignedInUser,
+    jar: CookieJar,
+    Json(request): Json<SwitchOrganizationRequest>,
+) -> Result<(CookieJar, StatusCode), AuthAPIError> {
+    let id = organization_id(&request.organization_id)?;
+
+    let auth_cookie = issue_auth_cookie(&state, &user.email, Some(&id)).await?;
+    Ok((jar.add(auth_cookie), StatusCode::OK))
+}
+
+// The unexpired, unused invitation an invitation link names
+pub async fn pending_invitation(
+    state: &AppState,
+    token: &Secret<String>,
+) -> Result<Invitation, AuthAPIError> {
+    let id = validate_invitation_token(token.expose_secret(), &state.settings.auth)
+        .map_err(|_| AuthAPIError::InvitationNotFound)?;
+
+    state
+        .organization_store
+        .get_invitation(&id)
+        .await
+        .map_err(organization_store_error)
+}
+
+// Ids that don't parse can't name an organization anyone belongs to
+fn organization_id(id: &str) -> Result<OrganizationId, AuthAPIError> {
+    OrganizationId::parse(id).map_err(|_| AuthAPIError::OrganizationNotFound)
+}
+
+// The caller's role in the organization. Organizations they don't belong to
+// are reported as not found, so outsiders can't probe for them.
+async fn member_role(
+    state: &AppState,
+    id: &OrganizationId,
+    email: &Email,
+) -> Result<OrgRole, AuthAPIError> {
+    state
+        .organization_store
+        .role(id, email)
+        .await
+        .map_err(|e| match e {
+            OrganizationStoreError::NotMember => AuthAPIError::OrganizationNotFound,
+            e => organization_store_error(e),
+        })
+}
+
+fn organization_store_error(e: OrganizationStoreError) -> AuthAPIError {
+    match e {
+        OrganizationStoreError::OrganizationNotFound => AuthAPIError::OrganizationNotFound,
+        OrganizationStoreError::InvitationNotFound => AuthAPIError::InvitationNotFound,
+        OrganizationStoreError::LastOwner => AuthAPIError::LastOwner,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+fn result_label<T>(result: &Result<T, AuthAPIError>) -> &'static str {
+    match result {
+        Ok(_) => outcome::SUCCESS,
+        Err(AuthAPIError::InvalidCredentials) => outcome::INVALID_INPUT,
+        Err(AuthAPIError::UnexpectedError(_)) => outcome::ERROR,
+        Err(_) => outcome::FAILURE,
+    }
+}
@@ -0,0 +1,124 @@
+use axum::extract::State;
+use axum::http::{header::AUTHORIZATION, HeaderMap, StatusCode};
+use axum::Json;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::UserStoreError;
+use crate::domain::error::AuthAPIError;
+use crate::domain::{Email, EmailStatus};
+use crate::utils::auth::constant_time_eq;
+
+// The parts of Postmark's webhook payloads we act on. Bounces and spam
+// complaints name the address in `Email`, deliveries in `Recipient`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct PostmarkEvent {
+    pub record_type: String,
+    #[serde(rename = "Type")]
+    pub bounce_type: Option<String>,
+    pub email: Option<String>,
+    pub recipient: Option<String>,
+    // Set when Postmark deactivated the address and won't send to it
+    pub inactive: Option<bool>,
+}
+
+impl PostmarkEvent {
+    // What the event says about the address, if anything
+    fn email_status(&self) -> Option<(&str, EmailStatus)> {
+        match self.record_type.as_str() {
+            "Bounce" => {
+                let hard = self.inactive == Some(true)
+                    || self.bounce_type.as_deref() == Some("HardBounce");
+                let status = if hard {
+                    EmailStatus::HardBounced
+                } else {
+                    EmailStatus::SoftBounced
+                };
+                Some((self.email.as_deref()?, status))
+            }
+            "SpamComplaint" => Some((self.email.as_deref()?, EmailStatus::SpamComplaint)),
+            // Mail got through again, e.g. after a full mailbox was emptied.
+            // Only bounces are cleared by it, see the handler.
+            "Delivery" => Some((self.recipient.as_deref()?, EmailStatus::Deliverable)),
+            _ => None,
+        }
+    }
+}
+
+// Receives bounce, spam complaint and delivery notifications from Postmark
+// and records them on the user, so we stop emailing dead addresses
+#[tracing::instrument(name = "Postmark webhook", skip_all)]
+pub async fn postmark_webhook_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(event): Json<PostmarkEvent>,
+) -> Result<StatusCode, AuthAPIError> {
+    authorize_webhook(&state, &headers)?;
+
+    let Some((address, status)) = event.email_status() else {
+        tracing::debug!(record_type = event.record_type, "ignoring Postmark event");
+        return Ok(StatusCode::OK);
+    };
+    // Answer 200 for anything we can't act on, or Postmark keeps retrying
+    let Ok(email) = Email::parse(address.to_owned()) else {
+        tracing::warn!(
+            record_type = event.record_type,
+            "Postmark event without a valid address"
+        );
+        return Ok(StatusCode::OK);
+    };
+
+    if status == EmailStatus::Deliverable {
+        match state.user_store.get_user(&email).await {
+            Ok(user) if user.email_status.cleared_by_delivery() => {}
+            // Nothing to clear, or a spam complaint that has to stay
+            Ok(_) | Err(UserStoreError::UserNotFound) => return Ok(StatusCode::OK),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+    }
+
+    match state.user_store.set_email_status(&email, status).await {
+        Ok(()) => {
+            tracing::info!(
+                record_type = event.record_type,
+                status = status.as_str(),
+                "recorded email status"
+            );
+        }
+        // e.g. an email to an address that isn't a user
+        Err(UserStoreError::UserNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+    Ok(StatusCode::OK)
+}
+
+fn authorize_webhook(state: &AppState, headers: &HeaderMap) -> Result<(), AuthAPIError> {
+    let credentials = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or(AuthAPIError::MissingToken)?;
+    let (username, password) = credentials
+        .split_once(':')
+        .ok_or(AuthAPIError::InvalidToken)?;
+
+    let settings = &state.settings.email_client.webhook;
+    match &settings.password {
+        // Both are checked, whatever the outcome of the first
+        Some(expected) => {
+            let username_ok = constant_time_eq(username, &settings.username);
+            let password_ok = constant_time_eq(password, expected.expose_secret());
+            if username_ok && password_ok {
+                Ok(())
+            } else {
+                Err(AuthAPIError::InvalidToken)
+            }
+        }
+        None => Err(AuthAPIError::InvalidToken),
+    }
+}
@@ -0,0 +1,127 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::audit_log::AuditEventKind;
+use crate::domain::data_stores::role_store::{RoleDefinition, RoleStoreError};
+use crate::domain::error::AuthAPIError;
+use crate::domain::{Email, Grants, ManageRoles, ReadRoles};
+use crate::routes::admin::{existing_user, record};
+use crate::utils::audit::AuditContext;
+use crate::utils::authorization::RequirePermission;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RolesResponse {
+    pub roles: Vec<RoleDefinition>,
+}
+
+// Every role that can be granted, and what it allows
+#[tracing::instrument(name = "List Roles", skip_all)]
+pub async fn list_roles_handler(
+    State(state): State<AppState>,
+    _: RequirePermission<ReadRoles>,
+) -> Result<Json<RolesResponse>, AuthAPIError> {
+    let roles = state
+        .role_store
+        .roles()
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(RolesResponse { roles }))
+}
+
+// A user's roles and the permissions they add up to
+#[tracing::instrument(name = "User Roles", skip_all)]
+pub async fn user_roles_handler(
+    State(state): State<AppState>,
+    _: RequirePermission<ReadRoles>,
+    Path(email): Path<String>,
+) -> Result<Json<Grants>, AuthAPIError> {
+    let email = existing_user(&state, email).await?;
+    let grants = state
+        .role_store
+        .grants(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(grants))
+}
+
+// Takes effect from the user's next login, when the role lands in their token
+#[tracing::instrument(name = "Grant Role", skip_all)]
+pub async fn grant_role_handler(
+    State(state): State<AppState>,
+    RequirePermission { caller, .. }: RequirePermission<ManageRoles>,
+    audit: AuditContext,
+    Path((email, role)): Path<(String, String)>,
+) -> Result<StatusCode, AuthAPIError> {
+    let email = existing_user(&state, email).await?;
+
+    let result = state
+        .role_store
+        .grant_role(&email, &role)
+        .await
+        .map_err(role_store_error);
+    record(
+        &state,
+        &audit,
+        &caller,
+        AuditEventKind::RoleGranted,
+        &email,
+        &result,
+    )
+    .await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Also ends the user's sessions, so tokens still carrying the role stop
+// working right away
+#[tracing::instrument(name = "Revoke Role", skip_all)]
+pub async fn revoke_role_handler(
+    State(state): State<AppState>,
+    RequirePermission { caller, .. }: RequirePermission<ManageRoles>,
+    audit: AuditContext,
+    Path((email, role)): Path<(String, String)>,
+) -> Result<StatusCode, AuthAPIError> {
+    let email = existing_user(&state, email).await?;
+
+    let result = revoke_role(&state, &email, &role).await;
+    record(
+        &state,
+        &audit,
+        &caller,
+        AuditEventKind::RoleRevoked,
+        &email,
+        &result,
+    )
+    .await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn revoke_role(state: &AppState, email: &Email, role: &str) -> Result<(), AuthAPIError> {
+    state
+        .role_store
+        .revoke_role(email, role)
+        .await
+        .map_err(role_store_error)?;
+
+    state
+        .banned_token_store
+        .revoke_sessions(email, Utc::now())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+fn role_store_error(e: RoleStoreError) -> AuthAPIError {
+    match e {
+        RoleStoreError::UnknownRole => AuthAPIError::UnknownRole,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
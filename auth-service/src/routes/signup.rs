@@ -1,15 +1,21 @@
-use secrecy::Secret;
 use axum::http::StatusCode;
 use axum::Json;
 use axum::{extract::State, response::IntoResponse};
+use secrecy::Secret;
 use serde::{Deserialize, Serialize};
 
 use crate::app_state::AppState;
+use crate::domain::data_stores::audit_log::AuditEventKind;
 use crate::domain::data_stores::UserStoreError;
 use crate::domain::email::Email;
 use crate::domain::error::AuthAPIError;
 use crate::domain::password::Password;
 use crate::domain::user::User;
+use crate::domain::{PhoneNumber, TwoFAChannel};
+use crate::i18n::{catalog, current_locale};
+use crate::routes::pending_invitation;
+use crate::utils::audit::AuditContext;
+use crate::utils::metrics::outcome;
 
 #[derive(Deserialize, Debug)]
 pub struct SignupRequest {
@@ -17,6 +23,21 @@ pub struct SignupRequest {
     pub password: Secret<String>,
     #[serde(rename = "requires2FA")]
     pub requires_2fa: bool,
+    // Language for the user's emails, e.g. "de". Defaults to the one
+    // negotiated from Accept-Language, as does one we don't support.
+    #[serde(default)]
+    pub locale: Option<String>,
+    // How login codes are sent: "email" (the default) or "sms", which needs
+    // `phone_number` and 2FA
+    #[serde(rename = "twoFAChannel", default)]
+    pub two_fa_channel: Option<String>,
+    // E.164, e.g. "+4915112345678"
+    #[serde(rename = "phoneNumber", default)]
+    pub phone_number: Option<String>,
+    // From an invitation link: the new user joins the organization that
+    // invited them. The email must be the invited one.
+    #[serde(rename = "invitationToken", default)]
+    pub invitation_token: Option<Secret<String>>,
 }
 
 #[derive(Eq, PartialEq, Debug, Serialize, Deserialize)]
@@ -27,24 +48,95 @@ pub struct SignupResponse {
 #[tracing::instrument(name = "Signup", skip_all, err(Debug))]
 pub async fn signup_handler(
     State(state): State<AppState>,
+    audit: AuditContext,
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
+    let actor = request.email.clone();
+    let result = add_user(&state, request).await;
+
+    let result_label = match &result {
+        Ok(()) => outcome::SUCCESS,
+        Err(
+            AuthAPIError::InvalidCredentials
+            | AuthAPIError::SmsUnavailable
+            | AuthAPIError::InvitationNotFound
+            | AuthAPIError::Forbidden,
+        ) => outcome::INVALID_INPUT,
+        Err(AuthAPIError::UserAlreadyExists) => outcome::ALREADY_EXISTS,
+        Err(_) => outcome::ERROR,
+    };
+    state.metrics.record_signup(result_label);
+    audit
+        .record(
+            &state.audit_log,
+            AuditEventKind::Signup,
+            Some(&actor),
+            result_label,
+        )
+        .await;
+    result?;
+
+    let response = Json(SignupResponse {
+        message: catalog(current_locale()).user_created().to_string(),
+    });
+
+    Ok((StatusCode::CREATED, response))
+}
+
+async fn add_user(state: &AppState, request: SignupRequest) -> Result<(), AuthAPIError> {
     if request.email.is_empty() || !request.email.contains("@") {
         return Err(AuthAPIError::InvalidCredentials);
     }
     // Create a new `User` instance using data in the `request`
-    let mut user_store = state.user_store.write().await;
-
     let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
     let password =
         Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let add_res = user_store.add_user(User {
-        email,
-        password,
-        requires_2fa: request.requires_2fa,
-    }).await;
+    let locale = request
+        .locale
+        .and_then(|locale| locale.parse().ok())
+        .unwrap_or_else(current_locale);
+
+    let two_fa_channel = match request.two_fa_channel {
+        Some(channel) => channel
+            .parse()
+            .map_err(|_| AuthAPIError::InvalidCredentials)?,
+        None => TwoFAChannel::default(),
+    };
+    let phone_number = request
+        .phone_number
+        .map(PhoneNumber::parse)
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // Checked before the account is created, so a bad link doesn't leave
+    // one behind
+    let invitation = match &request.invitation_token {
+        Some(token) => {
+            let invitation = pending_invitation(state, token).await?;
+            if !invitation.is_for(&email) {
+                return Err(AuthAPIError::Forbidden);
+            }
+            Some(invitation)
+        }
+        None => None,
+    };
+
+    let mut user = User::new(email.clone(), password, request.requires_2fa).with_locale(locale);
+    match (two_fa_channel, phone_number) {
+        (TwoFAChannel::Sms, Some(phone_number)) if request.requires_2fa => {
+            if state.sms_client.is_none() {
+                return Err(AuthAPIError::SmsUnavailable);
+            }
+            user = user.with_sms_2fa(phone_number);
+        }
+        // Nothing to text the codes to, or no codes to text
+        (TwoFAChannel::Sms, _) => return Err(AuthAPIError::InvalidCredentials),
+        (TwoFAChannel::Email, phone_number) => user.phone_number = phone_number,
+    }
+
+    let add_res = state.user_store.add_user(user).await;
 
     if let Err(e) = add_res {
         if e == UserStoreError::UserAlreadyExists {
@@ -54,9 +146,17 @@ pub async fn signup_handler(
         }
     }
 
-    let response = Json(SignupResponse {
-        message: "User created successfully!".to_string(),
-    });
+    if let Some(invitation) = invitation {
+        // The account exists by now, so this is no reason to fail the signup.
+        // The invitee can still accept the invitation after logging in.
+        if let Err(e) = state
+            .organization_store
+            .accept_invitation(&invitation.id, &email)
+            .await
+        {
+            tracing::warn!(error = ?e, "Failed to accept invitation on signup");
+        }
+    }
 
-    Ok((StatusCode::CREATED, response))
+    Ok(())
 }
@@ -5,10 +5,14 @@ use axum_extra::extract::CookieJar;
 use serde::Deserialize;
 
 use crate::app_state::AppState;
+use crate::domain::data_stores::audit_log::AuditEventKind;
 use crate::domain::data_stores::{LoginAttemptId, TwoFACode};
 use crate::domain::error::AuthAPIError;
 use crate::domain::Email;
-use crate::utils::auth::generate_auth_cookie;
+use crate::utils::audit::AuditContext;
+use crate::utils::metrics::outcome;
+use crate::utils::new_device_alert::alert_if_new_device;
+use crate::utils::session::issue_auth_cookie;
 use crate::LoginResponse;
 
 #[derive(Deserialize, Debug)]
@@ -23,9 +27,44 @@ pub struct Verify2FARequest {
 #[tracing::instrument(name = "Verify 2FA", skip_all)]
 pub async fn verify_2fa_handler(
     State(state): State<AppState>,
+    audit: AuditContext,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let actor = request.email.clone();
+    let (jar, result) = verify_2fa(&state, jar, request).await;
+
+    let result_label = match &result {
+        Ok(_) => outcome::SUCCESS,
+        Err(AuthAPIError::InvalidCredentials) => outcome::INVALID_INPUT,
+        Err(AuthAPIError::IncorrectCredentials) => outcome::INCORRECT_CODE,
+        Err(_) => outcome::ERROR,
+    };
+    state.metrics.record_two_fa_verification(result_label);
+    audit
+        .record(
+            &state.audit_log,
+            AuditEventKind::TwoFaVerification,
+            Some(&actor),
+            result_label,
+        )
+        .await;
+    if result_label == outcome::SUCCESS {
+        if let Ok(email) = Email::parse(actor.clone()) {
+            alert_if_new_device(&state, &audit, &email).await;
+        }
+    }
+    (jar, result)
+}
+
+async fn verify_2fa(
+    state: &AppState,
+    jar: CookieJar,
+    request: Verify2FARequest,
+) -> (
+    CookieJar,
+    Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
+) {
     // Update this to a custom message!
     let email = if let Ok(val) = Email::parse(request.email) {
         val
@@ -45,7 +84,7 @@ pub async fn verify_2fa_handler(
         return (jar, Err(AuthAPIError::InvalidCredentials));
     };
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+    let two_fa_code_store = &state.two_fa_code_store;
 
     let entry = if let Ok(val) = two_fa_code_store.get_code(&email).await {
         val
@@ -54,17 +93,13 @@ pub async fn verify_2fa_handler(
     };
 
     if entry == (id, code) {
-        let _ = if let Ok(val) = two_fa_code_store.remove_code(&email).await {
-            val
-        } else {
-            return (jar, Err(AuthAPIError::InvalidCredentials));
-        };
-
-        let auth_cookie = generate_auth_cookie(&email);
+        if let Err(e) = two_fa_code_store.remove_code(&email).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
 
-        let auth_cookie = match auth_cookie {
+        let auth_cookie = match issue_auth_cookie(state, &email, None).await {
             Ok(auth_cookie) => auth_cookie,
-            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+            Err(e) => return (jar, Err(e)),
         };
 
         let updated_jar = jar.add(auth_cookie);
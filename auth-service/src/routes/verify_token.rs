@@ -5,23 +5,66 @@ use axum::Json;
 use serde::{Deserialize, Serialize};
 
 use crate::app_state::AppState;
+use crate::domain::data_stores::audit_log::AuditEventKind;
 use crate::domain::error::AuthAPIError;
+use crate::utils::audit::AuditContext;
 use crate::utils::auth::validate_token;
 #[derive(Deserialize, Debug)]
 pub struct VerifyTokenRequest {
     pub token: String,
 }
 
+// Who the token belongs to and what they may do, so downstream services
+// can authorize requests
 #[derive(Eq, PartialEq, Debug, Serialize, Deserialize)]
-pub struct VerifyTokenResponse {}
+pub struct VerifyTokenResponse {
+    pub email: String,
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+    // The organization the user acts for and their role in it, if they
+    // belong to any
+    #[serde(default)]
+    pub org_id: Option<String>,
+    #[serde(default)]
+    pub org_role: Option<String>,
+}
 
 #[tracing::instrument(name = "Verify Token", skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
+    audit: AuditContext,
     Json(request): Json<VerifyTokenRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    match validate_token(&request.token, &state.banned_token_store).await {
-        Ok(_) => Ok(StatusCode::OK.into_response()),
-        Err(_) => Err(AuthAPIError::InvalidToken),
+    let validation = validate_token(
+        &request.token,
+        &state.banned_token_store,
+        &state.settings.auth,
+    )
+    .await;
+    state.metrics.record_token_validation(&validation);
+
+    match validation {
+        Ok(claims) => Ok((
+            StatusCode::OK,
+            Json(VerifyTokenResponse {
+                email: claims.sub,
+                roles: claims.roles,
+                permissions: claims.permissions,
+                org_id: claims.org_id,
+                org_role: claims.org_role,
+            }),
+        )
+            .into_response()),
+        Err(e) => {
+            audit
+                .record(
+                    &state.audit_log,
+                    AuditEventKind::TokenRejected,
+                    None,
+                    e.as_str(),
+                )
+                .await;
+            Err(AuthAPIError::InvalidToken)
+        }
     }
 }
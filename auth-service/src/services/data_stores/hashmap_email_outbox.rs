@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+
+use crate::domain::data_stores::email_outbox::{EmailOutbox, EmailOutboxError, OutboxEmail};
+use crate::domain::{Email, EmailMessage};
+
+struct Entry {
+    email: OutboxEmail,
+    next_attempt_at: DateTime<Utc>,
+    dead_lettered_at: Option<DateTime<Utc>>,
+}
+
+// Keeps the outbox in memory, so queued emails are lost on restart
+#[derive(Default)]
+pub struct HashMapEmailOutbox {
+    entries: RwLock<HashMap<i64, Entry>>,
+    last_id: AtomicI64,
+}
+
+#[async_trait::async_trait]
+impl EmailOutbox for HashMapEmailOutbox {
+    async fn enqueue(
+        &self,
+        recipient: Email,
+        message: EmailMessage,
+    ) -> Result<(), EmailOutboxError> {
+        let id = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.entries.write().await.insert(
+            id,
+            Entry {
+                email: OutboxEmail {
+                    id,
+                    recipient,
+                    message,
+                    attempts: 0,
+                    last_error: None,
+                },
+                next_attempt_at: Utc::now(),
+                dead_lettered_at: None,
+            },
+        );
+        Ok(())
+    }
+
+    async fn claim_due(
+        &self,
+        limit: usize,
+        lease: Duration,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxError> {
+        let now = Utc::now();
+        let mut entries = self.entries.write().await;
+        let mut due: Vec<&mut Entry> = entries
+            .values_mut()
+            .filter(|entry| entry.dead_lettered_at.is_none() && entry.next_attempt_at <= now)
+            .collect();
+        due.sort_by_key(|entry| (entry.next_attempt_at, entry.email.id));
+
+        Ok(due
+            .into_iter()
+            .take(limit)
+            .map(|entry| {
+                entry.email.attempts += 1;
+                entry.next_attempt_at = now + lease;
+                entry.email.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_sent(&self, id: i64) -> Result<(), EmailOutboxError> {
+        self.entries
+            .write()
+            .await
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(EmailOutboxError::EmailNotFound)
+    }
+
+    async fn retry_later(
+        &self,
+        id: i64,
+        delay: Duration,
+        error: &str,
+    ) -> Result<(), EmailOutboxError> {
+        let mut entries = self.entries.write().await;
+        let entry = entries
+            .get_mut(&id)
+            .ok_or(EmailOutboxError::EmailNotFound)?;
+        entry.next_attempt_at = Utc::now() + delay;
+        entry.email.last_error = Some(error.to_owned());
+        Ok(())
+    }
+
+    async fn dead_letter(&self, id: i64, error: &str) -> Result<(), EmailOutboxError> {
+        let mut entries = self.entries.write().await;
+        let entry = entries
+            .get_mut(&id)
+            .ok_or(EmailOutboxError::EmailNotFound)?;
+        entry.dead_lettered_at = Some(Utc::now());
+        entry.email.last_error = Some(error.to_owned());
+        Ok(())
+    }
+
+    async fn dead_letters(&self) -> Result<Vec<OutboxEmail>, EmailOutboxError> {
+        let mut dead_letters: Vec<OutboxEmail> = self
+            .entries
+            .read()
+            .await
+            .values()
+            .filter(|entry| entry.dead_lettered_at.is_some())
+            .map(|entry| entry.email.clone())
+            .collect();
+        dead_letters.sort_by_key(|email| email.id);
+        Ok(dead_letters)
+    }
+
+    async fn purge_dead_letters(&self, retention: Duration) -> Result<u64, EmailOutboxError> {
+        let cutoff = Utc::now() - retention;
+        let mut entries = self.entries.write().await;
+        let before = entries.len();
+        entries.retain(|_, entry| {
+            entry
+                .dead_lettered_at
+                .is_none_or(|dead_lettered_at| dead_lettered_at > cutoff)
+        });
+        Ok((before - entries.len()) as u64)
+    }
+
+    async fn pending_count(&self) -> Result<u64, EmailOutboxError> {
+        Ok(self
+            .entries
+            .read()
+            .await
+            .values()
+            .filter(|entry| entry.dead_lettered_at.is_none())
+            .count() as u64)
+    }
+}
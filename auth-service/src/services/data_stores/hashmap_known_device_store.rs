@@ -0,0 +1,34 @@
+use std::collections::{HashMap, HashSet};
+
+use tokio::sync::RwLock;
+
+use crate::domain::data_stores::known_device_store::{
+    Device, DeviceStatus, KnownDeviceStore, KnownDeviceStoreError,
+};
+use crate::domain::Email;
+
+#[derive(Default)]
+pub struct HashMapKnownDeviceStore {
+    devices: RwLock<HashMap<Email, HashSet<Device>>>,
+}
+
+#[async_trait::async_trait]
+impl KnownDeviceStore for HashMapKnownDeviceStore {
+    async fn remember_device(
+        &self,
+        email: &Email,
+        device: &Device,
+    ) -> Result<DeviceStatus, KnownDeviceStoreError> {
+        let mut devices = self.devices.write().await;
+        let known = devices.entry(email.clone()).or_default();
+        let status = if known.is_empty() {
+            DeviceStatus::First
+        } else if known.contains(device) {
+            DeviceStatus::Known
+        } else {
+            DeviceStatus::New
+        };
+        known.insert(device.clone());
+        Ok(status)
+    }
+}
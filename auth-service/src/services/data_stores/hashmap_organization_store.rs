@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::domain::data_stores::organization_store::{OrganizationStore, OrganizationStoreError};
+use crate::domain::{Email, Invitation, Member, Membership, OrgRole, Organization, OrganizationId};
+
+#[derive(Default)]
+struct Organizations {
+    organizations: HashMap<OrganizationId, Organization>,
+    // Members of each organization, in the order they joined
+    members: HashMap<OrganizationId, Vec<Member>>,
+    invitations: HashMap<Uuid, Invitation>,
+}
+
+impl Organizations {
+    fn membership(&self, id: &OrganizationId, member: &Member) -> Membership {
+        Membership {
+            organization_id: *id,
+            organization_name: self.organizations[id].name.clone(),
+            role: member.role,
+            joined_at: member.joined_at,
+        }
+    }
+
+    // Unexpired invitations only
+    fn invitation(&self, id: &Uuid) -> Result<&Invitation, OrganizationStoreError> {
+        self.invitations
+            .get(id)
+            .filter(|invitation| invitation.expires_at > Utc::now())
+            .ok_or(OrganizationStoreError::InvitationNotFound)
+    }
+}
+
+#[derive(Default)]
+pub struct HashMapOrganizationStore {
+    inner: RwLock<Organizations>,
+}
+
+#[async_trait::async_trait]
+impl OrganizationStore for HashMapOrganizationStore {
+    async fn create_organization(
+        &self,
+        name: &str,
+        owner: &Email,
+    ) -> Result<Organization, OrganizationStoreError> {
+        let organization = Organization {
+            id: OrganizationId::new(),
+            name: name.to_owned(),
+            created_at: Utc::now(),
+        };
+        let owner = Member {
+            email: owner.as_ref().expose_secret().to_owned(),
+            role: OrgRole::Owner,
+            joined_at: organization.created_at,
+        };
+
+        let mut inner = self.inner.write().await;
+        inner
+            .organizations
+            .insert(organization.id, organization.clone());
+        inner.members.insert(organization.id, vec![owner]);
+        Ok(organization)
+    }
+
+    async fn get_organization(
+        &self,
+        id: &OrganizationId,
+    ) -> Result<Organization, OrganizationStoreError> {
+        self.inner
+            .read()
+            .await
+            .organizations
+            .get(id)
+            .cloned()
+            .ok_or(OrganizationStoreError::OrganizationNotFound)
+    }
+
+    async fn memberships(&self, email: &Email) -> Result<Vec<Membership>, OrganizationStoreError> {
+        let email = email.as_ref().expose_secret();
+        let inner = self.inner.read().await;
+        let mut memberships: Vec<Membership> = inner
+            .members
+            .iter()
+            .flat_map(|(id, members)| {
+                members
+                    .iter()
+                    .filter(|member| &member.email == email)
+                    .map(|member| inner.membership(id, member))
+            })
+            .collect();
+        memberships.sort_by_key(|membership| (membership.joined_at, membership.organization_id));
+        Ok(memberships)
+    }
+
+    async fn role(
+        &self,
+        id: &OrganizationId,
+        email: &Email,
+    ) -> Result<OrgRole, OrganizationStoreError> {
+        let email = email.as_ref().expose_secret();
+        self.inner
+            .read()
+            .await
+            .members
+            .get(id)
+            .and_then(|members| members.iter().find(|member| &member.email == email))
+            .map(|member| member.role)
+            .ok_or(OrganizationStoreError::NotMember)
+    }
+
+    async fn members(&self, id: &OrganizationId) -> Result<Vec<Member>, OrganizationStoreError> {
+        self.inner
+            .read()
+            .await
+            .members
+            .get(id)
+            .cloned()
+            .ok_or(OrganizationStoreError::OrganizationNotFound)
+    }
+
+    async fn remove_member(
+        &self,
+        id: &OrganizationId,
+        email: &Email,
+    ) -> Result<(), OrganizationStoreError> {
+        let email = email.as_ref().expose_secret();
+        let mut inner = self.inner.write().await;
+        let members = inner
+            .members
+            .get_mut(id)
+            .ok_or(OrganizationStoreError::NotMember)?;
+        let position = members
+            .iter()
+            .position(|member| &member.email == email)
+            .ok_or(OrganizationStoreError::NotMember)?;
+        let owners = members
+            .iter()
+            .filter(|member| member.role == OrgRole::Owner)
+            .count();
+        if members[position].role == OrgRole::Owner && owners == 1 {
+            return Err(OrganizationStoreError::LastOwner);
+        }
+        members.remove(position);
+        Ok(())
+    }
+
+    async fn add_invitation(&self, invitation: Invitation) -> Result<(), OrganizationStoreError> {
+        let mut inner = self.inner.write().await;
+        if !inner
+            .organizations
+            .contains_key(&invitation.organization_id)
+        {
+            return Err(OrganizationStoreError::OrganizationNotFound);
+        }
+        inner.invitations.insert(invitation.id, invitation);
+        Ok(())
+    }
+
+    async fn invitations(
+        &self,
+        id: &OrganizationId,
+    ) -> Result<Vec<Invitation>, OrganizationStoreError> {
+        let now = Utc::now();
+        let mut invitations: Vec<Invitation> = self
+            .inner
+            .read()
+            .await
+            .invitations
+            .values()
+            .filter(|invitation| &invitation.organization_id == id && invitation.expires_at > now)
+            .cloned()
+            .collect();
+        invitations.sort_by_key(|invitation| (invitation.expires_at, invitation.id));
+        Ok(invitations)
+    }
+
+    async fn get_invitation(&self, id: &Uuid) -> Result<Invitation, OrganizationStoreError> {
+        self.inner.read().await.invitation(id).cloned()
+    }
+
+    async fn remove_invitation(&self, id: &Uuid) -> Result<(), OrganizationStoreError> {
+        self.inner
+            .write()
+            .await
+            .invitations
+            .remove(id)
+            .map(|_| ())
+            .ok_or(OrganizationStoreError::InvitationNotFound)
+    }
+
+    async fn accept_invitation(
+        &self,
+        id: &Uuid,
+        email: &Email,
+    ) -> Result<Membership, OrganizationStoreError> {
+        let email = email.as_ref().expose_secret();
+        let mut inner = self.inner.write().await;
+        let invitation = inner.invitation(id)?.clone();
+        inner.invitations.remove(id);
+
+        let organization_id = invitation.organization_id;
+        let members = inner.members.entry(organization_id).or_default();
+        let member = match members.iter().find(|member| &member.email == email) {
+            Some(member) => member.clone(),
+            None => {
+                let member = Member {
+                    email: email.to_owned(),
+                    role: invitation.role,
+                    joined_at: Utc::now(),
+                };
+                members.push(member.clone());
+                member
+            }
+        };
+        Ok(inner.membership(&organization_id, &member))
+    }
+}
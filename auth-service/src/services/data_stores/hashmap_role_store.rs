@@ -0,0 +1,84 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use tokio::sync::RwLock;
+
+use crate::domain::data_stores::role_store::{RoleDefinition, RoleStore, RoleStoreError};
+use crate::domain::{Email, Grants, BUILT_IN_ROLES};
+
+// Only knows the built-in roles
+pub struct HashMapRoleStore {
+    roles: BTreeMap<String, BTreeSet<String>>,
+    granted: RwLock<HashMap<Email, BTreeSet<String>>>,
+}
+
+impl Default for HashMapRoleStore {
+    fn default() -> Self {
+        let roles = BUILT_IN_ROLES
+            .iter()
+            .map(|(role, permissions)| {
+                let permissions = permissions.iter().map(|p| p.to_string()).collect();
+                (role.to_string(), permissions)
+            })
+            .collect();
+        Self {
+            roles,
+            granted: RwLock::default(),
+        }
+    }
+}
+
+impl HashMapRoleStore {
+    fn ensure_known(&self, role: &str) -> Result<(), RoleStoreError> {
+        if self.roles.contains_key(role) {
+            Ok(())
+        } else {
+            Err(RoleStoreError::UnknownRole)
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleStore for HashMapRoleStore {
+    async fn roles(&self) -> Result<Vec<RoleDefinition>, RoleStoreError> {
+        Ok(self
+            .roles
+            .iter()
+            .map(|(name, permissions)| RoleDefinition {
+                name: name.clone(),
+                permissions: permissions.iter().cloned().collect(),
+            })
+            .collect())
+    }
+
+    async fn grant_role(&self, email: &Email, role: &str) -> Result<(), RoleStoreError> {
+        self.ensure_known(role)?;
+        self.granted
+            .write()
+            .await
+            .entry(email.clone())
+            .or_default()
+            .insert(role.to_owned());
+        Ok(())
+    }
+
+    async fn revoke_role(&self, email: &Email, role: &str) -> Result<(), RoleStoreError> {
+        self.ensure_known(role)?;
+        if let Some(roles) = self.granted.write().await.get_mut(email) {
+            roles.remove(role);
+        }
+        Ok(())
+    }
+
+    async fn grants(&self, email: &Email) -> Result<Grants, RoleStoreError> {
+        let granted = self.granted.read().await;
+        let Some(roles) = granted.get(email) else {
+            return Ok(Grants::default());
+        };
+        let permissions: BTreeSet<&String> =
+            roles.iter().flat_map(|role| &self.roles[role]).collect();
+        Ok(Grants {
+            roles: roles.iter().cloned().collect(),
+            permissions: permissions.into_iter().cloned().collect(),
+        })
+    }
+}
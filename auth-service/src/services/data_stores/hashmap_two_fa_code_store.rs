@@ -1,31 +1,56 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use tokio::sync::RwLock;
 
 use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    data_stores::{
+        LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError, TWO_FA_CODE_TTL_SECONDS,
+    },
     email::Email,
 };
 
-#[derive(Default)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: RwLock<HashMap<Email, (LoginAttemptId, TwoFACode, Instant)>>,
+    ttl: Duration,
+}
+
+impl Default for HashmapTwoFACodeStore {
+    fn default() -> Self {
+        Self {
+            codes: RwLock::new(HashMap::new()),
+            ttl: Duration::from_secs(TWO_FA_CODE_TTL_SECONDS),
+        }
+    }
+}
+
+impl HashmapTwoFACodeStore {
+    // Overrides how long a 2FA code stays valid.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
 }
 
 #[async_trait]
 impl TwoFACodeStore for HashmapTwoFACodeStore {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        self.codes.insert(email, (login_attempt_id, code));
+        let now = Instant::now();
+        // Drop anything that has already expired so the map doesn't grow forever
+        let mut codes = self.codes.write().await;
+        codes.retain(|_, (_, _, expires_at)| *expires_at > now);
+        codes.insert(email, (login_attempt_id, code, now + self.ttl));
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        self.codes.remove(email);
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        self.codes.write().await.remove(email);
         Ok(())
     }
 
@@ -33,24 +58,31 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        match self.codes.get(email) {
-            Some(code) => Ok(code.clone()),
-            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        match self.codes.read().await.get(email) {
+            Some((id, code, expires_at)) if *expires_at > Instant::now() => {
+                Ok((id.clone(), code.clone()))
+            }
+            _ => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::domain::{data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore}, email::Email};
+    use crate::domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore},
+        email::Email,
+    };
 
     use super::HashmapTwoFACodeStore;
 
     #[tokio::test]
     async fn should_add_code() {
         let email = Email::parse("ken@cttm.io".to_string()).expect("email should be parsed");
-        let mut store = HashmapTwoFACodeStore::default();
-        let res = store.add_code(email, LoginAttemptId::default(), TwoFACode::default()).await;
+        let store = HashmapTwoFACodeStore::default();
+        let res = store
+            .add_code(email, LoginAttemptId::default(), TwoFACode::default())
+            .await;
         assert_eq!(res, Ok(()));
     }
 }
@@ -1,27 +1,50 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use secrecy::ExposeSecret;
+use tokio::sync::RwLock;
+
+use crate::domain::data_stores::{UserPage, UserQuery, UserStore, UserStoreError, UserSummary};
 use crate::domain::email::Email;
+use crate::domain::email_status::EmailStatus;
 use crate::domain::password::Password;
 use crate::domain::user::User;
-use crate::domain::data_stores::{UserStore, UserStoreError};
 
 // TODO: Create a new struct called `HashmapUserStore` containing a `users` field
 // which stores a `HashMap`` of email `String`s mapped to `User` objects.
 // Derive the `Default` trait for `HashmapUserStore`.
 #[derive(Default)]
 pub struct HashMapUserStore {
-    users: HashMap<Email, User>,
+    users: RwLock<HashMap<Email, User>>,
+    // Accounts locked by `require_password_reset`
+    reset_required: RwLock<HashSet<Email>>,
+    // Accounts locked by `set_disabled`
+    disabled: RwLock<HashSet<Email>>,
+}
+
+impl HashMapUserStore {
+    async fn summary(&self, user: &User) -> UserSummary {
+        UserSummary {
+            email: user.email.as_ref().expose_secret().to_owned(),
+            requires_2fa: user.requires_2fa,
+            locale: user.locale,
+            email_status: user.email_status,
+            two_fa_channel: user.two_fa_channel,
+            disabled: self.disabled.read().await.contains(&user.email),
+            password_reset_required: self.reset_required.read().await.contains(&user.email),
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl UserStore for HashMapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
         // Return `UserStoreError::UserAlreadyExists` if the user already exists,
         // otherwise insert the user into the hashmap and return `Ok(())`.
-        if self.users.contains_key(&user.email) {
+        let mut users = self.users.write().await;
+        if users.contains_key(&user.email) {
             Err(UserStoreError::UserAlreadyExists)
         } else {
-            self.users.insert(user.email.clone(), user);
+            users.insert(user.email.clone(), user);
             Ok(())
         }
     }
@@ -33,7 +56,7 @@ impl UserStore for HashMapUserStore {
     // Return `UserStoreError::UserNotFound` if the user can not be found.
 
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        match self.users.get(email) {
+        match self.users.read().await.get(email) {
             Some(user) => Ok(user.clone()),
             _ => Err(UserStoreError::UserNotFound),
         }
@@ -45,17 +68,121 @@ impl UserStore for HashMapUserStore {
     // Return `UserStoreError::UserNotFound` if the user can not be found.
     // Return `UserStoreError::InvalidCredentials` if the password is incorrect.
 
-    async fn validate_user(&self, email: &Email, password: &Password) -> Result<(), UserStoreError> {
-        match self.users.get(email) {
+    async fn validate_user(
+        &self,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError> {
+        match self.users.read().await.get(email) {
             Some(user) => {
-                if &user.password == password {
-                    Ok(())
-                } else {
+                if &user.password != password {
                     Err(UserStoreError::InvalidCredentials)
-                }},
-            _ => Err(UserStoreError::UserNotFound)
+                } else if self.disabled.read().await.contains(email) {
+                    Err(UserStoreError::AccountDisabled)
+                } else if self.reset_required.read().await.contains(email) {
+                    Err(UserStoreError::PasswordResetRequired)
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Err(UserStoreError::UserNotFound),
         }
     }
+
+    async fn require_password_reset(&self, email: &Email) -> Result<(), UserStoreError> {
+        if !self.users.read().await.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        self.reset_required.write().await.insert(email.clone());
+        Ok(())
+    }
+
+    async fn reset_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        if !self.reset_required.write().await.remove(email) {
+            return Err(UserStoreError::PasswordResetNotRequired);
+        }
+        user.password = password;
+        Ok(())
+    }
+
+    async fn set_email_status(
+        &self,
+        email: &Email,
+        status: EmailStatus,
+    ) -> Result<(), UserStoreError> {
+        match self.users.write().await.get_mut(email) {
+            Some(user) => user.email_status = status,
+            None => return Err(UserStoreError::UserNotFound),
+        }
+        Ok(())
+    }
+
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let search = query.search.as_deref().unwrap_or_default().to_lowercase();
+        let users = self.users.read().await;
+        let mut matching: Vec<&User> = users
+            .values()
+            .filter(|user| {
+                user.email
+                    .as_ref()
+                    .expose_secret()
+                    .to_lowercase()
+                    .contains(&search)
+            })
+            .collect();
+        matching.sort_by(|a, b| {
+            a.email
+                .as_ref()
+                .expose_secret()
+                .cmp(b.email.as_ref().expose_secret())
+        });
+
+        let mut page = Vec::new();
+        for user in matching.iter().skip(query.offset).take(query.limit) {
+            page.push(self.summary(user).await);
+        }
+        Ok(UserPage {
+            users: page,
+            total: matching.len(),
+        })
+    }
+
+    async fn user_summary(&self, email: &Email) -> Result<UserSummary, UserStoreError> {
+        match self.users.read().await.get(email) {
+            Some(user) => Ok(self.summary(user).await),
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_disabled(&self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        if !self.users.read().await.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        if disabled {
+            self.disabled.write().await.insert(email.clone());
+        } else {
+            self.disabled.write().await.remove(email);
+        }
+        Ok(())
+    }
+
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        match self.users.write().await.get_mut(email) {
+            Some(user) => user.requires_2fa = requires_2fa,
+            None => return Err(UserStoreError::UserNotFound),
+        }
+        Ok(())
+    }
 }
 
 // TODO: Add unit tests for your `HashmapUserStore` implementation
@@ -63,11 +190,13 @@ impl UserStore for HashMapUserStore {
 mod tests {
     use secrecy::Secret;
 
+    use crate::domain::{Locale, TwoFAChannel};
+
     use super::*;
 
     #[tokio::test]
     async fn test_add_user() {
-        let mut test_store = HashMapUserStore::default();
+        let test_store = HashMapUserStore::default();
 
         let email = Email::parse("email@yahoo.net".to_string()).unwrap();
         let secret = Secret::new("passwordistaco".to_string());
@@ -75,6 +204,10 @@ mod tests {
             email,
             password: Password::parse(secret).unwrap(),
             requires_2fa: false,
+            locale: Locale::default(),
+            email_status: EmailStatus::default(),
+            phone_number: None,
+            two_fa_channel: TwoFAChannel::default(),
         };
 
         let res = test_store.add_user(test_user).await;
@@ -84,13 +217,17 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_user() {
-        let mut test_store = HashMapUserStore::default();
+        let test_store = HashMapUserStore::default();
         let email = Email::parse("email@yahoo.net".to_string()).unwrap();
         let secret = Secret::new("passwordistaco".to_string());
         let test_user = User {
             email: email.clone(),
             password: Password::parse(secret).unwrap(),
             requires_2fa: false,
+            locale: Locale::default(),
+            email_status: EmailStatus::default(),
+            phone_number: None,
+            two_fa_channel: TwoFAChannel::default(),
         };
 
         let _ = test_store.add_user(test_user.clone()).await;
@@ -102,18 +239,24 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_user() {
-        let mut test_store = HashMapUserStore::default();
+        let test_store = HashMapUserStore::default();
         let email = Email::parse("email@yahoo.net".to_string()).unwrap();
         let secret = Secret::new("passwordistaco".to_string());
         let test_user = User {
             email,
             password: Password::parse(secret).unwrap(),
             requires_2fa: false,
+            locale: Locale::default(),
+            email_status: EmailStatus::default(),
+            phone_number: None,
+            two_fa_channel: TwoFAChannel::default(),
         };
 
         let _ = test_store.add_user(test_user.clone()).await;
 
-        let validate_res = test_store.validate_user(&test_user.email, &test_user.password).await;
+        let validate_res = test_store
+            .validate_user(&test_user.email, &test_user.password)
+            .await;
 
         assert_eq!(validate_res, Ok(()));
     }
@@ -1,26 +1,81 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
 use secrecy::{ExposeSecret, Secret};
+use tokio::sync::RwLock;
 
 use crate::domain::data_stores::banned_token_store::{BannedTokenStore, BannedTokenStoreError};
+use crate::domain::Email;
+use crate::utils::auth::TOKEN_TTL_SECONDS;
 
-#[derive(Default)]
 pub struct HashsetBannedTokenStore {
-    store: HashSet<String>,
+    // Each banned token is kept alongside the instant it stops mattering,
+    // mirroring the expiry the Redis store gets for free.
+    store: RwLock<HashMap<String, Instant>>,
+    // When each user's sessions were revoked, with the same expiry
+    revoked_sessions: RwLock<HashMap<Email, (DateTime<Utc>, Instant)>>,
+    ttl: Duration,
+}
+
+impl Default for HashsetBannedTokenStore {
+    fn default() -> Self {
+        Self {
+            store: RwLock::new(HashMap::new()),
+            revoked_sessions: RwLock::new(HashMap::new()),
+            ttl: Duration::from_secs(TOKEN_TTL_SECONDS as u64),
+        }
+    }
+}
+
+impl HashsetBannedTokenStore {
+    // Overrides how long a banned token is remembered.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
-        self.store.insert(token.expose_secret().to_string());
+    async fn add_token(&self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
+        let now = Instant::now();
+        // Drop anything that has already expired so the map doesn't grow forever
+        let mut store = self.store.write().await;
+        store.retain(|_, expires_at| *expires_at > now);
+        store.insert(token.expose_secret().to_string(), now + self.ttl);
         Ok(())
     }
 
     async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        if self.store.contains(token.expose_secret()) {
-            Ok(true)
-        } else {
-            Ok(false)
+        match self.store.read().await.get(token.expose_secret()) {
+            Some(expires_at) => Ok(*expires_at > Instant::now()),
+            None => Ok(false),
         }
     }
+
+    async fn revoke_sessions(
+        &self,
+        email: &Email,
+        revoked_at: DateTime<Utc>,
+    ) -> Result<(), BannedTokenStoreError> {
+        let now = Instant::now();
+        let mut revoked_sessions = self.revoked_sessions.write().await;
+        revoked_sessions.retain(|_, (_, expires_at)| *expires_at > now);
+        revoked_sessions.insert(email.clone(), (revoked_at, now + self.ttl));
+        Ok(())
+    }
+
+    async fn sessions_revoked_at(
+        &self,
+        email: &Email,
+    ) -> Result<Option<DateTime<Utc>>, BannedTokenStoreError> {
+        Ok(self
+            .revoked_sessions
+            .read()
+            .await
+            .get(email)
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(revoked_at, _)| *revoked_at))
+    }
 }
@@ -0,0 +1,729 @@
+// Decorators that time every operation of the store they wrap and record it
+// in the store latency histogram. They work on the trait objects, so any
+// backend (including custom ones) is covered without changes of its own.
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::app_state::{
+    AuditLogType, BannedTokenStoreType, EmailOutboxType, KnownDeviceStoreType,
+    OrganizationStoreType, RoleStoreType, TwoFACodeStoreType, UserStoreType,
+};
+use crate::domain::data_stores::audit_log::{
+    AuditEvent, AuditEventFilter, AuditLog, AuditLogError,
+};
+use crate::domain::data_stores::banned_token_store::{BannedTokenStore, BannedTokenStoreError};
+use crate::domain::data_stores::email_outbox::{EmailOutbox, EmailOutboxError, OutboxEmail};
+use crate::domain::data_stores::known_device_store::{
+    Device, DeviceStatus, KnownDeviceStore, KnownDeviceStoreError,
+};
+use crate::domain::data_stores::organization_store::{OrganizationStore, OrganizationStoreError};
+use crate::domain::data_stores::role_store::{RoleDefinition, RoleStore, RoleStoreError};
+use crate::domain::data_stores::{
+    LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError, UserPage, UserQuery, UserStore,
+    UserStoreError, UserSummary,
+};
+use crate::domain::{
+    Email, EmailMessage, EmailStatus, Grants, Invitation, Member, Membership, OrgRole,
+    Organization, OrganizationId, Password, User,
+};
+use crate::utils::metrics::Metrics;
+
+async fn timed<T, E>(
+    metrics: &Metrics,
+    store: &str,
+    operation: &str,
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let started = Instant::now();
+    let result = future.await;
+    metrics.record_store_operation(store, operation, result.is_ok(), started.elapsed());
+    result
+}
+
+const USER_STORE: &str = "user_store";
+const BANNED_TOKEN_STORE: &str = "banned_token_store";
+const TWO_FA_CODE_STORE: &str = "two_fa_code_store";
+const AUDIT_LOG: &str = "audit_log";
+const KNOWN_DEVICE_STORE: &str = "known_device_store";
+const EMAIL_OUTBOX: &str = "email_outbox";
+const ROLE_STORE: &str = "role_store";
+const ORGANIZATION_STORE: &str = "organization_store";
+
+pub struct InstrumentedUserStore {
+    inner: UserStoreType,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedUserStore {
+    pub fn new(inner: UserStoreType, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserStore for InstrumentedUserStore {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        timed(
+            &self.metrics,
+            USER_STORE,
+            "add_user",
+            self.inner.add_user(user),
+        )
+        .await
+    }
+
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        timed(
+            &self.metrics,
+            USER_STORE,
+            "get_user",
+            self.inner.get_user(email),
+        )
+        .await
+    }
+
+    async fn validate_user(
+        &self,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError> {
+        timed(
+            &self.metrics,
+            USER_STORE,
+            "validate_user",
+            self.inner.validate_user(email, password),
+        )
+        .await
+    }
+
+    async fn require_password_reset(&self, email: &Email) -> Result<(), UserStoreError> {
+        timed(
+            &self.metrics,
+            USER_STORE,
+            "require_password_reset",
+            self.inner.require_password_reset(email),
+        )
+        .await
+    }
+
+    async fn reset_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        timed(
+            &self.metrics,
+            USER_STORE,
+            "reset_password",
+            self.inner.reset_password(email, password),
+        )
+        .await
+    }
+
+    async fn set_email_status(
+        &self,
+        email: &Email,
+        status: EmailStatus,
+    ) -> Result<(), UserStoreError> {
+        timed(
+            &self.metrics,
+            USER_STORE,
+            "set_email_status",
+            self.inner.set_email_status(email, status),
+        )
+        .await
+    }
+
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        timed(
+            &self.metrics,
+            USER_STORE,
+            "list_users",
+            self.inner.list_users(query),
+        )
+        .await
+    }
+
+    async fn user_summary(&self, email: &Email) -> Result<UserSummary, UserStoreError> {
+        timed(
+            &self.metrics,
+            USER_STORE,
+            "user_summary",
+            self.inner.user_summary(email),
+        )
+        .await
+    }
+
+    async fn set_disabled(&self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        timed(
+            &self.metrics,
+            USER_STORE,
+            "set_disabled",
+            self.inner.set_disabled(email, disabled),
+        )
+        .await
+    }
+
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        timed(
+            &self.metrics,
+            USER_STORE,
+            "set_requires_2fa",
+            self.inner.set_requires_2fa(email, requires_2fa),
+        )
+        .await
+    }
+
+    async fn health_check(&self) -> Result<(), UserStoreError> {
+        timed(
+            &self.metrics,
+            USER_STORE,
+            "health_check",
+            self.inner.health_check(),
+        )
+        .await
+    }
+}
+
+pub struct InstrumentedBannedTokenStore {
+    inner: BannedTokenStoreType,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedBannedTokenStore {
+    pub fn new(inner: BannedTokenStoreType, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for InstrumentedBannedTokenStore {
+    async fn add_token(&self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
+        timed(
+            &self.metrics,
+            BANNED_TOKEN_STORE,
+            "add_token",
+            self.inner.add_token(token),
+        )
+        .await
+    }
+
+    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
+        timed(
+            &self.metrics,
+            BANNED_TOKEN_STORE,
+            "contains_token",
+            self.inner.contains_token(token),
+        )
+        .await
+    }
+
+    async fn revoke_sessions(
+        &self,
+        email: &Email,
+        revoked_at: DateTime<Utc>,
+    ) -> Result<(), BannedTokenStoreError> {
+        timed(
+            &self.metrics,
+            BANNED_TOKEN_STORE,
+            "revoke_sessions",
+            self.inner.revoke_sessions(email, revoked_at),
+        )
+        .await
+    }
+
+    async fn sessions_revoked_at(
+        &self,
+        email: &Email,
+    ) -> Result<Option<DateTime<Utc>>, BannedTokenStoreError> {
+        timed(
+            &self.metrics,
+            BANNED_TOKEN_STORE,
+            "sessions_revoked_at",
+            self.inner.sessions_revoked_at(email),
+        )
+        .await
+    }
+
+    async fn health_check(&self) -> Result<(), BannedTokenStoreError> {
+        timed(
+            &self.metrics,
+            BANNED_TOKEN_STORE,
+            "health_check",
+            self.inner.health_check(),
+        )
+        .await
+    }
+}
+
+pub struct InstrumentedTwoFACodeStore {
+    inner: TwoFACodeStoreType,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedTwoFACodeStore {
+    pub fn new(inner: TwoFACodeStoreType, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for InstrumentedTwoFACodeStore {
+    async fn add_code(
+        &self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        timed(
+            &self.metrics,
+            TWO_FA_CODE_STORE,
+            "add_code",
+            self.inner.add_code(email, login_attempt_id, code),
+        )
+        .await
+    }
+
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        timed(
+            &self.metrics,
+            TWO_FA_CODE_STORE,
+            "remove_code",
+            self.inner.remove_code(email),
+        )
+        .await
+    }
+
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        timed(
+            &self.metrics,
+            TWO_FA_CODE_STORE,
+            "get_code",
+            self.inner.get_code(email),
+        )
+        .await
+    }
+
+    async fn health_check(&self) -> Result<(), TwoFACodeStoreError> {
+        timed(
+            &self.metrics,
+            TWO_FA_CODE_STORE,
+            "health_check",
+            self.inner.health_check(),
+        )
+        .await
+    }
+}
+
+pub struct InstrumentedAuditLog {
+    inner: AuditLogType,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedAuditLog {
+    pub fn new(inner: AuditLogType, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLog for InstrumentedAuditLog {
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError> {
+        timed(&self.metrics, AUDIT_LOG, "record", self.inner.record(event)).await
+    }
+
+    async fn query(&self, filter: &AuditEventFilter) -> Result<Vec<AuditEvent>, AuditLogError> {
+        timed(&self.metrics, AUDIT_LOG, "query", self.inner.query(filter)).await
+    }
+
+    async fn health_check(&self) -> Result<(), AuditLogError> {
+        timed(
+            &self.metrics,
+            AUDIT_LOG,
+            "health_check",
+            self.inner.health_check(),
+        )
+        .await
+    }
+}
+
+pub struct InstrumentedKnownDeviceStore {
+    inner: KnownDeviceStoreType,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedKnownDeviceStore {
+    pub fn new(inner: KnownDeviceStoreType, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl KnownDeviceStore for InstrumentedKnownDeviceStore {
+    async fn remember_device(
+        &self,
+        email: &Email,
+        device: &Device,
+    ) -> Result<DeviceStatus, KnownDeviceStoreError> {
+        timed(
+            &self.metrics,
+            KNOWN_DEVICE_STORE,
+            "remember_device",
+            self.inner.remember_device(email, device),
+        )
+        .await
+    }
+
+    async fn health_check(&self) -> Result<(), KnownDeviceStoreError> {
+        timed(
+            &self.metrics,
+            KNOWN_DEVICE_STORE,
+            "health_check",
+            self.inner.health_check(),
+        )
+        .await
+    }
+}
+
+pub struct InstrumentedEmailOutbox {
+    inner: EmailOutboxType,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedEmailOutbox {
+    pub fn new(inner: EmailOutboxType, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutbox for InstrumentedEmailOutbox {
+    async fn enqueue(
+        &self,
+        recipient: Email,
+        message: EmailMessage,
+    ) -> Result<(), EmailOutboxError> {
+        timed(
+            &self.metrics,
+            EMAIL_OUTBOX,
+            "enqueue",
+            self.inner.enqueue(recipient, message),
+        )
+        .await
+    }
+
+    async fn claim_due(
+        &self,
+        limit: usize,
+        lease: Duration,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxError> {
+        timed(
+            &self.metrics,
+            EMAIL_OUTBOX,
+            "claim_due",
+            self.inner.claim_due(limit, lease),
+        )
+        .await
+    }
+
+    async fn mark_sent(&self, id: i64) -> Result<(), EmailOutboxError> {
+        timed(
+            &self.metrics,
+            EMAIL_OUTBOX,
+            "mark_sent",
+            self.inner.mark_sent(id),
+        )
+        .await
+    }
+
+    async fn retry_later(
+        &self,
+        id: i64,
+        delay: Duration,
+        error: &str,
+    ) -> Result<(), EmailOutboxError> {
+        timed(
+            &self.metrics,
+            EMAIL_OUTBOX,
+            "retry_later",
+            self.inner.retry_later(id, delay, error),
+        )
+        .await
+    }
+
+    async fn dead_letter(&self, id: i64, error: &str) -> Result<(), EmailOutboxError> {
+        timed(
+            &self.metrics,
+            EMAIL_OUTBOX,
+            "dead_letter",
+            self.inner.dead_letter(id, error),
+        )
+        .await
+    }
+
+    async fn dead_letters(&self) -> Result<Vec<OutboxEmail>, EmailOutboxError> {
+        timed(
+            &self.metrics,
+            EMAIL_OUTBOX,
+            "dead_letters",
+            self.inner.dead_letters(),
+        )
+        .await
+    }
+
+    async fn purge_dead_letters(&self, retention: Duration) -> Result<u64, EmailOutboxError> {
+        timed(
+            &self.metrics,
+            EMAIL_OUTBOX,
+            "purge_dead_letters",
+            self.inner.purge_dead_letters(retention),
+        )
+        .await
+    }
+
+    async fn pending_count(&self) -> Result<u64, EmailOutboxError> {
+        timed(
+            &self.metrics,
+            EMAIL_OUTBOX,
+            "pending_count",
+            self.inner.pending_count(),
+        )
+        .await
+    }
+
+    async fn health_check(&self) -> Result<(), EmailOutboxError> {
+        timed(
+            &self.metrics,
+            EMAIL_OUTBOX,
+            "health_check",
+            self.inner.health_check(),
+        )
+        .await
+    }
+}
+
+pub struct InstrumentedRoleStore {
+    inner: RoleStoreType,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedRoleStore {
+    pub fn new(inner: RoleStoreType, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleStore for InstrumentedRoleStore {
+    async fn roles(&self) -> Result<Vec<RoleDefinition>, RoleStoreError> {
+        timed(&self.metrics, ROLE_STORE, "roles", self.inner.roles()).await
+    }
+
+    async fn grant_role(&self, email: &Email, role: &str) -> Result<(), RoleStoreError> {
+        timed(
+            &self.metrics,
+            ROLE_STORE,
+            "grant_role",
+            self.inner.grant_role(email, role),
+        )
+        .await
+    }
+
+    async fn revoke_role(&self, email: &Email, role: &str) -> Result<(), RoleStoreError> {
+        timed(
+            &self.metrics,
+            ROLE_STORE,
+            "revoke_role",
+            self.inner.revoke_role(email, role),
+        )
+        .await
+    }
+
+    async fn grants(&self, email: &Email) -> Result<Grants, RoleStoreError> {
+        timed(
+            &self.metrics,
+            ROLE_STORE,
+            "grants",
+            self.inner.grants(email),
+        )
+        .await
+    }
+
+    async fn health_check(&self) -> Result<(), RoleStoreError> {
+        timed(
+            &self.metrics,
+            ROLE_STORE,
+            "health_check",
+            self.inner.health_check(),
+        )
+        .await
+    }
+}
+
+pub struct InstrumentedOrganizationStore {
+    inner: OrganizationStoreType,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedOrganizationStore {
+    pub fn new(inner: OrganizationStoreType, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl OrganizationStore for InstrumentedOrganizationStore {
+    async fn create_organization(
+        &self,
+        name: &str,
+        owner: &Email,
+    ) -> Result<Organization, OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "create_organization",
+            self.inner.create_organization(name, owner),
+        )
+        .await
+    }
+
+    async fn get_organization(
+        &self,
+        id: &OrganizationId,
+    ) -> Result<Organization, OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "get_organization",
+            self.inner.get_organization(id),
+        )
+        .await
+    }
+
+    async fn memberships(&self, email: &Email) -> Result<Vec<Membership>, OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "memberships",
+            self.inner.memberships(email),
+        )
+        .await
+    }
+
+    async fn role(
+        &self,
+        id: &OrganizationId,
+        email: &Email,
+    ) -> Result<OrgRole, OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "role",
+            self.inner.role(id, email),
+        )
+        .await
+    }
+
+    async fn members(&self, id: &OrganizationId) -> Result<Vec<Member>, OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "members",
+            self.inner.members(id),
+        )
+        .await
+    }
+
+    async fn remove_member(
+        &self,
+        id: &OrganizationId,
+        email: &Email,
+    ) -> Result<(), OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "remove_member",
+            self.inner.remove_member(id, email),
+        )
+        .await
+    }
+
+    async fn add_invitation(&self, invitation: Invitation) -> Result<(), OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "add_invitation",
+            self.inner.add_invitation(invitation),
+        )
+        .await
+    }
+
+    async fn invitations(
+        &self,
+        id: &OrganizationId,
+    ) -> Result<Vec<Invitation>, OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "invitations",
+            self.inner.invitations(id),
+        )
+        .await
+    }
+
+    async fn get_invitation(&self, id: &Uuid) -> Result<Invitation, OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "get_invitation",
+            self.inner.get_invitation(id),
+        )
+        .await
+    }
+
+    async fn remove_invitation(&self, id: &Uuid) -> Result<(), OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "remove_invitation",
+            self.inner.remove_invitation(id),
+        )
+        .await
+    }
+
+    async fn accept_invitation(
+        &self,
+        id: &Uuid,
+        email: &Email,
+    ) -> Result<Membership, OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "accept_invitation",
+            self.inner.accept_invitation(id, email),
+        )
+        .await
+    }
+
+    async fn health_check(&self) -> Result<(), OrganizationStoreError> {
+        timed(
+            &self.metrics,
+            ORGANIZATION_STORE,
+            "health_check",
+            self.inner.health_check(),
+        )
+        .await
+    }
+}
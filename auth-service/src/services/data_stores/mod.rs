@@ -1,6 +1,33 @@
+pub mod hashmap_email_outbox;
+pub mod hashmap_known_device_store;
+pub mod hashmap_organization_store;
+pub mod hashmap_role_store;
+pub mod hashmap_two_fa_code_store;
 pub mod hashmap_user_store;
 pub mod hashset_banned_token_store;
-pub mod hashmap_two_fa_code_store;
+pub mod instrumented;
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+mod password_hashing;
+#[cfg(feature = "postgres")]
+pub mod postgres_audit_log;
+#[cfg(feature = "postgres")]
+pub mod postgres_banned_token_store;
+#[cfg(feature = "postgres")]
+pub mod postgres_email_outbox;
+#[cfg(feature = "postgres")]
+pub mod postgres_expiry;
+#[cfg(feature = "postgres")]
+pub mod postgres_known_device_store;
+#[cfg(feature = "postgres")]
+pub mod postgres_organization_store;
+#[cfg(feature = "postgres")]
+pub mod postgres_role_store;
+#[cfg(feature = "postgres")]
+pub mod postgres_two_fa_code_store;
+#[cfg(feature = "postgres")]
 pub mod postgres_user_store;
 pub mod redis_banned_token_store;
 pub mod redis_two_fa_code_store;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_user_store;
+pub mod vec_audit_log;
@@ -0,0 +1,78 @@
+// Argon2 helpers shared by every user store that persists password hashes
+// rather than the passwords themselves.
+use color_eyre::eyre::{Context, Result};
+
+use argon2::{
+    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
+    PasswordVerifier, Version,
+};
+
+use secrecy::{ExposeSecret, Secret};
+
+// Cost parameters for stores that weren't handed any from the settings
+pub(crate) fn default_params() -> Params {
+    Params::new(15000, 2, 1, None).expect("default Argon2 params are valid")
+}
+
+// Helper function to verify if a given password matches an expected hash
+// TODO: Hashing is a CPU-intensive operation. To avoid blocking
+// other async tasks, update this function to perform hashing on a
+// separate thread pool using tokio::task::spawn_blocking
+#[tracing::instrument(name = "Verify password hash", skip_all)]
+pub(crate) async fn verify_password_hash(
+    expected_password_hash: Secret<String>,
+    password_candidate: Secret<String>,
+) -> Result<()> {
+    // This line retrieves the current span from the tracing context.
+    // The span represents the execution context for the compute_password_hash function.
+    let current_span: tracing::Span = tracing::Span::current(); // New!
+    let result = tokio::task::spawn_blocking(move || {
+        // This code block ensures that the operations within the closure are executed within the context of the current span.
+        // This is especially useful for tracing operations that are performed in a different thread or task, such as within tokio::task::spawn_blocking.
+        current_span.in_scope(|| {
+            // New!
+            let expected_password_hash: PasswordHash<'_> =
+                PasswordHash::new(expected_password_hash.expose_secret())?;
+
+            Argon2::default()
+                .verify_password(
+                    password_candidate.expose_secret().as_bytes(),
+                    &expected_password_hash,
+                )
+                .wrap_err("failed to verify password hash")
+        })
+    })
+    .await;
+
+    result?
+}
+
+// Helper function to hash passwords before persisting them in the database.
+// TODO: Hashing is a CPU-intensive operation. To avoid blocking
+// other async tasks, update this function to perform hashing on a
+// separate thread pool using tokio::task::spawn_blocking
+#[tracing::instrument(name = "Computing password hash", skip_all)]
+pub(crate) async fn compute_password_hash(
+    password: Secret<String>,
+    params: Params,
+) -> Result<String> {
+    // This line retrieves the current span from the tracing context.
+    // The span represents the execution context for the compute_password_hash function.
+    let current_span: tracing::Span = tracing::Span::current(); // New!
+
+    let result = tokio::task::spawn_blocking(move || {
+        // This code block ensures that the operations within the closure are executed within the context of the current span.
+        // This is especially useful for tracing operations that are performed in a different thread or task, such as within tokio::task::spawn_blocking.
+        current_span.in_scope(|| {
+            let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
+            let password_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password(password.expose_secret().as_bytes(), &salt)?
+                .to_string();
+
+            Ok(password_hash)
+        })
+    })
+    .await;
+
+    result?
+}
@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Report;
+use sqlx::{postgres::PgRow, PgPool, Row};
+
+use crate::domain::data_stores::audit_log::{
+    AuditEvent, AuditEventFilter, AuditLog, AuditLogError,
+};
+
+pub struct PostgresAuditLog {
+    pool: PgPool,
+}
+
+impl PostgresAuditLog {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLog for PostgresAuditLog {
+    #[tracing::instrument(name = "Recording audit event in PostgreSQL", skip_all)]
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError> {
+        sqlx::query(
+            "INSERT INTO audit_events
+                (kind, outcome, reason, actor, subject, ip, user_agent, request_id, occurred_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(event.kind.as_str())
+        .bind(event.outcome.as_str())
+        .bind(event.reason)
+        .bind(event.actor)
+        .bind(event.subject)
+        .bind(event.ip)
+        .bind(event.user_agent)
+        .bind(event.request_id)
+        .bind(event.occurred_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Querying audit events in PostgreSQL", skip_all)]
+    async fn query(&self, filter: &AuditEventFilter) -> Result<Vec<AuditEvent>, AuditLogError> {
+        let kinds: Vec<&str> = filter.kinds.iter().map(|kind| kind.as_str()).collect();
+        let rows = sqlx::query(
+            "SELECT kind, outcome, reason, actor, subject, ip, user_agent, request_id, occurred_at
+             FROM audit_events
+             WHERE ($1::TEXT IS NULL OR actor = $1)
+               AND ($7::TEXT IS NULL OR subject = $7)
+               AND (cardinality($2::TEXT[]) = 0 OR kind = ANY($2))
+               AND ($3::TEXT IS NULL OR outcome = $3)
+               AND ($4::TIMESTAMPTZ IS NULL OR occurred_at >= $4)
+               AND ($5::TIMESTAMPTZ IS NULL OR occurred_at < $5)
+             ORDER BY occurred_at DESC, id DESC
+             LIMIT $6",
+        )
+        .bind(filter.actor.as_deref())
+        .bind(kinds)
+        .bind(filter.outcome.map(|outcome| outcome.as_str()))
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.limit as i64)
+        .bind(filter.subject.as_deref())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        rows.iter()
+            .map(event_from_row)
+            .collect::<Result<_, Report>>()
+            .map_err(AuditLogError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "PostgreSQL health check", skip_all)]
+    async fn health_check(&self) -> Result<(), AuditLogError> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+fn event_from_row(row: &PgRow) -> Result<AuditEvent, Report> {
+    Ok(AuditEvent {
+        kind: row.try_get::<String, _>("kind")?.parse()?,
+        outcome: row.try_get::<String, _>("outcome")?.parse()?,
+        reason: row.try_get("reason")?,
+        actor: row.try_get("actor")?,
+        subject: row.try_get("subject")?,
+        ip: row.try_get("ip")?,
+        user_agent: row.try_get("user_agent")?,
+        request_id: row.try_get("request_id")?,
+        occurred_at: row.try_get::<DateTime<Utc>, _>("occurred_at")?,
+    })
+}
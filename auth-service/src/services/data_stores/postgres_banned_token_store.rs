@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use super::postgres_expiry::spawn_expiry_cleanup;
+use crate::{
+    domain::{
+        data_stores::banned_token_store::{BannedTokenStore, BannedTokenStoreError},
+        Email,
+    },
+    utils::auth::TOKEN_TTL_SECONDS,
+};
+
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+    ttl: Duration,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            ttl: Duration::from_secs(TOKEN_TTL_SECONDS as u64),
+        }
+    }
+
+    // Overrides how long a banned token is remembered.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    // Starts a background task that purges expired tokens and session
+    // revocations every `every`.
+    pub fn spawn_cleanup_task(&self, every: Duration) -> JoinHandle<()> {
+        spawn_expiry_cleanup(
+            self.pool.clone(),
+            &[BANNED_TOKENS_TABLE, REVOKED_SESSIONS_TABLE],
+            every,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    #[tracing::instrument(name = "Adding banned token to PostgreSQL", skip_all)]
+    async fn add_token(&self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
+        // Banning an already banned token just pushes its expiry out
+        sqlx::query(
+            "INSERT INTO banned_tokens (token, expires_at)
+             VALUES ($1, NOW() + make_interval(secs => $2))
+             ON CONFLICT (token) DO UPDATE SET expires_at = EXCLUDED.expires_at",
+        )
+        .bind(token.expose_secret())
+        .bind(self.ttl.as_secs_f64())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in PostgreSQL", skip_all)]
+    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM banned_tokens WHERE token = $1 AND expires_at > NOW())",
+        )
+        .bind(token.expose_secret())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(exists)
+    }
+
+    #[tracing::instrument(name = "Revoking sessions in PostgreSQL", skip_all)]
+    async fn revoke_sessions(
+        &self,
+        email: &Email,
+        revoked_at: DateTime<Utc>,
+    ) -> Result<(), BannedTokenStoreError> {
+        sqlx::query(
+            "INSERT INTO revoked_sessions (email, revoked_at, expires_at)
+             VALUES ($1, $2, NOW() + make_interval(secs => $3))
+             ON CONFLICT (email) DO UPDATE
+             SET revoked_at = EXCLUDED.revoked_at, expires_at = EXCLUDED.expires_at",
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(revoked_at)
+        .bind(self.ttl.as_secs_f64())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking revoked sessions in PostgreSQL", skip_all)]
+    async fn sessions_revoked_at(
+        &self,
+        email: &Email,
+    ) -> Result<Option<DateTime<Utc>>, BannedTokenStoreError> {
+        sqlx::query_scalar(
+            "SELECT revoked_at FROM revoked_sessions WHERE email = $1 AND expires_at > NOW()",
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "PostgreSQL health check", skip_all)]
+    async fn health_check(&self) -> Result<(), BannedTokenStoreError> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+pub const BANNED_TOKENS_TABLE: &str = "banned_tokens";
+pub const REVOKED_SESSIONS_TABLE: &str = "revoked_sessions";
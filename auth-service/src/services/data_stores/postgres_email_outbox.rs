@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use color_eyre::eyre::Report;
+use secrecy::ExposeSecret;
+use sqlx::{postgres::PgRow, PgPool, Row};
+
+use crate::domain::data_stores::email_outbox::{EmailOutbox, EmailOutboxError, OutboxEmail};
+use crate::domain::{Email, EmailMessage};
+
+pub struct PostgresEmailOutbox {
+    pool: PgPool,
+}
+
+impl PostgresEmailOutbox {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutbox for PostgresEmailOutbox {
+    #[tracing::instrument(name = "Enqueueing email in PostgreSQL", skip_all)]
+    async fn enqueue(
+        &self,
+        recipient: Email,
+        message: EmailMessage,
+    ) -> Result<(), EmailOutboxError> {
+        sqlx::query(
+            "INSERT INTO email_outbox
+                (recipient, subject, html_body, text_body, next_attempt_at, created_at)
+             VALUES ($1, $2, $3, $4, NOW(), NOW())",
+        )
+        .bind(recipient.as_ref().expose_secret())
+        .bind(message.subject)
+        .bind(message.html_body)
+        .bind(message.text_body)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Claiming due emails in PostgreSQL", skip_all)]
+    async fn claim_due(
+        &self,
+        limit: usize,
+        lease: Duration,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxError> {
+        // SKIP LOCKED lets several instances claim from the outbox at once
+        // without handing out the same email twice
+        let rows = sqlx::query(
+            "UPDATE email_outbox
+             SET attempts = attempts + 1,
+                 next_attempt_at = NOW() + make_interval(secs => $2)
+             WHERE id IN (
+                 SELECT id FROM email_outbox
+                 WHERE dead_lettered_at IS NULL AND next_attempt_at <= NOW()
+                 ORDER BY next_attempt_at, id
+                 LIMIT $1
+                 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING id, recipient, subject, html_body, text_body, attempts, last_error",
+        )
+        .bind(limit as i64)
+        .bind(lease.as_secs_f64())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        let mut emails = rows
+            .iter()
+            .map(outbox_email_from_row)
+            .collect::<Result<Vec<_>, Report>>()
+            .map_err(EmailOutboxError::UnexpectedError)?;
+        // RETURNING doesn't keep the subquery's order
+        emails.sort_by_key(|email| email.id);
+        Ok(emails)
+    }
+
+    #[tracing::instrument(name = "Marking email sent in PostgreSQL", skip_all)]
+    async fn mark_sent(&self, id: i64) -> Result<(), EmailOutboxError> {
+        let res = sqlx::query("DELETE FROM email_outbox WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        found(res.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Rescheduling email in PostgreSQL", skip_all)]
+    async fn retry_later(
+        &self,
+        id: i64,
+        delay: Duration,
+        error: &str,
+    ) -> Result<(), EmailOutboxError> {
+        let res = sqlx::query(
+            "UPDATE email_outbox
+             SET next_attempt_at = NOW() + make_interval(secs => $2), last_error = $3
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(delay.as_secs_f64())
+        .bind(error)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        found(res.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Dead-lettering email in PostgreSQL", skip_all)]
+    async fn dead_letter(&self, id: i64, error: &str) -> Result<(), EmailOutboxError> {
+        let res = sqlx::query(
+            "UPDATE email_outbox SET dead_lettered_at = NOW(), last_error = $2 WHERE id = $1",
+        )
+        .bind(id)
+        .bind(error)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        found(res.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Listing dead-lettered emails in PostgreSQL", skip_all)]
+    async fn dead_letters(&self) -> Result<Vec<OutboxEmail>, EmailOutboxError> {
+        let rows = sqlx::query(
+            "SELECT id, recipient, subject, html_body, text_body, attempts, last_error
+             FROM email_outbox
+             WHERE dead_lettered_at IS NOT NULL
+             ORDER BY id",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        rows.iter()
+            .map(outbox_email_from_row)
+            .collect::<Result<_, Report>>()
+            .map_err(EmailOutboxError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Purging dead-lettered emails in PostgreSQL", skip_all)]
+    async fn purge_dead_letters(&self, retention: Duration) -> Result<u64, EmailOutboxError> {
+        let res = sqlx::query(
+            "DELETE FROM email_outbox
+             WHERE dead_lettered_at <= NOW() - make_interval(secs => $1)",
+        )
+        .bind(retention.as_secs_f64())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        Ok(res.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Counting pending emails in PostgreSQL", skip_all)]
+    async fn pending_count(&self) -> Result<u64, EmailOutboxError> {
+        let pending: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM email_outbox WHERE dead_lettered_at IS NULL")
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        Ok(pending as u64)
+    }
+
+    #[tracing::instrument(name = "PostgreSQL health check", skip_all)]
+    async fn health_check(&self) -> Result<(), EmailOutboxError> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+fn found(rows_affected: u64) -> Result<(), EmailOutboxError> {
+    if rows_affected == 0 {
+        Err(EmailOutboxError::EmailNotFound)
+    } else {
+        Ok(())
+    }
+}
+
+fn outbox_email_from_row(row: &PgRow) -> Result<OutboxEmail, Report> {
+    Ok(OutboxEmail {
+        id: row.try_get("id")?,
+        recipient: Email::parse(row.try_get("recipient")?)?,
+        message: EmailMessage {
+            subject: row.try_get("subject")?,
+            html_body: row.try_get("html_body")?,
+            text_body: row.try_get("text_body")?,
+        },
+        attempts: row.try_get::<i32, _>("attempts")? as u32,
+        last_error: row.try_get("last_error")?,
+    })
+}
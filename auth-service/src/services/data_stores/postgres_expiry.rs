@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+// Deletes every row of `table` whose `expires_at` is in the past and
+// returns how many rows were removed.
+#[tracing::instrument(name = "Deleting expired rows from PostgreSQL", skip(pool))]
+pub async fn delete_expired_rows(pool: &PgPool, table: &'static str) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query(&format!("DELETE FROM {} WHERE expires_at <= NOW()", table))
+        .execute(pool)
+        .await?;
+
+    Ok(res.rows_affected())
+}
+
+// Expired rows are already ignored on read, so this task only exists to keep
+// the tables from growing without bound. Failures are logged and retried on
+// the next tick rather than bringing the task down.
+pub fn spawn_expiry_cleanup(
+    pool: PgPool,
+    tables: &'static [&'static str],
+    every: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            for &table in tables {
+                match delete_expired_rows(&pool, table).await {
+                    Ok(deleted) => tracing::debug!(table, deleted, "removed expired rows"),
+                    Err(e) => tracing::warn!(table, error = %e, "failed to remove expired rows"),
+                }
+            }
+        }
+    })
+}
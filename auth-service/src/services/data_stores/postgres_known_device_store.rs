@@ -0,0 +1,71 @@
+use secrecy::ExposeSecret;
+use sqlx::{PgPool, Row};
+
+use crate::domain::data_stores::known_device_store::{
+    Device, DeviceStatus, KnownDeviceStore, KnownDeviceStoreError,
+};
+use crate::domain::Email;
+
+pub struct PostgresKnownDeviceStore {
+    pool: PgPool,
+}
+
+impl PostgresKnownDeviceStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl KnownDeviceStore for PostgresKnownDeviceStore {
+    #[tracing::instrument(name = "Remembering device in PostgreSQL", skip_all)]
+    async fn remember_device(
+        &self,
+        email: &Email,
+        device: &Device,
+    ) -> Result<DeviceStatus, KnownDeviceStoreError> {
+        // Missing values are stored as empty strings so they can be part of
+        // the primary key. `existing` is evaluated before the insert, and
+        // `xmax = 0` only holds for freshly inserted rows.
+        let row = sqlx::query(
+            "WITH existing AS (
+                 SELECT COUNT(*) AS devices FROM known_devices WHERE email = $1
+             ), remembered AS (
+                 INSERT INTO known_devices (email, ip, user_agent, first_seen_at, last_seen_at)
+                 VALUES ($1, $2, $3, NOW(), NOW())
+                 ON CONFLICT (email, ip, user_agent) DO UPDATE SET last_seen_at = NOW()
+                 RETURNING (xmax = 0) AS inserted
+             )
+             SELECT existing.devices, remembered.inserted FROM existing, remembered",
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(device.ip.as_deref().unwrap_or_default())
+        .bind(device.user_agent.as_deref().unwrap_or_default())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+
+        let devices: i64 = row
+            .try_get("devices")
+            .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+        let inserted: bool = row
+            .try_get("inserted")
+            .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+
+        Ok(match (devices, inserted) {
+            (_, false) => DeviceStatus::Known,
+            (0, true) => DeviceStatus::First,
+            (_, true) => DeviceStatus::New,
+        })
+    }
+
+    #[tracing::instrument(name = "PostgreSQL health check", skip_all)]
+    async fn health_check(&self) -> Result<(), KnownDeviceStoreError> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| KnownDeviceStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
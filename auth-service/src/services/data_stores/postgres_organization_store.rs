@@ -0,0 +1,376 @@
+use std::time::Duration;
+
+use chrono::{DateTime, SubsecRound, Utc};
+use color_eyre::eyre::Report;
+use secrecy::ExposeSecret;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::postgres_expiry::spawn_expiry_cleanup;
+use crate::domain::data_stores::organization_store::{OrganizationStore, OrganizationStoreError};
+use crate::domain::{Email, Invitation, Member, Membership, OrgRole, Organization, OrganizationId};
+
+pub struct PostgresOrganizationStore {
+    pool: PgPool,
+}
+
+impl PostgresOrganizationStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Starts a background task that purges expired invitations every `every`.
+    pub fn spawn_cleanup_task(&self, every: Duration) -> JoinHandle<()> {
+        spawn_expiry_cleanup(self.pool.clone(), &[ORGANIZATION_INVITATIONS_TABLE], every)
+    }
+}
+
+#[async_trait::async_trait]
+impl OrganizationStore for PostgresOrganizationStore {
+    #[tracing::instrument(name = "Creating organization in PostgreSQL", skip_all)]
+    async fn create_organization(
+        &self,
+        name: &str,
+        owner: &Email,
+    ) -> Result<Organization, OrganizationStoreError> {
+        let organization = Organization {
+            id: OrganizationId::new(),
+            name: name.to_owned(),
+            // As precise as Postgres keeps it, so it reads back the same
+            created_at: Utc::now().trunc_subsecs(6),
+        };
+
+        let mut transaction = self.pool.begin().await.map_err(unexpected)?;
+        sqlx::query("INSERT INTO organizations (id, name, created_at) VALUES ($1, $2, $3)")
+            .bind(organization.id.as_ref())
+            .bind(&organization.name)
+            .bind(organization.created_at)
+            .execute(&mut transaction)
+            .await
+            .map_err(unexpected)?;
+        sqlx::query(
+            "INSERT INTO organization_members (organization_id, email, role, joined_at)
+             VALUES ($1, $2, $3, $4)",
+        )
+        .bind(organization.id.as_ref())
+        .bind(owner.as_ref().expose_secret())
+        .bind(OrgRole::Owner.as_str())
+        .bind(organization.created_at)
+        .execute(&mut transaction)
+        .await
+        .map_err(unexpected)?;
+        transaction.commit().await.map_err(unexpected)?;
+
+        Ok(organization)
+    }
+
+    #[tracing::instrument(name = "Retrieving organization from PostgreSQL", skip_all)]
+    async fn get_organization(
+        &self,
+        id: &OrganizationId,
+    ) -> Result<Organization, OrganizationStoreError> {
+        let row = sqlx::query("SELECT id, name, created_at FROM organizations WHERE id = $1")
+            .bind(id.as_ref())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(unexpected)?
+            .ok_or(OrganizationStoreError::OrganizationNotFound)?;
+
+        Ok(Organization {
+            id: row.try_get::<Uuid, _>("id").map_err(unexpected)?.into(),
+            name: row.try_get("name").map_err(unexpected)?,
+            created_at: row.try_get("created_at").map_err(unexpected)?,
+        })
+    }
+
+    #[tracing::instrument(name = "Retrieving memberships from PostgreSQL", skip_all)]
+    async fn memberships(&self, email: &Email) -> Result<Vec<Membership>, OrganizationStoreError> {
+        let rows = sqlx::query(
+            "SELECT organizations.id, organizations.name,
+                    organization_members.role, organization_members.joined_at
+             FROM organization_members
+             JOIN organizations ON organizations.id = organization_members.organization_id
+             WHERE organization_members.email = $1
+             ORDER BY organization_members.joined_at, organizations.id",
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        rows.iter().map(membership).collect()
+    }
+
+    #[tracing::instrument(name = "Retrieving organization role from PostgreSQL", skip_all)]
+    async fn role(
+        &self,
+        id: &OrganizationId,
+        email: &Email,
+    ) -> Result<OrgRole, OrganizationStoreError> {
+        let row = sqlx::query(
+            "SELECT role FROM organization_members WHERE organization_id = $1 AND email = $2",
+        )
+        .bind(id.as_ref())
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .ok_or(OrganizationStoreError::NotMember)?;
+
+        role(&row)
+    }
+
+    #[tracing::instrument(name = "Retrieving organization members from PostgreSQL", skip_all)]
+    async fn members(&self, id: &OrganizationId) -> Result<Vec<Member>, OrganizationStoreError> {
+        // Goes through organizations so an unknown id isn't an empty list
+        let rows = sqlx::query(
+            "SELECT organization_members.email, organization_members.role,
+                    organization_members.joined_at
+             FROM organizations
+             LEFT JOIN organization_members
+                 ON organization_members.organization_id = organizations.id
+             WHERE organizations.id = $1
+             ORDER BY organization_members.joined_at, organization_members.email",
+        )
+        .bind(id.as_ref())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?;
+        if rows.is_empty() {
+            return Err(OrganizationStoreError::OrganizationNotFound);
+        }
+
+        let mut members = Vec::with_capacity(rows.len());
+        for row in rows {
+            // The organization's own row when it has no members left
+            let Some(email) = row
+                .try_get::<Option<String>, _>("email")
+                .map_err(unexpected)?
+            else {
+                continue;
+            };
+            members.push(Member {
+                email,
+                role: role(&row)?,
+                joined_at: row.try_get("joined_at").map_err(unexpected)?,
+            });
+        }
+        Ok(members)
+    }
+
+    #[tracing::instrument(name = "Removing organization member from PostgreSQL", skip_all)]
+    async fn remove_member(
+        &self,
+        id: &OrganizationId,
+        email: &Email,
+    ) -> Result<(), OrganizationStoreError> {
+        // Locking the organization makes concurrent removals take turns, so
+        // each one counts the owners the one before it left
+        let mut transaction = self.pool.begin().await.map_err(unexpected)?;
+        sqlx::query("SELECT id FROM organizations WHERE id = $1 FOR UPDATE")
+            .bind(id.as_ref())
+            .execute(&mut transaction)
+            .await
+            .map_err(unexpected)?;
+        let row = sqlx::query(
+            "DELETE FROM organization_members
+             WHERE organization_id = $1 AND email = $2
+             RETURNING role",
+        )
+        .bind(id.as_ref())
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&mut transaction)
+        .await
+        .map_err(unexpected)?
+        .ok_or(OrganizationStoreError::NotMember)?;
+
+        if role(&row)? == OrgRole::Owner {
+            let owners: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM organization_members
+                 WHERE organization_id = $1 AND role = $2",
+            )
+            .bind(id.as_ref())
+            .bind(OrgRole::Owner.as_str())
+            .fetch_one(&mut transaction)
+            .await
+            .map_err(unexpected)?;
+            // Dropping the transaction rolls the removal back
+            if owners == 0 {
+                return Err(OrganizationStoreError::LastOwner);
+            }
+        }
+        transaction.commit().await.map_err(unexpected)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Adding invitation to PostgreSQL", skip_all)]
+    async fn add_invitation(&self, invitation: Invitation) -> Result<(), OrganizationStoreError> {
+        let result = sqlx::query(
+            "INSERT INTO organization_invitations
+                 (id, organization_id, email, role, invited_by, expires_at)
+             SELECT $1, id, $3, $4, $5, $6 FROM organizations WHERE id = $2",
+        )
+        .bind(invitation.id)
+        .bind(invitation.organization_id.as_ref())
+        .bind(&invitation.email)
+        .bind(invitation.role.as_str())
+        .bind(&invitation.invited_by)
+        .bind(invitation.expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(OrganizationStoreError::OrganizationNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving invitations from PostgreSQL", skip_all)]
+    async fn invitations(
+        &self,
+        id: &OrganizationId,
+    ) -> Result<Vec<Invitation>, OrganizationStoreError> {
+        let rows = sqlx::query(
+            "SELECT id, organization_id, email, role, invited_by, expires_at
+             FROM organization_invitations
+             WHERE organization_id = $1 AND expires_at > NOW()
+             ORDER BY expires_at, id",
+        )
+        .bind(id.as_ref())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(unexpected)?;
+
+        rows.iter().map(invitation).collect()
+    }
+
+    #[tracing::instrument(name = "Retrieving invitation from PostgreSQL", skip_all)]
+    async fn get_invitation(&self, id: &Uuid) -> Result<Invitation, OrganizationStoreError> {
+        let row = sqlx::query(
+            "SELECT id, organization_id, email, role, invited_by, expires_at
+             FROM organization_invitations
+             WHERE id = $1 AND expires_at > NOW()",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(unexpected)?
+        .ok_or(OrganizationStoreError::InvitationNotFound)?;
+
+        invitation(&row)
+    }
+
+    #[tracing::instrument(name = "Removing invitation from PostgreSQL", skip_all)]
+    async fn remove_invitation(&self, id: &Uuid) -> Result<(), OrganizationStoreError> {
+        let result = sqlx::query("DELETE FROM organization_invitations WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(unexpected)?;
+
+        if result.rows_affected() == 0 {
+            return Err(OrganizationStoreError::InvitationNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Accepting invitation in PostgreSQL", skip_all)]
+    async fn accept_invitation(
+        &self,
+        id: &Uuid,
+        email: &Email,
+    ) -> Result<Membership, OrganizationStoreError> {
+        // Deleting first means two concurrent accepts can't both use it
+        let mut transaction = self.pool.begin().await.map_err(unexpected)?;
+        let row = sqlx::query(
+            "DELETE FROM organization_invitations
+             WHERE id = $1 AND expires_at > NOW()
+             RETURNING id, organization_id, email, role, invited_by, expires_at",
+        )
+        .bind(id)
+        .fetch_optional(&mut transaction)
+        .await
+        .map_err(unexpected)?
+        .ok_or(OrganizationStoreError::InvitationNotFound)?;
+        let invitation = invitation(&row)?;
+
+        sqlx::query(
+            "INSERT INTO organization_members (organization_id, email, role, joined_at)
+             VALUES ($1, $2, $3, NOW())
+             ON CONFLICT (organization_id, email) DO NOTHING",
+        )
+        .bind(invitation.organization_id.as_ref())
+        .bind(email.as_ref().expose_secret())
+        .bind(invitation.role.as_str())
+        .execute(&mut transaction)
+        .await
+        .map_err(unexpected)?;
+        let row = sqlx::query(
+            "SELECT organizations.id, organizations.name,
+                    organization_members.role, organization_members.joined_at
+             FROM organization_members
+             JOIN organizations ON organizations.id = organization_members.organization_id
+             WHERE organization_members.organization_id = $1
+               AND organization_members.email = $2",
+        )
+        .bind(invitation.organization_id.as_ref())
+        .bind(email.as_ref().expose_secret())
+        .fetch_one(&mut transaction)
+        .await
+        .map_err(unexpected)?;
+        transaction.commit().await.map_err(unexpected)?;
+
+        membership(&row)
+    }
+
+    #[tracing::instrument(name = "PostgreSQL health check", skip_all)]
+    async fn health_check(&self) -> Result<(), OrganizationStoreError> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(unexpected)?;
+
+        Ok(())
+    }
+}
+
+pub const ORGANIZATION_INVITATIONS_TABLE: &str = "organization_invitations";
+
+fn unexpected(e: impl Into<Report>) -> OrganizationStoreError {
+    OrganizationStoreError::UnexpectedError(e.into())
+}
+
+fn role(row: &PgRow) -> Result<OrgRole, OrganizationStoreError> {
+    row.try_get::<String, _>("role")
+        .map_err(unexpected)?
+        .parse()
+        .map_err(unexpected)
+}
+
+fn membership(row: &PgRow) -> Result<Membership, OrganizationStoreError> {
+    Ok(Membership {
+        organization_id: row.try_get::<Uuid, _>("id").map_err(unexpected)?.into(),
+        organization_name: row.try_get("name").map_err(unexpected)?,
+        role: role(row)?,
+        joined_at: row
+            .try_get::<DateTime<Utc>, _>("joined_at")
+            .map_err(unexpected)?,
+    })
+}
+
+fn invitation(row: &PgRow) -> Result<Invitation, OrganizationStoreError> {
+    Ok(Invitation {
+        id: row.try_get("id").map_err(unexpected)?,
+        organization_id: row
+            .try_get::<Uuid, _>("organization_id")
+            .map_err(unexpected)?
+            .into(),
+        email: row.try_get("email").map_err(unexpected)?,
+        role: role(row)?,
+        invited_by: row.try_get("invited_by").map_err(unexpected)?,
+        expires_at: row.try_get("expires_at").map_err(unexpected)?,
+    })
+}
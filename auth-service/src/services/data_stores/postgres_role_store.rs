@@ -0,0 +1,127 @@
+use secrecy::ExposeSecret;
+use sqlx::{PgPool, Row};
+
+use crate::domain::data_stores::role_store::{RoleDefinition, RoleStore, RoleStoreError};
+use crate::domain::{Email, Grants};
+
+// Roles and their permissions live in the `roles`, `permissions` and
+// `role_permissions` tables, which migrations seed with the built-in roles.
+// `user_roles` says who has which.
+pub struct PostgresRoleStore {
+    pool: PgPool,
+}
+
+impl PostgresRoleStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn ensure_known(&self, role: &str) -> Result<(), RoleStoreError> {
+        sqlx::query("SELECT 1 FROM roles WHERE name = $1")
+            .bind(role)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?
+            .map(|_| ())
+            .ok_or(RoleStoreError::UnknownRole)
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleStore for PostgresRoleStore {
+    #[tracing::instrument(name = "Listing roles in PostgreSQL", skip_all)]
+    async fn roles(&self) -> Result<Vec<RoleDefinition>, RoleStoreError> {
+        let rows = sqlx::query(
+            "SELECT roles.name,
+                    ARRAY_REMOVE(ARRAY_AGG(role_permissions.permission ORDER BY role_permissions.permission), NULL)
+                        AS permissions
+             FROM roles
+             LEFT JOIN role_permissions ON role_permissions.role = roles.name
+             GROUP BY roles.name
+             ORDER BY roles.name",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(RoleDefinition {
+                    name: row
+                        .try_get("name")
+                        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?,
+                    permissions: row
+                        .try_get("permissions")
+                        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Granting role in PostgreSQL", skip_all)]
+    async fn grant_role(&self, email: &Email, role: &str) -> Result<(), RoleStoreError> {
+        self.ensure_known(role).await?;
+        sqlx::query(
+            "INSERT INTO user_roles (email, role, granted_at) VALUES ($1, $2, NOW())
+             ON CONFLICT (email, role) DO NOTHING",
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(role)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking role in PostgreSQL", skip_all)]
+    async fn revoke_role(&self, email: &Email, role: &str) -> Result<(), RoleStoreError> {
+        self.ensure_known(role).await?;
+        sqlx::query("DELETE FROM user_roles WHERE email = $1 AND role = $2")
+            .bind(email.as_ref().expose_secret())
+            .bind(role)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving grants from PostgreSQL", skip_all)]
+    async fn grants(&self, email: &Email) -> Result<Grants, RoleStoreError> {
+        let row = sqlx::query(
+            "SELECT
+                 ARRAY(SELECT role FROM user_roles WHERE email = $1 ORDER BY role) AS roles,
+                 ARRAY(
+                     SELECT DISTINCT role_permissions.permission
+                     FROM user_roles
+                     JOIN role_permissions ON role_permissions.role = user_roles.role
+                     WHERE user_roles.email = $1
+                     ORDER BY role_permissions.permission
+                 ) AS permissions",
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(Grants {
+            roles: row
+                .try_get("roles")
+                .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?,
+            permissions: row
+                .try_get("permissions")
+                .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?,
+        })
+    }
+
+    #[tracing::instrument(name = "PostgreSQL health check", skip_all)]
+    async fn health_check(&self) -> Result<(), RoleStoreError> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
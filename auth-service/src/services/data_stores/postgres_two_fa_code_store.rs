@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{PgPool, Row};
+use tokio::task::JoinHandle;
+
+use super::postgres_expiry::spawn_expiry_cleanup;
+use crate::domain::{
+    data_stores::{
+        LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError, TWO_FA_CODE_TTL_SECONDS,
+    },
+    Email,
+};
+
+pub struct PostgresTwoFACodeStore {
+    pool: PgPool,
+    ttl: Duration,
+}
+
+impl PostgresTwoFACodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            ttl: Duration::from_secs(TWO_FA_CODE_TTL_SECONDS),
+        }
+    }
+
+    // Overrides how long a 2FA code stays valid.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    // Starts a background task that purges expired codes every `every`.
+    pub fn spawn_cleanup_task(&self, every: Duration) -> JoinHandle<()> {
+        spawn_expiry_cleanup(self.pool.clone(), &[TWO_FA_CODES_TABLE], every)
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for PostgresTwoFACodeStore {
+    #[tracing::instrument(name = "Adding 2FA code to PostgreSQL", skip_all)]
+    async fn add_code(
+        &self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        // A new login attempt replaces whatever code was pending for the email
+        sqlx::query(
+            "INSERT INTO two_fa_codes (email, login_attempt_id, code, expires_at)
+             VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+             ON CONFLICT (email) DO UPDATE SET
+                login_attempt_id = EXCLUDED.login_attempt_id,
+                code = EXCLUDED.code,
+                expires_at = EXCLUDED.expires_at",
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(login_attempt_id.as_ref())
+        .bind(code.as_ref())
+        .bind(self.ttl.as_secs_f64())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing 2FA code from PostgreSQL", skip_all)]
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        sqlx::query("DELETE FROM two_fa_codes WHERE email = $1")
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving 2FA code from PostgreSQL", skip_all)]
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let row = sqlx::query(
+            "SELECT login_attempt_id, code FROM two_fa_codes
+             WHERE email = $1 AND expires_at > NOW()",
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let login_attempt_id: String = row
+            .try_get("login_attempt_id")
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        let code: String = row
+            .try_get("code")
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok((
+            LoginAttemptId(Secret::new(login_attempt_id)),
+            TwoFACode(Secret::new(code)),
+        ))
+    }
+
+    #[tracing::instrument(name = "PostgreSQL health check", skip_all)]
+    async fn health_check(&self) -> Result<(), TwoFACodeStoreError> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+pub const TWO_FA_CODES_TABLE: &str = "two_fa_codes";
@@ -1,49 +1,71 @@
-use color_eyre::eyre::{Context, Result};
-
-use argon2::{
-    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
-    PasswordVerifier, Version,
-};
-
 use secrecy::{ExposeSecret, Secret};
+use sqlx::postgres::PgRow;
 use sqlx::{PgPool, Row};
 
+use super::password_hashing::{compute_password_hash, default_params, verify_password_hash};
 use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+    data_stores::{UserPage, UserQuery, UserStore, UserStoreError, UserSummary},
+    Email, EmailStatus, Password, PhoneNumber, User,
 };
+use argon2::Params;
 
 pub struct PostgresUserStore {
     pool: PgPool,
+    hashing_params: Params,
 }
 
 impl PostgresUserStore {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            hashing_params: default_params(),
+        }
+    }
+
+    // Hash new passwords with these Argon2 cost parameters
+    pub fn with_hashing_params(mut self, hashing_params: Params) -> Self {
+        self.hashing_params = hashing_params;
+        self
     }
 }
 
 #[async_trait::async_trait]
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        let _ = sqlx::query("INSERT INTO users VALUES ($1, $2, $3)")
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let _ = sqlx::query(
+            "INSERT INTO users (email, password_hash, requires_2fa, locale, phone_number, two_fa_channel)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
             .bind(user.email.as_ref().expose_secret())
             .bind(
                 // TODO is this an OK place to use expose_secret()?
-                compute_password_hash(user.password.as_ref().to_owned())
-                    .await
-                    .map_err(UserStoreError::UnexpectedError)?,
+                compute_password_hash(
+                    user.password.as_ref().to_owned(),
+                    self.hashing_params.clone(),
+                )
+                .await
+                .map_err(UserStoreError::UnexpectedError)?,
             )
             .bind(user.requires_2fa)
+            .bind(user.locale.as_str())
+            .bind(
+                user.phone_number
+                    .as_ref()
+                    .map(|phone_number| phone_number.as_ref().expose_secret()),
+            )
+            .bind(user.two_fa_channel.as_str())
             .execute(&self.pool)
             .await
-            .map_err(|_| {
-                // technically we should be looking at e to make sure the failure
-                // really was due to a primary key conflict, but it seemed
-                // like doing that was going to be nontrivial, so just
-                // assume for now
-                UserStoreError::UserAlreadyExists
+            .map_err(|e| match e {
+                // Only a primary key conflict means the user already exists,
+                // anything else (connection issues etc.) is unexpected
+                sqlx::Error::Database(ref db_err)
+                    if db_err.code().as_deref() == Some(UNIQUE_VIOLATION) =>
+                {
+                    UserStoreError::UserAlreadyExists
+                }
+                _ => UserStoreError::UnexpectedError(e.into()),
             })?;
 
         Ok(())
@@ -55,29 +77,27 @@ impl UserStore for PostgresUserStore {
             .bind(email.as_ref().expose_secret())
             .fetch_one(&self.pool)
             .await
-            .map_err(|e| {
-                println!("error getting user for validation");
-                dbg!(&e);
-                match e {
-                    sqlx::Error::RowNotFound => UserStoreError::UserNotFound,
-                    _ => UserStoreError::UnexpectedError(e.into()),
-                }
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => UserStoreError::UserNotFound,
+                _ => UserStoreError::UnexpectedError(e.into()),
             })?;
 
-        let email: String = res
-            .try_get("email")
-            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
-        let password: String = res
+        let summary = row_to_summary(&res)?;
+        let password_hash: String = res
             .try_get("password_hash")
             .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let phone_number: Option<String> = res
+            .try_get("phone_number")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-        let email_secret = Secret::new(email);
         Ok(User {
-            email: Email(email_secret),
-            password: Password(password.into()),
-            requires_2fa: res
-                .try_get("requires_2fa")
-                .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+            email: Email(Secret::new(summary.email)),
+            password: Password(Secret::new(password_hash)),
+            requires_2fa: summary.requires_2fa,
+            locale: summary.locale,
+            email_status: summary.email_status,
+            phone_number: phone_number.map(|phone_number| PhoneNumber(Secret::new(phone_number))),
+            two_fa_channel: summary.two_fa_channel,
         })
     }
 
@@ -91,86 +111,232 @@ impl UserStore for PostgresUserStore {
             .bind(email.as_ref().expose_secret())
             .fetch_one(&self.pool)
             .await
-            .map_err(|e| {
-                dbg!(&e);
-                match e {
-                    sqlx::Error::RowNotFound => UserStoreError::UserNotFound,
-                    _ => UserStoreError::UnexpectedError(e.into()),
-                }
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => UserStoreError::UserNotFound,
+                _ => UserStoreError::UnexpectedError(e.into()),
             })?;
 
         let expected_hash: String = res
             .try_get("password_hash")
             .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let password_reset_required: bool = res
+            .try_get("password_reset_required")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let disabled: bool = res
+            .try_get("disabled")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-        match verify_password_hash(Secret::new(expected_hash), password.as_ref().clone()).await {
-            Ok(()) => Ok(()),
-            _ => Err(UserStoreError::InvalidCredentials),
+        verify_password_hash(Secret::new(expected_hash), password.as_ref().clone())
+            .await
+            .map_err(|_| UserStoreError::InvalidCredentials)?;
+
+        if disabled {
+            return Err(UserStoreError::AccountDisabled);
+        }
+        if password_reset_required {
+            return Err(UserStoreError::PasswordResetRequired);
         }
-        .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+        Ok(())
     }
-}
 
-// Helper function to verify if a given password matches an expected hash
-// TODO: Hashing is a CPU-intensive operation. To avoid blocking
-// other async tasks, update this function to perform hashing on a
-// separate thread pool using tokio::task::spawn_blocking
-#[tracing::instrument(name = "Verify password hash", skip_all)]
-async fn verify_password_hash(
-    expected_password_hash: Secret<String>,
-    password_candidate: Secret<String>,
-) -> Result<()> {
-    // This line retrieves the current span from the tracing context.
-    // The span represents the execution context for the compute_password_hash function.
-    let current_span: tracing::Span = tracing::Span::current(); // New!
-    let result = tokio::task::spawn_blocking(move || {
-        // This code block ensures that the operations within the closure are executed within the context of the current span.
-        // This is especially useful for tracing operations that are performed in a different thread or task, such as within tokio::task::spawn_blocking.
-        current_span.in_scope(|| {
-            // New!
-            let expected_password_hash: PasswordHash<'_> =
-                PasswordHash::new(&expected_password_hash.expose_secret())?;
-
-            Argon2::default()
-                .verify_password(
-                    password_candidate.expose_secret().as_bytes(),
-                    &expected_password_hash,
-                )
-                .wrap_err("failed to verify password hash")
+    #[tracing::instrument(name = "Requiring password reset in PostgreSQL", skip_all)]
+    async fn require_password_reset(&self, email: &Email) -> Result<(), UserStoreError> {
+        let res = sqlx::query("UPDATE users SET password_reset_required = TRUE WHERE email = $1")
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Resetting password in PostgreSQL", skip_all)]
+    async fn reset_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash =
+            compute_password_hash(password.as_ref().to_owned(), self.hashing_params.clone())
+                .await
+                .map_err(UserStoreError::UnexpectedError)?;
+
+        let res = sqlx::query(
+            "UPDATE users SET password_hash = $2, password_reset_required = FALSE
+             WHERE email = $1 AND password_reset_required",
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(password_hash)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            let exists: bool =
+                sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE email = $1)")
+                    .bind(email.as_ref().expose_secret())
+                    .fetch_one(&self.pool)
+                    .await
+                    .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+            return Err(if exists {
+                UserStoreError::PasswordResetNotRequired
+            } else {
+                UserStoreError::UserNotFound
+            });
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting email status in PostgreSQL", skip_all)]
+    async fn set_email_status(
+        &self,
+        email: &Email,
+        status: EmailStatus,
+    ) -> Result<(), UserStoreError> {
+        let res = sqlx::query("UPDATE users SET email_status = $2 WHERE email = $1")
+            .bind(email.as_ref().expose_secret())
+            .bind(status.as_str())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing users in PostgreSQL", skip_all)]
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        // An empty search is contained in every email
+        let search = query.search.as_deref().unwrap_or_default();
+
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM users WHERE strpos(lower(email), lower($1)) > 0",
+        )
+        .bind(search)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let rows = sqlx::query(
+            "SELECT email, requires_2fa, locale, email_status, two_fa_channel, disabled,
+                    password_reset_required
+             FROM users WHERE strpos(lower(email), lower($1)) > 0
+             ORDER BY email LIMIT $2 OFFSET $3",
+        )
+        .bind(search)
+        // Past `i64::MAX` there are no more rows to skip or return anyway
+        .bind(i64::try_from(query.limit).unwrap_or(i64::MAX))
+        .bind(i64::try_from(query.offset).unwrap_or(i64::MAX))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(UserPage {
+            users: rows.iter().map(row_to_summary).collect::<Result<_, _>>()?,
+            total: total as usize,
         })
-    })
-    .await;
+    }
+
+    #[tracing::instrument(name = "Retrieving user summary from PostgreSQL", skip_all)]
+    async fn user_summary(&self, email: &Email) -> Result<UserSummary, UserStoreError> {
+        let row = sqlx::query(
+            "SELECT email, requires_2fa, locale, email_status, two_fa_channel, disabled,
+                    password_reset_required
+             FROM users WHERE email = $1",
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        row_to_summary(&row)
+    }
+
+    #[tracing::instrument(name = "Setting account disabled in PostgreSQL", skip_all)]
+    async fn set_disabled(&self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        let res = sqlx::query("UPDATE users SET disabled = $2 WHERE email = $1")
+            .bind(email.as_ref().expose_secret())
+            .bind(disabled)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
 
-    result?
+    #[tracing::instrument(name = "Setting 2FA requirement in PostgreSQL", skip_all)]
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let res = sqlx::query("UPDATE users SET requires_2fa = $2 WHERE email = $1")
+            .bind(email.as_ref().expose_secret())
+            .bind(requires_2fa)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "PostgreSQL health check", skip_all)]
+    async fn health_check(&self) -> Result<(), UserStoreError> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
 }
 
-// Helper function to hash passwords before persisting them in the database.
-// TODO: Hashing is a CPU-intensive operation. To avoid blocking
-// other async tasks, update this function to perform hashing on a
-// separate thread pool using tokio::task::spawn_blocking
-#[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: Secret<String>) -> Result<String> {
-    // This line retrieves the current span from the tracing context.
-    // The span represents the execution context for the compute_password_hash function.
-    let current_span: tracing::Span = tracing::Span::current(); // New!
-
-    let result = tokio::task::spawn_blocking(move || {
-        // This code block ensures that the operations within the closure are executed within the context of the current span.
-        // This is especially useful for tracing operations that are performed in a different thread or task, such as within tokio::task::spawn_blocking.
-        current_span.in_scope(|| {
-            let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
-            let password_hash = Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None)?,
-            )
-            .hash_password(password.expose_secret().as_bytes(), &salt)?
-            .to_string();
+// SQLSTATE Postgres reports when an insert violates a unique/primary key constraint
+const UNIQUE_VIOLATION: &str = "23505";
 
-            Ok(password_hash)
-        })
-    })
-    .await;
+// The columns `get_user` and the admin views have in common
+fn row_to_summary(row: &PgRow) -> Result<UserSummary, UserStoreError> {
+    let locale: String = row
+        .try_get("locale")
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+    let email_status: String = row
+        .try_get("email_status")
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+    let two_fa_channel: String = row
+        .try_get("two_fa_channel")
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-    result?
+    Ok(UserSummary {
+        email: row
+            .try_get("email")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+        requires_2fa: row
+            .try_get("requires_2fa")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+        // A locale we no longer ship falls back to the default
+        locale: locale.parse().unwrap_or_default(),
+        email_status: email_status
+            .parse()
+            .map_err(UserStoreError::UnexpectedError)?,
+        two_fa_channel: two_fa_channel
+            .parse()
+            .map_err(UserStoreError::UnexpectedError)?,
+        disabled: row
+            .try_get("disabled")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+        password_reset_required: row
+            .try_get("password_reset_required")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+    })
 }
@@ -1,32 +1,50 @@
-use std::sync::Arc;
+use std::time::Duration;
 
-use redis::{Commands, Connection};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use redis::{aio::ConnectionManager, AsyncCommands};
 use secrecy::{ExposeSecret, Secret};
-use tokio::sync::RwLock;
 
 use crate::{
-    domain::data_stores::banned_token_store::{BannedTokenStore, BannedTokenStoreError},
+    domain::{
+        data_stores::banned_token_store::{BannedTokenStore, BannedTokenStoreError},
+        Email,
+    },
     utils::auth::TOKEN_TTL_SECONDS,
 };
 
+// `ConnectionManager` multiplexes commands over one connection and reconnects
+// on its own, so each call works on a cheap clone instead of taking a lock.
 pub struct RedisBannedTokenStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: ConnectionManager,
+    ttl: Duration,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self {
+            conn,
+            ttl: Duration::from_secs(TOKEN_TTL_SECONDS as u64),
+        }
+    }
+
+    // Overrides how long a banned token is remembered. Redis expiry has
+    // whole-second granularity, so anything below a second is rounded down.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
     }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Add Token", skip_all)]
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
-        let key = get_key(&token.expose_secret());
-        let mut write_lock = self.conn.write().await;
-        write_lock
-            .set_ex(key, true, TOKEN_TTL_SECONDS as u64)
+    async fn add_token(&self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
+        let key = get_key(token.expose_secret());
+        self.conn
+            .clone()
+            .set_ex::<_, _, ()>(key, true, self.ttl.as_secs())
+            .await
             .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
 
         Ok(())
@@ -35,21 +53,83 @@ impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Contains Token", skip_all)]
     async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
         // Check if the token exists by calling the exists method on the Redis connection
-        let key = get_key(&token.expose_secret());
-        // TODO tried getting a read lock, didn't work for some reason
-        let mut read_lock = self.conn.write().await;
+        let key = get_key(token.expose_secret());
 
-        let exists = read_lock
+        let exists = self
+            .conn
+            .clone()
             .exists(key)
+            .await
             .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
 
         Ok(exists)
     }
+
+    #[tracing::instrument(name = "Revoke Sessions", skip_all)]
+    async fn revoke_sessions(
+        &self,
+        email: &Email,
+        revoked_at: DateTime<Utc>,
+    ) -> Result<(), BannedTokenStoreError> {
+        let key = get_revoked_sessions_key(email);
+        self.conn
+            .clone()
+            .set_ex::<_, _, ()>(key, revoked_at.timestamp_millis(), self.ttl.as_secs())
+            .await
+            .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Sessions Revoked At", skip_all)]
+    async fn sessions_revoked_at(
+        &self,
+        email: &Email,
+    ) -> Result<Option<DateTime<Utc>>, BannedTokenStoreError> {
+        let key = get_revoked_sessions_key(email);
+        let revoked_at: Option<i64> = self
+            .conn
+            .clone()
+            .get(key)
+            .await
+            .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        revoked_at
+            .map(|millis| {
+                DateTime::from_timestamp_millis(millis).ok_or_else(|| {
+                    BannedTokenStoreError::UnexpectedError(eyre!(
+                        "invalid session revocation time {}",
+                        millis
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "Redis health check", skip_all)]
+    async fn health_check(&self) -> Result<(), BannedTokenStoreError> {
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut self.conn.clone())
+            .await
+            .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
 }
 
 // We are using a key prefix to prevent collisions and organize data!
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
 
+const REVOKED_SESSIONS_KEY_PREFIX: &str = "revoked_sessions:";
+
 fn get_key(token: &str) -> String {
     format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
 }
+
+fn get_revoked_sessions_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        REVOKED_SESSIONS_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
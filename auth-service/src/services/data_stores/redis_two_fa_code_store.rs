@@ -1,22 +1,36 @@
-use std::sync::Arc;
+use std::time::Duration;
 
-use redis::{Commands, Connection};
+use redis::{aio::ConnectionManager, AsyncCommands};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
 
 use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    data_stores::{
+        LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError, TWO_FA_CODE_TTL_SECONDS,
+    },
     Email,
 };
 
+// `ConnectionManager` multiplexes commands over one connection and reconnects
+// on its own, so each call works on a cheap clone instead of taking a lock.
 pub struct RedisTwoFACodeStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: ConnectionManager,
+    ttl: Duration,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self {
+            conn,
+            ttl: Duration::from_secs(TWO_FA_CODE_TTL_SECONDS),
+        }
+    }
+
+    // Overrides how long a 2FA code stays valid. Redis expiry has
+    // whole-second granularity, so anything below a second is rounded down.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
     }
 }
 
@@ -24,7 +38,7 @@ impl RedisTwoFACodeStore {
 impl TwoFACodeStore for RedisTwoFACodeStore {
     #[tracing::instrument(name = "Add Two FA Code", skip_all)]
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
@@ -36,7 +50,7 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         // Return TwoFACodeStoreError::UnexpectedError if serialization fails.
         // 4. Call the set_ex command on the Redis connection to set a new key/value pair with an expiration time (TTL).
         // The value should be the serialized 2FA tuple.
-        // The expiration time should be set to TEN_MINUTES_IN_SECONDS.
+        // The expiration time should be set to the store's TTL.
         // Return TwoFACodeStoreError::UnexpectedError if casting fails or the call to set_ex fails.
 
         let key = get_key(&email);
@@ -44,27 +58,29 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
             login_attempt_id.as_ref().to_string(),
             code.as_ref().to_string(),
         );
-        let mut write_lock = self.conn.write().await;
         let json_string = serde_json::to_string(&tuple)
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
-        write_lock
-            .set_ex(key, json_string, TEN_MINUTES_IN_SECONDS)
+        self.conn
+            .clone()
+            .set_ex::<_, _, ()>(key, json_string, self.ttl.as_secs())
+            .await
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
 
         Ok(())
     }
 
     #[tracing::instrument(name = "Remove Two FA Code", skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+    async fn remove_code(&self, email: &Email) -> Result<(), TwoFACodeStoreError> {
         // TODO:
         // 1. Create a new key using the get_key helper function.
         // 2. Call the del command on the Redis connection to delete the 2FA code entry.
         // Return TwoFACodeStoreError::UnexpectedError if the operation fails.
 
-        let key = get_key(&email);
-        let mut write_lock = self.conn.write().await;
-        write_lock
-            .del(key)
+        let key = get_key(email);
+        self.conn
+            .clone()
+            .del::<_, ()>(key)
+            .await
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
         Ok(())
     }
@@ -77,28 +93,43 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         // TODO:
         // 1. Create a new key using the get_key helper function.
         // 2. Call the get command on the Redis connection to get the value stored for the key.
-        // Return TwoFACodeStoreError::LoginAttemptIdNotFound if the operation fails.
+        // Return TwoFACodeStoreError::LoginAttemptIdNotFound if there is no value for the key.
         // If the operation succeeds, call serde_json::from_str to parse the JSON string into a TwoFATuple.
         // Then, parse the login attempt ID string and 2FA code string into a LoginAttemptId and TwoFACode type respectively.
         // Return TwoFACodeStoreError::UnexpectedError if parsing fails.
 
-        let key = get_key(&email);
-        let mut write_lock = self.conn.write().await;
-        let val: String = write_lock
+        let key = get_key(email);
+        let val: Option<String> = self
+            .conn
+            .clone()
             .get(key)
-            .map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        let val = val.ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
 
         let val: TwoFATuple = serde_json::from_str(&val)
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
 
-        Ok((LoginAttemptId(Secret::new(val.0)), TwoFACode(Secret::new(val.1))))
+        Ok((
+            LoginAttemptId(Secret::new(val.0)),
+            TwoFACode(Secret::new(val.1)),
+        ))
+    }
+
+    #[tracing::instrument(name = "Redis health check", skip_all)]
+    async fn health_check(&self) -> Result<(), TwoFACodeStoreError> {
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut self.conn.clone())
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
     }
 }
 
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
-const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
 
 fn get_key(email: &Email) -> String {
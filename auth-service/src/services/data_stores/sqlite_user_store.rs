@@ -0,0 +1,337 @@
+use secrecy::{ExposeSecret, Secret};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqlitePool};
+
+use super::password_hashing::{compute_password_hash, default_params, verify_password_hash};
+use crate::domain::{
+    data_stores::{UserPage, UserQuery, UserStore, UserStoreError, UserSummary},
+    Email, EmailStatus, Password, PhoneNumber, User,
+};
+use argon2::Params;
+
+pub struct SqliteUserStore {
+    pool: SqlitePool,
+    hashing_params: Params,
+}
+
+impl SqliteUserStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            hashing_params: default_params(),
+        }
+    }
+
+    // Hash new passwords with these Argon2 cost parameters
+    pub fn with_hashing_params(mut self, hashing_params: Params) -> Self {
+        self.hashing_params = hashing_params;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl UserStore for SqliteUserStore {
+    #[tracing::instrument(name = "Adding user to SQLite", skip_all)]
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(
+            user.password.as_ref().to_owned(),
+            self.hashing_params.clone(),
+        )
+        .await
+        .map_err(UserStoreError::UnexpectedError)?;
+
+        sqlx::query(
+            "INSERT INTO users (email, password_hash, requires_2fa, locale, phone_number, two_fa_channel)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+            .bind(user.email.as_ref().expose_secret())
+            .bind(password_hash)
+            .bind(user.requires_2fa)
+            .bind(user.locale.as_str())
+            .bind(
+                user.phone_number
+                    .as_ref()
+                    .map(|phone_number| phone_number.as_ref().expose_secret()),
+            )
+            .bind(user.two_fa_channel.as_str())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(ref db_err)
+                    if matches!(
+                        db_err.code().as_deref(),
+                        Some(CONSTRAINT_PRIMARYKEY | CONSTRAINT_UNIQUE)
+                    ) =>
+                {
+                    UserStoreError::UserAlreadyExists
+                }
+                _ => UserStoreError::UnexpectedError(e.into()),
+            })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving user from SQLite", skip_all)]
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        let row = sqlx::query(
+            "SELECT email, password_hash, requires_2fa, locale, email_status, phone_number,
+                    two_fa_channel, disabled, password_reset_required
+             FROM users WHERE email = $1",
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        let summary = row_to_summary(&row)?;
+        let password_hash: String = row
+            .try_get("password_hash")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let phone_number: Option<String> = row
+            .try_get("phone_number")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(User {
+            email: Email(Secret::new(summary.email)),
+            password: Password(Secret::new(password_hash)),
+            requires_2fa: summary.requires_2fa,
+            locale: summary.locale,
+            email_status: summary.email_status,
+            phone_number: phone_number.map(|phone_number| PhoneNumber(Secret::new(phone_number))),
+            two_fa_channel: summary.two_fa_channel,
+        })
+    }
+
+    #[tracing::instrument(name = "Validating user credentials in SQLite", skip_all)]
+    async fn validate_user(
+        &self,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError> {
+        let (expected_hash, disabled, password_reset_required): (String, bool, bool) =
+            sqlx::query_as(
+                "SELECT password_hash, disabled, password_reset_required
+                 FROM users WHERE email = $1",
+            )
+            .bind(email.as_ref().expose_secret())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        verify_password_hash(Secret::new(expected_hash), password.as_ref().clone())
+            .await
+            .map_err(|_| UserStoreError::InvalidCredentials)?;
+
+        if disabled {
+            return Err(UserStoreError::AccountDisabled);
+        }
+        if password_reset_required {
+            return Err(UserStoreError::PasswordResetRequired);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Requiring password reset in SQLite", skip_all)]
+    async fn require_password_reset(&self, email: &Email) -> Result<(), UserStoreError> {
+        let res = sqlx::query("UPDATE users SET password_reset_required = TRUE WHERE email = $1")
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Resetting password in SQLite", skip_all)]
+    async fn reset_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash =
+            compute_password_hash(password.as_ref().to_owned(), self.hashing_params.clone())
+                .await
+                .map_err(UserStoreError::UnexpectedError)?;
+
+        let res = sqlx::query(
+            "UPDATE users SET password_hash = $2, password_reset_required = FALSE
+             WHERE email = $1 AND password_reset_required",
+        )
+        .bind(email.as_ref().expose_secret())
+        .bind(password_hash)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            let exists: bool =
+                sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE email = $1)")
+                    .bind(email.as_ref().expose_secret())
+                    .fetch_one(&self.pool)
+                    .await
+                    .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+            return Err(if exists {
+                UserStoreError::PasswordResetNotRequired
+            } else {
+                UserStoreError::UserNotFound
+            });
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting email status in SQLite", skip_all)]
+    async fn set_email_status(
+        &self,
+        email: &Email,
+        status: EmailStatus,
+    ) -> Result<(), UserStoreError> {
+        let res = sqlx::query("UPDATE users SET email_status = $2 WHERE email = $1")
+            .bind(email.as_ref().expose_secret())
+            .bind(status.as_str())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing users in SQLite", skip_all)]
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        // An empty search is contained in every email
+        let search = query.search.as_deref().unwrap_or_default();
+
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM users WHERE instr(lower(email), lower($1)) > 0",
+        )
+        .bind(search)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let rows = sqlx::query(
+            "SELECT email, requires_2fa, locale, email_status, two_fa_channel, disabled,
+                    password_reset_required
+             FROM users WHERE instr(lower(email), lower($1)) > 0
+             ORDER BY email LIMIT $2 OFFSET $3",
+        )
+        .bind(search)
+        // Past `i64::MAX` there are no more rows to skip or return anyway
+        .bind(i64::try_from(query.limit).unwrap_or(i64::MAX))
+        .bind(i64::try_from(query.offset).unwrap_or(i64::MAX))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(UserPage {
+            users: rows.iter().map(row_to_summary).collect::<Result<_, _>>()?,
+            total: total as usize,
+        })
+    }
+
+    #[tracing::instrument(name = "Retrieving user summary from SQLite", skip_all)]
+    async fn user_summary(&self, email: &Email) -> Result<UserSummary, UserStoreError> {
+        let row = sqlx::query(
+            "SELECT email, requires_2fa, locale, email_status, two_fa_channel, disabled,
+                    password_reset_required
+             FROM users WHERE email = $1",
+        )
+        .bind(email.as_ref().expose_secret())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        row_to_summary(&row)
+    }
+
+    #[tracing::instrument(name = "Setting account disabled in SQLite", skip_all)]
+    async fn set_disabled(&self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        let res = sqlx::query("UPDATE users SET disabled = $2 WHERE email = $1")
+            .bind(email.as_ref().expose_secret())
+            .bind(disabled)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting 2FA requirement in SQLite", skip_all)]
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let res = sqlx::query("UPDATE users SET requires_2fa = $2 WHERE email = $1")
+            .bind(email.as_ref().expose_secret())
+            .bind(requires_2fa)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "SQLite health check", skip_all)]
+    async fn health_check(&self) -> Result<(), UserStoreError> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+// Extended result codes SQLite reports when an insert violates a key constraint
+const CONSTRAINT_PRIMARYKEY: &str = "1555";
+const CONSTRAINT_UNIQUE: &str = "2067";
+
+// The columns `get_user` and the admin views have in common
+fn row_to_summary(row: &SqliteRow) -> Result<UserSummary, UserStoreError> {
+    let locale: String = row
+        .try_get("locale")
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+    let email_status: String = row
+        .try_get("email_status")
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+    let two_fa_channel: String = row
+        .try_get("two_fa_channel")
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+    Ok(UserSummary {
+        email: row
+            .try_get("email")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+        requires_2fa: row
+            .try_get("requires_2fa")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+        // A locale we no longer ship falls back to the default
+        locale: locale.parse().unwrap_or_default(),
+        email_status: email_status
+            .parse()
+            .map_err(UserStoreError::UnexpectedError)?,
+        two_fa_channel: two_fa_channel
+            .parse()
+            .map_err(UserStoreError::UnexpectedError)?,
+        disabled: row
+            .try_get("disabled")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+        password_reset_required: row
+            .try_get("password_reset_required")
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+    })
+}
@@ -0,0 +1,41 @@
+use std::collections::VecDeque;
+
+use tokio::sync::RwLock;
+
+use crate::domain::data_stores::audit_log::{
+    AuditEvent, AuditEventFilter, AuditLog, AuditLogError,
+};
+
+// Oldest events are dropped past this, so a long-running dev instance
+// doesn't grow forever
+const MAX_EVENTS: usize = 10_000;
+
+#[derive(Default)]
+pub struct VecAuditLog {
+    events: RwLock<VecDeque<AuditEvent>>,
+}
+
+#[async_trait::async_trait]
+impl AuditLog for VecAuditLog {
+    async fn record(&self, event: AuditEvent) -> Result<(), AuditLogError> {
+        let mut events = self.events.write().await;
+        if events.len() == MAX_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(event);
+        Ok(())
+    }
+
+    async fn query(&self, filter: &AuditEventFilter) -> Result<Vec<AuditEvent>, AuditLogError> {
+        Ok(self
+            .events
+            .read()
+            .await
+            .iter()
+            .rev()
+            .filter(|event| filter.matches(event))
+            .take(filter.limit)
+            .cloned()
+            .collect())
+    }
+}
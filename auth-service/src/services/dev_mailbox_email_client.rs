@@ -0,0 +1,187 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{Context, Result};
+use tokio::sync::RwLock;
+
+use crate::domain::{Email, EmailClient, EmailMessage};
+use crate::services::smtp_email_client::mime_message;
+use crate::settings::DevMailboxSettings;
+
+// An email caught by the dev mailbox instead of being delivered
+#[derive(Debug, Clone)]
+pub struct CapturedEmail {
+    pub id: u64,
+    pub recipient: Email,
+    pub message: EmailMessage,
+    pub received_at: DateTime<Utc>,
+}
+
+// For local development: nothing leaves the machine. Emails are kept in
+// memory, where /dev/mailbox shows them, and optionally written to a
+// directory as .eml files that any mail client can open.
+pub struct DevMailbox {
+    sender: Email,
+    directory: Option<PathBuf>,
+    max_messages: usize,
+    emails: RwLock<VecDeque<CapturedEmail>>,
+    last_id: AtomicU64,
+}
+
+impl DevMailbox {
+    pub fn new(settings: &DevMailboxSettings, sender: Email) -> Result<Self> {
+        let directory = settings.directory.as_ref().map(PathBuf::from);
+        if let Some(directory) = &directory {
+            std::fs::create_dir_all(directory).wrap_err_with(|| {
+                format!("Failed to create mailbox directory {}", directory.display())
+            })?;
+        }
+
+        Ok(Self {
+            sender,
+            directory,
+            max_messages: settings.max_messages,
+            emails: RwLock::new(VecDeque::new()),
+            last_id: AtomicU64::new(0),
+        })
+    }
+
+    // Newest first
+    pub async fn emails(&self) -> Vec<CapturedEmail> {
+        self.emails.read().await.iter().rev().cloned().collect()
+    }
+
+    pub async fn email(&self, id: u64) -> Option<CapturedEmail> {
+        self.emails
+            .read()
+            .await
+            .iter()
+            .find(|email| email.id == id)
+            .cloned()
+    }
+
+    async fn write_to_directory(&self, email: &CapturedEmail) -> Result<Option<PathBuf>> {
+        let Some(directory) = &self.directory else {
+            return Ok(None);
+        };
+        let path = directory.join(format!(
+            "{}-{}.eml",
+            email.received_at.format("%Y%m%dT%H%M%S"),
+            email.id
+        ));
+        let contents = mime_message(&self.sender, &email.recipient, &email.message)?.formatted();
+        tokio::fs::write(&path, contents)
+            .await
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+        Ok(Some(path))
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for DevMailbox {
+    #[tracing::instrument(name = "Capturing email in dev mailbox", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let email = CapturedEmail {
+            id: self.last_id.fetch_add(1, Ordering::Relaxed) + 1,
+            recipient: recipient.clone(),
+            message: message.clone(),
+            received_at: Utc::now(),
+        };
+        let path = self.write_to_directory(&email).await?;
+        // Codes and links are redacted from the logs, so point at where the
+        // email can be read in full
+        tracing::info!(
+            id = email.id,
+            subject = email.message.subject,
+            path = path.map(|path| path.display().to_string()),
+            "Captured email, see /dev/mailbox/{}",
+            email.id
+        );
+
+        if self.max_messages > 0 {
+            let mut emails = self.emails.write().await;
+            if emails.len() == self.max_messages {
+                emails.pop_front();
+            }
+            emails.push_back(email);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::ExposeSecret;
+
+    use super::*;
+
+    fn email(address: &str) -> Email {
+        Email::parse(address.to_owned()).unwrap()
+    }
+
+    fn message(subject: &str) -> EmailMessage {
+        EmailMessage {
+            subject: subject.to_owned(),
+            html_body: "<p>Your code is 123456</p>".to_owned(),
+            text_body: "Your code is 123456".to_owned(),
+        }
+    }
+
+    fn mailbox(directory: Option<&PathBuf>, max_messages: usize) -> DevMailbox {
+        let settings = DevMailboxSettings {
+            directory: directory.map(|directory| directory.display().to_string()),
+            max_messages,
+        };
+        DevMailbox::new(&settings, email("sender@example.com")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn keeps_the_newest_emails() {
+        let mailbox = mailbox(None, 2);
+
+        for subject in ["first", "second", "third"] {
+            mailbox
+                .send_email(&email("user@example.com"), &message(subject))
+                .await
+                .unwrap();
+        }
+
+        let subjects: Vec<String> = mailbox
+            .emails()
+            .await
+            .into_iter()
+            .map(|email| email.message.subject)
+            .collect();
+        assert_eq!(subjects, vec!["third", "second"]);
+        assert!(mailbox.email(1).await.is_none());
+        let third = mailbox.email(3).await.unwrap();
+        assert_eq!(third.recipient.as_ref().expose_secret(), "user@example.com");
+    }
+
+    #[tokio::test]
+    async fn writes_emails_to_the_directory() {
+        let directory = std::env::temp_dir().join(format!("dev-mailbox-{}", uuid::Uuid::new_v4()));
+        let mailbox = mailbox(Some(&directory), 0);
+
+        mailbox
+            .send_email(&email("user@example.com"), &message("Your login code"))
+            .await
+            .unwrap();
+
+        let files: Vec<PathBuf> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+        let contents = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(contents.contains("To: user@example.com"));
+        assert!(contents.contains("Subject: Your login code"));
+        assert!(contents.contains("Your code is 123456"));
+        // Nothing is kept in memory with max_messages = 0
+        assert!(mailbox.emails().await.is_empty());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
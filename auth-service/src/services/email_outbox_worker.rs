@@ -0,0 +1,289 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::app_state::{EmailClientType, EmailOutboxType};
+use crate::domain::data_stores::email_outbox::{EmailOutboxError, OutboxEmail};
+use crate::settings::EmailOutboxSettings;
+use crate::utils::metrics::Metrics;
+
+// Delivers emails from the outbox through the email client. Failed deliveries
+// are retried with exponential backoff, and dead-lettered once they've used
+// up their attempts. Dead letters are deleted once past their retention.
+// Several instances can run side by side, since every email is claimed
+// before it's sent.
+pub struct EmailOutboxWorker {
+    outbox: EmailOutboxType,
+    email_client: EmailClientType,
+    metrics: Arc<Metrics>,
+    poll_interval: Duration,
+    batch_size: usize,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    lease: Duration,
+    dead_letter_retention: Duration,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(
+        outbox: EmailOutboxType,
+        email_client: EmailClientType,
+        metrics: Arc<Metrics>,
+        settings: &EmailOutboxSettings,
+    ) -> Self {
+        Self {
+            outbox,
+            email_client,
+            metrics,
+            poll_interval: settings.poll_interval(),
+            batch_size: settings.batch_size,
+            max_attempts: settings.max_attempts,
+            initial_backoff: settings.initial_backoff(),
+            max_backoff: settings.max_backoff(),
+            lease: settings.lease(),
+            dead_letter_retention: settings.dead_letter_retention(),
+        }
+    }
+
+    // Polls the outbox until the runtime shuts down. Failures are logged and
+    // retried on the next tick rather than bringing the task down.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.poll_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                // Keep going without waiting while there's a backlog
+                loop {
+                    match self.deliver_due().await {
+                        Ok(handled) if handled == self.batch_size => continue,
+                        Ok(_) => break,
+                        Err(e) => {
+                            tracing::warn!(error = %e, "failed to deliver emails from outbox");
+                            break;
+                        }
+                    }
+                }
+                if let Err(e) = self
+                    .outbox
+                    .purge_dead_letters(self.dead_letter_retention)
+                    .await
+                {
+                    tracing::warn!(error = %e, "failed to purge dead-lettered emails");
+                }
+                if let Ok(pending) = self.outbox.pending_count().await {
+                    self.metrics.record_email_outbox_pending(pending);
+                }
+            }
+        })
+    }
+
+    // Makes one delivery attempt for every due email, up to the batch size,
+    // and returns how many were claimed
+    #[tracing::instrument(name = "Delivering emails from outbox", skip_all)]
+    pub async fn deliver_due(&self) -> Result<usize, EmailOutboxError> {
+        let emails = self.outbox.claim_due(self.batch_size, self.lease).await?;
+        for email in &emails {
+            self.deliver(email).await?;
+        }
+        Ok(emails.len())
+    }
+
+    async fn deliver(&self, email: &OutboxEmail) -> Result<(), EmailOutboxError> {
+        let error = match self
+            .email_client
+            .send_email(&email.recipient, &email.message)
+            .await
+        {
+            Ok(()) => return self.outbox.mark_sent(email.id).await,
+            Err(e) => format!("{:#}", e),
+        };
+
+        if email.attempts >= self.max_attempts {
+            tracing::error!(
+                id = email.id,
+                attempts = email.attempts,
+                error,
+                "giving up on email, dead-lettering it"
+            );
+            self.outbox.dead_letter(email.id, &error).await?;
+            self.metrics.record_email_dead_lettered();
+        } else {
+            let delay = backoff(email.attempts, self.initial_backoff, self.max_backoff);
+            tracing::warn!(
+                id = email.id,
+                attempts = email.attempts,
+                retry_in_ms = delay.as_millis() as u64,
+                error,
+                "failed to send email, will retry"
+            );
+            self.outbox.retry_later(email.id, delay, &error).await?;
+        }
+        Ok(())
+    }
+}
+
+// Wait after the given number of failed attempts: `initial`, then doubling
+// every time, but never more than `max`
+fn backoff(attempts: u32, initial: Duration, max: Duration) -> Duration {
+    let doublings = attempts.saturating_sub(1).min(31);
+    initial.saturating_mul(1 << doublings).min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use color_eyre::eyre::{eyre, Result};
+
+    use super::*;
+    use crate::domain::{Email, EmailClient, EmailMessage};
+    use crate::services::data_stores::hashmap_email_outbox::HashMapEmailOutbox;
+
+    // Fails the first `failures` sends, then succeeds
+    struct FlakyEmailClient {
+        failures: usize,
+        calls: AtomicUsize,
+    }
+
+    impl FlakyEmailClient {
+        fn failing(failures: usize) -> Arc<Self> {
+            Arc::new(Self {
+                failures,
+                calls: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for FlakyEmailClient {
+        async fn send_email(&self, _recipient: &Email, _message: &EmailMessage) -> Result<()> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                Err(eyre!("provider unavailable"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn settings() -> EmailOutboxSettings {
+        EmailOutboxSettings {
+            poll_interval_milliseconds: 10,
+            batch_size: 10,
+            max_attempts: 3,
+            // Retries are due straight away, so each pass makes one attempt
+            initial_backoff_milliseconds: 0,
+            max_backoff_milliseconds: 0,
+            lease_milliseconds: 60_000,
+            dead_letter_retention_seconds: 3600,
+        }
+    }
+
+    fn worker(outbox: EmailOutboxType, client: Arc<FlakyEmailClient>) -> EmailOutboxWorker {
+        EmailOutboxWorker::new(outbox, client, Arc::new(Metrics::new()), &settings())
+    }
+
+    async fn enqueue(outbox: &EmailOutboxType) {
+        outbox
+            .enqueue(
+                Email::parse("user@example.com".to_owned()).unwrap(),
+                EmailMessage {
+                    subject: "Subject".to_owned(),
+                    html_body: "<p>Body</p>".to_owned(),
+                    text_body: "Body".to_owned(),
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let initial = Duration::from_secs(1);
+        let max = Duration::from_secs(10);
+
+        let delays: Vec<u64> = (1..=6)
+            .map(|attempts| backoff(attempts, initial, max).as_secs())
+            .collect();
+
+        assert_eq!(delays, vec![1, 2, 4, 8, 10, 10]);
+        assert_eq!(backoff(u32::MAX, initial, max), max);
+    }
+
+    #[tokio::test]
+    async fn delivered_emails_leave_the_outbox() {
+        let outbox: EmailOutboxType = Arc::new(HashMapEmailOutbox::default());
+        let client = FlakyEmailClient::failing(0);
+        enqueue(&outbox).await;
+
+        let handled = worker(outbox.clone(), client.clone())
+            .deliver_due()
+            .await
+            .unwrap();
+
+        assert_eq!(handled, 1);
+        assert_eq!(client.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(outbox.pending_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried() {
+        let outbox: EmailOutboxType = Arc::new(HashMapEmailOutbox::default());
+        let client = FlakyEmailClient::failing(2);
+        let worker = worker(outbox.clone(), client.clone());
+        enqueue(&outbox).await;
+
+        for _ in 0..3 {
+            worker.deliver_due().await.unwrap();
+        }
+
+        assert_eq!(client.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(outbox.pending_count().await.unwrap(), 0);
+        assert!(outbox.dead_letters().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn emails_are_dead_lettered_after_max_attempts() {
+        let outbox: EmailOutboxType = Arc::new(HashMapEmailOutbox::default());
+        let client = FlakyEmailClient::failing(usize::MAX);
+        let worker = worker(outbox.clone(), client.clone());
+        enqueue(&outbox).await;
+
+        for _ in 0..5 {
+            worker.deliver_due().await.unwrap();
+        }
+
+        // No attempts past the third
+        assert_eq!(client.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(outbox.pending_count().await.unwrap(), 0);
+        let dead_letters = outbox.dead_letters().await.unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].attempts, 3);
+        assert_eq!(
+            dead_letters[0].last_error.as_deref(),
+            Some("provider unavailable")
+        );
+    }
+
+    #[tokio::test]
+    async fn spawned_worker_delivers_queued_emails() {
+        let outbox: EmailOutboxType = Arc::new(HashMapEmailOutbox::default());
+        let client = FlakyEmailClient::failing(1);
+        let handle = worker(outbox.clone(), client.clone()).spawn();
+        enqueue(&outbox).await;
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while outbox.pending_count().await.unwrap() > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("email should be delivered");
+        handle.abort();
+
+        assert_eq!(client.calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,472 @@
+// Every email the service sends, rendered from the HTML and plain-text
+// templates in templates/emails. Templates are compiled in and checked at
+// build time; both versions extend a shared layout carrying the branding,
+// and take their wording from the recipient's locale.
+use std::time::Duration;
+
+use askama::Template;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+
+use crate::domain::data_stores::known_device_store::Device;
+use crate::domain::data_stores::TwoFACode;
+use crate::domain::{EmailMessage, Locale};
+use crate::i18n::{catalog, Catalog};
+use crate::settings::BrandingSettings;
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.html")]
+struct TwoFaCodeHtml<'a> {
+    t: &'a dyn Catalog,
+    lang: &'a str,
+    subject: &'a str,
+    branding: &'a BrandingSettings,
+    code: &'a str,
+    expiry: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.txt")]
+struct TwoFaCodeText<'a> {
+    t: &'a dyn Catalog,
+    branding: &'a BrandingSettings,
+    code: &'a str,
+    expiry: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/email_verification.html")]
+struct EmailVerificationHtml<'a> {
+    t: &'a dyn Catalog,
+    lang: &'a str,
+    subject: &'a str,
+    branding: &'a BrandingSettings,
+    link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/email_verification.txt")]
+struct EmailVerificationText<'a> {
+    t: &'a dyn Catalog,
+    branding: &'a BrandingSettings,
+    link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.html")]
+struct PasswordResetHtml<'a> {
+    t: &'a dyn Catalog,
+    lang: &'a str,
+    subject: &'a str,
+    branding: &'a BrandingSettings,
+    link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.txt")]
+struct PasswordResetText<'a> {
+    t: &'a dyn Catalog,
+    branding: &'a BrandingSettings,
+    link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset_required.html")]
+struct PasswordResetRequiredHtml<'a> {
+    t: &'a dyn Catalog,
+    lang: &'a str,
+    subject: &'a str,
+    branding: &'a BrandingSettings,
+    link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset_required.txt")]
+struct PasswordResetRequiredText<'a> {
+    t: &'a dyn Catalog,
+    branding: &'a BrandingSettings,
+    link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/new_device_alert.html")]
+struct NewDeviceAlertHtml<'a> {
+    t: &'a dyn Catalog,
+    lang: &'a str,
+    subject: &'a str,
+    branding: &'a BrandingSettings,
+    time: &'a str,
+    ip: &'a str,
+    user_agent: &'a str,
+    link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/new_device_alert.txt")]
+struct NewDeviceAlertText<'a> {
+    t: &'a dyn Catalog,
+    branding: &'a BrandingSettings,
+    time: &'a str,
+    ip: &'a str,
+    user_agent: &'a str,
+    link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/invitation.html")]
+struct InvitationHtml<'a> {
+    t: &'a dyn Catalog,
+    lang: &'a str,
+    subject: &'a str,
+    branding: &'a BrandingSettings,
+    inviter: &'a str,
+    organization: &'a str,
+    link: &'a str,
+    expiry: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/invitation.txt")]
+struct InvitationText<'a> {
+    t: &'a dyn Catalog,
+    branding: &'a BrandingSettings,
+    inviter: &'a str,
+    organization: &'a str,
+    link: &'a str,
+    expiry: &'a str,
+}
+
+fn message(subject: String, html: impl Template, text: impl Template) -> Result<EmailMessage> {
+    Ok(EmailMessage {
+        html_body: html.render()?,
+        text_body: text.render()?,
+        subject,
+    })
+}
+
+// The code that completes a login with 2FA
+pub fn two_fa_code(
+    branding: &BrandingSettings,
+    locale: Locale,
+    code: &TwoFACode,
+    valid_for: Duration,
+) -> Result<EmailMessage> {
+    let t = catalog(locale);
+    let subject = t.two_fa_code_subject(&branding.product_name);
+    let code = code.as_ref();
+    let expiry = t.two_fa_code_expiry(valid_for.as_secs().div_ceil(60));
+    message(
+        subject.clone(),
+        TwoFaCodeHtml {
+            t,
+            lang: locale.as_str(),
+            subject: &subject,
+            branding,
+            code,
+            expiry: &expiry,
+        },
+        TwoFaCodeText {
+            t,
+            branding,
+            code,
+            expiry: &expiry,
+        },
+    )
+}
+
+// Asks a new user to confirm they own their email address
+pub fn email_verification(
+    branding: &BrandingSettings,
+    locale: Locale,
+    link: &str,
+) -> Result<EmailMessage> {
+    let t = catalog(locale);
+    let subject = t.email_verification_subject(&branding.product_name);
+    message(
+        subject.clone(),
+        EmailVerificationHtml {
+            t,
+            lang: locale.as_str(),
+            subject: &subject,
+            branding,
+            link,
+        },
+        EmailVerificationText { t, branding, link },
+    )
+}
+
+// Lets a user who asked for it choose a new password
+pub fn password_reset(
+    branding: &BrandingSettings,
+    locale: Locale,
+    link: &str,
+) -> Result<EmailMessage> {
+    let t = catalog(locale);
+    let subject = t.password_reset_subject(&branding.product_name);
+    message(
+        subject.clone(),
+        PasswordResetHtml {
+            t,
+            lang: locale.as_str(),
+            subject: &subject,
+            branding,
+            link,
+        },
+        PasswordResetText { t, branding, link },
+    )
+}
+
+// Sent when an admin locks the user's password, with a link to choose a
+// new one
+pub fn password_reset_required(
+    branding: &BrandingSettings,
+    locale: Locale,
+    link: &str,
+) -> Result<EmailMessage> {
+    let t = catalog(locale);
+    let subject = t.password_reset_required_subject(&branding.product_name);
+    message(
+        subject.clone(),
+        PasswordResetRequiredHtml {
+            t,
+            lang: locale.as_str(),
+            subject: &subject,
+            branding,
+            link,
+        },
+        PasswordResetRequiredText { t, branding, link },
+    )
+}
+
+// Tells the owner their account was logged in to from an unfamiliar device,
+// with a link to lock it if that wasn't them
+pub fn new_device_alert(
+    branding: &BrandingSettings,
+    locale: Locale,
+    time: DateTime<Utc>,
+    device: &Device,
+    link: &str,
+) -> Result<EmailMessage> {
+    let t = catalog(locale);
+    let subject = t.new_device_alert_subject(&branding.product_name);
+    let time = time.format("%Y-%m-%d %H:%M UTC").to_string();
+    let ip = device.ip.as_deref().unwrap_or(t.unknown());
+    let user_agent = device.user_agent.as_deref().unwrap_or(t.unknown());
+    message(
+        subject.clone(),
+        NewDeviceAlertHtml {
+            t,
+            lang: locale.as_str(),
+            subject: &subject,
+            branding,
+            time: &time,
+            ip,
+            user_agent,
+            link,
+        },
+        NewDeviceAlertText {
+            t,
+            branding,
+            time: &time,
+            ip,
+            user_agent,
+            link,
+        },
+    )
+}
+
+// Invites someone to join an organization, whether or not they have an
+// account yet
+pub fn invitation(
+    branding: &BrandingSettings,
+    locale: Locale,
+    inviter: &str,
+    organization: &str,
+    link: &str,
+    valid_for: Duration,
+) -> Result<EmailMessage> {
+    let t = catalog(locale);
+    let subject = t.invitation_subject(organization, &branding.product_name);
+    let expiry = t.invitation_expiry(valid_for.as_secs().div_ceil(24 * 60 * 60));
+    message(
+        subject.clone(),
+        InvitationHtml {
+            t,
+            lang: locale.as_str(),
+            subject: &subject,
+            branding,
+            inviter,
+            organization,
+            link,
+            expiry: &expiry,
+        },
+        InvitationText {
+            t,
+            branding,
+            inviter,
+            organization,
+            link,
+            expiry: &expiry,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn branding() -> BrandingSettings {
+        BrandingSettings {
+            product_name: "Acme".to_owned(),
+            logo_url: None,
+            accent_color: "#123456".to_owned(),
+            support_email: None,
+        }
+    }
+
+    #[test]
+    fn two_fa_code_is_in_both_bodies() {
+        let code = TwoFACode::parse("123456".to_owned()).unwrap();
+
+        let message =
+            two_fa_code(&branding(), Locale::En, &code, Duration::from_secs(600)).unwrap();
+
+        assert_eq!(message.subject, "Your Acme login code");
+        for body in [&message.html_body, &message.text_body] {
+            assert!(body.contains("123456"));
+            assert!(body.contains("10 minutes"));
+        }
+        assert!(message.html_body.contains("#123456"));
+        assert!(!message.text_body.contains('<'));
+    }
+
+    #[test]
+    fn support_email_and_logo_are_optional() {
+        let link = "https://auth.example.com/reset?token=abc";
+        let message = password_reset(&branding(), Locale::En, link).unwrap();
+        assert!(!message.text_body.contains("Questions?"));
+        assert!(!message.html_body.contains("<img"));
+
+        let branding = BrandingSettings {
+            logo_url: Some("https://example.com/logo.png".to_owned()),
+            support_email: Some("help@example.com".to_owned()),
+            ..branding()
+        };
+        let message = password_reset(&branding, Locale::En, link).unwrap();
+        assert!(message
+            .text_body
+            .contains("Questions? Contact us at help@example.com"));
+        assert!(message.html_body.contains("mailto:help@example.com"));
+        assert!(message
+            .html_body
+            .contains(r#"src="https://example.com/logo.png""#));
+    }
+
+    #[test]
+    fn client_details_are_escaped_in_html_only() {
+        let time = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let device = Device {
+            ip: Some("203.0.113.7".to_owned()),
+            user_agent: Some("<script>alert(1)</script>".to_owned()),
+        };
+
+        let message = new_device_alert(
+            &branding(),
+            Locale::En,
+            time,
+            &device,
+            "https://auth.example.com/r",
+        )
+        .unwrap();
+
+        assert_eq!(message.subject, "New sign-in to your Acme account");
+        assert!(message.text_body.contains("Time: 2023-11-14 22:13 UTC"));
+        assert!(message
+            .text_body
+            .contains("Browser: <script>alert(1)</script>"));
+        assert!(!message.html_body.contains("<script>"));
+        assert!(message.html_body.contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn verification_links_to_the_given_url() {
+        let link = "https://auth.example.com/verify?token=abc";
+
+        let message = email_verification(&branding(), Locale::En, link).unwrap();
+
+        assert!(message.text_body.contains(link));
+        assert!(message.html_body.contains(link));
+    }
+
+    #[test]
+    fn invitation_names_the_inviter_and_organization() {
+        let link = "https://auth.example.com/?invitation=abc";
+
+        let message = invitation(
+            &branding(),
+            Locale::En,
+            "boss@initech.com",
+            "<Initech>",
+            link,
+            Duration::from_secs(7 * 24 * 60 * 60),
+        )
+        .unwrap();
+
+        assert_eq!(message.subject, "Join <Initech> on Acme");
+        assert!(message
+            .text_body
+            .contains("boss@initech.com invited you to join <Initech> on Acme."));
+        assert!(message.text_body.contains("valid for 7 days"));
+        assert!(message.text_body.contains(link));
+        assert!(message.html_body.contains("&lt;Initech&gt;"));
+    }
+
+    #[test]
+    fn every_message_renders_in_every_locale() {
+        let code = TwoFACode::parse("123456".to_owned()).unwrap();
+        let device = Device {
+            ip: None,
+            user_agent: None,
+        };
+        let link = "https://auth.example.com/r";
+
+        for locale in Locale::ALL {
+            let t = catalog(locale);
+            let messages = [
+                two_fa_code(&branding(), locale, &code, Duration::from_secs(600)).unwrap(),
+                email_verification(&branding(), locale, link).unwrap(),
+                password_reset(&branding(), locale, link).unwrap(),
+                password_reset_required(&branding(), locale, link).unwrap(),
+                new_device_alert(&branding(), locale, Utc::now(), &device, link).unwrap(),
+                invitation(
+                    &branding(),
+                    locale,
+                    "boss@initech.com",
+                    "Initech",
+                    link,
+                    Duration::from_secs(86400),
+                )
+                .unwrap(),
+            ];
+            for message in messages {
+                let footer = t.email_footer("Acme");
+                assert!(message.text_body.contains(&footer), "{:?}", locale);
+                assert!(message
+                    .html_body
+                    .contains(&format!(r#"<html lang="{}">"#, locale.as_str())));
+            }
+        }
+    }
+
+    #[test]
+    fn german_messages_are_in_german() {
+        let code = TwoFACode::parse("123456".to_owned()).unwrap();
+
+        let message =
+            two_fa_code(&branding(), Locale::De, &code, Duration::from_secs(600)).unwrap();
+
+        assert_eq!(message.subject, "Ihr Anmeldecode für Acme");
+        assert!(message.text_body.contains("Er ist 10 Minuten lang gültig."));
+        assert!(message.html_body.contains("Ihr Anmeldecode für Acme"));
+    }
+}
@@ -0,0 +1,406 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{eyre, Result};
+
+use crate::app_state::EmailClientType;
+use crate::domain::{Email, EmailClient, EmailMessage};
+use crate::settings::CircuitBreakerSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BreakerState {
+    // Sending normally, counting failures in a row
+    Closed { failures: u32 },
+    // Skipped until `until`, after too many failures in a row
+    Open { until: Instant },
+    // One trial send is in flight to see if the provider recovered. Another
+    // is allowed after `until`, in case this one never reports back.
+    HalfOpen { until: Instant },
+}
+
+// Stops trying a provider that keeps failing, so every email doesn't wait
+// for it to time out first, and lets one email through now and then to
+// notice when it's back
+struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+    failure_threshold: u32,
+    open_duration: Duration,
+}
+
+impl CircuitBreaker {
+    fn new(settings: &CircuitBreakerSettings) -> Self {
+        Self {
+            state: Mutex::new(BreakerState::Closed { failures: 0 }),
+            failure_threshold: settings.failure_threshold,
+            open_duration: settings.open_duration(),
+        }
+    }
+
+    // Whether the provider should be tried now
+    fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match *state {
+            BreakerState::Closed { .. } => true,
+            BreakerState::Open { until } | BreakerState::HalfOpen { until } if now >= until => {
+                *state = BreakerState::HalfOpen {
+                    until: now + self.open_duration,
+                };
+                true
+            }
+            BreakerState::Open { .. } | BreakerState::HalfOpen { .. } => false,
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        matches!(*self.state.lock().unwrap(), BreakerState::Open { until } if Instant::now() < until)
+    }
+
+    // Returns true if this closed the breaker
+    fn record_success(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let was_closed = matches!(*state, BreakerState::Closed { .. });
+        *state = BreakerState::Closed { failures: 0 };
+        !was_closed
+    }
+
+    // Returns true if this opened the breaker
+    fn record_failure(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            BreakerState::Closed { failures } => failures + 1,
+            // The trial failed, so it's still down
+            BreakerState::HalfOpen { .. } => self.failure_threshold,
+            BreakerState::Open { .. } => return false,
+        };
+        if failures >= self.failure_threshold {
+            *state = BreakerState::Open {
+                until: Instant::now() + self.open_duration,
+            };
+            true
+        } else {
+            *state = BreakerState::Closed { failures };
+            false
+        }
+    }
+}
+
+struct Provider {
+    name: String,
+    client: EmailClientType,
+    breaker: CircuitBreaker,
+}
+
+// Sends through the first provider that's up, in order of preference. Each
+// provider has its own circuit breaker, so a failing primary is skipped
+// until it has had time to recover.
+pub struct FailoverEmailClient {
+    providers: Vec<Provider>,
+}
+
+impl FailoverEmailClient {
+    pub fn new(
+        providers: Vec<(String, EmailClientType)>,
+        settings: &CircuitBreakerSettings,
+    ) -> Self {
+        Self {
+            providers: providers
+                .into_iter()
+                .map(|(name, client)| Provider {
+                    name,
+                    client,
+                    breaker: CircuitBreaker::new(settings),
+                })
+                .collect(),
+        }
+    }
+
+    // Names of the providers currently being skipped
+    pub fn unavailable_providers(&self) -> Vec<&str> {
+        self.providers
+            .iter()
+            .filter(|provider| provider.breaker.is_open())
+            .map(|provider| provider.name.as_str())
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for FailoverEmailClient {
+    #[tracing::instrument(name = "Sending email with failover", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let mut errors = Vec::new();
+        for provider in &self.providers {
+            if !provider.breaker.allow() {
+                continue;
+            }
+            match provider.client.send_email(recipient, message).await {
+                Ok(()) => {
+                    if provider.breaker.record_success() {
+                        tracing::info!(provider = provider.name, "email provider recovered");
+                    }
+                    return Ok(());
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        provider = provider.name,
+                        error = format!("{:#}", e),
+                        "email provider failed, trying the next one"
+                    );
+                    if provider.breaker.record_failure() {
+                        tracing::error!(
+                            provider = provider.name,
+                            "email provider keeps failing, skipping it for now"
+                        );
+                    }
+                    errors.push(format!("{}: {:#}", provider.name, e));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Err(eyre!("every email provider is unavailable"))
+        } else {
+            Err(eyre!("every email provider failed: {}", errors.join("; ")))
+        }
+    }
+
+    // Healthy as long as one provider can send
+    #[tracing::instrument(name = "Email providers health check", skip_all)]
+    async fn health_check(&self) -> Result<()> {
+        let mut errors = Vec::new();
+        for provider in &self.providers {
+            if provider.breaker.is_open() {
+                errors.push(format!(
+                    "{}: skipped after repeated failures",
+                    provider.name
+                ));
+                continue;
+            }
+            match provider.client.health_check().await {
+                Ok(()) => return Ok(()),
+                Err(e) => errors.push(format!("{}: {:#}", provider.name, e)),
+            }
+        }
+        Err(eyre!(
+            "no email provider is available: {}",
+            errors.join("; ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use reqwest::Client;
+    use secrecy::Secret;
+    use wiremock::matchers::{any, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::services::postmark_email_client::PostmarkEmailClient;
+    use crate::services::sendgrid_email_client::SendGridEmailClient;
+
+    const OPEN_DURATION: Duration = Duration::from_millis(100);
+
+    fn email(address: &str) -> Email {
+        Email::parse(address.to_owned()).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "Your login code".to_owned(),
+            html_body: "<p>123456</p>".to_owned(),
+            text_body: "123456".to_owned(),
+        }
+    }
+
+    fn http_client() -> Client {
+        Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap()
+    }
+
+    // Postmark first, SendGrid as the fallback, opening a breaker after two
+    // failures in a row
+    fn failover_client(postmark: &MockServer, sendgrid: &MockServer) -> FailoverEmailClient {
+        let postmark = PostmarkEmailClient::new(
+            postmark.uri(),
+            email("sender@example.com"),
+            Secret::new("postmark-token".to_owned()),
+            http_client(),
+        );
+        let sendgrid = SendGridEmailClient::new(
+            sendgrid.uri(),
+            email("sender@example.com"),
+            Secret::new("sendgrid-key".to_owned()),
+            http_client(),
+        );
+        FailoverEmailClient::new(
+            vec![
+                ("postmark".to_owned(), Arc::new(postmark)),
+                ("sendgrid".to_owned(), Arc::new(sendgrid)),
+            ],
+            &CircuitBreakerSettings {
+                failure_threshold: 2,
+                open_milliseconds: OPEN_DURATION.as_millis() as u64,
+            },
+        )
+    }
+
+    async fn send(client: &FailoverEmailClient) -> Result<()> {
+        client
+            .send_email(&email("user@example.com"), &message())
+            .await
+    }
+
+    #[tokio::test]
+    async fn sends_through_the_primary_while_it_is_up() {
+        let (postmark, sendgrid) = (MockServer::start().await, MockServer::start().await);
+        Mock::given(path("/email"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&postmark)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(202))
+            .expect(0)
+            .mount(&sendgrid)
+            .await;
+
+        assert!(send(&failover_client(&postmark, &sendgrid)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn fails_over_to_the_next_provider() {
+        let (postmark, sendgrid) = (MockServer::start().await, MockServer::start().await);
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&postmark)
+            .await;
+        Mock::given(path("/v3/mail/send"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&sendgrid)
+            .await;
+
+        assert!(send(&failover_client(&postmark, &sendgrid)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn failing_provider_is_skipped_once_its_breaker_opens() {
+        let (postmark, sendgrid) = (MockServer::start().await, MockServer::start().await);
+        // Only tried until the breaker opens after two failures
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(503))
+            .expect(2)
+            .mount(&postmark)
+            .await;
+        Mock::given(path("/v3/mail/send"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(4)
+            .mount(&sendgrid)
+            .await;
+        let client = failover_client(&postmark, &sendgrid);
+
+        for _ in 0..4 {
+            assert!(send(&client).await.is_ok());
+        }
+
+        assert_eq!(client.unavailable_providers(), vec!["postmark"]);
+    }
+
+    #[tokio::test]
+    async fn recovered_provider_is_used_again() {
+        let (postmark, sendgrid) = (MockServer::start().await, MockServer::start().await);
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .with_priority(1)
+            .expect(2)
+            .mount(&postmark)
+            .await;
+        Mock::given(path("/email"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&postmark)
+            .await;
+        Mock::given(path("/v3/mail/send"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(2)
+            .mount(&sendgrid)
+            .await;
+        let client = failover_client(&postmark, &sendgrid);
+        for _ in 0..2 {
+            send(&client).await.unwrap();
+        }
+        assert_eq!(client.unavailable_providers(), vec!["postmark"]);
+
+        // Once the breaker has been open long enough, the next email is a
+        // trial through Postmark, which closes it again
+        tokio::time::sleep(OPEN_DURATION).await;
+        for _ in 0..2 {
+            send(&client).await.unwrap();
+        }
+
+        assert!(client.unavailable_providers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn fails_when_every_provider_fails() {
+        let (postmark, sendgrid) = (MockServer::start().await, MockServer::start().await);
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&postmark)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&sendgrid)
+            .await;
+        let client = failover_client(&postmark, &sendgrid);
+
+        let error = send(&client).await.unwrap_err().to_string();
+
+        assert!(error.contains("postmark"));
+        assert!(error.contains("sendgrid"));
+    }
+
+    #[tokio::test]
+    async fn health_check_passes_while_any_provider_is_up() {
+        let (postmark, sendgrid) = (MockServer::start().await, MockServer::start().await);
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&postmark)
+            .await;
+        Mock::given(path("/v3/scopes"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&sendgrid)
+            .await;
+
+        assert!(failover_client(&postmark, &sendgrid)
+            .health_check()
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn trial_failure_reopens_the_breaker() {
+        let breaker = CircuitBreaker::new(&CircuitBreakerSettings {
+            failure_threshold: 3,
+            open_milliseconds: 50,
+        });
+        for _ in 0..3 {
+            breaker.record_failure();
+        }
+        assert!(!breaker.allow());
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(breaker.allow());
+        assert!(!breaker.allow(), "only one trial at a time");
+        assert!(breaker.record_failure(), "one failed trial is enough");
+    }
+}
@@ -0,0 +1,148 @@
+use color_eyre::eyre::Result;
+use reqwest::{header::HeaderMap, Client};
+use secrecy::{ExposeSecret, Secret};
+use serde::Serialize;
+
+use crate::domain::{PhoneNumber, SmsClient};
+use crate::utils::tracing::inject_trace_context;
+
+// Sends texts by POSTing them as JSON to an SMS gateway, which most of them
+// (or a small adapter in front of them) accept
+pub struct HttpSmsClient {
+    http_client: Client,
+    url: String,
+    sender: String,
+    auth_token: Secret<String>,
+}
+
+impl HttpSmsClient {
+    pub fn new(
+        url: String,
+        sender: String,
+        auth_token: Secret<String>,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            http_client,
+            url,
+            sender,
+            auth_token,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsClient for HttpSmsClient {
+    #[tracing::instrument(name = "Sending SMS", skip_all)]
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> Result<()> {
+        let request_body = SendSmsRequest {
+            from: &self.sender,
+            to: recipient.as_ref().expose_secret(),
+            body,
+        };
+
+        let mut trace_headers = HeaderMap::new();
+        inject_trace_context(&mut trace_headers);
+
+        self.http_client
+            .post(&self.url)
+            .bearer_auth(self.auth_token.expose_secret())
+            .headers(trace_headers)
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct SendSmsRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    body: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+    use wiremock::matchers::{any, body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn sms_client(base_url: String) -> HttpSmsClient {
+        let http_client = Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        HttpSmsClient::new(
+            format!("{}/messages", base_url),
+            "AuthService".to_owned(),
+            Secret::new("gateway-token".to_owned()),
+            http_client,
+        )
+    }
+
+    fn phone_number() -> PhoneNumber {
+        PhoneNumber::parse("+4915112345678".to_owned()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn send_sms_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/messages"))
+            .and(method("POST"))
+            .and(header("Authorization", "Bearer gateway-token"))
+            .and(body_json(json!({
+                "from": "AuthService",
+                "to": "+4915112345678",
+                "body": "Your login code is 123456",
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client(mock_server.uri())
+            .send_sms(&phone_number(), "Your login code is 123456")
+            .await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_sms_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client(mock_server.uri())
+            .send_sms(&phone_number(), "Your login code is 123456")
+            .await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn send_sms_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(180)))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client(mock_server.uri())
+            .send_sms(&phone_number(), "Your login code is 123456")
+            .await;
+
+        assert!(outcome.is_err());
+    }
+}
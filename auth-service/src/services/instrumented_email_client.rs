@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use color_eyre::Result;
+
+use crate::app_state::EmailClientType;
+use crate::domain::{Email, EmailClient, EmailMessage};
+use crate::utils::metrics::{outcome, Metrics};
+
+// Counts every email handed to the wrapped client, whichever provider it is
+pub struct InstrumentedEmailClient {
+    inner: EmailClientType,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedEmailClient {
+    pub fn new(inner: EmailClientType, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for InstrumentedEmailClient {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let result = self.inner.send_email(recipient, message).await;
+        self.metrics.record_email(if result.is_ok() {
+            outcome::SUCCESS
+        } else {
+            outcome::FAILURE
+        });
+        result
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.inner.health_check().await
+    }
+}
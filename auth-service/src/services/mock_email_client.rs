@@ -1,24 +1,19 @@
+use crate::domain::{Email, EmailClient, EmailMessage};
 use color_eyre::Result;
 use secrecy::ExposeSecret;
-use crate::domain::{Email, EmailClient};
 
 #[derive(Default)]
 pub struct MockEmailClient;
 
 #[async_trait::async_trait]
 impl EmailClient for MockEmailClient {
-    async fn send_email(
-        &self,
-        recipient: &Email,
-        subject: &str,
-        content: &str,
-    ) -> Result<()> {
-        // Our mock email client will simply log the recipient, subject, and content to standard output
-        println!(
-            "Sending email to {} with subject: {} and content: {}",
-            recipient.as_ref().expose_secret(),
-            subject,
-            content
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        // Our mock email client will simply log the recipient, subject, and text body
+        tracing::info!(
+            recipient = recipient.as_ref().expose_secret().as_str(),
+            subject = message.subject,
+            content = message.text_body,
+            "Sending email"
         );
 
         Ok(())
@@ -1,3 +1,12 @@
-pub mod mock_email_client;
 pub mod data_stores;
+pub mod dev_mailbox_email_client;
+pub mod email_outbox_worker;
+pub mod email_templates;
+pub mod failover_email_client;
+pub mod http_sms_client;
+pub mod instrumented_email_client;
+pub mod mock_email_client;
 pub mod postmark_email_client;
+pub mod sendgrid_email_client;
+pub mod smtp_email_client;
+pub mod suppressing_email_client;
@@ -1,14 +1,15 @@
 use color_eyre::eyre::Result; // For improved error handling and reporting
-use reqwest::{Client, Url}; // For making HTTP requests
+use reqwest::{header::HeaderMap, Client, Url}; // For making HTTP requests
 use secrecy::{ExposeSecret, Secret}; // For securely handling sensitive data
 
-use crate::domain::{Email, EmailClient}; // Import domain-specific modules
+use crate::domain::{Email, EmailClient, EmailMessage}; // Import domain-specific modules
+use crate::utils::tracing::inject_trace_context;
 
 // Define the PostmarkEmailClient struct
 pub struct PostmarkEmailClient {
-    http_client: Client, // HTTP client for making requests
-    base_url: String, // Base URL for the email service
-    sender: Email, // Email address of the sender
+    http_client: Client,                 // HTTP client for making requests
+    base_url: String,                    // Base URL for the email service
+    sender: Email,                       // Email address of the sender
     authorization_token: Secret<String>, // Authorization token for the email service, wrapped in Secret for security
 }
 
@@ -32,7 +33,7 @@ impl PostmarkEmailClient {
 #[async_trait::async_trait]
 impl EmailClient for PostmarkEmailClient {
     #[tracing::instrument(name = "Sending email", skip_all)] // Trace this function, skipping logging its parameters
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
         // Parse the base URL and join it with the email endpoint
         let base = Url::parse(&self.base_url)?;
         let url = base.join("/email")?;
@@ -41,12 +42,16 @@ impl EmailClient for PostmarkEmailClient {
         let request_body = SendEmailRequest {
             from: self.sender.as_ref().expose_secret(),
             to: recipient.as_ref().expose_secret(),
-            subject,
-            html_body: content,
-            text_body: content,
+            subject: &message.subject,
+            html_body: &message.html_body,
+            text_body: &message.text_body,
             message_stream: MESSAGE_STREAM,
         };
 
+        // Carry the current trace over to Postmark
+        let mut trace_headers = HeaderMap::new();
+        inject_trace_context(&mut trace_headers);
+
         // Build the HTTP POST request
         let request = self
             .http_client
@@ -55,6 +60,7 @@ impl EmailClient for PostmarkEmailClient {
                 POSTMARK_AUTH_HEADER,
                 self.authorization_token.expose_secret(), // Securely expose the authorization token
             )
+            .headers(trace_headers)
             .json(&request_body);
 
         // Send the request and handle the response
@@ -62,6 +68,26 @@ impl EmailClient for PostmarkEmailClient {
 
         Ok(())
     }
+
+    // Fetch the server details, which proves both that Postmark is reachable
+    // and that our token is still accepted
+    #[tracing::instrument(name = "Postmark health check", skip_all)]
+    async fn health_check(&self) -> Result<()> {
+        let url = Url::parse(&self.base_url)?.join("/server")?;
+
+        self.http_client
+            .get(url)
+            .header(
+                POSTMARK_AUTH_HEADER,
+                self.authorization_token.expose_secret(),
+            )
+            .header("Accept", "application/json")
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
 }
 
 // Constants for message stream and authorization header
@@ -83,25 +109,29 @@ struct SendEmailRequest<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::constants::test;
-
     use super::*;
+    use axum::body::Body;
     use fake::faker::internet::en::SafeEmail;
     use fake::faker::lorem::en::{Paragraph, Sentence};
     use fake::{Fake, Faker};
-    use wiremock::matchers::{any, header, header_exists, method, path};
+    use opentelemetry::trace::TracerProvider as _;
+    use tracing::Instrument;
+    use tracing_subscriber::layer::SubscriberExt;
+    use wiremock::matchers::{any, header, header_exists, header_regex, method, path};
     use wiremock::{Mock, MockServer, Request, ResponseTemplate};
 
     use super::PostmarkEmailClient;
+    use crate::utils::tracing::make_span_with_request_id;
 
-    // Helper function to generate a test subject
-    fn subject() -> String {
-        Sentence(1..2).fake()
-    }
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_millis(200);
 
-    // Helper function to generate test content
-    fn content() -> String {
-        Paragraph(1..10).fake()
+    // Helper function to generate a test message
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: Sentence(1..2).fake(),
+            html_body: Paragraph(1..10).fake(),
+            text_body: Paragraph(1..10).fake(),
+        }
     }
 
     // Helper function to generate a test email
@@ -111,10 +141,7 @@ mod tests {
 
     // Helper function to create a test email client
     fn email_client(base_url: String) -> PostmarkEmailClient {
-        let http_client = Client::builder()
-            .timeout(test::email_client::TIMEOUT)
-            .build()
-            .unwrap();
+        let http_client = Client::builder().timeout(TIMEOUT).build().unwrap();
         PostmarkEmailClient::new(base_url, email(), Secret::new(Faker.fake()), http_client)
     }
 
@@ -155,9 +182,7 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &message()).await;
 
         assert!(outcome.is_ok());
     }
@@ -176,9 +201,7 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &message()).await;
 
         assert!(outcome.is_err());
     }
@@ -198,10 +221,78 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
+        let outcome = email_client.send_email(&email(), &message()).await;
+
+        assert!(outcome.is_err());
+    }
+
+    // Test the health check authenticates against the server endpoint
+    #[tokio::test]
+    async fn health_check_fetches_the_server_details() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists(POSTMARK_AUTH_HEADER))
+            .and(path("/server"))
+            .and(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(email_client.health_check().await.is_ok());
+    }
+
+    // Test a rejected token makes the health check fail
+    #[tokio::test]
+    async fn health_check_fails_if_the_server_rejects_the_token() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(email_client.health_check().await.is_err());
+    }
+
+    // Test the trace the email is sent in carries over to Postmark
+    #[tokio::test]
+    async fn send_email_propagates_the_trace_context() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_regex(
+            "traceparent",
+            "^00-0af7651916cd43dd8448eb211c80319c-[0-9a-f]{16}-01$",
+        ))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+        let provider = opentelemetry_sdk::trace::TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        // A request that arrived as part of an existing trace
+        let request = axum::extract::Request::builder()
+            .header(
+                "traceparent",
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            )
+            .body(Body::empty())
+            .unwrap();
+        let span = make_span_with_request_id(&request);
+
         let outcome = email_client
-            .send_email(&email(), &subject(), &content())
+            .send_email(&email(), &message())
+            .instrument(span)
             .await;
 
-        assert!(outcome.is_err());
+        assert!(outcome.is_ok());
     }
 }
@@ -0,0 +1,218 @@
+use color_eyre::eyre::Result;
+use reqwest::{header::HeaderMap, Client, Url};
+use secrecy::{ExposeSecret, Secret};
+use serde::Serialize;
+
+use crate::domain::{Email, EmailClient, EmailMessage};
+use crate::utils::tracing::inject_trace_context;
+
+// Sends through SendGrid's v3 mail API, e.g. as a fallback for Postmark
+pub struct SendGridEmailClient {
+    http_client: Client,
+    base_url: String,
+    sender: Email,
+    api_key: Secret<String>,
+}
+
+impl SendGridEmailClient {
+    pub fn new(
+        base_url: String,
+        sender: Email,
+        api_key: Secret<String>,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            http_client,
+            base_url,
+            sender,
+            api_key,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SendGridEmailClient {
+    #[tracing::instrument(name = "Sending email through SendGrid", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let url = Url::parse(&self.base_url)?.join("/v3/mail/send")?;
+        // Plain text has to come before HTML
+        let request_body = SendMailRequest {
+            personalizations: [Personalization {
+                to: [Address {
+                    email: recipient.as_ref().expose_secret(),
+                }],
+            }],
+            from: Address {
+                email: self.sender.as_ref().expose_secret(),
+            },
+            subject: &message.subject,
+            content: [
+                Content {
+                    r#type: "text/plain",
+                    value: &message.text_body,
+                },
+                Content {
+                    r#type: "text/html",
+                    value: &message.html_body,
+                },
+            ],
+        };
+
+        let mut trace_headers = HeaderMap::new();
+        inject_trace_context(&mut trace_headers);
+
+        self.http_client
+            .post(url)
+            .bearer_auth(self.api_key.expose_secret())
+            .headers(trace_headers)
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    // Listing the key's scopes proves SendGrid is reachable and still
+    // accepts the key
+    #[tracing::instrument(name = "SendGrid health check", skip_all)]
+    async fn health_check(&self) -> Result<()> {
+        let url = Url::parse(&self.base_url)?.join("/v3/scopes")?;
+
+        self.http_client
+            .get(url)
+            .bearer_auth(self.api_key.expose_secret())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+// See https://www.twilio.com/docs/sendgrid/api-reference/mail-send/mail-send
+#[derive(Serialize, Debug)]
+struct SendMailRequest<'a> {
+    personalizations: [Personalization<'a>; 1],
+    from: Address<'a>,
+    subject: &'a str,
+    content: [Content<'a>; 2],
+}
+
+#[derive(Serialize, Debug)]
+struct Personalization<'a> {
+    to: [Address<'a>; 1],
+}
+
+#[derive(Serialize, Debug)]
+struct Address<'a> {
+    email: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct Content<'a> {
+    r#type: &'a str,
+    value: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+    use wiremock::matchers::{any, body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn email(address: &str) -> Email {
+        Email::parse(address.to_owned()).unwrap()
+    }
+
+    fn email_client(base_url: String) -> SendGridEmailClient {
+        let http_client = Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        SendGridEmailClient::new(
+            base_url,
+            email("sender@example.com"),
+            Secret::new("sendgrid-key".to_owned()),
+            http_client,
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/v3/mail/send"))
+            .and(method("POST"))
+            .and(header("Authorization", "Bearer sendgrid-key"))
+            .and(body_json(json!({
+                "personalizations": [{ "to": [{ "email": "user@example.com" }] }],
+                "from": { "email": "sender@example.com" },
+                "subject": "Your login code",
+                "content": [
+                    { "type": "text/plain", "value": "Code: 123456" },
+                    { "type": "text/html", "value": "<p>Code: 123456</p>" },
+                ],
+            })))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client(mock_server.uri())
+            .send_email(
+                &email("user@example.com"),
+                &EmailMessage {
+                    subject: "Your login code".to_owned(),
+                    html_body: "<p>Code: 123456</p>".to_owned(),
+                    text_body: "Code: 123456".to_owned(),
+                },
+            )
+            .await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client(mock_server.uri())
+            .send_email(
+                &email("user@example.com"),
+                &EmailMessage {
+                    subject: "Subject".to_owned(),
+                    html_body: "<p>Body</p>".to_owned(),
+                    text_body: "Body".to_owned(),
+                },
+            )
+            .await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn health_check_fails_if_the_key_is_rejected() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/v3/scopes"))
+            .and(method("GET"))
+            .and(header("Authorization", "Bearer sendgrid-key"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(email_client(mock_server.uri())
+            .health_check()
+            .await
+            .is_err());
+    }
+}
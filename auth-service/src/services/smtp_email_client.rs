@@ -0,0 +1,362 @@
+use std::time::Duration;
+
+use color_eyre::eyre::{eyre, Result};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::PoolConfig;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::ExposeSecret;
+
+use crate::domain::{Email, EmailClient, EmailMessage};
+use crate::settings::{SmtpSettings, SmtpTls};
+
+type Transport = AsyncSmtpTransport<Tokio1Executor>;
+
+// Sends through any SMTP relay, e.g. a self-hosted one. Connections are
+// pooled, so consecutive emails reuse an open, already authenticated session.
+pub struct SmtpEmailClient {
+    transport: Transport,
+    sender: Email,
+    timeout: Duration,
+}
+
+impl SmtpEmailClient {
+    // `timeout` bounds sending each email as a whole, since lettre on its
+    // own only gives up on connecting, not on a relay that stops answering
+    pub fn new(settings: &SmtpSettings, sender: Email, timeout: Duration) -> Result<Self> {
+        let builder = match settings.tls {
+            SmtpTls::StartTls => Transport::starttls_relay(&settings.host)?,
+            SmtpTls::Tls => Transport::relay(&settings.host)?,
+            SmtpTls::None => Transport::builder_dangerous(&settings.host),
+        };
+        let mut builder = builder
+            .port(settings.port)
+            .timeout(Some(timeout))
+            .pool_config(
+                PoolConfig::new()
+                    .max_size(settings.max_connections)
+                    .idle_timeout(settings.idle_timeout()),
+            );
+        if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                password.expose_secret().clone(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            sender,
+            timeout,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    #[tracing::instrument(name = "Sending email over SMTP", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let email = mime_message(&self.sender, recipient, message)?;
+
+        tokio::time::timeout(self.timeout, self.transport.send(email))
+            .await
+            .map_err(|_| eyre!("SMTP relay did not answer within {:?}", self.timeout))??;
+
+        Ok(())
+    }
+
+    // Connecting (and logging in) proves the relay is reachable and still
+    // accepts our credentials
+    #[tracing::instrument(name = "SMTP health check", skip_all)]
+    async fn health_check(&self) -> Result<()> {
+        let connected = tokio::time::timeout(self.timeout, self.transport.test_connection())
+            .await
+            .map_err(|_| eyre!("SMTP relay did not answer within {:?}", self.timeout))??;
+        if connected {
+            Ok(())
+        } else {
+            Err(eyre!("SMTP relay did not respond to NOOP"))
+        }
+    }
+}
+
+// The email as it goes over the wire, with the plain-text and HTML versions
+// as alternatives
+pub(crate) fn mime_message(
+    sender: &Email,
+    recipient: &Email,
+    message: &EmailMessage,
+) -> Result<Message> {
+    Ok(Message::builder()
+        .from(mailbox(sender)?)
+        .to(mailbox(recipient)?)
+        .subject(&message.subject)
+        .multipart(MultiPart::alternative_plain_html(
+            message.text_body.clone(),
+            message.html_body.clone(),
+        ))?)
+}
+
+fn mailbox(email: &Email) -> Result<Mailbox> {
+    Ok(email.as_ref().expose_secret().parse()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use secrecy::Secret;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::Mutex;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(200);
+    // base64("\0relay-user\0relay-pass")
+    const PLAIN_CREDENTIALS: &str = "AHJlbGF5LXVzZXIAcmVsYXktcGFzcw==";
+
+    #[derive(Debug, Clone)]
+    struct CapturedEmail {
+        from: String,
+        to: Vec<String>,
+        data: String,
+    }
+
+    // Just enough of an SMTP server to accept mail from lettre and keep it
+    // for inspection. Only `credentials` are accepted when it asks for a login.
+    #[derive(Clone, Default)]
+    struct SmtpStandIn {
+        credentials: Option<&'static str>,
+        offer_starttls: bool,
+        emails: Arc<Mutex<Vec<CapturedEmail>>>,
+        connections: Arc<AtomicUsize>,
+    }
+
+    impl SmtpStandIn {
+        async fn start(self) -> (Self, u16) {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = self.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    server.connections.fetch_add(1, Ordering::SeqCst);
+                    tokio::spawn(server.clone().serve(stream));
+                }
+            });
+            (self, port)
+        }
+
+        async fn serve(self, stream: TcpStream) {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut authenticated = self.credentials.is_none();
+            let mut from = String::new();
+            let mut to = Vec::new();
+
+            writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let command = line.to_ascii_uppercase();
+                let reply = if command.starts_with("EHLO") {
+                    let mut reply = String::from("250-localhost\r\n");
+                    if self.offer_starttls {
+                        reply.push_str("250-STARTTLS\r\n");
+                    }
+                    reply.push_str("250 AUTH PLAIN\r\n");
+                    reply
+                } else if command.starts_with("AUTH PLAIN") {
+                    if Some(&line["AUTH PLAIN ".len()..]) == self.credentials {
+                        authenticated = true;
+                        "235 Authentication succeeded\r\n".to_owned()
+                    } else {
+                        "535 Authentication failed\r\n".to_owned()
+                    }
+                } else if !authenticated && !command.starts_with("QUIT") {
+                    "530 Authentication required\r\n".to_owned()
+                } else if command.starts_with("MAIL FROM:") {
+                    from = line["MAIL FROM:".len()..].to_owned();
+                    "250 OK\r\n".to_owned()
+                } else if command.starts_with("RCPT TO:") {
+                    to.push(line["RCPT TO:".len()..].to_owned());
+                    "250 OK\r\n".to_owned()
+                } else if command == "DATA" {
+                    writer.write_all(b"354 Go ahead\r\n").await.unwrap();
+                    let mut data = String::new();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if line == "." {
+                            break;
+                        }
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    self.emails.lock().await.push(CapturedEmail {
+                        from: std::mem::take(&mut from),
+                        to: std::mem::take(&mut to),
+                        data,
+                    });
+                    "250 Queued\r\n".to_owned()
+                } else if command == "RSET" || command == "NOOP" {
+                    "250 OK\r\n".to_owned()
+                } else if command == "QUIT" {
+                    writer.write_all(b"221 Bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    "502 Not implemented\r\n".to_owned()
+                };
+                writer.write_all(reply.as_bytes()).await.unwrap();
+            }
+        }
+    }
+
+    fn settings(port: u16) -> SmtpSettings {
+        SmtpSettings {
+            host: "127.0.0.1".to_owned(),
+            port,
+            tls: SmtpTls::None,
+            username: None,
+            password: None,
+            max_connections: 2,
+            idle_timeout_seconds: 60,
+        }
+    }
+
+    fn sender() -> Email {
+        Email::parse("sender@example.com".to_owned()).unwrap()
+    }
+
+    fn recipient() -> Email {
+        Email::parse("recipient@example.com".to_owned()).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "Your code".to_owned(),
+            html_body: "<p>Use 123456</p>".to_owned(),
+            text_body: "Use 123456".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_delivers_both_bodies_to_the_relay() {
+        let (relay, port) = SmtpStandIn::default().start().await;
+        let client = SmtpEmailClient::new(&settings(port), sender(), TIMEOUT).unwrap();
+
+        client.send_email(&recipient(), &message()).await.unwrap();
+
+        let emails = relay.emails.lock().await;
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].from, "<sender@example.com>");
+        assert_eq!(emails[0].to, vec!["<recipient@example.com>"]);
+        let data = &emails[0].data;
+        assert!(data.contains("Subject: Your code"));
+        assert!(data.contains("multipart/alternative"));
+        assert!(data.contains("Use 123456"));
+        assert!(data.contains("<p>Use 123456</p>"));
+    }
+
+    #[tokio::test]
+    async fn send_email_logs_in_with_configured_credentials() {
+        let (relay, port) = SmtpStandIn {
+            credentials: Some(PLAIN_CREDENTIALS),
+            ..Default::default()
+        }
+        .start()
+        .await;
+        let mut settings = settings(port);
+        settings.username = Some("relay-user".to_owned());
+        settings.password = Some(Secret::new("relay-pass".to_owned()));
+        let client = SmtpEmailClient::new(&settings, sender(), TIMEOUT).unwrap();
+
+        client.send_email(&recipient(), &message()).await.unwrap();
+
+        assert_eq!(relay.emails.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_relay_rejects_credentials() {
+        let (relay, port) = SmtpStandIn {
+            credentials: Some(PLAIN_CREDENTIALS),
+            ..Default::default()
+        }
+        .start()
+        .await;
+        let mut settings = settings(port);
+        settings.username = Some("relay-user".to_owned());
+        settings.password = Some(Secret::new("wrong".to_owned()));
+        let client = SmtpEmailClient::new(&settings, sender(), TIMEOUT).unwrap();
+
+        assert!(client.send_email(&recipient(), &message()).await.is_err());
+        assert!(relay.emails.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn consecutive_emails_reuse_the_connection() {
+        let (relay, port) = SmtpStandIn::default().start().await;
+        let client = SmtpEmailClient::new(&settings(port), sender(), TIMEOUT).unwrap();
+
+        for _ in 0..3 {
+            client.send_email(&recipient(), &message()).await.unwrap();
+            // Connections go back to the pool in a background task
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        assert_eq!(relay.emails.lock().await.len(), 3);
+        assert_eq!(relay.connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn starttls_is_required_when_configured() {
+        // The relay doesn't offer STARTTLS, so we must not fall back to
+        // sending in plain text
+        let (relay, port) = SmtpStandIn {
+            offer_starttls: false,
+            ..Default::default()
+        }
+        .start()
+        .await;
+        let mut settings = settings(port);
+        settings.tls = SmtpTls::StartTls;
+        let client = SmtpEmailClient::new(&settings, sender(), TIMEOUT).unwrap();
+
+        assert!(client.send_email(&recipient(), &message()).await.is_err());
+        assert!(relay.emails.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_relay_never_answers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // Accept connections but never send a greeting
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                open.push(stream);
+            }
+        });
+        let client = SmtpEmailClient::new(&settings(port), sender(), TIMEOUT).unwrap();
+
+        let started = std::time::Instant::now();
+        let result = client.send_email(&recipient(), &message()).await;
+
+        assert!(result.is_err());
+        assert!(started.elapsed() < TIMEOUT * 10);
+    }
+
+    #[tokio::test]
+    async fn health_check_reflects_whether_relay_is_reachable() {
+        let (_relay, port) = SmtpStandIn::default().start().await;
+        let client = SmtpEmailClient::new(&settings(port), sender(), TIMEOUT).unwrap();
+        assert!(client.health_check().await.is_ok());
+
+        // Nothing listens on a port we just released
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client = SmtpEmailClient::new(&settings(port), sender(), TIMEOUT).unwrap();
+        assert!(client.health_check().await.is_err());
+    }
+}
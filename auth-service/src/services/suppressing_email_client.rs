@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use color_eyre::Result;
+
+use crate::app_state::{EmailClientType, UserStoreType};
+use crate::domain::data_stores::UserStoreError;
+use crate::domain::{Email, EmailClient, EmailMessage};
+use crate::utils::metrics::{outcome, Metrics};
+
+// Drops emails to users whose address hard-bounced or complained about spam,
+// instead of handing them to the provider. Addresses that don't belong to a
+// user are sent to as usual.
+pub struct SuppressingEmailClient {
+    inner: EmailClientType,
+    user_store: UserStoreType,
+    metrics: Arc<Metrics>,
+}
+
+impl SuppressingEmailClient {
+    pub fn new(inner: EmailClientType, user_store: UserStoreType, metrics: Arc<Metrics>) -> Self {
+        Self {
+            inner,
+            user_store,
+            metrics,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SuppressingEmailClient {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        match self.user_store.get_user(recipient).await {
+            Ok(user) if user.email_status.is_suppressed() => {
+                tracing::warn!(
+                    status = user.email_status.as_str(),
+                    subject = message.subject,
+                    "not sending email to suppressed address"
+                );
+                self.metrics.record_email(outcome::SUPPRESSED);
+                return Ok(());
+            }
+            Ok(_) | Err(UserStoreError::UserNotFound) => {}
+            // Fail the send so the outbox retries it, rather than risk
+            // mailing a suppressed address
+            Err(e) => return Err(e.into()),
+        }
+        self.inner.send_email(recipient, message).await
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::domain::data_stores::UserStore;
+    use crate::domain::{EmailStatus, Password, User};
+    use crate::services::data_stores::hashmap_user_store::HashMapUserStore;
+
+    #[derive(Default)]
+    struct CountingEmailClient {
+        sent: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for CountingEmailClient {
+        async fn send_email(&self, _recipient: &Email, _message: &EmailMessage) -> Result<()> {
+            self.sent.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn email(address: &str) -> Email {
+        Email::parse(address.to_owned()).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "Subject".to_owned(),
+            html_body: "<p>Body</p>".to_owned(),
+            text_body: "Body".to_owned(),
+        }
+    }
+
+    async fn user_store_with(address: &str, status: EmailStatus) -> UserStoreType {
+        let user_store = HashMapUserStore::default();
+        let user = User::new(
+            email(address),
+            Password::parse(Secret::new("password123".to_owned())).unwrap(),
+            true,
+        );
+        user_store.add_user(user).await.unwrap();
+        user_store
+            .set_email_status(&email(address), status)
+            .await
+            .unwrap();
+        Arc::new(user_store)
+    }
+
+    #[tokio::test]
+    async fn suppressed_addresses_are_not_sent_to() {
+        let inner = Arc::new(CountingEmailClient::default());
+        let client = SuppressingEmailClient::new(
+            inner.clone(),
+            user_store_with("user@example.com", EmailStatus::HardBounced).await,
+            Arc::new(Metrics::new()),
+        );
+
+        client
+            .send_email(&email("user@example.com"), &message())
+            .await
+            .unwrap();
+
+        assert_eq!(inner.sent.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn deliverable_and_unknown_addresses_are_sent_to() {
+        let inner = Arc::new(CountingEmailClient::default());
+        let client = SuppressingEmailClient::new(
+            inner.clone(),
+            user_store_with("user@example.com", EmailStatus::SoftBounced).await,
+            Arc::new(Metrics::new()),
+        );
+
+        for address in ["user@example.com", "someone-else@example.com"] {
+            client
+                .send_email(&email(address), &message())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(inner.sent.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,1151 @@
+use std::time::Duration;
+
+use argon2::Params;
+use axum_extra::extract::cookie::SameSite;
+use config::{Config, ConfigError, Environment, File};
+use dotenvy::dotenv;
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::domain::Email;
+use crate::utils::constants::env;
+use crate::utils::cors::cors_layer;
+
+// Directory holding `base.toml` and the per-environment overlays
+const CONFIG_DIR: &str = "config";
+const DEFAULT_ENVIRONMENT: &str = "local";
+
+// Everything the service can be configured with. Values are layered, each
+// layer overriding the previous one:
+//   1. config/base.toml
+//   2. config/{APP_ENVIRONMENT}.toml (optional, APP_ENVIRONMENT defaults to "local")
+//   3. APP_* environment variables, e.g. APP_AUTH__TOKEN_TTL_SECONDS=900
+//   4. the original variables (JWT_SECRET, DATABASE_URL, ...) for existing deployments
+#[derive(Debug, Deserialize)]
+pub struct Settings {
+    pub application: ApplicationSettings,
+    pub auth: AuthSettings,
+    pub cors: CorsSettings,
+    pub database: DatabaseSettings,
+    pub redis: RedisSettings,
+    pub stores: StoreSettings,
+    pub email_client: EmailClientSettings,
+    pub email_outbox: EmailOutboxSettings,
+    pub sms_client: SmsClientSettings,
+    pub branding: BrandingSettings,
+    pub health: HealthSettings,
+    pub telemetry: TelemetrySettings,
+    pub logging: LoggingSettings,
+    #[serde(default)]
+    pub admin: AdminSettings,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplicationSettings {
+    pub address: String,
+    // Where users reach the service, used for links in emails
+    pub public_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthSettings {
+    pub jwt_secret: Secret<String>,
+    pub token_ttl_seconds: u64,
+    pub two_fa_code_ttl_seconds: u64,
+    // How long the "this wasn't me" link in a new device alert works for
+    pub recovery_token_ttl_seconds: u64,
+    // How long an invitation to join an organization can be accepted
+    pub invitation_ttl_seconds: u64,
+    pub cookie: CookieSettings,
+    pub password_hashing: PasswordHashingSettings,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CookieSettings {
+    pub name: String,
+    pub path: String,
+    pub http_only: bool,
+    pub secure: bool,
+    pub same_site: CookieSameSite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CookieSameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+// Argon2id cost parameters used when hashing new passwords. Existing hashes
+// carry their own parameters, so changing these never locks anyone out.
+#[derive(Debug, Deserialize)]
+pub struct PasswordHashingSettings {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+// Which browser origins may call the service. Origins are either exact
+// (`https://app.example.com`) or match every subdomain (`https://*.example.com`).
+#[derive(Debug, Deserialize)]
+pub struct CorsSettings {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    // How long browsers may cache a preflight response
+    pub max_age_seconds: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DatabaseSettings {
+    pub url: Secret<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RedisSettings {
+    pub host_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StoreSettings {
+    pub banned_tokens: StoreBackend,
+    pub two_fa_codes: StoreBackend,
+    pub audit_log: PersistentStoreBackend,
+    pub known_devices: PersistentStoreBackend,
+    pub email_outbox: PersistentStoreBackend,
+    pub roles: PersistentStoreBackend,
+    pub organizations: PersistentStoreBackend,
+    // How often the Postgres-backed stores purge expired rows
+    pub cleanup_interval_seconds: u64,
+}
+
+// Where the banned token and 2FA code stores keep their data. Redis is the
+// default; Postgres lets a deployment run without Redis at all, and memory
+// suits single-node deployments that can live with losing both on restart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    Redis,
+    Postgres,
+    Memory,
+}
+
+// Where the audit log and known devices are kept. Postgres keeps them across
+// restarts; memory suits single-node deployments that can live without.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PersistentStoreBackend {
+    Postgres,
+    Memory,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailClientSettings {
+    pub provider: EmailProvider,
+    // Tried in order when `provider` fails or is skipped by its circuit breaker
+    #[serde(default)]
+    pub fallback_providers: Vec<EmailProvider>,
+    pub sender: String,
+    pub timeout_milliseconds: u64,
+    // Postmark API, used with provider = "postmark"
+    pub base_url: String,
+    pub auth_token: Option<Secret<String>>,
+    // SendGrid API, used with provider = "sendgrid"
+    pub sendgrid: SendGridSettings,
+    // Mail relay, used with provider = "smtp"
+    pub smtp: SmtpSettings,
+    // Local capture, used with provider = "dev_mailbox"
+    pub dev_mailbox: DevMailboxSettings,
+    // Bounce and spam complaint notifications from Postmark
+    pub webhook: EmailWebhookSettings,
+    // When to skip a failing provider, with fallback_providers set
+    pub circuit_breaker: CircuitBreakerSettings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailProvider {
+    Postmark,
+    #[serde(rename = "sendgrid")]
+    SendGrid,
+    Smtp,
+    // Nothing is delivered; emails are shown at /dev/mailbox instead
+    DevMailbox,
+}
+
+impl EmailProvider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailProvider::Postmark => "postmark",
+            EmailProvider::SendGrid => "sendgrid",
+            EmailProvider::Smtp => "smtp",
+            EmailProvider::DevMailbox => "dev_mailbox",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SendGridSettings {
+    pub base_url: String,
+    pub api_key: Option<Secret<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    // Leave both unset for relays that don't ask for a login
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    // Connections kept open between emails, and for how long when unused
+    pub max_connections: u32,
+    pub idle_timeout_seconds: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    // Upgrade a plain connection, usually on port 587. Refuses to continue
+    // if the relay doesn't offer it.
+    StartTls,
+    // TLS from the first byte, usually on port 465
+    Tls,
+    // Plain text, only for relays on the same host or private network
+    None,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DevMailboxSettings {
+    // Also write every email here as an .eml file
+    pub directory: Option<String>,
+    // Emails kept in memory for /dev/mailbox, oldest dropped first. 0 keeps
+    // none, e.g. when only the directory is wanted.
+    pub max_messages: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailWebhookSettings {
+    // Basic auth credentials Postmark is configured to send. Unset disables
+    // the webhook.
+    pub username: String,
+    pub password: Option<Secret<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CircuitBreakerSettings {
+    // Failures in a row before a provider is skipped
+    pub failure_threshold: u32,
+    // How long it's skipped before one email tries it again
+    pub open_milliseconds: u64,
+}
+
+// Gateway for texting login codes to users who chose SMS as their 2FA
+// channel. Unset `url` turns SMS off.
+#[derive(Debug, Deserialize)]
+pub struct SmsClientSettings {
+    pub url: Option<String>,
+    // Shown as the sender, e.g. an alphanumeric ID or a number we own
+    pub sender: String,
+    pub auth_token: Option<Secret<String>>,
+    pub timeout_milliseconds: u64,
+}
+
+// How the background worker delivers queued emails
+#[derive(Debug, Deserialize)]
+pub struct EmailOutboxSettings {
+    // How often the outbox is checked for due emails
+    pub poll_interval_milliseconds: u64,
+    // Emails claimed per check
+    pub batch_size: usize,
+    // Attempts before an email is dead-lettered
+    pub max_attempts: u32,
+    // Wait before the first retry, doubled for every one after it
+    pub initial_backoff_milliseconds: u64,
+    pub max_backoff_milliseconds: u64,
+    // How long a claimed email is hidden from other workers. Must outlast
+    // email_client.timeout_milliseconds so it isn't sent twice.
+    pub lease_milliseconds: u64,
+    // How long dead-lettered emails are kept for inspection before deletion
+    pub dead_letter_retention_seconds: u64,
+}
+
+// How emails present the service to users
+#[derive(Debug, Clone, Deserialize)]
+pub struct BrandingSettings {
+    pub product_name: String,
+    // Shown in the email header, e.g. "https://example.com/logo.png"
+    pub logo_url: Option<String>,
+    // Header and button colour, as #rrggbb
+    pub accent_color: String,
+    // Where users can reply for help; left out of emails when unset
+    pub support_email: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HealthSettings {
+    // How long each readiness check may take before it counts as failed
+    pub timeout_milliseconds: u64,
+    // Whether /readyz also checks the email provider can be reached
+    pub check_email_provider: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TelemetrySettings {
+    // Reported as `service.name` on every exported span
+    pub service_name: String,
+    // Base URL of an OTLP/HTTP collector, e.g. http://localhost:4318. Spans
+    // are only exported when this is set.
+    pub otlp_endpoint: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoggingSettings {
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    // Human-readable single lines, for local development
+    Compact,
+    // One JSON object per line, for log aggregation
+    Json,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AdminSettings {
+    // Bearer token for the /admin endpoints. Unset disables them.
+    pub api_token: Option<Secret<String>>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error("failed to read configuration sources")]
+    Load(#[from] ConfigError),
+    #[error("invalid configuration value for `{key}`: {reason}")]
+    Invalid { key: &'static str, reason: String },
+}
+
+impl Settings {
+    // Load settings for the environment named by APP_ENVIRONMENT
+    pub fn load() -> Result<Self, SettingsError> {
+        dotenv().ok();
+        let environment =
+            std::env::var(env::APP_ENVIRONMENT_ENV_VAR).unwrap_or(DEFAULT_ENVIRONMENT.to_owned());
+        Self::load_for(&environment)
+    }
+
+    // Load settings for an explicit environment, e.g. "test"
+    pub fn load_for(environment: &str) -> Result<Self, SettingsError> {
+        dotenv().ok();
+        let mut builder = Config::builder()
+            .add_source(File::with_name(&format!("{}/base", CONFIG_DIR)))
+            .add_source(File::with_name(&format!("{}/{}", CONFIG_DIR, environment)).required(false))
+            .add_source(
+                Environment::with_prefix("APP")
+                    .prefix_separator("_")
+                    .separator("__")
+                    // e.g. APP_CORS__ALLOWED_ORIGINS=https://a.example,https://b.example
+                    .list_separator(",")
+                    .with_list_parse_key("cors.allowed_origins")
+                    .with_list_parse_key("cors.allowed_methods")
+                    .with_list_parse_key("cors.allowed_headers")
+                    .with_list_parse_key("email_client.fallback_providers")
+                    .try_parsing(true),
+            );
+
+        for (key, env_var) in LEGACY_ENV_VARS.iter().chain(OTEL_ENV_VARS) {
+            // An empty variable counts as unset, as it always has
+            let value = std::env::var(env_var)
+                .ok()
+                .filter(|value| !value.is_empty());
+            builder = builder.set_override_option(*key, value)?;
+        }
+
+        let settings: Settings = builder.build()?.try_deserialize()?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn validate(&self) -> Result<(), SettingsError> {
+        ensure(
+            self.application.public_url.starts_with("http://")
+                || self.application.public_url.starts_with("https://"),
+            "application.public_url",
+            "must be an http:// or https:// URL",
+        )?;
+        ensure(
+            !self.auth.jwt_secret.expose_secret().is_empty(),
+            "auth.jwt_secret",
+            "must not be empty",
+        )?;
+        ensure(
+            self.auth.token_ttl_seconds > 0,
+            "auth.token_ttl_seconds",
+            "must be positive",
+        )?;
+        ensure(
+            self.auth.two_fa_code_ttl_seconds > 0,
+            "auth.two_fa_code_ttl_seconds",
+            "must be positive",
+        )?;
+        ensure(
+            self.auth.recovery_token_ttl_seconds > 0,
+            "auth.recovery_token_ttl_seconds",
+            "must be positive",
+        )?;
+        ensure(
+            self.auth.invitation_ttl_seconds > 0,
+            "auth.invitation_ttl_seconds",
+            "must be positive",
+        )?;
+        ensure(
+            !self.auth.cookie.name.is_empty(),
+            "auth.cookie.name",
+            "must not be empty",
+        )?;
+        ensure(
+            self.auth.cookie.secure || self.auth.cookie.same_site != CookieSameSite::None,
+            "auth.cookie.same_site",
+            "\"none\" requires auth.cookie.secure = true",
+        )?;
+        self.auth
+            .password_hashing
+            .argon2_params()
+            .map_err(|e| SettingsError::Invalid {
+                key: "auth.password_hashing",
+                reason: e.to_string(),
+            })?;
+        if let Err(e) = cors_layer(&self.cors) {
+            return Err(SettingsError::Invalid {
+                key: "cors",
+                reason: e.to_string(),
+            });
+        }
+        ensure(
+            !self.database.url.expose_secret().is_empty(),
+            "database.url",
+            "must not be empty",
+        )?;
+        ensure(
+            self.stores.cleanup_interval_seconds > 0,
+            "stores.cleanup_interval_seconds",
+            "must be positive",
+        )?;
+        self.validate_database()?;
+        self.validate_email_provider(self.email_client.provider)?;
+        for (i, provider) in self.email_client.fallback_providers.iter().enumerate() {
+            ensure(
+                *provider != EmailProvider::DevMailbox,
+                "email_client.fallback_providers",
+                "can't include \"dev_mailbox\"",
+            )?;
+            ensure(
+                *provider != self.email_client.provider
+                    && !self.email_client.fallback_providers[..i].contains(provider),
+                "email_client.fallback_providers",
+                "must not repeat a provider",
+            )?;
+            self.validate_email_provider(*provider)?;
+        }
+        if !self.email_client.fallback_providers.is_empty() {
+            ensure(
+                self.email_client.provider != EmailProvider::DevMailbox,
+                "email_client.fallback_providers",
+                "must be empty when email_client.provider = \"dev_mailbox\"",
+            )?;
+            ensure(
+                self.email_client.circuit_breaker.failure_threshold > 0,
+                "email_client.circuit_breaker.failure_threshold",
+                "must be positive",
+            )?;
+        }
+        Email::parse(self.email_client.sender.clone()).map_err(|_| SettingsError::Invalid {
+            key: "email_client.sender",
+            reason: format!(
+                "\"{}\" is not a valid email address",
+                self.email_client.sender
+            ),
+        })?;
+        ensure(
+            self.email_client.timeout_milliseconds > 0,
+            "email_client.timeout_milliseconds",
+            "must be positive",
+        )?;
+        if let Some(url) = &self.sms_client.url {
+            ensure(
+                url.starts_with("http://") || url.starts_with("https://"),
+                "sms_client.url",
+                "must be an http:// or https:// URL",
+            )?;
+            ensure(
+                !self.sms_client.sender.is_empty(),
+                "sms_client.sender",
+                "must not be empty",
+            )?;
+            ensure(
+                self.sms_client
+                    .auth_token
+                    .as_ref()
+                    .is_some_and(|token| !token.expose_secret().is_empty()),
+                "sms_client.auth_token",
+                "must be set when sms_client.url is",
+            )?;
+            ensure(
+                self.sms_client.timeout_milliseconds > 0,
+                "sms_client.timeout_milliseconds",
+                "must be positive",
+            )?;
+        }
+        ensure(
+            self.email_outbox.poll_interval_milliseconds > 0,
+            "email_outbox.poll_interval_milliseconds",
+            "must be positive",
+        )?;
+        ensure(
+            self.email_outbox.batch_size > 0,
+            "email_outbox.batch_size",
+            "must be positive",
+        )?;
+        ensure(
+            self.email_outbox.max_attempts > 0,
+            "email_outbox.max_attempts",
+            "must be positive",
+        )?;
+        ensure(
+            self.email_outbox.initial_backoff_milliseconds > 0
+                && self.email_outbox.initial_backoff_milliseconds
+                    <= self.email_outbox.max_backoff_milliseconds,
+            "email_outbox.initial_backoff_milliseconds",
+            "must be positive and at most email_outbox.max_backoff_milliseconds",
+        )?;
+        ensure(
+            self.email_outbox.lease_milliseconds > self.email_client.timeout_milliseconds,
+            "email_outbox.lease_milliseconds",
+            "must be longer than email_client.timeout_milliseconds",
+        )?;
+        ensure(
+            !self.branding.product_name.is_empty(),
+            "branding.product_name",
+            "must not be empty",
+        )?;
+        if let Some(logo_url) = &self.branding.logo_url {
+            ensure(
+                logo_url.starts_with("http://") || logo_url.starts_with("https://"),
+                "branding.logo_url",
+                "must be an http:// or https:// URL",
+            )?;
+        }
+        ensure(
+            is_hex_color(&self.branding.accent_color),
+            "branding.accent_color",
+            "must be a colour like #1a2b3c",
+        )?;
+        if let Some(support_email) = &self.branding.support_email {
+            Email::parse(support_email.clone()).map_err(|_| SettingsError::Invalid {
+                key: "branding.support_email",
+                reason: format!("\"{}\" is not a valid email address", support_email),
+            })?;
+        }
+        ensure(
+            self.health.timeout_milliseconds > 0,
+            "health.timeout_milliseconds",
+            "must be positive",
+        )?;
+        ensure(
+            !self.telemetry.service_name.is_empty(),
+            "telemetry.service_name",
+            "must not be empty",
+        )?;
+        if let Some(endpoint) = &self.telemetry.otlp_endpoint {
+            ensure(
+                endpoint.starts_with("http://") || endpoint.starts_with("https://"),
+                "telemetry.otlp_endpoint",
+                "must be an http:// or https:// URL",
+            )?;
+        }
+        if let Some(password) = &self.email_client.webhook.password {
+            ensure(
+                !password.expose_secret().is_empty(),
+                "email_client.webhook.password",
+                "must not be empty",
+            )?;
+            ensure(
+                !self.email_client.webhook.username.is_empty(),
+                "email_client.webhook.username",
+                "must not be empty",
+            )?;
+        }
+        if let Some(api_token) = &self.admin.api_token {
+            ensure(
+                !api_token.expose_secret().is_empty(),
+                "admin.api_token",
+                "must not be empty",
+            )?;
+        }
+        Ok(())
+    }
+
+    // What sending through `provider` needs, whether it's the primary or a
+    // fallback
+    // The database has to be one this build can talk to, and stores can only
+    // live in Postgres when that's the database
+    fn validate_database(&self) -> Result<(), SettingsError> {
+        let sqlite = self.database.url.expose_secret().starts_with("sqlite:");
+        #[cfg(not(feature = "sqlite"))]
+        ensure(
+            !sqlite,
+            "database.url",
+            "sqlite: URLs need a build with the sqlite feature",
+        )?;
+        #[cfg(not(feature = "postgres"))]
+        ensure(
+            sqlite,
+            "database.url",
+            "must be a sqlite: URL when built without the postgres feature",
+        )?;
+        if sqlite {
+            let stores = &self.stores;
+            for (key, on_postgres) in [
+                (
+                    "stores.banned_tokens",
+                    stores.banned_tokens == StoreBackend::Postgres,
+                ),
+                (
+                    "stores.two_fa_codes",
+                    stores.two_fa_codes == StoreBackend::Postgres,
+                ),
+                (
+                    "stores.audit_log",
+                    stores.audit_log == PersistentStoreBackend::Postgres,
+                ),
+                (
+                    "stores.known_devices",
+                    stores.known_devices == PersistentStoreBackend::Postgres,
+                ),
+                (
+                    "stores.email_outbox",
+                    stores.email_outbox == PersistentStoreBackend::Postgres,
+                ),
+                (
+                    "stores.roles",
+                    stores.roles == PersistentStoreBackend::Postgres,
+                ),
+                (
+                    "stores.organizations",
+                    stores.organizations == PersistentStoreBackend::Postgres,
+                ),
+            ] {
+                ensure(
+                    !on_postgres,
+                    key,
+                    "can't be \"postgres\" when database.url is a sqlite: URL",
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_email_provider(&self, provider: EmailProvider) -> Result<(), SettingsError> {
+        match provider {
+            EmailProvider::Postmark => {
+                ensure(
+                    !self.email_client.base_url.is_empty(),
+                    "email_client.base_url",
+                    "must not be empty",
+                )?;
+                ensure(
+                    self.email_client
+                        .auth_token
+                        .as_ref()
+                        .is_some_and(|token| !token.expose_secret().is_empty()),
+                    "email_client.auth_token",
+                    "must be set to send through Postmark",
+                )?;
+            }
+            EmailProvider::SendGrid => {
+                let sendgrid = &self.email_client.sendgrid;
+                ensure(
+                    !sendgrid.base_url.is_empty(),
+                    "email_client.sendgrid.base_url",
+                    "must not be empty",
+                )?;
+                ensure(
+                    sendgrid
+                        .api_key
+                        .as_ref()
+                        .is_some_and(|key| !key.expose_secret().is_empty()),
+                    "email_client.sendgrid.api_key",
+                    "must be set to send through SendGrid",
+                )?;
+            }
+            EmailProvider::Smtp => {
+                let smtp = &self.email_client.smtp;
+                ensure(
+                    !smtp.host.is_empty(),
+                    "email_client.smtp.host",
+                    "must not be empty",
+                )?;
+                ensure(smtp.port > 0, "email_client.smtp.port", "must be positive")?;
+                ensure(
+                    smtp.username.is_some() == smtp.password.is_some(),
+                    "email_client.smtp.username",
+                    "must be set together with email_client.smtp.password",
+                )?;
+                ensure(
+                    smtp.max_connections > 0,
+                    "email_client.smtp.max_connections",
+                    "must be positive",
+                )?;
+            }
+            EmailProvider::DevMailbox => {
+                let dev_mailbox = &self.email_client.dev_mailbox;
+                ensure(
+                    dev_mailbox.max_messages > 0 || dev_mailbox.directory.is_some(),
+                    "email_client.dev_mailbox.max_messages",
+                    "must be positive unless email_client.dev_mailbox.directory is set",
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Environment variables the service read before it had a config file,
+// mapped to the settings they now override
+const LEGACY_ENV_VARS: &[(&str, &str)] = &[
+    ("auth.jwt_secret", env::JWT_SECRET_ENV_VAR),
+    ("database.url", env::DATABASE_URL_ENV_VAR),
+    ("redis.host_name", env::REDIS_HOST_NAME_ENV_VAR),
+    ("email_client.auth_token", env::POSTMARK_AUTH_TOKEN_ENV_VAR),
+    ("stores.banned_tokens", env::BANNED_TOKEN_STORE_ENV_VAR),
+    ("stores.two_fa_codes", env::TWO_FA_CODE_STORE_ENV_VAR),
+];
+
+// The standard OpenTelemetry variables, so the service can be pointed at a
+// collector the same way as everything else in the deployment
+const OTEL_ENV_VARS: &[(&str, &str)] = &[
+    ("telemetry.service_name", env::OTEL_SERVICE_NAME_ENV_VAR),
+    (
+        "telemetry.otlp_endpoint",
+        env::OTEL_EXPORTER_OTLP_ENDPOINT_ENV_VAR,
+    ),
+];
+
+fn ensure(condition: bool, key: &'static str, reason: &str) -> Result<(), SettingsError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(SettingsError::Invalid {
+            key,
+            reason: reason.to_owned(),
+        })
+    }
+}
+
+// Only plain #rrggbb colours, since the value ends up inside inline CSS
+fn is_hex_color(value: &str) -> bool {
+    value.len() == 7 && value.starts_with('#') && value[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl AuthSettings {
+    pub fn token_ttl(&self) -> Duration {
+        Duration::from_secs(self.token_ttl_seconds)
+    }
+
+    pub fn two_fa_code_ttl(&self) -> Duration {
+        Duration::from_secs(self.two_fa_code_ttl_seconds)
+    }
+
+    pub fn recovery_token_ttl(&self) -> Duration {
+        Duration::from_secs(self.recovery_token_ttl_seconds)
+    }
+
+    pub fn invitation_ttl(&self) -> Duration {
+        Duration::from_secs(self.invitation_ttl_seconds)
+    }
+}
+
+impl From<CookieSameSite> for SameSite {
+    fn from(same_site: CookieSameSite) -> Self {
+        match same_site {
+            CookieSameSite::Strict => SameSite::Strict,
+            CookieSameSite::Lax => SameSite::Lax,
+            CookieSameSite::None => SameSite::None,
+        }
+    }
+}
+
+impl PasswordHashingSettings {
+    pub fn argon2_params(&self) -> Result<Params, argon2::Error> {
+        Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+    }
+}
+
+impl StoreSettings {
+    pub fn cleanup_interval(&self) -> Duration {
+        Duration::from_secs(self.cleanup_interval_seconds)
+    }
+}
+
+impl EmailClientSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+impl SmsClientSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+impl CircuitBreakerSettings {
+    pub fn open_duration(&self) -> Duration {
+        Duration::from_millis(self.open_milliseconds)
+    }
+}
+
+impl SmtpSettings {
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout_seconds)
+    }
+}
+
+impl EmailOutboxSettings {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_milliseconds)
+    }
+
+    pub fn initial_backoff(&self) -> Duration {
+        Duration::from_millis(self.initial_backoff_milliseconds)
+    }
+
+    pub fn max_backoff(&self) -> Duration {
+        Duration::from_millis(self.max_backoff_milliseconds)
+    }
+
+    pub fn lease(&self) -> Duration {
+        Duration::from_millis(self.lease_milliseconds)
+    }
+
+    pub fn dead_letter_retention(&self) -> Duration {
+        Duration::from_secs(self.dead_letter_retention_seconds)
+    }
+}
+
+impl HealthSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_config_with_secrets_is_valid() {
+        let settings = Config::builder()
+            .add_source(File::with_name(&format!("{}/base", CONFIG_DIR)))
+            .set_override("auth.jwt_secret", "secret")
+            .unwrap()
+            .set_override("database.url", "postgres://localhost")
+            .unwrap()
+            .set_override("email_client.auth_token", "token")
+            .unwrap()
+            .build()
+            .unwrap()
+            .try_deserialize::<Settings>()
+            .unwrap();
+
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn smtp_provider_needs_no_postmark_token() {
+        let settings = Config::builder()
+            .add_source(File::with_name(&format!("{}/base", CONFIG_DIR)))
+            .set_override("auth.jwt_secret", "secret")
+            .unwrap()
+            .set_override("database.url", "postgres://localhost")
+            .unwrap()
+            .set_override("email_client.provider", "smtp")
+            .unwrap()
+            .build()
+            .unwrap()
+            .try_deserialize::<Settings>()
+            .unwrap();
+
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.email_client.smtp.tls, SmtpTls::StartTls);
+    }
+
+    #[test]
+    fn dev_mailbox_provider_needs_no_postmark_token() {
+        let settings = Config::builder()
+            .add_source(File::with_name(&format!("{}/base", CONFIG_DIR)))
+            .set_override("auth.jwt_secret", "secret")
+            .unwrap()
+            .set_override("database.url", "postgres://localhost")
+            .unwrap()
+            .set_override("email_client.provider", "dev_mailbox")
+            .unwrap()
+            .build()
+            .unwrap()
+            .try_deserialize::<Settings>()
+            .unwrap();
+
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.email_client.provider, EmailProvider::DevMailbox);
+        assert!(settings.email_client.dev_mailbox.directory.is_none());
+    }
+
+    #[test]
+    fn fallback_providers_are_validated_too() {
+        let builder = || {
+            Config::builder()
+                .add_source(File::with_name(&format!("{}/base", CONFIG_DIR)))
+                .set_override("auth.jwt_secret", "secret")
+                .unwrap()
+                .set_override("database.url", "postgres://localhost")
+                .unwrap()
+                .set_override("email_client.auth_token", "token")
+                .unwrap()
+                .set_override("email_client.fallback_providers", vec!["sendgrid"])
+                .unwrap()
+        };
+        let load = |builder: config::ConfigBuilder<config::builder::DefaultState>| {
+            builder
+                .build()
+                .unwrap()
+                .try_deserialize::<Settings>()
+                .unwrap()
+        };
+
+        let without_key = load(builder());
+        let with_key = load(
+            builder()
+                .set_override("email_client.sendgrid.api_key", "key")
+                .unwrap(),
+        );
+
+        assert!(matches!(
+            without_key.validate(),
+            Err(SettingsError::Invalid {
+                key: "email_client.sendgrid.api_key",
+                ..
+            })
+        ));
+        assert!(with_key.validate().is_ok());
+        assert_eq!(
+            with_key.email_client.fallback_providers,
+            vec![EmailProvider::SendGrid]
+        );
+    }
+
+    #[test]
+    fn primary_provider_cant_be_its_own_fallback() {
+        let settings = Config::builder()
+            .add_source(File::with_name(&format!("{}/base", CONFIG_DIR)))
+            .set_override("auth.jwt_secret", "secret")
+            .unwrap()
+            .set_override("database.url", "postgres://localhost")
+            .unwrap()
+            .set_override("email_client.auth_token", "token")
+            .unwrap()
+            .set_override("email_client.fallback_providers", vec!["postmark"])
+            .unwrap()
+            .build()
+            .unwrap()
+            .try_deserialize::<Settings>()
+            .unwrap();
+
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsError::Invalid {
+                key: "email_client.fallback_providers",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn sms_gateway_needs_an_auth_token() {
+        let settings = Config::builder()
+            .add_source(File::with_name(&format!("{}/base", CONFIG_DIR)))
+            .set_override("auth.jwt_secret", "secret")
+            .unwrap()
+            .set_override("database.url", "postgres://localhost")
+            .unwrap()
+            .set_override("email_client.auth_token", "token")
+            .unwrap()
+            .set_override("sms_client.url", "https://sms.example.com/messages")
+            .unwrap()
+            .build()
+            .unwrap()
+            .try_deserialize::<Settings>()
+            .unwrap();
+
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsError::Invalid {
+                key: "sms_client.auth_token",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn missing_secret_is_a_load_error() {
+        let result = Config::builder()
+            .add_source(File::with_name(&format!("{}/base", CONFIG_DIR)))
+            .build()
+            .unwrap()
+            .try_deserialize::<Settings>();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn unknown_store_backend_is_rejected() {
+        let result = Config::builder()
+            .set_override("banned_tokens", "memcached")
+            .unwrap()
+            .set_override("two_fa_codes", "redis")
+            .unwrap()
+            .set_override("audit_log", "memory")
+            .unwrap()
+            .set_override("known_devices", "memory")
+            .unwrap()
+            .set_override("email_outbox", "memory")
+            .unwrap()
+            .set_override("roles", "memory")
+            .unwrap()
+            .set_override("organizations", "memory")
+            .unwrap()
+            .set_override("cleanup_interval_seconds", 60)
+            .unwrap()
+            .build()
+            .unwrap()
+            .try_deserialize::<StoreSettings>();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn invalid_values_name_the_offending_key() {
+        let mut settings = test_settings();
+        settings.email_client.sender = "not-an-email".to_owned();
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("email_client.sender"));
+
+        let mut settings = test_settings();
+        settings.auth.token_ttl_seconds = 0;
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("auth.token_ttl_seconds"));
+
+        let mut settings = test_settings();
+        settings.auth.password_hashing.iterations = 0;
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("auth.password_hashing"));
+
+        let mut settings = test_settings();
+        settings.auth.cookie.same_site = CookieSameSite::None;
+        settings.auth.cookie.secure = false;
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("auth.cookie.same_site"));
+
+        let mut settings = test_settings();
+        settings.telemetry.otlp_endpoint = Some("localhost:4318".to_owned());
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("telemetry.otlp_endpoint"));
+
+        let mut settings = test_settings();
+        settings.application.public_url = "localhost:3000".to_owned();
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("application.public_url"));
+
+        let mut settings = test_settings();
+        settings.branding.accent_color = "red; display: none".to_owned();
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("branding.accent_color"));
+
+        let mut settings = test_settings();
+        settings.email_client.auth_token = None;
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("email_client.auth_token"));
+
+        let mut settings = test_settings();
+        settings.email_client.provider = EmailProvider::Smtp;
+        settings.email_client.smtp.username = Some("relay-user".to_owned());
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("email_client.smtp.username"));
+
+        let mut settings = test_settings();
+        settings.email_client.provider = EmailProvider::DevMailbox;
+        settings.email_client.dev_mailbox.max_messages = 0;
+        let err = settings.validate().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("email_client.dev_mailbox.max_messages"));
+
+        let mut settings = test_settings();
+        settings.email_outbox.lease_milliseconds = settings.email_client.timeout_milliseconds;
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("email_outbox.lease_milliseconds"));
+
+        let mut settings = test_settings();
+        settings.admin.api_token = Some(Secret::new(String::new()));
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("admin.api_token"));
+
+        let mut settings = test_settings();
+        settings.email_client.webhook.password = Some(Secret::new(String::new()));
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("email_client.webhook.password"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "sqlite"))]
+    fn sqlite_database_needs_the_sqlite_feature() {
+        let mut settings = test_settings();
+        settings.database.url = Secret::new("sqlite://auth.db".to_owned());
+
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("database.url"));
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite")]
+    fn sqlite_database_rejects_stores_on_postgres() {
+        let mut settings = test_settings();
+        settings.database.url = Secret::new("sqlite://auth.db".to_owned());
+        settings.stores.banned_tokens = StoreBackend::Memory;
+        settings.stores.two_fa_codes = StoreBackend::Memory;
+        settings.stores.audit_log = PersistentStoreBackend::Memory;
+        settings.stores.known_devices = PersistentStoreBackend::Memory;
+        settings.stores.email_outbox = PersistentStoreBackend::Memory;
+        settings.stores.roles = PersistentStoreBackend::Memory;
+        settings.stores.organizations = PersistentStoreBackend::Postgres;
+
+        let err = settings.validate().unwrap_err();
+        assert!(err.to_string().contains("stores.organizations"));
+        settings.stores.organizations = PersistentStoreBackend::Memory;
+        assert!(settings.validate().is_ok());
+    }
+
+    fn test_settings() -> Settings {
+        Config::builder()
+            .add_source(File::with_name(&format!("{}/base", CONFIG_DIR)))
+            .add_source(File::with_name(&format!("{}/test", CONFIG_DIR)))
+            .set_override("database.url", "postgres://localhost")
+            .unwrap()
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+}
@@ -0,0 +1,111 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+use chrono::Utc;
+
+use super::metrics::outcome;
+use super::request_id::RequestId;
+use crate::app_state::AuditLogType;
+use crate::domain::data_stores::audit_log::{AuditEvent, AuditEventKind, AuditOutcome};
+use crate::domain::data_stores::known_device_store::Device;
+
+// Client supplied values are capped so one request can't bloat the log
+const MAX_ACTOR_LENGTH: usize = 320;
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+// Where a request came from, extracted for the audit events it produces
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    ip: Option<String>,
+    user_agent: Option<String>,
+    request_id: Option<String>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuditContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(AuditContext {
+            ip: parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| address.ip().to_string()),
+            user_agent: parts
+                .headers
+                .get(USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| truncate(value, MAX_USER_AGENT_LENGTH)),
+            request_id: parts.extensions.get::<RequestId>().map(|id| id.to_string()),
+        })
+    }
+}
+
+impl AuditContext {
+    // The device the request came from
+    pub fn device(&self) -> Device {
+        Device {
+            ip: self.ip.clone(),
+            user_agent: self.user_agent.clone(),
+        }
+    }
+
+    // Record an event, where `result` is one of the `metrics::outcome`
+    // labels: `success` and `2fa_required` are successes, anything else is
+    // a failure with the label as its reason. A failed write is logged
+    // rather than failing the request.
+    pub async fn record(
+        &self,
+        audit_log: &AuditLogType,
+        kind: AuditEventKind,
+        actor: Option<&str>,
+        result: &str,
+    ) {
+        self.record_with_subject(audit_log, kind, actor, None, result)
+            .await
+    }
+
+    // Like `record`, for an action `actor` took on `subject`'s account
+    pub async fn record_with_subject(
+        &self,
+        audit_log: &AuditLogType,
+        kind: AuditEventKind,
+        actor: Option<&str>,
+        subject: Option<&str>,
+        result: &str,
+    ) {
+        let (outcome, reason) = match result {
+            outcome::SUCCESS => (AuditOutcome::Success, None),
+            outcome::TWO_FA_REQUIRED => (AuditOutcome::Success, Some(result.to_owned())),
+            _ => (AuditOutcome::Failure, Some(result.to_owned())),
+        };
+        let event = AuditEvent {
+            kind,
+            outcome,
+            reason,
+            actor: actor
+                .filter(|actor| !actor.is_empty())
+                .map(|actor| truncate(actor, MAX_ACTOR_LENGTH)),
+            subject: subject
+                .filter(|subject| !subject.is_empty())
+                .map(|subject| truncate(subject, MAX_ACTOR_LENGTH)),
+            ip: self.ip.clone(),
+            user_agent: self.user_agent.clone(),
+            request_id: self.request_id.clone(),
+            occurred_at: Utc::now(),
+        };
+
+        if let Err(e) = audit_log.record(event).await {
+            tracing::warn!(kind = %kind, error = ?e, "Failed to record audit event");
+        }
+    }
+}
+
+fn truncate(value: &str, max_chars: usize) -> String {
+    value.chars().take(max_chars).collect()
+}
@@ -1,63 +1,121 @@
 use crate::app_state::BannedTokenStoreType;
 use crate::domain::email::Email;
+use crate::domain::{Grants, Invitation, Membership};
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use chrono::Utc;
-use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
+use color_eyre::eyre::{eyre, Context, Result};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
 use secrecy::ExposeSecret;
 use secrecy::Secret;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use super::constants::JWT_COOKIE_NAME;
-use super::constants::JWT_SECRET;
-
-// This is definitely NOT a good secret. We will update it soon!
+use crate::settings::{AuthSettings, CookieSettings};
 
 // Create cookie with a new JWT auth token
 #[tracing::instrument(name = "Generate Auth Cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
-    Ok(create_auth_cookie(token.to_string()))
+pub fn generate_auth_cookie(
+    email: &Email,
+    grants: &Grants,
+    organization: Option<&Membership>,
+    settings: &AuthSettings,
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(email, grants, organization, settings)?;
+    Ok(create_auth_cookie(token.to_string(), &settings.cookie))
 }
 
 // Create cookie and set the value to the passed-in token string
 #[tracing::instrument(name = "Create Auth Cookie", skip_all)]
-fn create_auth_cookie(token: String) -> Cookie<'static> {
-    let cookie = Cookie::build((JWT_COOKIE_NAME, token))
-        .path("/") // apple cookie to all URLs on the server
-        .http_only(true) // prevent JavaScript from accessing the cookie
-        .same_site(SameSite::Lax) // send cookie with "same-site" requests, and with "cross-site" top-level navigations.
+fn create_auth_cookie(token: String, settings: &CookieSettings) -> Cookie<'static> {
+    let cookie = Cookie::build((settings.name.clone(), token))
+        .path(settings.path.clone())
+        .http_only(settings.http_only)
+        .secure(settings.secure)
+        .same_site(SameSite::from(settings.same_site))
         .build();
 
     cookie
 }
 
-// This value determines how long the JWT auth token is valid for
+// How long a JWT auth token is valid for unless configured otherwise.
+// Token-keyed stores use it as their default TTL.
 pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
 
-// Create JWT auth token
+// Create JWT auth token, carrying the user's roles and permissions, and the
+// organization they act for, so downstream services can authorize without
+// asking us
 #[tracing::instrument(name = "Generate Auth Token", skip_all)]
-pub fn generate_auth_token(email: &Email) -> Result<String> {
-    let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
-        .wrap_err("failed to create 10 minute time delta")?;
+pub fn generate_auth_token(
+    email: &Email,
+    grants: &Grants,
+    organization: Option<&Membership>,
+    settings: &AuthSettings,
+) -> Result<String> {
+    let sub = email.as_ref().expose_secret().to_string();
+    let exp = expires_after(settings.token_ttl())?;
+    let iat = Utc::now().timestamp() as usize;
+
+    let claims = Claims {
+        sub,
+        exp,
+        iat,
+        roles: grants.roles.clone(),
+        permissions: grants.permissions.clone(),
+        org_id: organization.map(|membership| membership.organization_id.to_string()),
+        org_role: organization.map(|membership| membership.role.as_str().to_owned()),
+    };
+
+    create_token(&claims, &settings.jwt_secret)
+}
+
+// Create the token for the "this wasn't me" link in a new device alert
+#[tracing::instrument(name = "Generate Recovery Token", skip_all)]
+pub fn generate_recovery_token(email: &Email, settings: &AuthSettings) -> Result<String> {
+    let claims = RecoveryClaims {
+        sub: email.as_ref().expose_secret().to_string(),
+        aud: RECOVERY_AUDIENCE.to_owned(),
+        exp: expires_after(settings.recovery_token_ttl())?,
+    };
+
+    create_token(&claims, &settings.jwt_secret)
+}
+
+// Create the token for the link in an invitation email. It names the
+// invitation, which has the details and is used up when accepted.
+#[tracing::instrument(name = "Generate Invitation Token", skip_all)]
+pub fn generate_invitation_token(
+    invitation: &Invitation,
+    settings: &AuthSettings,
+) -> Result<String> {
+    let exp = invitation.expires_at.timestamp();
+    let claims = InvitationClaims {
+        sub: invitation.id.to_string(),
+        aud: INVITATION_AUDIENCE.to_owned(),
+        exp: exp.try_into().wrap_err(format!(
+            "failed to cast exp time to usize. exp time: {}",
+            exp
+        ))?,
+    };
+
+    create_token(&claims, &settings.jwt_secret)
+}
+
+// Expiration time (a Unix timestamp) of a token created now
+fn expires_after(ttl: std::time::Duration) -> Result<usize> {
+    let delta =
+        chrono::Duration::from_std(ttl).wrap_err("failed to create token TTL time delta")?;
 
     // Create JWT expiration time
     let exp = Utc::now()
         .checked_add_signed(delta)
-        .ok_or(eyre!("failed to add 10 minutes to current time"))?
+        .ok_or(eyre!("failed to add token TTL to current time"))?
         .timestamp();
 
     // Cast exp to a usize, which is what Claims expects
-    let exp: usize = exp.try_into().wrap_err(format!(
+    exp.try_into().wrap_err(format!(
         "failed to cast exp time to usize. exp time: {}",
         exp
-    ))?;
-
-    let sub = email.as_ref().expose_secret().to_string();
-
-    let claims = Claims { sub, exp };
-
-    create_token(&claims)
+    ))
 }
 
 #[derive(Debug)]
@@ -66,37 +124,107 @@ pub enum TokenValidationError {
     InvalidToken,
     IssueWithBannedStore,
 }
+
+impl TokenValidationError {
+    // Label used for this failure in metrics and audit events
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenValidationError::BannedToken => "banned_token",
+            TokenValidationError::InvalidToken => "invalid_token",
+            TokenValidationError::IssueWithBannedStore => "issue_with_banned_store",
+        }
+    }
+}
 // Check if JWT auth token is valid by decoding it using the JWT secret
 #[tracing::instrument(name = "Validate Token", skip_all)]
 pub async fn validate_token(
     token: &str,
     banned_token_store: &BannedTokenStoreType,
+    settings: &AuthSettings,
 ) -> Result<Claims, TokenValidationError> {
-    {
-        let banned_store_read_lock = banned_token_store.read().await;
-
-        match banned_store_read_lock.contains_token(&Secret::new(token.to_string())).await {
-            Ok(true) => return Err(TokenValidationError::BannedToken),
-            _ => {}
-        }
+    // Fail closed: a token we can't check against the ban list is rejected
+    let banned = banned_token_store
+        .contains_token(&Secret::new(token.to_string()))
+        .await
+        .map_err(|_| TokenValidationError::IssueWithBannedStore)?;
+    if banned {
+        return Err(TokenValidationError::BannedToken);
     }
 
-    decode::<Claims>(
+    let claims = decode::<Claims>(
         token,
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &DecodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
         &Validation::default(),
     )
     .map(|data| data.claims)
-    .map_err(|_| TokenValidationError::InvalidToken)
+    .map_err(|_| TokenValidationError::InvalidToken)?;
+
+    // Tokens issued before the user's sessions were revoked are banned too.
+    // `iat` only has second precision, so the revocation's own second counts
+    // as before it.
+    let email = Email::parse(claims.sub.clone()).map_err(|_| TokenValidationError::InvalidToken)?;
+    let revoked_at = banned_token_store
+        .sessions_revoked_at(&email)
+        .await
+        .map_err(|_| TokenValidationError::IssueWithBannedStore)?;
+    if let Some(revoked_at) = revoked_at {
+        if (claims.iat as i64) <= revoked_at.timestamp() {
+            return Err(TokenValidationError::BannedToken);
+        }
+    }
+
+    Ok(claims)
 }
 
-// Create JWT auth token by encoding claims using the JWT secret
+// Check a "this wasn't me" link token, returning whose account it is for
+#[tracing::instrument(name = "Validate Recovery Token", skip_all)]
+pub fn validate_recovery_token(
+    token: &str,
+    settings: &AuthSettings,
+) -> Result<Email, TokenValidationError> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[RECOVERY_AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "aud", "sub"]);
+
+    let claims = decode::<RecoveryClaims>(
+        token,
+        &DecodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
+        &validation,
+    )
+    .map_err(|_| TokenValidationError::InvalidToken)?
+    .claims;
+
+    Email::parse(claims.sub).map_err(|_| TokenValidationError::InvalidToken)
+}
+
+// Check an invitation link token, returning which invitation it is for
+#[tracing::instrument(name = "Validate Invitation Token", skip_all)]
+pub fn validate_invitation_token(
+    token: &str,
+    settings: &AuthSettings,
+) -> Result<Uuid, TokenValidationError> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[INVITATION_AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "aud", "sub"]);
+
+    let claims = decode::<InvitationClaims>(
+        token,
+        &DecodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
+        &validation,
+    )
+    .map_err(|_| TokenValidationError::InvalidToken)?
+    .claims;
+
+    Uuid::parse_str(&claims.sub).map_err(|_| TokenValidationError::InvalidToken)
+}
+
+// Create a JWT by encoding claims using the JWT secret
 #[tracing::instrument(name = "Create Token", skip_all)]
-fn create_token(claims: &Claims) -> Result<String> {
+fn create_token<T: Serialize>(claims: &T, jwt_secret: &Secret<String>) -> Result<String> {
     encode(
         &jsonwebtoken::Header::default(),
         &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &EncodingKey::from_secret(jwt_secret.expose_secret().as_bytes()),
     )
     .wrap_err("failed to create token")
 }
@@ -105,22 +233,104 @@ fn create_token(claims: &Claims) -> Result<String> {
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    // Issued at. Tokens from before it was added count as issued at 0.
+    #[serde(default)]
+    pub iat: usize,
+    // What the user was granted when the token was issued. Tokens from
+    // before roles existed grant nothing.
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    // The organization the user acts for and their role in it, if they
+    // belong to any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_role: Option<String>,
+}
+
+impl Claims {
+    pub fn grants(&self) -> Grants {
+        Grants {
+            roles: self.roles.clone(),
+            permissions: self.permissions.clone(),
+        }
+    }
+}
+
+// Audience of recovery tokens. Auth token validation rejects any token with
+// an audience, so a recovery token can never be used to sign in.
+const RECOVERY_AUDIENCE: &str = "account-recovery";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryClaims {
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+}
+
+// Audience of invitation tokens, which like recovery tokens can't be used
+// to sign in
+const INVITATION_AUDIENCE: &str = "org-invitation";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvitationClaims {
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+}
+
+// Compare without returning early, so response times don't reveal how much
+// of a secret was right
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |difference, (x, y)| difference | (x ^ y))
+            == 0
 }
 
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
-    use tokio::sync::RwLock;
 
+    use chrono::DateTime;
+
+    use crate::domain::data_stores::banned_token_store::{BannedTokenStore, BannedTokenStoreError};
+    use crate::domain::{OrgRole, OrganizationId};
     use crate::services::data_stores::hashset_banned_token_store::HashsetBannedTokenStore;
+    use crate::settings::{CookieSameSite, PasswordHashingSettings};
 
     use super::*;
 
+    fn settings() -> AuthSettings {
+        AuthSettings {
+            jwt_secret: Secret::new("secret".to_owned()),
+            token_ttl_seconds: 600,
+            two_fa_code_ttl_seconds: 600,
+            recovery_token_ttl_seconds: 3600,
+            invitation_ttl_seconds: 3600,
+            cookie: CookieSettings {
+                name: "jwt".to_owned(),
+                path: "/".to_owned(),
+                http_only: true,
+                secure: false,
+                same_site: CookieSameSite::Lax,
+            },
+            password_hashing: PasswordHashingSettings {
+                memory_kib: 15000,
+                iterations: 2,
+                parallelism: 1,
+            },
+        }
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let email = Email::parse("test@example.com".to_string()).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
-        assert_eq!(cookie.name(), JWT_COOKIE_NAME);
+        let cookie = generate_auth_cookie(&email, &Grants::default(), None, &settings()).unwrap();
+        assert_eq!(cookie.name(), "jwt");
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
@@ -130,8 +340,8 @@ mod tests {
     #[tokio::test]
     async fn test_create_auth_cookie() {
         let token = "test_token".to_owned();
-        let cookie = create_auth_cookie(token.clone());
-        assert_eq!(cookie.name(), JWT_COOKIE_NAME);
+        let cookie = create_auth_cookie(token.clone(), &settings().cookie);
+        assert_eq!(cookie.name(), "jwt");
         assert_eq!(cookie.value(), token);
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
@@ -141,17 +351,16 @@ mod tests {
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&email, &Grants::default(), None, &settings()).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let banned_token_store: BannedTokenStoreType =
-            Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, &banned_token_store)
+        let token = generate_auth_token(&email, &Grants::default(), None, &settings()).unwrap();
+        let banned_token_store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+        let result = validate_token(&token, &banned_token_store, &settings())
             .await
             .expect("issue validating token");
 
@@ -165,12 +374,168 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[tokio::test]
+    async fn test_validate_token_returns_the_grants() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let grants = Grants {
+            roles: vec!["admin".to_owned()],
+            permissions: vec!["roles:read".to_owned(), "roles:write".to_owned()],
+        };
+        let token = generate_auth_token(&email, &grants, None, &settings()).unwrap();
+        let banned_token_store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+
+        let claims = validate_token(&token, &banned_token_store, &settings())
+            .await
+            .unwrap();
+
+        assert_eq!(claims.grants(), grants);
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
-        let banned_token_store: BannedTokenStoreType =
-            Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, &banned_token_store).await;
+        let banned_token_store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+        let result = validate_token(&token, &banned_token_store, &settings()).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_auth_cookie_uses_configured_attributes() {
+        let mut settings = settings();
+        settings.cookie.name = "session".to_owned();
+        settings.cookie.secure = true;
+        settings.cookie.same_site = CookieSameSite::Strict;
+        let cookie = create_auth_cookie("test_token".to_owned(), &settings.cookie);
+        assert_eq!(cookie.name(), "session");
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_other_secret() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = generate_auth_token(&email, &Grants::default(), None, &settings()).unwrap();
+        let mut other = settings();
+        other.jwt_secret = Secret::new("other secret".to_owned());
+        let banned_token_store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+        let result = validate_token(&token, &banned_token_store, &other).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_validate_token_issued_before_sessions_were_revoked() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = generate_auth_token(&email, &Grants::default(), None, &settings()).unwrap();
+        let banned_token_store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+        let revoked_at = Utc::now();
+        banned_token_store
+            .revoke_sessions(&email, revoked_at)
+            .await
+            .unwrap();
+
+        let result = validate_token(&token, &banned_token_store, &settings()).await;
+        assert!(matches!(result, Err(TokenValidationError::BannedToken)));
+
+        // Other users' sessions are unaffected
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+        let token = generate_auth_token(&other, &Grants::default(), None, &settings()).unwrap();
+        assert!(validate_token(&token, &banned_token_store, &settings())
+            .await
+            .is_ok());
+    }
+
+    // A ban list that can't be reached, to check tokens aren't let through
+    struct UnreachableBannedTokenStore;
+
+    #[async_trait::async_trait]
+    impl BannedTokenStore for UnreachableBannedTokenStore {
+        async fn add_token(&self, _: Secret<String>) -> Result<(), BannedTokenStoreError> {
+            Err(unreachable_store())
+        }
+
+        async fn contains_token(&self, _: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
+            Err(unreachable_store())
+        }
+
+        async fn revoke_sessions(
+            &self,
+            _: &Email,
+            _: DateTime<Utc>,
+        ) -> Result<(), BannedTokenStoreError> {
+            Err(unreachable_store())
+        }
+
+        async fn sessions_revoked_at(
+            &self,
+            _: &Email,
+        ) -> Result<Option<DateTime<Utc>>, BannedTokenStoreError> {
+            Err(unreachable_store())
+        }
+    }
+
+    fn unreachable_store() -> BannedTokenStoreError {
+        BannedTokenStoreError::UnexpectedError(eyre!("connection refused"))
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_fails_closed_when_the_banned_store_errors() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = generate_auth_token(&email, &Grants::default(), None, &settings()).unwrap();
+        let banned_token_store: BannedTokenStoreType = Arc::new(UnreachableBannedTokenStore);
+
+        let result = validate_token(&token, &banned_token_store, &settings()).await;
+
+        assert!(matches!(
+            result,
+            Err(TokenValidationError::IssueWithBannedStore)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invitation_tokens_name_their_invitation_and_nothing_else() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let invitation = Invitation {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            email: "invitee@example.com".to_owned(),
+            role: OrgRole::Member,
+            invited_by: "test@example.com".to_owned(),
+            expires_at: Utc::now() + chrono::Duration::try_hours(1).unwrap(),
+        };
+        let invitation_token = generate_invitation_token(&invitation, &settings()).unwrap();
+        let recovery_token = generate_recovery_token(&email, &settings()).unwrap();
+        let banned_token_store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+
+        assert_eq!(
+            validate_invitation_token(&invitation_token, &settings()).unwrap(),
+            invitation.id
+        );
+        assert!(validate_invitation_token(&recovery_token, &settings()).is_err());
+        assert!(validate_recovery_token(&invitation_token, &settings()).is_err());
+        assert!(
+            validate_token(&invitation_token, &banned_token_store, &settings())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recovery_and_auth_tokens_are_not_interchangeable() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let recovery_token = generate_recovery_token(&email, &settings()).unwrap();
+        let auth_token =
+            generate_auth_token(&email, &Grants::default(), None, &settings()).unwrap();
+        let banned_token_store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::default());
+
+        assert_eq!(
+            validate_recovery_token(&recovery_token, &settings()).unwrap(),
+            email
+        );
+        assert!(validate_recovery_token(&auth_token, &settings()).is_err());
+        assert!(
+            validate_token(&recovery_token, &banned_token_store, &settings())
+                .await
+                .is_err()
+        );
+    }
 }
@@ -0,0 +1,161 @@
+use std::marker::PhantomData;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts},
+};
+use axum_extra::extract::CookieJar;
+use secrecy::ExposeSecret;
+
+use super::auth::{constant_time_eq, validate_token};
+use crate::app_state::AppState;
+use crate::domain::error::AuthAPIError;
+use crate::domain::{Email, Grants, Permission, Role};
+
+// Who made a request to a guarded route
+#[derive(Debug, Clone)]
+pub enum Caller {
+    // The operator, with `Authorization: Bearer <admin.api_token>`. It
+    // passes every guard.
+    AdminApiToken,
+    // A signed in user, by the JWT in the auth cookie or the
+    // `Authorization: Bearer` header
+    User { email: String, grants: Grants },
+}
+
+impl Caller {
+    // Name recorded as the actor of audit events
+    pub fn actor(&self) -> &str {
+        match self {
+            Caller::AdminApiToken => "admin_api_token",
+            Caller::User { email, .. } => email,
+        }
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        match self {
+            Caller::AdminApiToken => true,
+            Caller::User { grants, .. } => grants.has_permission(permission),
+        }
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        match self {
+            Caller::AdminApiToken => true,
+            Caller::User { grants, .. } => grants.has_role(role),
+        }
+    }
+
+    async fn from_parts(parts: &Parts, state: &AppState) -> Result<Self, AuthAPIError> {
+        let bearer = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if let (Some(token), Some(expected)) = (bearer, &state.settings.admin.api_token) {
+            if constant_time_eq(token, expected.expose_secret()) {
+                return Ok(Caller::AdminApiToken);
+            }
+        }
+
+        let jar = CookieJar::from_headers(&parts.headers);
+        let token = match bearer {
+            Some(token) => token.to_owned(),
+            None => jar
+                .get(&state.settings.auth.cookie.name)
+                .ok_or(AuthAPIError::MissingToken)?
+                .value()
+                .to_owned(),
+        };
+        let claims = validate_token(&token, &state.banned_token_store, &state.settings.auth)
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        // The grants are the ones in the token. Newly granted roles apply
+        // from the next login, revoked ones end the user's sessions.
+        Ok(Caller::User {
+            grants: claims.grants(),
+            email: claims.sub,
+        })
+    }
+}
+
+// Extractor for routes only callers with permission `P` may use. Rejects
+// requests without credentials with 400, with invalid ones with 401 and
+// callers lacking the permission with 403.
+pub struct RequirePermission<P> {
+    pub caller: Caller,
+    permission: PhantomData<fn() -> P>,
+}
+
+#[async_trait]
+impl<P: Permission> FromRequestParts<AppState> for RequirePermission<P> {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let caller = Caller::from_parts(parts, state).await?;
+        if !caller.has_permission(P::NAME) {
+            tracing::warn!(permission = P::NAME, "caller lacks permission");
+            return Err(AuthAPIError::Forbidden);
+        }
+        Ok(Self {
+            caller,
+            permission: PhantomData,
+        })
+    }
+}
+
+// Like `RequirePermission`, for routes only callers with role `R` may use
+pub struct RequireRole<R> {
+    pub caller: Caller,
+    role: PhantomData<fn() -> R>,
+}
+
+#[async_trait]
+impl<R: Role> FromRequestParts<AppState> for RequireRole<R> {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let caller = Caller::from_parts(parts, state).await?;
+        if !caller.has_role(R::NAME) {
+            tracing::warn!(role = R::NAME, "caller lacks role");
+            return Err(AuthAPIError::Forbidden);
+        }
+        Ok(Self {
+            caller,
+            role: PhantomData,
+        })
+    }
+}
+
+// Extractor for routes any signed in user may use on their own behalf. The
+// admin API token acts for no one in particular, so it gets 403 here.
+pub struct SignedInUser {
+    pub email: Email,
+    pub grants: Grants,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for SignedInUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        match Caller::from_parts(parts, state).await? {
+            Caller::AdminApiToken => Err(AuthAPIError::Forbidden),
+            Caller::User { email, grants } => Ok(Self {
+                email: Email::parse(email).map_err(|_| AuthAPIError::InvalidToken)?,
+                grants,
+            }),
+        }
+    }
+}
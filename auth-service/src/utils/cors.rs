@@ -0,0 +1,190 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use super::request_id::REQUEST_ID_HEADER;
+use crate::settings::CorsSettings;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CorsConfigError {
+    #[error(
+        "invalid origin \"{0}\": expected e.g. https://app.example.com or https://*.example.com"
+    )]
+    InvalidOrigin(String),
+    #[error("invalid method \"{0}\"")]
+    InvalidMethod(String),
+    #[error("invalid header name \"{0}\"")]
+    InvalidHeader(String),
+}
+
+// An entry of `cors.allowed_origins`: either an exact origin, or a wildcard
+// pattern like `https://*.example.com` that matches any subdomain (but not
+// `example.com` itself) with the same scheme and port
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OriginPattern {
+    Exact(HeaderValue),
+    Subdomains { scheme: String, suffix: String },
+}
+
+impl FromStr for OriginPattern {
+    type Err = CorsConfigError;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let invalid = || CorsConfigError::InvalidOrigin(pattern.to_owned());
+        let (scheme, host) = pattern.split_once("://").ok_or_else(invalid)?;
+        if scheme.is_empty() || host.is_empty() || host.contains('/') {
+            return Err(invalid());
+        }
+
+        match host.strip_prefix('*') {
+            Some(suffix) if suffix.starts_with('.') && !suffix.contains('*') => {
+                Ok(OriginPattern::Subdomains {
+                    scheme: scheme.to_ascii_lowercase(),
+                    suffix: suffix.to_ascii_lowercase(),
+                })
+            }
+            Some(_) => Err(invalid()),
+            None if host.contains('*') => Err(invalid()),
+            None => HeaderValue::from_str(pattern)
+                .map(OriginPattern::Exact)
+                .map_err(|_| invalid()),
+        }
+    }
+}
+
+impl OriginPattern {
+    pub fn matches(&self, origin: &HeaderValue) -> bool {
+        match self {
+            OriginPattern::Exact(allowed) => allowed == origin,
+            OriginPattern::Subdomains { scheme, suffix } => {
+                let Ok(origin) = origin.to_str() else {
+                    return false;
+                };
+                let origin = origin.to_ascii_lowercase();
+                let Some(host) = origin
+                    .strip_prefix(scheme.as_str())
+                    .and_then(|rest| rest.strip_prefix("://"))
+                else {
+                    return false;
+                };
+                match host.strip_suffix(suffix.as_str()) {
+                    // Only subdomain labels may precede the suffix, so
+                    // `https://evil.com?.example.com` and friends don't match
+                    Some(subdomain) => {
+                        !subdomain.is_empty()
+                            && subdomain
+                                .chars()
+                                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                    }
+                    None => false,
+                }
+            }
+        }
+    }
+}
+
+// Build the CORS layer from the configured policy. Credentials are always
+// allowed since the auth cookie has to travel with cross-origin requests, and
+// the request id is exposed so browser clients can report it.
+pub fn cors_layer(settings: &CorsSettings) -> Result<CorsLayer, CorsConfigError> {
+    let origins = settings
+        .allowed_origins
+        .iter()
+        .map(|origin| origin.parse::<OriginPattern>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let methods = settings
+        .allowed_methods
+        .iter()
+        .map(|method| {
+            Method::from_str(&method.to_ascii_uppercase())
+                .map_err(|_| CorsConfigError::InvalidMethod(method.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let headers = settings
+        .allowed_headers
+        .iter()
+        .map(|header| {
+            HeaderName::from_str(header).map_err(|_| CorsConfigError::InvalidHeader(header.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(move |origin, _| {
+            origins.iter().any(|pattern| pattern.matches(origin))
+        }))
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .allow_credentials(true)
+        .expose_headers([REQUEST_ID_HEADER])
+        .max_age(Duration::from_secs(settings.max_age_seconds)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, origin: &str) -> bool {
+        pattern
+            .parse::<OriginPattern>()
+            .unwrap()
+            .matches(&HeaderValue::from_str(origin).unwrap())
+    }
+
+    #[test]
+    fn exact_origin_only_matches_itself() {
+        assert!(matches("http://localhost:8000", "http://localhost:8000"));
+        assert!(!matches("http://localhost:8000", "http://localhost:8001"));
+        assert!(!matches("http://localhost:8000", "https://localhost:8000"));
+    }
+
+    #[test]
+    fn wildcard_matches_subdomains_only() {
+        assert!(matches("https://*.example.com", "https://app.example.com"));
+        assert!(matches("https://*.example.com", "https://a.b.example.com"));
+        assert!(matches("https://*.example.com", "https://APP.Example.com"));
+        assert!(!matches("https://*.example.com", "https://example.com"));
+        assert!(!matches("https://*.example.com", "http://app.example.com"));
+        assert!(!matches(
+            "https://*.example.com",
+            "https://app.example.com:8443"
+        ));
+        assert!(!matches(
+            "https://*.example.com",
+            "https://app.example.com.evil.com"
+        ));
+        assert!(!matches(
+            "https://*.example.com",
+            "https://evil.com?.example.com"
+        ));
+    }
+
+    #[test]
+    fn wildcard_with_port_requires_that_port() {
+        assert!(matches(
+            "https://*.example.com:8443",
+            "https://app.example.com:8443"
+        ));
+        assert!(!matches(
+            "https://*.example.com:8443",
+            "https://app.example.com"
+        ));
+    }
+
+    #[test]
+    fn malformed_patterns_are_rejected() {
+        for pattern in [
+            "*",
+            "localhost:8000",
+            "https://",
+            "https://app.*.com",
+            "https://*example.com",
+            "https://a.com/path",
+        ] {
+            assert!(pattern.parse::<OriginPattern>().is_err(), "{}", pattern);
+        }
+    }
+}
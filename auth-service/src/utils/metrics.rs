@@ -0,0 +1,299 @@
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+use super::auth::TokenValidationError;
+use crate::app_state::AppState;
+
+// All Prometheus metrics the service exposes on /metrics. Each `Metrics`
+// owns its own registry, so apps built side by side (e.g. in tests) don't
+// share counters.
+pub struct Metrics {
+    registry: Registry,
+    http_requests_total: IntCounterVec,
+    http_request_duration_seconds: HistogramVec,
+    signups_total: IntCounterVec,
+    logins_total: IntCounterVec,
+    two_fa_verifications_total: IntCounterVec,
+    token_validations_total: IntCounterVec,
+    emails_total: IntCounterVec,
+    sms_total: IntCounterVec,
+    emails_dead_lettered_total: IntCounter,
+    email_outbox_pending: IntGauge,
+    store_operation_duration_seconds: HistogramVec,
+}
+
+// Outcome label values for the domain counters
+pub mod outcome {
+    pub const SUCCESS: &str = "success";
+    pub const FAILURE: &str = "failure";
+    pub const INVALID_INPUT: &str = "invalid_input";
+    pub const ALREADY_EXISTS: &str = "already_exists";
+    pub const BAD_PASSWORD: &str = "bad_password";
+    pub const PASSWORD_RESET_REQUIRED: &str = "password_reset_required";
+    pub const ACCOUNT_DISABLED: &str = "account_disabled";
+    pub const EMAIL_SUPPRESSED: &str = "email_suppressed";
+    pub const SUPPRESSED: &str = "suppressed";
+    pub const TWO_FA_REQUIRED: &str = "2fa_required";
+    pub const INCORRECT_CODE: &str = "incorrect_code";
+    pub const ERROR: &str = "error";
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("auth_service".to_owned()), None)
+            .expect("metric prefix is valid");
+
+        let http_requests_total = counter(
+            &registry,
+            "http_requests_total",
+            "HTTP requests by route and status",
+            &["method", "route", "status"],
+        );
+        let http_request_duration_seconds = histogram(
+            &registry,
+            "http_request_duration_seconds",
+            "HTTP request latency by route and status",
+            &["method", "route", "status"],
+        );
+        let signups_total = counter(
+            &registry,
+            "signups_total",
+            "Signup attempts by outcome",
+            &["outcome"],
+        );
+        let logins_total = counter(
+            &registry,
+            "logins_total",
+            "Login attempts by outcome",
+            &["outcome"],
+        );
+        let two_fa_verifications_total = counter(
+            &registry,
+            "two_fa_verifications_total",
+            "2FA code verifications by outcome",
+            &["outcome"],
+        );
+        let token_validations_total = counter(
+            &registry,
+            "token_validations_total",
+            "JWT validations by result",
+            &["result"],
+        );
+        let emails_total = counter(
+            &registry,
+            "emails_total",
+            "Emails handed to the email provider by outcome, or suppressed instead",
+            &["outcome"],
+        );
+        let sms_total = counter(
+            &registry,
+            "sms_total",
+            "Text messages handed to the SMS gateway by outcome",
+            &["outcome"],
+        );
+        let emails_dead_lettered_total = IntCounter::new(
+            "emails_dead_lettered_total",
+            "Emails given up on after every delivery attempt failed",
+        )
+        .expect("metric is valid");
+        registry
+            .register(Box::new(emails_dead_lettered_total.clone()))
+            .expect("metric is only registered once");
+        let email_outbox_pending = IntGauge::new(
+            "email_outbox_pending",
+            "Emails waiting in the outbox to be delivered",
+        )
+        .expect("metric is valid");
+        registry
+            .register(Box::new(email_outbox_pending.clone()))
+            .expect("metric is only registered once");
+        let store_operation_duration_seconds = histogram(
+            &registry,
+            "store_operation_duration_seconds",
+            "Latency of data store operations",
+            &["store", "operation", "outcome"],
+        );
+
+        Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            signups_total,
+            logins_total,
+            two_fa_verifications_total,
+            token_validations_total,
+            emails_total,
+            sms_total,
+            emails_dead_lettered_total,
+            email_outbox_pending,
+            store_operation_duration_seconds,
+        }
+    }
+
+    // Render every metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("metrics can always be text-encoded");
+        String::from_utf8(buffer).expect("text encoding is UTF-8")
+    }
+
+    pub fn record_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        self.http_requests_total.with_label_values(&labels).inc();
+        self.http_request_duration_seconds
+            .with_label_values(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_signup(&self, outcome: &str) {
+        self.signups_total.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_login(&self, outcome: &str) {
+        self.logins_total.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_two_fa_verification(&self, outcome: &str) {
+        self.two_fa_verifications_total
+            .with_label_values(&[outcome])
+            .inc();
+    }
+
+    pub fn record_token_validation<T>(&self, result: &Result<T, TokenValidationError>) {
+        let result = match result {
+            Ok(_) => "valid",
+            Err(e) => e.as_str(),
+        };
+        self.token_validations_total
+            .with_label_values(&[result])
+            .inc();
+    }
+
+    pub fn record_email(&self, outcome: &str) {
+        self.emails_total.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_sms(&self, outcome: &str) {
+        self.sms_total.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_email_dead_lettered(&self) {
+        self.emails_dead_lettered_total.inc();
+    }
+
+    pub fn record_email_outbox_pending(&self, pending: u64) {
+        self.email_outbox_pending.set(pending as i64);
+    }
+
+    pub fn record_store_operation(
+        &self,
+        store: &str,
+        operation: &str,
+        succeeded: bool,
+        elapsed: Duration,
+    ) {
+        let outcome = if succeeded {
+            outcome::SUCCESS
+        } else {
+            outcome::FAILURE
+        };
+        self.store_operation_duration_seconds
+            .with_label_values(&[store, operation, outcome])
+            .observe(elapsed.as_secs_f64());
+    }
+}
+
+fn counter(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    let counter = IntCounterVec::new(Opts::new(name, help), labels).expect("metric is valid");
+    registry
+        .register(Box::new(counter.clone()))
+        .expect("metric is only registered once");
+    counter
+}
+
+fn histogram(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> HistogramVec {
+    let histogram =
+        HistogramVec::new(HistogramOpts::new(name, help), labels).expect("metric is valid");
+    registry
+        .register(Box::new(histogram.clone()))
+        .expect("metric is only registered once");
+    histogram
+}
+
+// Middleware recording the count and latency of every request. Requests are
+// labelled with the route template rather than the raw path, so unknown
+// paths can't blow up the number of series.
+pub async fn track_requests(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_else(|| "unmatched".to_owned());
+
+    let response = next.run(request).await;
+
+    state.metrics.record_request(
+        &method,
+        &route,
+        response.status().as_u16(),
+        started.elapsed(),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_includes_recorded_metrics() {
+        let metrics = Metrics::new();
+        metrics.record_login(outcome::BAD_PASSWORD);
+        metrics.record_token_validation::<()>(&Err(TokenValidationError::BannedToken));
+        metrics.record_store_operation("user_store", "get_user", true, Duration::from_millis(3));
+
+        let rendered = metrics.render();
+
+        assert!(rendered.contains(r#"auth_service_logins_total{outcome="bad_password"} 1"#));
+        assert!(
+            rendered.contains(r#"auth_service_token_validations_total{result="banned_token"} 1"#)
+        );
+        assert!(rendered.contains(
+            r#"auth_service_store_operation_duration_seconds_count{operation="get_user",outcome="success",store="user_store"} 1"#
+        ));
+    }
+
+    #[test]
+    fn registries_are_independent() {
+        let first = Metrics::new();
+        let second = Metrics::new();
+        first.record_signup(outcome::SUCCESS);
+
+        assert!(first.render().contains("auth_service_signups_total"));
+        assert!(!second.render().contains("auth_service_signups_total"));
+    }
+}
@@ -1,3 +1,11 @@
-pub mod constants;
+pub mod audit;
 pub mod auth;
+pub mod authorization;
+pub mod constants;
+pub mod cors;
+pub mod metrics;
+pub mod new_device_alert;
+pub mod redact;
+pub mod request_id;
+pub mod session;
 pub mod tracing;
@@ -0,0 +1,78 @@
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use secrecy::ExposeSecret;
+
+use super::audit::AuditContext;
+use super::auth::generate_recovery_token;
+use super::metrics::outcome;
+use crate::app_state::AppState;
+use crate::domain::data_stores::audit_log::AuditEventKind;
+use crate::domain::data_stores::known_device_store::{Device, DeviceStatus};
+use crate::domain::Email;
+use crate::i18n::current_locale;
+use crate::services::email_templates;
+
+// Email the owner when their account is logged in to from an IP or user
+// agent it hasn't used before. The very first device is only remembered.
+// Failures are logged rather than failing the login.
+#[tracing::instrument(name = "Alert If New Device", skip_all)]
+pub async fn alert_if_new_device(state: &AppState, audit: &AuditContext, email: &Email) {
+    let device = audit.device();
+    match state
+        .known_device_store
+        .remember_device(email, &device)
+        .await
+    {
+        Ok(DeviceStatus::New) => {}
+        Ok(DeviceStatus::First | DeviceStatus::Known) => return,
+        Err(e) => {
+            tracing::warn!(error = ?e, "Failed to look up known devices");
+            return;
+        }
+    }
+
+    let result = send_alert(state, email, &device).await;
+    let result_label = match &result {
+        Ok(()) => outcome::SUCCESS,
+        Err(e) => {
+            tracing::warn!(error = ?e, "Failed to send new device alert");
+            outcome::ERROR
+        }
+    };
+    audit
+        .record(
+            &state.audit_log,
+            AuditEventKind::NewDeviceAlert,
+            Some(email.as_ref().expose_secret()),
+            result_label,
+        )
+        .await;
+}
+
+async fn send_alert(state: &AppState, email: &Email, device: &Device) -> Result<()> {
+    let token = generate_recovery_token(email, &state.settings.auth)?;
+    let link = format!(
+        "{}/account-recovery.html?token={}",
+        state.settings.application.public_url.trim_end_matches('/'),
+        token
+    );
+    // Written in the owner's language, which matters more than the one the
+    // (possibly unfamiliar) browser that logged in asked for
+    let locale = state
+        .user_store
+        .get_user(email)
+        .await
+        .map(|user| user.locale)
+        .unwrap_or_else(|_| current_locale());
+    let message = email_templates::new_device_alert(
+        &state.settings.branding,
+        locale,
+        Utc::now(),
+        device,
+        &link,
+    )?;
+
+    // Delivered in the background, so a slow provider doesn't hold up the login
+    state.email_outbox.enqueue(email.clone(), message).await?;
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// Upstream ids are echoed back in headers and logs, so only accept short
+// values made of characters that can't mangle either
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+// Identifies one request across our logs, the response and any upstream or
+// downstream service that saw it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    pub fn parse(value: &str) -> Option<Self> {
+        let valid = !value.is_empty()
+            && value.len() <= MAX_REQUEST_ID_LENGTH
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+        valid.then(|| Self(value.to_owned()))
+    }
+
+    pub fn generate() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    // The id of the request currently being handled, if any
+    pub fn current() -> Option<Self> {
+        CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// Middleware taking the request id from a valid `X-Request-Id` header, or
+// generating a new one. The id is added to the request extensions (for the
+// request span), kept in scope while the request is handled (for error
+// bodies) and echoed in the response header.
+pub async fn propagate_request_id(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(RequestId::parse)
+        .unwrap_or_else(RequestId::generate);
+    request.extensions_mut().insert(request_id.clone());
+
+    let mut response = CURRENT_REQUEST_ID
+        .scope(request_id.clone(), next.run(request))
+        .await;
+
+    let value = HeaderValue::from_str(request_id.as_str()).expect("request ids are valid headers");
+    response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_well_formed_ids() {
+        for id in [
+            "4f9c1b8e-6a0d-4d4e-9a53-2f1f0c7b5e11",
+            "req_42",
+            "edge:eu-west-1.abc",
+        ] {
+            assert_eq!(RequestId::parse(id).unwrap().as_str(), id);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_ids() {
+        let too_long = "a".repeat(MAX_REQUEST_ID_LENGTH + 1);
+        for id in ["", "has space", "new\nline", "<script>", too_long.as_str()] {
+            assert!(RequestId::parse(id).is_none(), "{:?}", id);
+        }
+    }
+
+    #[test]
+    fn generated_ids_are_valid() {
+        let id = RequestId::generate();
+        assert_eq!(RequestId::parse(id.as_str()), Some(id));
+    }
+}
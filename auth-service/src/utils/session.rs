@@ -0,0 +1,39 @@
+use axum_extra::extract::cookie::Cookie;
+
+use super::auth::generate_auth_cookie;
+use crate::app_state::AppState;
+use crate::domain::error::AuthAPIError;
+use crate::domain::{Email, OrganizationId};
+
+// The auth cookie that signs `email` in, carrying their current roles and
+// the organization they act for: `organization`, which they must belong to,
+// or else the one they joined first
+#[tracing::instrument(name = "Issue Auth Cookie", skip_all)]
+pub async fn issue_auth_cookie(
+    state: &AppState,
+    email: &Email,
+    organization: Option<&OrganizationId>,
+) -> Result<Cookie<'static>, AuthAPIError> {
+    let grants = state
+        .role_store
+        .grants(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let memberships = state
+        .organization_store
+        .memberships(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let active = match organization {
+        Some(id) => Some(
+            memberships
+                .iter()
+                .find(|membership| &membership.organization_id == id)
+                .ok_or(AuthAPIError::OrganizationNotFound)?,
+        ),
+        None => memberships.first(),
+    };
+
+    generate_auth_cookie(email, &grants, active, &state.settings.auth)
+        .map_err(AuthAPIError::UnexpectedError)
+}
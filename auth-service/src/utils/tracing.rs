@@ -1,44 +1,179 @@
-use axum::{body::Body, extract::Request, response::Response};
-use tracing_error::ErrorLayer;
-use tracing_subscriber::{layer::SubscriberExt, EnvFilter};
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{HeaderMap, Uri},
+    response::Response,
+};
 use color_eyre::eyre::Result;
-use tracing_subscriber::prelude::*;
-use tracing_subscriber::fmt;
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
 use std::time::Duration;
+use tracing_error::ErrorLayer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::fmt;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{layer::SubscriberExt, EnvFilter};
 
 use tracing::{Level, Span};
 
-pub fn init_tracing() -> Result<()> {
-    // Create a formatting layer for tracing output with a compact format
-    let fmt_layer = fmt::layer().compact();
+use super::redact::{redact, RedactingMakeWriter};
+use super::request_id::RequestId;
+use crate::settings::{LogFormat, LoggingSettings, TelemetrySettings};
+
+// Keeps the tracer provider alive for as long as the service runs. Dropping
+// it flushes any spans that are still waiting to be exported.
+pub struct TracingGuard {
+    provider: TracerProvider,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("Failed to shut down the tracer provider: {e}");
+        }
+    }
+}
+
+pub fn init_tracing(
+    logging: &LoggingSettings,
+    telemetry: &TelemetrySettings,
+) -> Result<TracingGuard> {
+    // Create a formatting layer for tracing output, either compact or JSON.
+    // Every line passes through the redacting writer, so no email, token or
+    // 2FA code reaches the logs in plaintext.
+    let writer = RedactingMakeWriter::new(std::io::stdout);
+    let fmt_layer = match logging.format {
+        LogFormat::Compact => fmt::layer().compact().with_writer(writer).boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(writer)
+            .boxed(),
+    };
 
     // Create a filter layer to control the verbosity of logs
     // Try to get the filter configuration from the environment variables
     // If it fails, default to the "info" log level
     let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
 
+    // Turn spans into OpenTelemetry spans, so trace ids can be propagated
+    // to and from other services (and exported, if a collector is set)
+    let provider = tracer_provider(telemetry)?;
+    let otel_layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("auth-service"));
+
     // Build the tracing subscriber registry with the formatting layer,
     // the filter layer, and the error layer for enhanced error reporting
     tracing_subscriber::registry()
+        .with(fmt_layer) // Add the formatting layer for compact or JSON log output
         .with(filter_layer) // Add the filter layer to control log verbosity
-        .with(fmt_layer) // Add the formatting layer for compact log output
+        .with(otel_layer) // Add the OpenTelemetry layer for distributed tracing
         .with(ErrorLayer::default()) // Add the error layer to capture error contexts
         .init(); // Initialize the tracing subscriber
 
-    Ok(())
+    Ok(TracingGuard { provider })
+}
+
+// Build the tracer provider, batching spans to the OTLP/HTTP collector when
+// `telemetry.otlp_endpoint` is set. Without one, spans still get trace ids
+// (and propagate them) but aren't exported anywhere.
+pub fn tracer_provider(settings: &TelemetrySettings) -> Result<TracerProvider> {
+    let resource = Resource::new([KeyValue::new("service.name", settings.service_name.clone())]);
+    let mut builder = TracerProvider::builder().with_resource(resource);
+
+    if let Some(endpoint) = &settings.otlp_endpoint {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()?;
+        builder = builder.with_batch_exporter(exporter, runtime::Tokio);
+    }
+
+    Ok(builder.build())
+}
+
+// The trace context sent by the caller in W3C `traceparent`/`tracestate`
+// headers, if any
+fn extract_trace_context(headers: &HeaderMap) -> Context {
+    TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+}
+
+// Add the current span's trace context to the headers of an outgoing
+// request, so the receiving service continues the same trace
+pub fn inject_trace_context(headers: &mut reqwest::header::HeaderMap) {
+    TraceContextPropagator::new()
+        .inject_context(&Span::current().context(), &mut HeaderInjector(headers));
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+// reqwest 0.11 still uses the `http` 0.2 header types, unlike axum
+struct HeaderInjector<'a>(&'a mut reqwest::header::HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(key.as_bytes()),
+            reqwest::header::HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
 }
-// Creates a new tracing span with a unique request ID for each incoming request.
+
+// Creates a new tracing span with the request's ID (see `propagate_request_id`).
 // This helps in tracking and correlating logs for individual requests.
 pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
-    let request_id = uuid::Uuid::new_v4();
-    tracing::span!(
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .cloned()
+        .unwrap_or_else(RequestId::generate);
+    let span = tracing::span!(
         Level::INFO,
         "[REQUEST]",
         method = tracing::field::display(request.method()),
-        uri = tracing::field::display(request.uri()),
+        uri = tracing::field::display(span_path(request.uri())),
         version = tracing::field::debug(request.version()),
         request_id = tracing::field::display(request_id),
-    )
+    );
+    // Continue the caller's trace if it sent one
+    span.set_parent(extract_trace_context(request.headers()));
+    span
+}
+
+// The request path as it's safe to record on the span. Spans are exported
+// without passing through the redacting writer, so the query string (which
+// can carry a recovery token) is dropped and emails in the path, as the
+// admin routes take them, are masked even when percent-encoded.
+fn span_path(uri: &Uri) -> String {
+    let path = uri
+        .path()
+        .split('/')
+        .map(|segment| {
+            if segment.contains('@') || segment.to_ascii_lowercase().contains("%40") {
+                "[REDACTED EMAIL]"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    redact(&path)
 }
 
 // Logs an event indicating the start of a request.
@@ -72,3 +207,146 @@ pub fn on_response(response: &Response, latency: Duration, _span: &Span) {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+    use opentelemetry_proto::tonic::common::v1::any_value::Value;
+    use opentelemetry_proto::tonic::common::v1::KeyValue;
+    use opentelemetry_proto::tonic::trace::v1::ResourceSpans;
+    use prost::Message;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    const TRACE_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+    const PARENT_SPAN_ID: &str = "b7ad6b7169203331";
+
+    fn request_in_trace() -> Request<Body> {
+        Request::builder()
+            .uri("/verify-token")
+            .header("traceparent", format!("00-{TRACE_ID}-{PARENT_SPAN_ID}-01"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    // A wiremock server stands in for the collector, accepting OTLP/HTTP
+    // exports so we can decode what was sent
+    async fn export_request_span(request: Request<Body>) -> ResourceSpans {
+        let collector = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/traces"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&collector)
+            .await;
+
+        let provider = tracer_provider(&TelemetrySettings {
+            service_name: "auth-service".to_owned(),
+            otlp_endpoint: Some(collector.uri()),
+        })
+        .unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        tracing::subscriber::with_default(subscriber, || {
+            drop(make_span_with_request_id(&request));
+        });
+
+        // The batch processor blocks until the export has completed
+        tokio::task::spawn_blocking(move || provider.force_flush())
+            .await
+            .unwrap();
+
+        let requests = collector.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        let mut export = ExportTraceServiceRequest::decode(requests[0].body.as_slice()).unwrap();
+        export.resource_spans.remove(0)
+    }
+
+    fn attribute(attributes: &[KeyValue], key: &str) -> Option<Value> {
+        attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .and_then(|attribute| attribute.value.as_ref()?.value.clone())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn request_spans_continue_the_incoming_trace_and_are_exported() {
+        let resource_spans = export_request_span(request_in_trace()).await;
+
+        let service_name = attribute(
+            &resource_spans.resource.as_ref().unwrap().attributes,
+            "service.name",
+        );
+        assert_eq!(
+            service_name,
+            Some(Value::StringValue("auth-service".to_owned()))
+        );
+
+        let span = &resource_spans.scope_spans[0].spans[0];
+        assert_eq!(span.name, "[REQUEST]");
+        assert_eq!(hex(&span.trace_id), TRACE_ID);
+        assert_eq!(hex(&span.parent_span_id), PARENT_SPAN_ID);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn exported_spans_carry_no_emails_or_tokens() {
+        let request = Request::builder()
+            .uri("/admin/users/alice%40example.com/disable?token=eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiJhIn0.c2ln")
+            .body(Body::empty())
+            .unwrap();
+
+        let resource_spans = export_request_span(request).await;
+
+        let span = &resource_spans.scope_spans[0].spans[0];
+        assert_eq!(
+            attribute(&span.attributes, "uri"),
+            Some(Value::StringValue(
+                "/admin/users/[REDACTED EMAIL]/disable".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn outgoing_requests_carry_the_current_trace() {
+        let provider = TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        let headers = tracing::subscriber::with_default(subscriber, || {
+            let span = make_span_with_request_id(&request_in_trace());
+            let _entered = span.enter();
+            let mut headers = reqwest::header::HeaderMap::new();
+            inject_trace_context(&mut headers);
+            headers
+        });
+
+        let traceparent = headers["traceparent"].to_str().unwrap();
+        assert!(traceparent.starts_with(&format!("00-{TRACE_ID}-")));
+        // Our own span is the parent downstream, not the caller's
+        assert!(!traceparent.contains(PARENT_SPAN_ID));
+    }
+
+    #[test]
+    fn requests_without_a_trace_start_a_new_one() {
+        let provider = TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        let headers = tracing::subscriber::with_default(subscriber, || {
+            let request = Request::builder().body(Body::empty()).unwrap();
+            let span = make_span_with_request_id(&request);
+            let _entered = span.enter();
+            let mut headers = reqwest::header::HeaderMap::new();
+            inject_trace_context(&mut headers);
+            headers
+        });
+
+        let traceparent = headers["traceparent"].to_str().unwrap();
+        assert!(!traceparent.contains(TRACE_ID));
+    }
+}
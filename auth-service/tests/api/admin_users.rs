@@ -0,0 +1,350 @@
+use auth_service::routes::{AuditEventsResponse, UserDetails, UsersResponse};
+use reqwest::Method;
+use secrecy::ExposeSecret;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PASSWORD_RESET_SUBJECT: &str = "Choose a new";
+
+fn admin_token(app: &TestApp) -> String {
+    app.settings
+        .admin
+        .api_token
+        .as_ref()
+        .expect("test settings have an admin token")
+        .expose_secret()
+        .clone()
+}
+
+async fn sign_up(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn log_in(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&json!({ "email": email, "password": password }))
+        .await
+}
+
+fn auth_cookie(app: &TestApp, response: &reqwest::Response) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == app.settings.auth.cookie.name)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+// Call an admin route with the admin API token
+async fn send(app: &TestApp, method: Method, path: &str, body: Option<Value>) -> reqwest::Response {
+    let mut request = app
+        .http_client
+        .request(method, format!("{}{}", &app.address, path))
+        .bearer_auth(admin_token(app));
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    request.send().await.expect("Failed to execute request.")
+}
+
+async fn details(app: &TestApp, email: &str) -> UserDetails {
+    let response = send(app, Method::GET, &format!("/admin/users/{}", email), None).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+async fn verify(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn users_are_listed_by_page_and_search() {
+    let mut app = TestApp::new().await;
+    // Only these users contain the marker, whatever else is in the store
+    let marker = Uuid::new_v4().simple().to_string();
+    for name in ["carol", "alice", "bob"] {
+        sign_up(&app, &format!("{}-{}@example.com", name, marker)).await;
+    }
+    let search = marker.to_uppercase();
+
+    let first = send(
+        &app,
+        Method::GET,
+        &format!("/admin/users?search={}&limit=2", search),
+        None,
+    )
+    .await;
+    let second = send(
+        &app,
+        Method::GET,
+        &format!("/admin/users?search={}&limit=2&offset=2", search),
+        None,
+    )
+    .await;
+
+    assert_eq!(first.status().as_u16(), 200);
+    let first = first.json::<UsersResponse>().await.unwrap();
+    let second = second.json::<UsersResponse>().await.unwrap();
+    let emails: Vec<&str> = first
+        .users
+        .iter()
+        .chain(&second.users)
+        .map(|user| user.email.as_str())
+        .collect();
+    assert_eq!(
+        emails,
+        [
+            format!("alice-{}@example.com", marker),
+            format!("bob-{}@example.com", marker),
+            format!("carol-{}@example.com", marker),
+        ]
+    );
+    assert_eq!(first.users.len(), 2);
+    assert_eq!(first.total, 3);
+    assert_eq!(second.total, 3);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn user_details_include_roles() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+    let response = send(
+        &app,
+        Method::PUT,
+        &format!("/admin/users/{}/roles/admin", email),
+        None,
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let user = details(&app, &email).await;
+
+    assert_eq!(user.user.email, email);
+    assert!(!user.user.requires_2fa);
+    assert!(!user.user.disabled);
+    assert!(!user.user.password_reset_required);
+    assert_eq!(user.roles, vec!["admin"]);
+    assert!(user.organizations.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn disabled_users_are_signed_out_until_enabled() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+    let token = auth_cookie(&app, &log_in(&app, &email, "password123").await);
+
+    let response = send(
+        &app,
+        Method::POST,
+        &format!("/admin/users/{}/disable", email),
+        None,
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    assert_eq!(verify(&app, &token).await, 401);
+    assert_eq!(
+        log_in(&app, &email, "password123").await.status().as_u16(),
+        403
+    );
+    // A wrong password doesn't give away that the account is disabled
+    assert_eq!(
+        log_in(&app, &email, "wrong-password")
+            .await
+            .status()
+            .as_u16(),
+        401
+    );
+    assert!(details(&app, &email).await.user.disabled);
+    let events = app
+        .get_audit_events(
+            &format!("kind=account_disabled&subject={}", email),
+            Some(&admin_token(&app)),
+        )
+        .await
+        .json::<AuditEventsResponse>()
+        .await
+        .unwrap()
+        .events;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].actor.as_deref(), Some("admin_api_token"));
+    assert_eq!(events[0].subject.as_deref(), Some(email.as_str()));
+
+    let response = send(
+        &app,
+        Method::POST,
+        &format!("/admin/users/{}/enable", email),
+        None,
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    // `iat` has second precision, so a login in the same second would count
+    // as revoked too
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let response = log_in(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(verify(&app, &auth_cookie(&app, &response)).await, 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn forced_password_reset_emails_a_link_to_choose_a_new_one() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+
+    let response = send(
+        &app,
+        Method::POST,
+        &format!("/admin/users/{}/password-reset", email),
+        None,
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(
+        log_in(&app, &email, "password123").await.status().as_u16(),
+        403
+    );
+    assert!(details(&app, &email).await.user.password_reset_required);
+
+    let emails = app
+        .sent_with_subject_containing(PASSWORD_RESET_SUBJECT)
+        .await;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].recipient, email);
+    let token = emails[0]
+        .message
+        .text_body
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split('&').next())
+        .expect("email should link to account recovery")
+        .to_owned();
+    assert!(emails[0].message.text_body.contains("step=reset"));
+
+    let response = app
+        .post_reset_password(&json!({ "token": token, "password": "new-password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        log_in(&app, &email, "new-password123")
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn requiring_2fa_asks_for_a_code_at_the_next_login() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+
+    let response = send(
+        &app,
+        Method::PUT,
+        &format!("/admin/users/{}/requires-2fa", email),
+        Some(json!({ "requires2FA": true })),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    assert!(details(&app, &email).await.user.requires_2fa);
+    assert_eq!(
+        log_in(&app, &email, "password123").await.status().as_u16(),
+        206
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn revoked_sessions_stop_working_but_the_user_can_log_in_again() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+    let token = auth_cookie(&app, &log_in(&app, &email, "password123").await);
+
+    let response = send(
+        &app,
+        Method::POST,
+        &format!("/admin/users/{}/revoke-sessions", email),
+        None,
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    assert_eq!(verify(&app, &token).await, 401);
+    assert_eq!(
+        log_in(&app, &email, "password123").await.status().as_u16(),
+        200
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn unknown_and_invalid_users_are_rejected() {
+    let mut app = TestApp::new().await;
+
+    let unknown = send(
+        &app,
+        Method::POST,
+        &format!("/admin/users/{}/disable", get_random_email()),
+        None,
+    )
+    .await;
+    let unknown_details = send(
+        &app,
+        Method::GET,
+        &format!("/admin/users/{}", get_random_email()),
+        None,
+    )
+    .await;
+    let invalid = send(&app, Method::POST, "/admin/users/not-an-email/enable", None).await;
+
+    assert_eq!(unknown.status().as_u16(), 404);
+    assert_eq!(unknown_details.status().as_u16(), 404);
+    assert_eq!(invalid.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn user_admin_needs_the_permission() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+    let token = auth_cookie(&app, &log_in(&app, &email, "password123").await);
+
+    let forbidden = app
+        .admin_request(Method::GET, "/admin/users", Some(&token))
+        .await;
+    let own_account = app
+        .admin_request(
+            Method::POST,
+            &format!("/admin/users/{}/disable", email),
+            Some(&token),
+        )
+        .await;
+
+    assert_eq!(forbidden.status().as_u16(), 403);
+    assert_eq!(own_account.status().as_u16(), 403);
+    assert!(!details(&app, &email).await.user.disabled);
+    app.clean_up().await;
+}
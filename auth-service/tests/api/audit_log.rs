@@ -0,0 +1,212 @@
+use auth_service::domain::data_stores::audit_log::{
+    AuditEventFilter, AuditEventKind, AuditOutcome,
+};
+use auth_service::routes::AuditEventsResponse;
+use secrecy::ExposeSecret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn sign_up(app: &TestApp, email: &str, requires_2fa: bool) {
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": requires_2fa,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+fn admin_token(app: &TestApp) -> String {
+    app.settings
+        .admin
+        .api_token
+        .as_ref()
+        .expect("test settings have an admin token")
+        .expose_secret()
+        .clone()
+}
+
+#[tokio::test]
+async fn signup_and_logins_are_recorded() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email, false).await;
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": "wrong-password" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let filter = AuditEventFilter {
+        actor: Some(email.clone()),
+        ..Default::default()
+    };
+    let events = app.audit_log.query(&filter).await.unwrap();
+    let summary: Vec<_> = events
+        .iter()
+        .map(|event| (event.kind, event.outcome, event.reason.as_deref()))
+        .collect();
+
+    assert_eq!(
+        summary,
+        vec![
+            (AuditEventKind::Login, AuditOutcome::Success, None),
+            (
+                AuditEventKind::Login,
+                AuditOutcome::Failure,
+                Some("bad_password")
+            ),
+            (AuditEventKind::Signup, AuditOutcome::Success, None),
+        ]
+    );
+    for event in &events {
+        assert_eq!(event.ip.as_deref(), Some("127.0.0.1"));
+        assert!(event.request_id.is_some());
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn two_fa_logins_record_the_code_being_sent() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email, true).await;
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let filter = AuditEventFilter {
+        actor: Some(email.clone()),
+        kinds: vec![AuditEventKind::Login, AuditEventKind::TwoFaCodeSent],
+        ..Default::default()
+    };
+    let events = app.audit_log.query(&filter).await.unwrap();
+
+    assert_eq!(events.len(), 2);
+    assert!(events
+        .iter()
+        .any(|event| event.kind == AuditEventKind::TwoFaCodeSent));
+    let login = events
+        .iter()
+        .find(|event| event.kind == AuditEventKind::Login)
+        .unwrap();
+    assert_eq!(login.outcome, AuditOutcome::Success);
+    assert_eq!(login.reason.as_deref(), Some("2fa_required"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn rejected_tokens_are_recorded() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_verify_token(&json!({ "token": "invalid" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let filter = AuditEventFilter {
+        kinds: vec![AuditEventKind::TokenRejected],
+        ..Default::default()
+    };
+    let events = app.audit_log.query(&filter).await.unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].outcome, AuditOutcome::Failure);
+    assert_eq!(events[0].reason.as_deref(), Some("invalid_token"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn admin_endpoint_requires_the_admin_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_audit_events("", None).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_audit_events("", Some("not-the-admin-token")).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn admin_endpoint_filters_events() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email, false).await;
+    app.post_login(&json!({ "email": email, "password": "wrong-password" }))
+        .await;
+    app.post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+
+    let response = app
+        .get_audit_events(
+            &format!("actor={}&kind=login&outcome=failure", email),
+            Some(&admin_token(&app)),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response.json::<AuditEventsResponse>().await.unwrap();
+    assert_eq!(body.events.len(), 1);
+    assert_eq!(body.events[0].kind, AuditEventKind::Login);
+    assert_eq!(body.events[0].reason.as_deref(), Some("bad_password"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn admin_endpoint_rejects_an_unknown_kind() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .get_audit_events("kind=not_a_kind", Some(&admin_token(&app)))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_history_lists_only_the_callers_logins() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let someone_else = get_random_email();
+    sign_up(&app, &email, false).await;
+    sign_up(&app, &someone_else, false).await;
+
+    app.post_login(&json!({ "email": someone_else, "password": "password123" }))
+        .await;
+    app.post_login(&json!({ "email": email, "password": "wrong-password" }))
+        .await;
+    let response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_login_history().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response.json::<AuditEventsResponse>().await.unwrap();
+    let outcomes: Vec<_> = body.events.iter().map(|event| event.outcome).collect();
+    assert_eq!(outcomes, vec![AuditOutcome::Success, AuditOutcome::Failure]);
+    for event in &body.events {
+        assert_eq!(event.kind, AuditEventKind::Login);
+        assert_eq!(event.actor.as_deref(), Some(email.as_str()));
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_history_requires_a_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_login_history().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
@@ -0,0 +1,161 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use auth_service::domain::data_stores::{
+    UserPage, UserQuery, UserStore, UserStoreError, UserSummary,
+};
+use auth_service::domain::{Email, EmailStatus, Password, User};
+use auth_service::services::data_stores::hashmap_user_store::HashMapUserStore;
+use secrecy::Secret;
+use serde_json::json;
+use tokio::sync::Barrier;
+use tokio::task::JoinSet;
+
+use crate::helpers::{get_random_email, TestApp};
+
+// User store that holds every `add_user` and `validate_user` call until
+// `parties` of them are in flight at the same time. If the server serialized
+// access to the store, that point would never be reached and the requests
+// would hang instead of completing.
+struct RendezvousUserStore {
+    inner: HashMapUserStore,
+    barrier: Barrier,
+}
+
+#[async_trait::async_trait]
+impl UserStore for RendezvousUserStore {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        self.barrier.wait().await;
+        self.inner.add_user(user).await
+    }
+
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        self.inner.get_user(email).await
+    }
+
+    async fn validate_user(
+        &self,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError> {
+        self.barrier.wait().await;
+        self.inner.validate_user(email, password).await
+    }
+
+    async fn require_password_reset(&self, email: &Email) -> Result<(), UserStoreError> {
+        self.inner.require_password_reset(email).await
+    }
+
+    async fn reset_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        self.inner.reset_password(email, password).await
+    }
+
+    async fn set_email_status(
+        &self,
+        email: &Email,
+        status: EmailStatus,
+    ) -> Result<(), UserStoreError> {
+        self.inner.set_email_status(email, status).await
+    }
+
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        self.inner.list_users(query).await
+    }
+
+    async fn user_summary(&self, email: &Email) -> Result<UserSummary, UserStoreError> {
+        self.inner.user_summary(email).await
+    }
+
+    async fn set_disabled(&self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        self.inner.set_disabled(email, disabled).await
+    }
+
+    async fn set_requires_2fa(
+        &self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        self.inner.set_requires_2fa(email, requires_2fa).await
+    }
+}
+
+#[tokio::test]
+async fn signups_and_logins_proceed_in_parallel() {
+    const SIGNUPS: usize = 4;
+    const LOGINS: usize = 4;
+    let password = "password123";
+
+    // Logins need existing users, added straight to the inner store so they
+    // don't count towards the barrier
+    let inner = HashMapUserStore::default();
+    let mut existing = Vec::new();
+    for _ in 0..LOGINS {
+        let email = get_random_email();
+        inner
+            .add_user(User::new(
+                Email::parse(email.clone()).unwrap(),
+                Password::parse(Secret::new(password.to_owned())).unwrap(),
+                false,
+            ))
+            .await
+            .unwrap();
+        existing.push(email);
+    }
+
+    let user_store = Arc::new(RendezvousUserStore {
+        inner,
+        barrier: Barrier::new(SIGNUPS + LOGINS),
+    });
+    let mut app = TestApp::new_with_user_store(user_store).await;
+
+    // Fire every request at once, each on its own task
+    let mut requests = JoinSet::new();
+    for _ in 0..SIGNUPS {
+        let (client, url) = (app.http_client.clone(), format!("{}/signup", app.address));
+        let body = json!({
+            "email": get_random_email(),
+            "password": password,
+            "requires2FA": false,
+        });
+        requests.spawn(async move { (201, send(client, url, body).await) });
+    }
+    for email in existing {
+        let (client, url) = (app.http_client.clone(), format!("{}/login", app.address));
+        let body = json!({
+            "email": email,
+            "password": password,
+        });
+        requests.spawn(async move { (200, send(client, url, body).await) });
+    }
+
+    let statuses = tokio::time::timeout(Duration::from_secs(10), async {
+        let mut statuses = Vec::new();
+        while let Some(res) = requests.join_next().await {
+            statuses.push(res.unwrap());
+        }
+        statuses
+    })
+    .await
+    .expect("signups and logins were serialized instead of running concurrently");
+
+    for (expected, actual) in statuses {
+        assert_eq!(actual, expected);
+    }
+
+    app.clean_up().await;
+}
+
+async fn send(client: reqwest::Client, url: String, body: serde_json::Value) -> u16 {
+    client
+        .post(url)
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .status()
+        .as_u16()
+}
@@ -0,0 +1,101 @@
+use crate::helpers::TestApp;
+
+// Allowed origins come from config/test.toml:
+// "http://localhost:8000" and "https://*.example.com"
+
+#[tokio::test]
+async fn preflight_from_exact_origin_is_allowed() {
+    let mut app = TestApp::new().await;
+
+    let response = app.preflight("/login", "http://localhost:8000").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let headers = response.headers();
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "http://localhost:8000"
+    );
+    assert_eq!(headers["access-control-allow-credentials"], "true");
+    assert!(headers["access-control-allow-methods"]
+        .to_str()
+        .unwrap()
+        .contains("POST"));
+    assert_eq!(
+        headers["access-control-allow-headers"],
+        "content-type,x-request-id"
+    );
+    assert_eq!(headers["access-control-max-age"], "600");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn preflight_from_wildcard_subdomain_is_allowed() {
+    let mut app = TestApp::new().await;
+
+    for origin in ["https://app.example.com", "https://eu.app.example.com"] {
+        let response = app.preflight("/signup", origin).await;
+
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["access-control-allow-origin"], origin);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn preflight_from_other_origins_is_rejected() {
+    let mut app = TestApp::new().await;
+
+    let rejected = [
+        "http://localhost:8001",
+        "https://evil.com",
+        // The wildcard covers subdomains only, and only over https
+        "https://example.com",
+        "http://app.example.com",
+        "https://app.example.com.evil.com",
+    ];
+    for origin in rejected {
+        let response = app.preflight("/login", origin).await;
+
+        assert!(
+            response
+                .headers()
+                .get("access-control-allow-origin")
+                .is_none(),
+            "{} should not be allowed",
+            origin
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn simple_request_echoes_only_allowed_origins() {
+    let mut app = TestApp::new().await;
+
+    let allowed = app
+        .http_client
+        .post(format!("{}/verify-token", &app.address))
+        .header("Origin", "https://app.example.com")
+        .json(&serde_json::json!({ "token": "invalid" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(
+        allowed.headers()["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+
+    let rejected = app
+        .http_client
+        .post(format!("{}/verify-token", &app.address))
+        .header("Origin", "https://evil.com")
+        .json(&serde_json::json!({ "token": "invalid" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(rejected
+        .headers()
+        .get("access-control-allow-origin")
+        .is_none());
+    app.clean_up().await;
+}
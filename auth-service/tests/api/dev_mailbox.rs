@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use auth_service::domain::Email;
+use auth_service::routes::TwoFactorAuthResponse;
+use auth_service::services::dev_mailbox_email_client::DevMailbox;
+use auth_service::settings::DevMailboxSettings;
+use regex::Regex;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn dev_mailbox() -> Arc<DevMailbox> {
+    let settings = DevMailboxSettings {
+        directory: None,
+        max_messages: 10,
+    };
+    Arc::new(
+        DevMailbox::new(
+            &settings,
+            Email::parse("test@email.com".to_owned()).unwrap(),
+        )
+        .unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn login_with_2fa_can_be_completed_from_the_dev_mailbox() {
+    let dev_mailbox = dev_mailbox();
+    let mut app = TestApp::new_with_dev_mailbox(dev_mailbox.clone()).await;
+    let email = get_random_email();
+    app.post_signup(&json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true,
+    }))
+    .await;
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .unwrap()
+        .login_attempt_id;
+    app.wait_for_outbox_to_drain().await;
+
+    let inbox = app.get_dev_mailbox("").await;
+    assert_eq!(inbox.status().as_u16(), 200);
+    let inbox = inbox.text().await.unwrap();
+    assert!(inbox.contains(&email));
+    assert!(inbox.contains("login code"));
+
+    let id = dev_mailbox.emails().await[0].id;
+    let html = app.get_dev_mailbox(&format!("/{}", id)).await;
+    assert_eq!(html.status().as_u16(), 200);
+    assert!(html.text().await.unwrap().contains("<html"));
+    let text = app
+        .get_dev_mailbox(&format!("/{}/text", id))
+        .await
+        .text()
+        .await
+        .unwrap();
+    let code = Regex::new(r"\b\d{6}\b")
+        .unwrap()
+        .find(&text)
+        .expect("email should contain the 2FA code")
+        .as_str()
+        .to_owned();
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn unknown_email_is_not_found() {
+    let mut app = TestApp::new_with_dev_mailbox(dev_mailbox()).await;
+
+    let response = app.get_dev_mailbox("/42").await;
+
+    assert_eq!(response.status().as_u16(), 404);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn dev_mailbox_is_not_routed_unless_configured() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_dev_mailbox("").await;
+
+    assert_eq!(response.status().as_u16(), 404);
+    app.clean_up().await;
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use auth_service::domain::Email;
+use auth_service::services::postmark_email_client::PostmarkEmailClient;
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn postmark_client(mock_server: &MockServer) -> Arc<PostmarkEmailClient> {
+    let http_client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(200))
+        .build()
+        .unwrap();
+    Arc::new(PostmarkEmailClient::new(
+        mock_server.uri(),
+        Email::parse("test@email.com".to_owned()).unwrap(),
+        Secret::new("test-token".to_owned()),
+        http_client,
+    ))
+}
+
+async fn log_in_with_2fa(app: &TestApp) -> reqwest::Response {
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": true,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.post_login(&json!({ "email": email, "password": "password123" }))
+        .await
+}
+
+#[tokio::test]
+async fn login_succeeds_while_email_provider_is_failing() {
+    let mock_server = MockServer::start().await;
+    // Two transient failures, then Postmark recovers
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .with_priority(1)
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let mut app = TestApp::new_with_email_client(postmark_client(&mock_server)).await;
+
+    let response = log_in_with_2fa(&app).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+    app.wait_for_outbox_to_drain().await;
+    assert!(app.email_outbox.dead_letters().await.unwrap().is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn emails_are_dead_lettered_when_provider_keeps_failing() {
+    let mock_server = MockServer::start().await;
+    // As many attempts as `email_outbox.max_attempts` in config/test.toml
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&mock_server)
+        .await;
+    let mut app = TestApp::new_with_email_client(postmark_client(&mock_server)).await;
+
+    let response = log_in_with_2fa(&app).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+    app.wait_for_outbox_to_drain().await;
+    let dead_letters = app.email_outbox.dead_letters().await.unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].attempts, 3);
+    assert!(dead_letters[0].message.subject.contains("login code"));
+    assert!(dead_letters[0]
+        .last_error
+        .as_deref()
+        .is_some_and(|error| error.contains("500")));
+    app.clean_up().await;
+}
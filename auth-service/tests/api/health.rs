@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use auth_service::domain::data_stores::{
+    UserPage, UserQuery, UserStore, UserStoreError, UserSummary,
+};
+use auth_service::domain::{Email, EmailStatus, Password, User};
+use auth_service::routes::{HealthResponse, HealthStatus, ReadinessResponse};
+use auth_service::services::data_stores::hashmap_user_store::HashMapUserStore;
+use color_eyre::eyre::eyre;
+
+use crate::helpers::TestApp;
+
+// User store whose health check fails or never completes, standing in for a
+// database that's down or unreachable
+enum BrokenUserStore {
+    Failing,
+    Hanging,
+}
+
+#[async_trait::async_trait]
+impl UserStore for BrokenUserStore {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        HashMapUserStore::default().add_user(user).await
+    }
+
+    async fn get_user(&self, _email: &Email) -> Result<User, UserStoreError> {
+        Err(UserStoreError::UserNotFound)
+    }
+
+    async fn validate_user(
+        &self,
+        _email: &Email,
+        _password: &Password,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::UserNotFound)
+    }
+
+    async fn require_password_reset(&self, _email: &Email) -> Result<(), UserStoreError> {
+        Err(UserStoreError::UserNotFound)
+    }
+
+    async fn reset_password(
+        &self,
+        _email: &Email,
+        _password: Password,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::UserNotFound)
+    }
+
+    async fn set_email_status(
+        &self,
+        _email: &Email,
+        _status: EmailStatus,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::UserNotFound)
+    }
+
+    async fn list_users(&self, _query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        Ok(UserPage {
+            users: Vec::new(),
+            total: 0,
+        })
+    }
+
+    async fn user_summary(&self, _email: &Email) -> Result<UserSummary, UserStoreError> {
+        Err(UserStoreError::UserNotFound)
+    }
+
+    async fn set_disabled(&self, _email: &Email, _disabled: bool) -> Result<(), UserStoreError> {
+        Err(UserStoreError::UserNotFound)
+    }
+
+    async fn set_requires_2fa(
+        &self,
+        _email: &Email,
+        _requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::UserNotFound)
+    }
+
+    async fn health_check(&self) -> Result<(), UserStoreError> {
+        match self {
+            BrokenUserStore::Failing => {
+                Err(UserStoreError::UnexpectedError(eyre!("connection refused")))
+            }
+            BrokenUserStore::Hanging => std::future::pending().await,
+        }
+    }
+}
+
+#[tokio::test]
+async fn healthz_returns_200() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_healthz().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<HealthResponse>()
+        .await
+        .expect("Could not deserialize response body to HealthResponse");
+    assert_eq!(body.status, HealthStatus::Ok);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn readyz_returns_200_when_all_dependencies_are_reachable() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_readyz().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<ReadinessResponse>()
+        .await
+        .expect("Could not deserialize response body to ReadinessResponse");
+    assert_eq!(body.status, HealthStatus::Ok);
+    for check in [
+        "user_store",
+        "banned_token_store",
+        "two_fa_code_store",
+        "audit_log",
+        "known_device_store",
+    ] {
+        assert_eq!(body.checks[check].status, HealthStatus::Ok, "{}", check);
+        assert!(body.checks[check].error.is_none());
+    }
+    // The email provider check is disabled in config/test.toml
+    assert!(!body.checks.contains_key("email_client"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn readyz_returns_503_when_a_dependency_fails() {
+    let mut app = TestApp::new_with_user_store(Arc::new(BrokenUserStore::Failing)).await;
+
+    let response = app.get_readyz().await;
+
+    assert_eq!(response.status().as_u16(), 503);
+    let body = response
+        .json::<ReadinessResponse>()
+        .await
+        .expect("Could not deserialize response body to ReadinessResponse");
+    assert_eq!(body.status, HealthStatus::Unavailable);
+    assert_eq!(body.checks["user_store"].status, HealthStatus::Unavailable);
+    assert_eq!(
+        body.checks["user_store"].error.as_deref(),
+        Some("connection refused")
+    );
+    // The other dependencies are still reported individually
+    assert_eq!(body.checks["banned_token_store"].status, HealthStatus::Ok);
+    assert_eq!(body.checks["two_fa_code_store"].status, HealthStatus::Ok);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn readyz_times_out_hanging_checks() {
+    let mut app = TestApp::new_with_user_store(Arc::new(BrokenUserStore::Hanging)).await;
+
+    let response = app.get_readyz().await;
+
+    assert_eq!(response.status().as_u16(), 503);
+    let body = response
+        .json::<ReadinessResponse>()
+        .await
+        .expect("Could not deserialize response body to ReadinessResponse");
+    let user_store = &body.checks["user_store"];
+    assert_eq!(user_store.status, HealthStatus::Unavailable);
+    assert!(user_store.error.as_deref().unwrap().contains("timed out"));
+    assert!(user_store.latency_ms >= app.settings.health.timeout_milliseconds);
+    app.clean_up().await;
+}
@@ -1,7 +1,18 @@
+use auth_service::services::data_stores::hashmap_email_outbox::HashMapEmailOutbox;
+use auth_service::services::data_stores::hashmap_known_device_store::HashMapKnownDeviceStore;
+use auth_service::services::data_stores::hashmap_organization_store::HashMapOrganizationStore;
+use auth_service::services::data_stores::hashmap_role_store::HashMapRoleStore;
+use auth_service::services::data_stores::postgres_audit_log::PostgresAuditLog;
+use auth_service::services::data_stores::postgres_email_outbox::PostgresEmailOutbox;
+use auth_service::services::data_stores::postgres_known_device_store::PostgresKnownDeviceStore;
+use auth_service::services::data_stores::postgres_organization_store::PostgresOrganizationStore;
+use auth_service::services::data_stores::postgres_role_store::PostgresRoleStore;
 use auth_service::services::data_stores::postgres_user_store::PostgresUserStore;
 use auth_service::services::data_stores::redis_banned_token_store::RedisBannedTokenStore;
 use auth_service::services::data_stores::redis_two_fa_code_store::RedisTwoFACodeStore;
-use auth_service::utils::constants::{DATABASE_URL, REDIS_HOST_NAME};
+use auth_service::services::data_stores::vec_audit_log::VecAuditLog;
+use auth_service::services::dev_mailbox_email_client::DevMailbox;
+use redis::aio::ConnectionManager;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
@@ -10,11 +21,12 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use auth_service::app_state::{
-    AppState, BannedTokenStoreType, EmailClientType, TwoFACodeStoreType,
+    AppState, AuditLogType, BannedTokenStoreType, EmailClientType, EmailOutboxType,
+    KnownDeviceStoreType, OrganizationStoreType, RoleStoreType, TwoFACodeStoreType, UserStoreType,
 };
-use auth_service::services::mock_email_client::MockEmailClient;
-use auth_service::utils::constants::test::APP_ADDRESS;
-use auth_service::{get_postgres_pool, get_redis_client, Application};
+use auth_service::domain::{Email, EmailClient, EmailMessage, PhoneNumber, SmsClient};
+use auth_service::settings::Settings;
+use auth_service::{get_postgres_pool, get_redis_connection_manager, Application};
 use reqwest::cookie::Jar;
 use uuid::Uuid;
 pub struct TestApp {
@@ -23,7 +35,12 @@ pub struct TestApp {
     pub http_client: reqwest::Client,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_store: TwoFACodeStoreType,
-    pub db_name: String,
+    pub audit_log: AuditLogType,
+    pub email_outbox: EmailOutboxType,
+    pub email_client: Arc<RecordingEmailClient>,
+    pub sms_client: Arc<RecordingSmsClient>,
+    pub settings: Arc<Settings>,
+    pub db_name: Option<String>,
     pub clean_up_called: bool,
 }
 
@@ -36,26 +53,117 @@ impl Drop for TestApp {
     }
 }
 
+// The stores a test app persists to
+struct Stores {
+    user_store: UserStoreType,
+    audit_log: AuditLogType,
+    known_device_store: KnownDeviceStoreType,
+    email_outbox: EmailOutboxType,
+    role_store: RoleStoreType,
+    organization_store: OrganizationStoreType,
+}
+
+impl Stores {
+    fn postgres(pg_pool: PgPool) -> Self {
+        Self {
+            user_store: Arc::new(PostgresUserStore::new(pg_pool.clone())),
+            audit_log: Arc::new(PostgresAuditLog::new(pg_pool.clone())),
+            known_device_store: Arc::new(PostgresKnownDeviceStore::new(pg_pool.clone())),
+            email_outbox: Arc::new(PostgresEmailOutbox::new(pg_pool.clone())),
+            role_store: Arc::new(PostgresRoleStore::new(pg_pool.clone())),
+            organization_store: Arc::new(PostgresOrganizationStore::new(pg_pool)),
+        }
+    }
+
+    // Everything but `user_store` in memory
+    fn in_memory(user_store: UserStoreType) -> Self {
+        Self {
+            user_store,
+            audit_log: Arc::new(VecAuditLog::default()),
+            known_device_store: Arc::new(HashMapKnownDeviceStore::default()),
+            email_outbox: Arc::new(HashMapEmailOutbox::default()),
+            role_store: Arc::new(HashMapRoleStore::default()),
+            organization_store: Arc::new(HashMapOrganizationStore::default()),
+        }
+    }
+}
+
 impl TestApp {
     pub async fn new() -> Self {
         let db_name = Uuid::new_v4().to_string();
         let pg_pool = configure_postgresql(&db_name).await;
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-        let redis_connection = Arc::new(RwLock::new(configure_redis()));
+        let mut app = Self::build(Stores::postgres(pg_pool), None, None).await;
+        app.db_name = Some(db_name);
+        app
+    }
+
+    // Same as `new`, but emails are delivered through `email_client` instead
+    // of being recorded
+    pub async fn new_with_email_client(email_client: EmailClientType) -> Self {
+        let db_name = Uuid::new_v4().to_string();
+        let pg_pool = configure_postgresql(&db_name).await;
+        let mut app = Self::build(Stores::postgres(pg_pool), Some(email_client), None).await;
+        app.db_name = Some(db_name);
+        app
+    }
+
+    // Same as `new`, but emails are captured by `dev_mailbox`, which is also
+    // served at /dev/mailbox
+    pub async fn new_with_dev_mailbox(dev_mailbox: Arc<DevMailbox>) -> Self {
+        let db_name = Uuid::new_v4().to_string();
+        let pg_pool = configure_postgresql(&db_name).await;
+        let mut app = Self::build(
+            Stores::postgres(pg_pool),
+            Some(dev_mailbox.clone()),
+            Some(dev_mailbox),
+        )
+        .await;
+        app.db_name = Some(db_name);
+        app
+    }
+
+    // Same as `new`, but lets a test swap in its own user store. Audit events,
+    // known devices, queued emails, roles and organizations are kept in
+    // memory.
+    pub async fn new_with_user_store(user_store: UserStoreType) -> Self {
+        Self::build(Stores::in_memory(user_store), None, None).await
+    }
+
+    // Emails are recorded in `TestApp::email_client` unless `email_client`
+    // is given
+    async fn build(
+        stores: Stores,
+        email_client: Option<EmailClientType>,
+        dev_mailbox: Option<Arc<DevMailbox>>,
+    ) -> Self {
+        let settings = Arc::new(test_settings());
+        let redis_connection = configure_redis(&settings).await;
         let banned_token_store: BannedTokenStoreType =
-            Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_connection.clone())));
-        let two_fa_store: TwoFACodeStoreType =
-            Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
+            Arc::new(RedisBannedTokenStore::new(redis_connection.clone()));
+        let two_fa_store: TwoFACodeStoreType = Arc::new(RedisTwoFACodeStore::new(redis_connection));
 
-        let email_client: EmailClientType = Arc::new(RwLock::new(MockEmailClient::default()));
+        let recording_email_client = Arc::new(RecordingEmailClient::default());
+        let sms_client = Arc::new(RecordingSmsClient::default());
         let cookie_jar = Arc::new(Jar::default());
-        let app_state = AppState::new(
-            user_store,
+        let audit_log = stores.audit_log;
+        let email_outbox = stores.email_outbox;
+        let mut app_state = AppState::new(
+            stores.user_store,
             banned_token_store.clone(),
             two_fa_store.clone(),
-            email_client,
-        );
-        let app = Application::build(app_state, APP_ADDRESS)
+            email_client.unwrap_or_else(|| recording_email_client.clone()),
+            audit_log.clone(),
+            stores.known_device_store,
+            settings.clone(),
+        )
+        .with_email_outbox(email_outbox.clone())
+        .with_role_store(stores.role_store)
+        .with_organization_store(stores.organization_store)
+        .with_sms_client(sms_client.clone());
+        if let Some(dev_mailbox) = dev_mailbox {
+            app_state = app_state.with_dev_mailbox(dev_mailbox);
+        }
+        let app = Application::build(app_state)
             .await
             .expect("Failed to build app");
 
@@ -78,14 +186,40 @@ impl TestApp {
             cookie_jar,
             banned_token_store,
             two_fa_store,
-            db_name,
+            audit_log,
+            email_outbox,
+            email_client: recording_email_client,
+            sms_client,
+            settings,
+            db_name: None,
             clean_up_called: false,
         }
     }
 
+    // Emails are delivered in the background, so wait for the outbox to
+    // empty before looking at what was sent
+    pub async fn wait_for_outbox_to_drain(&self) {
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while self.email_outbox.pending_count().await.unwrap() > 0 {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("Emails in the outbox were not delivered in time");
+    }
+
+    pub async fn sent_with_subject_containing(&self, subject: &str) -> Vec<SentEmail> {
+        self.wait_for_outbox_to_drain().await;
+        self.email_client
+            .sent_with_subject_containing(subject)
+            .await
+    }
+
     pub async fn clean_up(&mut self) {
         self.clean_up_called = true;
-        delete_database(&self.db_name).await;
+        if let Some(db_name) = &self.db_name {
+            delete_database(db_name).await;
+        }
     }
 
     pub async fn post_signup<Body>(&self, body: &Body) -> reqwest::Response
@@ -93,16 +227,100 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_signup_with_language<Body>(
+        &self,
+        body: &Body,
+        accept_language: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/signup", &self.address))
+            .header("Accept-Language", accept_language)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_login_with_language<Body>(
+        &self,
+        body: &Body,
+        accept_language: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login", &self.address))
+            .header("Accept-Language", accept_language)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_healthz(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/healthz", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_metrics(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/metrics", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_dev_mailbox(&self, path: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/dev/mailbox{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_postmark_webhook<Body>(
+        &self,
+        body: &Body,
+        credentials: Option<(&str, &str)>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/webhooks/postmark", &self.address))
+            .json(body);
+        if let Some((username, password)) = credentials {
+            request = request.basic_auth(username, Some(password));
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_readyz(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/readyz", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -126,7 +344,7 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/logout", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -153,7 +371,49 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Log in as a browser identifying itself with `user_agent` would
+    pub async fn post_login_with_user_agent<Body>(
+        &self,
+        body: &Body,
+        user_agent: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login", &self.address))
+            .header("User-Agent", user_agent)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_not_me<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/not-me", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/reset-password", &self.address))
             .json(body)
             .send()
             .await
@@ -172,6 +432,57 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_login_history(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/login-history", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Query the audit log as an admin, with `token` as the bearer token
+    pub async fn get_audit_events(&self, query: &str, token: Option<&str>) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .get(format!("{}/admin/audit-events?{}", &self.address, query));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    // Call an admin route with `token` as the bearer token, or with just the
+    // auth cookie if there's none
+    pub async fn admin_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        token: Option<&str>,
+    ) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .request(method, format!("{}{}", &self.address, path));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    // CORS preflight request, as a browser would send before a cross-origin POST
+    pub async fn preflight(&self, path: &str, origin: &str) -> reqwest::Response {
+        self.http_client
+            .request(
+                reqwest::Method::OPTIONS,
+                format!("{}{}", &self.address, path),
+            )
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", "POST")
+            .header("Access-Control-Request-Headers", "content-type")
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -185,16 +496,94 @@ impl TestApp {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct SentEmail {
+    pub recipient: String,
+    pub message: EmailMessage,
+}
+
+// Keeps every email the app sends, so tests can follow the links in them
+#[derive(Default)]
+pub struct RecordingEmailClient {
+    sent: RwLock<Vec<SentEmail>>,
+}
+
+impl RecordingEmailClient {
+    pub async fn sent_with_subject_containing(&self, subject: &str) -> Vec<SentEmail> {
+        self.sent
+            .read()
+            .await
+            .iter()
+            .filter(|email| email.message.subject.contains(subject))
+            .cloned()
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for RecordingEmailClient {
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        message: &EmailMessage,
+    ) -> color_eyre::Result<()> {
+        self.sent.write().await.push(SentEmail {
+            recipient: recipient.as_ref().expose_secret().to_owned(),
+            message: message.clone(),
+        });
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SentSms {
+    pub recipient: String,
+    pub body: String,
+}
+
+// Keeps every text the app sends, so tests can read the codes in them
+#[derive(Default)]
+pub struct RecordingSmsClient {
+    sent: RwLock<Vec<SentSms>>,
+}
+
+impl RecordingSmsClient {
+    pub async fn sent_to(&self, recipient: &str) -> Vec<SentSms> {
+        self.sent
+            .read()
+            .await
+            .iter()
+            .filter(|sms| sms.recipient == recipient)
+            .cloned()
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsClient for RecordingSmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> color_eyre::Result<()> {
+        self.sent.write().await.push(SentSms {
+            recipient: recipient.as_ref().expose_secret().to_owned(),
+            body: body.to_owned(),
+        });
+        Ok(())
+    }
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
 
 pub async fn configure_postgresql(db_name: &str) -> PgPool {
-    let postgresql_conn_url = DATABASE_URL.to_owned();
+    let postgresql_conn_url = test_settings().database.url;
 
-    configure_database(&postgresql_conn_url, &db_name).await;
+    configure_database(&postgresql_conn_url, db_name).await;
 
-    let postgresql_conn_url_with_db = Secret::new(format!("{}/{}", postgresql_conn_url.expose_secret(), db_name));
+    let postgresql_conn_url_with_db = Secret::new(format!(
+        "{}/{}",
+        postgresql_conn_url.expose_secret(),
+        db_name
+    ));
 
     // Create a new connection pool and return it
     get_postgres_pool(&postgresql_conn_url_with_db)
@@ -231,9 +620,9 @@ pub async fn configure_database(db_conn_string: &Secret<String>, db_name: &str)
 }
 
 pub async fn delete_database(db_name: &str) {
-    let postgresql_conn_url: Secret<String> = DATABASE_URL.to_owned();
+    let postgresql_conn_url: Secret<String> = test_settings().database.url;
 
-    let connection_options = PgConnectOptions::from_str(&postgresql_conn_url.expose_secret())
+    let connection_options = PgConnectOptions::from_str(postgresql_conn_url.expose_secret())
         .expect("Failed to parse PostgreSQL connection string");
 
     let mut connection = PgConnection::connect_with(&connection_options)
@@ -257,16 +646,20 @@ pub async fn delete_database(db_name: &str) {
         .await
         .expect("Failed to drop the database.");
 
-    // Drop the database
+    // Drop the database. FORCE also ends connections the app opened since,
+    // e.g. the outbox worker polling in the background.
     connection
-        .execute(format!(r#"DROP DATABASE "{}";"#, db_name).as_str())
+        .execute(format!(r#"DROP DATABASE "{}" WITH (FORCE);"#, db_name).as_str())
         .await
         .expect("Failed to drop the database.");
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
+pub fn test_settings() -> Settings {
+    Settings::load_for("test").expect("Failed to load test settings")
+}
+
+async fn configure_redis(settings: &Settings) -> ConnectionManager {
+    get_redis_connection_manager(settings.redis.host_name.clone())
+        .await
         .expect("Failed to get Redis connection")
 }
@@ -0,0 +1,158 @@
+use auth_service::{routes::TwoFactorAuthResponse, ErrorResponse};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn errors_follow_accept_language() {
+    let mut app = TestApp::new().await;
+    let signup = json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false,
+    });
+    assert_eq!(app.post_signup(&signup).await.status().as_u16(), 201);
+
+    let response = app
+        .post_signup_with_language(&signup, "de-DE,de;q=0.9,en;q=0.8")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.error, "Benutzer existiert bereits");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn unsupported_or_missing_language_falls_back_to_english() {
+    let mut app = TestApp::new().await;
+    let signup = json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false,
+    });
+    assert_eq!(app.post_signup(&signup).await.status().as_u16(), 201);
+
+    for accept_language in ["fr-FR,fr;q=0.9", "*", "not a language tag"] {
+        let response = app
+            .post_signup_with_language(&signup, accept_language)
+            .await;
+        let body = response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse");
+        assert_eq!(body.error, "User already exists", "{}", accept_language);
+    }
+
+    let response = app.post_signup(&signup).await;
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.error, "User already exists");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn emails_use_the_users_locale_not_the_browsers() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": true,
+            "locale": "de",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login_with_language(&json!({ "email": email, "password": "password123" }), "en")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+    // The response is for the browser, the email for the user
+    let body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(body.message, "2FA required");
+    let sent = app
+        .sent_with_subject_containing("Ihr Anmeldecode für Auth Service")
+        .await;
+    assert_eq!(sent.len(), 1);
+    assert!(sent[0].message.html_body.contains("<html lang=\"de\">"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn signup_locale_defaults_to_the_negotiated_one() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let response = app
+        .post_signup_with_language(
+            &json!({
+                "email": email,
+                "password": "password123",
+                "requires2FA": true,
+                // Not one we ship, so ignored
+                "locale": "fr",
+            }),
+            "de",
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(
+        response.json::<serde_json::Value>().await.unwrap()["message"],
+        "Benutzer erfolgreich angelegt!"
+    );
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(
+        app.sent_with_subject_containing("Ihr Anmeldecode")
+            .await
+            .len(),
+        1
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn new_device_alert_uses_the_users_locale() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false,
+            "locale": "de",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login = json!({ "email": email, "password": "password123" });
+    assert_eq!(app.post_login(&login).await.status().as_u16(), 200);
+    let response = app
+        .post_login_with_user_agent(&login, "Other Browser")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let alerts = app
+        .sent_with_subject_containing("Neue Anmeldung bei Ihrem Auth Service-Konto")
+        .await;
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0]
+        .message
+        .text_body
+        .contains("Browser: Other Browser"));
+    app.clean_up().await;
+}
@@ -1,5 +1,5 @@
 use crate::helpers::{get_random_email, TestApp};
-use auth_service::{domain::email::Email, routes::TwoFactorAuthResponse, utils::constants::JWT_COOKIE_NAME};
+use auth_service::{domain::email::Email, routes::TwoFactorAuthResponse};
 use serde_json::json;
 
 #[tokio::test]
@@ -66,7 +66,11 @@ async fn should_return_200_if_correct_credentials() {
 
     // first add the user
     let response = app.post_signup(&test_data).await; // call `post_signup`
-    assert_eq!(response.status().as_u16(), 201, "failed to add user prior to login test");
+    assert_eq!(
+        response.status().as_u16(),
+        201,
+        "failed to add user prior to login test"
+    );
 
     let body = json!({
         "email": random_email,
@@ -80,7 +84,7 @@ async fn should_return_200_if_correct_credentials() {
 
     let auth_cookie = response
         .cookies()
-        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .find(|cookie| cookie.name() == app.settings.auth.cookie.name)
         .expect("No auth cookie found");
 
     assert!(!auth_cookie.value().is_empty());
@@ -114,9 +118,12 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     // let id = json_body.login_attempt_id;
     {
-        let two_fa_stuff = app.two_fa_store.read().await;
-        match two_fa_stuff.get_code(&Email::parse(random_email).expect("parse email")).await {
-            Ok((_, _)) => {},
+        match app
+            .two_fa_store
+            .get_code(&Email::parse(random_email).expect("parse email"))
+            .await
+        {
+            Ok((_, _)) => {}
             Err(_) => {
                 panic!("Email wasn't present in 2FA code store")
             }
@@ -1,6 +1,6 @@
 use auth_service::{
-    domain::email::Email,
-    utils::{auth::generate_auth_cookie, constants::JWT_COOKIE_NAME},
+    domain::{email::Email, Grants},
+    utils::auth::generate_auth_cookie,
 };
 use reqwest::Url;
 use secrecy::Secret;
@@ -25,7 +25,7 @@ async fn should_return_401_if_invalid_token() {
     app.cookie_jar.add_cookie_str(
         &format!(
             "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
-            JWT_COOKIE_NAME
+            app.settings.auth.cookie.name
         ),
         &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
     );
@@ -41,7 +41,8 @@ async fn should_return_200_if_valid_jwt_cookie() {
     let mut app = TestApp::new().await;
 
     let email = Email::parse(get_random_email()).expect("email should be parseable");
-    let cookie = generate_auth_cookie(&email).expect("should generate auth cookie");
+    let cookie = generate_auth_cookie(&email, &Grants::default(), None, &app.settings.auth)
+        .expect("should generate auth cookie");
     app.cookie_jar.add_cookie_str(
         &cookie.to_string(),
         &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
@@ -51,8 +52,8 @@ async fn should_return_200_if_valid_jwt_cookie() {
     assert_eq!(response.status().as_u16(), 200);
 
     {
-        let banned_store = app.banned_token_store.read().await;
-        let contains_token = banned_store
+        let contains_token = app
+            .banned_token_store
             .contains_token(&Secret::new(cookie.value().to_string()))
             .await;
 
@@ -69,7 +70,8 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
     let mut app = TestApp::new().await;
 
     let email = Email::parse(get_random_email()).expect("email should be parseable");
-    let cookie = generate_auth_cookie(&email).expect("should generate auth cookie");
+    let cookie = generate_auth_cookie(&email, &Grants::default(), None, &app.settings.auth)
+        .expect("should generate auth cookie");
     app.cookie_jar.add_cookie_str(
         &cookie.to_string(),
         &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
@@ -1,7 +1,22 @@
+mod admin_users;
+mod audit_log;
+mod concurrency;
+mod cors;
+mod dev_mailbox;
+mod email_outbox;
+mod health;
 mod helpers;
+mod locale;
 mod login;
 mod logout;
+mod metrics;
+mod new_device_alert;
+mod organizations;
+mod postmark_webhook;
+mod request_id;
+mod roles;
 mod root;
 mod signup;
+mod sms_2fa;
 mod verify_2fa;
 mod verify_token;
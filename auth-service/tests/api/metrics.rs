@@ -0,0 +1,71 @@
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_expose_prometheus_text_format() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_metrics().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .expect("content type should be set");
+    assert!(content_type.to_str().unwrap().starts_with("text/plain"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_count_requests_by_route_and_status() {
+    let mut app = TestApp::new().await;
+
+    app.get_healthz().await;
+    let body = app.get_metrics().await.text().await.unwrap();
+
+    assert!(body.contains(
+        r#"auth_service_http_requests_total{method="GET",route="/healthz",status="200"} 1"#
+    ));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_count_signups_and_logins_by_outcome() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+
+    let signup = json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup).await;
+    app.post_signup(&signup).await;
+    app.post_login(&json!({ "email": email, "password": "wrong-password" }))
+        .await;
+    app.post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+
+    let body = app.get_metrics().await.text().await.unwrap();
+
+    assert!(body.contains(r#"auth_service_signups_total{outcome="success"} 1"#));
+    assert!(body.contains(r#"auth_service_signups_total{outcome="already_exists"} 1"#));
+    assert!(body.contains(r#"auth_service_logins_total{outcome="bad_password"} 1"#));
+    assert!(body.contains(r#"auth_service_logins_total{outcome="success"} 1"#));
+    assert!(body.contains("auth_service_store_operation_duration_seconds_count"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_count_token_validations_by_result() {
+    let mut app = TestApp::new().await;
+
+    app.post_verify_token(&json!({ "token": "not-a-jwt" }))
+        .await;
+
+    let body = app.get_metrics().await.text().await.unwrap();
+
+    assert!(body.contains(r#"auth_service_token_validations_total{result="invalid_token"} 1"#));
+    app.clean_up().await;
+}
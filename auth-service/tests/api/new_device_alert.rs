@@ -0,0 +1,206 @@
+use auth_service::domain::data_stores::audit_log::{AuditEventFilter, AuditEventKind};
+use auth_service::domain::data_stores::{LoginAttemptId, TwoFACode};
+use auth_service::domain::Email;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const NEW_DEVICE_ALERT_SUBJECT: &str = "New sign-in";
+
+async fn sign_up(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+fn auth_cookie(app: &TestApp, response: &reqwest::Response) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == app.settings.auth.cookie.name)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+// Log in from a known device, then from a new one, and return the recovery
+// token from the alert that follows
+async fn trigger_alert(app: &TestApp, email: &str) -> String {
+    let login = json!({ "email": email, "password": "password123" });
+    assert_eq!(app.post_login(&login).await.status().as_u16(), 200);
+    let response = app
+        .post_login_with_user_agent(&login, "Other Browser")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let alerts = app
+        .sent_with_subject_containing(NEW_DEVICE_ALERT_SUBJECT)
+        .await;
+    assert_eq!(alerts.len(), 1);
+    alerts[0]
+        .message
+        .text_body
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("alert should link to account recovery")
+        .to_owned()
+}
+
+#[tokio::test]
+async fn no_alert_for_first_or_known_device() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+
+    let login = json!({ "email": email, "password": "password123" });
+    for _ in 0..2 {
+        assert_eq!(app.post_login(&login).await.status().as_u16(), 200);
+    }
+
+    assert!(app
+        .sent_with_subject_containing(NEW_DEVICE_ALERT_SUBJECT)
+        .await
+        .is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_from_new_device_sends_alert() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+
+    trigger_alert(&app, &email).await;
+
+    let alert = &app
+        .sent_with_subject_containing(NEW_DEVICE_ALERT_SUBJECT)
+        .await[0];
+    assert_eq!(alert.recipient, email);
+    let text = &alert.message.text_body;
+    assert!(text.contains("Browser: Other Browser"));
+    assert!(text.contains("IP address: 127.0.0.1"));
+    assert!(text.contains(&format!(
+        "{}/account-recovery.html?token=",
+        app.settings.application.public_url
+    )));
+
+    let filter = AuditEventFilter {
+        actor: Some(email.clone()),
+        kinds: vec![AuditEventKind::NewDeviceAlert],
+        ..Default::default()
+    };
+    assert_eq!(app.audit_log.query(&filter).await.unwrap().len(), 1);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn not_me_revokes_sessions_and_locks_password() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+    let login = json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login).await;
+    let session = auth_cookie(&app, &response);
+    let token = trigger_alert(&app, &email).await;
+
+    let response = app.post_not_me(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&json!({ "token": session })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status().as_u16(), 403);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn not_me_drops_a_pending_2fa_code() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+    let token = trigger_alert(&app, &email).await;
+    // As if someone with the password were waiting on the code
+    let parsed = Email::parse(email.clone()).unwrap();
+    app.two_fa_store
+        .add_code(
+            parsed.clone(),
+            LoginAttemptId::default(),
+            TwoFACode::default(),
+        )
+        .await
+        .unwrap();
+
+    let response = app.post_not_me(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert!(app.two_fa_store.get_code(&parsed).await.is_err());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn reset_password_unlocks_login_with_new_password() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+    let token = trigger_alert(&app, &email).await;
+    assert_eq!(
+        app.post_not_me(&json!({ "token": token }))
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+
+    let response = app
+        .post_reset_password(&json!({ "token": token, "password": "short" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app
+        .post_reset_password(&json!({ "token": token, "password": "new-password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_login(&json!({ "email": email, "password": "new-password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The link is used up once the password is reset
+    let response = app
+        .post_reset_password(&json!({ "token": token, "password": "another-password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_login(&json!({ "email": email, "password": "new-password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn recovery_routes_reject_auth_tokens() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+    let response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    let session = auth_cookie(&app, &response);
+
+    let response = app.post_not_me(&json!({ "token": session })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_reset_password(&json!({ "token": session, "password": "new-password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
@@ -0,0 +1,613 @@
+use auth_service::domain::{Membership, OrgRole, Organization};
+use auth_service::routes::{
+    AuditEventsResponse, InvitationDetails, MembersResponse, OrganizationsResponse,
+    VerifyTokenResponse,
+};
+use reqwest::Method;
+use secrecy::ExposeSecret;
+use serde_json::{json, Value};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const INVITATION_SUBJECT: &str = "Join Initech";
+
+fn admin_token(app: &TestApp) -> String {
+    app.settings
+        .admin
+        .api_token
+        .as_ref()
+        .expect("test settings have an admin token")
+        .expose_secret()
+        .clone()
+}
+
+async fn sign_up(app: &TestApp) -> String {
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    email
+}
+
+async fn log_in(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    auth_cookie(app, &response)
+}
+
+fn auth_cookie(app: &TestApp, response: &reqwest::Response) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == app.settings.auth.cookie.name)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+// Call a route as the user `token` belongs to
+async fn send(
+    app: &TestApp,
+    method: Method,
+    path: &str,
+    token: &str,
+    body: Option<Value>,
+) -> reqwest::Response {
+    let mut request = app
+        .http_client
+        .request(method, format!("{}{}", &app.address, path))
+        .bearer_auth(token);
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    request.send().await.expect("Failed to execute request.")
+}
+
+async fn create_organization(app: &TestApp, token: &str, name: &str) -> Organization {
+    let response = send(
+        app,
+        Method::POST,
+        "/organizations",
+        token,
+        Some(json!({ "name": name })),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 201);
+    response.json().await.unwrap()
+}
+
+async fn invite(
+    app: &TestApp,
+    token: &str,
+    organization: &Organization,
+    email: &str,
+    role: &str,
+) -> reqwest::Response {
+    send(
+        app,
+        Method::POST,
+        &format!("/organizations/{}/invitations", organization.id),
+        token,
+        Some(json!({ "email": email, "role": role })),
+    )
+    .await
+}
+
+// The token from the link in the last invitation sent to `email`
+async fn invitation_token(app: &TestApp, email: &str) -> String {
+    let invitations = app.sent_with_subject_containing(INVITATION_SUBJECT).await;
+    invitations
+        .iter()
+        .rev()
+        .find(|sent| sent.recipient == email)
+        .expect("no invitation was sent")
+        .message
+        .text_body
+        .split("?invitation=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("invitation should link to the login page")
+        .to_owned()
+}
+
+async fn verify(app: &TestApp, token: &str) -> VerifyTokenResponse {
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn creator_owns_the_new_organization() {
+    let mut app = TestApp::new().await;
+    let email = sign_up(&app).await;
+    let token = log_in(&app, &email).await;
+
+    let organization = create_organization(&app, &token, "  Initech ").await;
+    assert_eq!(organization.name, "Initech");
+
+    let response = send(&app, Method::GET, "/organizations", &token, None).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let organizations = response
+        .json::<OrganizationsResponse>()
+        .await
+        .unwrap()
+        .organizations;
+    assert_eq!(organizations.len(), 1);
+    assert_eq!(organizations[0].organization_id, organization.id);
+    assert_eq!(organizations[0].role, OrgRole::Owner);
+
+    for name in ["", "   ", &"x".repeat(101)] {
+        let response = send(
+            &app,
+            Method::POST,
+            "/organizations",
+            &token,
+            Some(json!({ "name": name })),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 400, "{:?}", name);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn invitation_link_signs_a_new_user_up_into_the_organization() {
+    let mut app = TestApp::new().await;
+    let owner = sign_up(&app).await;
+    let owner_token = log_in(&app, &owner).await;
+    let organization = create_organization(&app, &owner_token, "Initech").await;
+    let invitee = get_random_email();
+
+    let response = invite(&app, &owner_token, &organization, &invitee, "admin").await;
+    assert_eq!(response.status().as_u16(), 201);
+    let token = invitation_token(&app, &invitee).await;
+
+    // The login page looks the link up to pre-fill the signup form
+    let response = app
+        .http_client
+        .post(format!("{}/invitation", &app.address))
+        .json(&json!({ "token": token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let details = response.json::<InvitationDetails>().await.unwrap();
+    assert_eq!(details.organization_name, "Initech");
+    assert_eq!(details.email, invitee);
+    assert_eq!(details.role, OrgRole::Admin);
+    assert!(!details.account_exists);
+
+    let response = app
+        .post_signup(&json!({
+            "email": invitee,
+            "password": "password123",
+            "requires2FA": false,
+            "invitationToken": token,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let claims = verify(&app, &log_in(&app, &invitee).await).await;
+    assert_eq!(claims.org_id, Some(organization.id.to_string()));
+    assert_eq!(claims.org_role.as_deref(), Some("admin"));
+
+    // Used up
+    let response = app
+        .http_client
+        .post(format!("{}/invitation", &app.address))
+        .json(&json!({ "token": token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn signup_with_someone_elses_invitation_is_rejected() {
+    let mut app = TestApp::new().await;
+    let owner = sign_up(&app).await;
+    let owner_token = log_in(&app, &owner).await;
+    let organization = create_organization(&app, &owner_token, "Initech").await;
+    let invitee = get_random_email();
+    invite(&app, &owner_token, &organization, &invitee, "member").await;
+    let token = invitation_token(&app, &invitee).await;
+
+    let intruder = get_random_email();
+    let response = app
+        .post_signup(&json!({
+            "email": intruder,
+            "password": "password123",
+            "requires2FA": false,
+            "invitationToken": token,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // No account was created for them
+    let response = app
+        .post_login(&json!({ "email": intruder, "password": "password123" }))
+        .await;
+    assert_ne!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_signup(&json!({
+            "email": intruder,
+            "password": "password123",
+            "requires2FA": false,
+            "invitationToken": "not-a-token",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn existing_user_joins_by_accepting_after_logging_in() {
+    let mut app = TestApp::new().await;
+    let owner = sign_up(&app).await;
+    let owner_token = log_in(&app, &owner).await;
+    let organization = create_organization(&app, &owner_token, "Initech").await;
+    let invitee = sign_up(&app).await;
+    let other = sign_up(&app).await;
+
+    invite(&app, &owner_token, &organization, &invitee, "member").await;
+    let token = invitation_token(&app, &invitee).await;
+
+    // Only the invitee can accept, even if the link was forwarded
+    let other_token = log_in(&app, &other).await;
+    let response = send(
+        &app,
+        Method::POST,
+        "/accept-invitation",
+        &other_token,
+        Some(json!({ "token": token })),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let invitee_token = log_in(&app, &invitee).await;
+    assert_eq!(verify(&app, &invitee_token).await.org_id, None);
+    let response = send(
+        &app,
+        Method::POST,
+        "/accept-invitation",
+        &invitee_token,
+        Some(json!({ "token": token })),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let cookie = auth_cookie(&app, &response);
+    let membership = response.json::<Membership>().await.unwrap();
+    assert_eq!(membership.organization_id, organization.id);
+    assert_eq!(membership.role, OrgRole::Member);
+    // The new cookie acts for the organization just joined
+    let claims = verify(&app, &cookie).await;
+    assert_eq!(claims.org_id, Some(organization.id.to_string()));
+    assert_eq!(claims.org_role.as_deref(), Some("member"));
+
+    let response = send(
+        &app,
+        Method::POST,
+        "/accept-invitation",
+        &invitee_token,
+        Some(json!({ "token": token })),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = send(
+        &app,
+        Method::GET,
+        &format!("/organizations/{}/members", organization.id),
+        &owner_token,
+        None,
+    )
+    .await;
+    let members = response.json::<MembersResponse>().await.unwrap().members;
+    let emails: Vec<&str> = members.iter().map(|member| member.email.as_str()).collect();
+    assert_eq!(emails, vec![owner.as_str(), invitee.as_str()]);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn switching_organization_changes_the_org_id_claim() {
+    let mut app = TestApp::new().await;
+    let email = sign_up(&app).await;
+    let token = log_in(&app, &email).await;
+    let first = create_organization(&app, &token, "Initech").await;
+    let second = create_organization(&app, &token, "Initrode").await;
+    let stranger = sign_up(&app).await;
+    let strangers = create_organization(&app, &log_in(&app, &stranger).await, "Other").await;
+
+    // Logging in picks the organization joined first
+    let token = log_in(&app, &email).await;
+    assert_eq!(
+        verify(&app, &token).await.org_id,
+        Some(first.id.to_string())
+    );
+
+    let response = send(
+        &app,
+        Method::POST,
+        "/switch-organization",
+        &token,
+        Some(json!({ "organizationId": second.id })),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let claims = verify(&app, &auth_cookie(&app, &response)).await;
+    assert_eq!(claims.org_id, Some(second.id.to_string()));
+    assert_eq!(claims.org_role.as_deref(), Some("owner"));
+
+    for id in [strangers.id.to_string(), "not-an-id".to_owned()] {
+        let response = send(
+            &app,
+            Method::POST,
+            "/switch-organization",
+            &token,
+            Some(json!({ "organizationId": id })),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 404);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn outsiders_cannot_see_or_manage_an_organization() {
+    let mut app = TestApp::new().await;
+    let owner = sign_up(&app).await;
+    let owner_token = log_in(&app, &owner).await;
+    let organization = create_organization(&app, &owner_token, "Initech").await;
+    let outsider = sign_up(&app).await;
+    let outsider_token = log_in(&app, &outsider).await;
+    create_organization(&app, &outsider_token, "Initrode").await;
+
+    let members = format!("/organizations/{}/members", organization.id);
+    let invitations = format!("/organizations/{}/invitations", organization.id);
+    let responses = [
+        send(&app, Method::GET, &members, &outsider_token, None).await,
+        send(&app, Method::GET, &invitations, &outsider_token, None).await,
+        invite(&app, &outsider_token, &organization, &outsider, "owner").await,
+        send(
+            &app,
+            Method::DELETE,
+            &format!("{}/{}", members, owner),
+            &outsider_token,
+            None,
+        )
+        .await,
+    ];
+    for response in responses {
+        assert_eq!(response.status().as_u16(), 404);
+    }
+
+    // The admin API token acts for no user, so it has no organizations
+    let admin_token = app
+        .settings
+        .admin
+        .api_token
+        .as_ref()
+        .unwrap()
+        .expose_secret()
+        .clone();
+    let response = send(&app, Method::GET, &members, &admin_token, None).await;
+    assert_eq!(response.status().as_u16(), 403);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn members_cannot_invite_and_admins_cannot_invite_owners() {
+    let mut app = TestApp::new().await;
+    let owner = sign_up(&app).await;
+    let owner_token = log_in(&app, &owner).await;
+    let organization = create_organization(&app, &owner_token, "Initech").await;
+    let member = sign_up(&app).await;
+    let admin = sign_up(&app).await;
+    for (email, role) in [(&member, "member"), (&admin, "admin")] {
+        invite(&app, &owner_token, &organization, email, role).await;
+        let token = invitation_token(&app, email).await;
+        let response = send(
+            &app,
+            Method::POST,
+            "/accept-invitation",
+            &log_in(&app, email).await,
+            Some(json!({ "token": token })),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    let member_token = log_in(&app, &member).await;
+    let admin_token = log_in(&app, &admin).await;
+    let invitations = format!("/organizations/{}/invitations", organization.id);
+
+    let response = invite(
+        &app,
+        &member_token,
+        &organization,
+        &get_random_email(),
+        "member",
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = send(&app, Method::GET, &invitations, &member_token, None).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = invite(
+        &app,
+        &admin_token,
+        &organization,
+        &get_random_email(),
+        "owner",
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = invite(
+        &app,
+        &admin_token,
+        &organization,
+        &get_random_email(),
+        "member",
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let response = invite(
+        &app,
+        &admin_token,
+        &organization,
+        &get_random_email(),
+        "janitor",
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = send(&app, Method::GET, &invitations, &admin_token, None).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn removed_members_are_signed_out_and_the_last_owner_stays() {
+    let mut app = TestApp::new().await;
+    let owner = sign_up(&app).await;
+    let owner_token = log_in(&app, &owner).await;
+    let organization = create_organization(&app, &owner_token, "Initech").await;
+    let member = sign_up(&app).await;
+    invite(&app, &owner_token, &organization, &member, "member").await;
+    let token = invitation_token(&app, &member).await;
+    let member_token = log_in(&app, &member).await;
+    send(
+        &app,
+        Method::POST,
+        "/accept-invitation",
+        &member_token,
+        Some(json!({ "token": token })),
+    )
+    .await;
+    let members = format!("/organizations/{}/members", organization.id);
+
+    // Members can't remove others
+    let response = send(
+        &app,
+        Method::DELETE,
+        &format!("{}/{}", members, owner),
+        &member_token,
+        None,
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = send(
+        &app,
+        Method::DELETE,
+        &format!("{}/{}", members, member),
+        &owner_token,
+        None,
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app
+        .post_verify_token(&json!({ "token": member_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = send(
+        &app,
+        Method::DELETE,
+        &format!("{}/{}", members, member),
+        &owner_token,
+        None,
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 404);
+    let response = send(
+        &app,
+        Method::DELETE,
+        &format!("{}/{}", members, owner),
+        &owner_token,
+        None,
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 409);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn invitations_match_the_address_in_any_case() {
+    let mut app = TestApp::new().await;
+    let owner = sign_up(&app).await;
+    let owner_token = log_in(&app, &owner).await;
+    let organization = create_organization(&app, &owner_token, "Initech").await;
+    let invitee = sign_up(&app).await;
+    let shouted = invitee.to_uppercase();
+
+    invite(&app, &owner_token, &organization, &shouted, "member").await;
+    let token = invitation_token(&app, &shouted).await;
+
+    let invitee_token = log_in(&app, &invitee).await;
+    let response = send(
+        &app,
+        Method::POST,
+        "/accept-invitation",
+        &invitee_token,
+        Some(json!({ "token": token })),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let membership = response.json::<Membership>().await.unwrap();
+    assert_eq!(membership.organization_id, organization.id);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn organization_changes_are_audited_as_the_caller() {
+    let mut app = TestApp::new().await;
+    let owner = sign_up(&app).await;
+    let owner_token = log_in(&app, &owner).await;
+    let organization = create_organization(&app, &owner_token, "Initech").await;
+    let member = sign_up(&app).await;
+    invite(&app, &owner_token, &organization, &member, "member").await;
+    let token = invitation_token(&app, &member).await;
+    let member_token = log_in(&app, &member).await;
+    send(
+        &app,
+        Method::POST,
+        "/accept-invitation",
+        &member_token,
+        Some(json!({ "token": token })),
+    )
+    .await;
+
+    let response = send(
+        &app,
+        Method::DELETE,
+        &format!("/organizations/{}/members/{}", organization.id, member),
+        &owner_token,
+        None,
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    for kind in ["invitation_sent", "member_removed"] {
+        let events = app
+            .get_audit_events(
+                &format!("kind={}&subject={}", kind, member),
+                Some(&admin_token(&app)),
+            )
+            .await
+            .json::<AuditEventsResponse>()
+            .await
+            .unwrap()
+            .events;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].actor.as_deref(), Some(owner.as_str()));
+    }
+    app.clean_up().await;
+}
@@ -0,0 +1,162 @@
+use auth_service::ErrorResponse;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+// As configured in config/test.toml
+const CREDENTIALS: Option<(&str, &str)> = Some(("postmark", "test-webhook-password"));
+
+async fn sign_up_with_2fa(app: &TestApp) -> String {
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": true,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    email
+}
+
+async fn log_in(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&json!({ "email": email, "password": "password123" }))
+        .await
+}
+
+fn bounce(email: &str, bounce_type: &str, inactive: bool) -> serde_json::Value {
+    json!({
+        "RecordType": "Bounce",
+        "Type": bounce_type,
+        "TypeCode": 1,
+        "Email": email,
+        "Inactive": inactive,
+        "BouncedAt": "2024-12-20T12:00:00Z",
+    })
+}
+
+#[tokio::test]
+async fn webhook_requires_credentials() {
+    let mut app = TestApp::new().await;
+    let body = bounce(&get_random_email(), "HardBounce", true);
+
+    let missing = app.post_postmark_webhook(&body, None).await;
+    let wrong = app
+        .post_postmark_webhook(&body, Some(("postmark", "wrong-password")))
+        .await;
+
+    assert_eq!(missing.status().as_u16(), 400);
+    assert_eq!(wrong.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn hard_bounce_blocks_2fa_login_with_a_clear_error() {
+    let mut app = TestApp::new().await;
+    let email = sign_up_with_2fa(&app).await;
+
+    let response = app
+        .post_postmark_webhook(&bounce(&email, "HardBounce", true), CREDENTIALS)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = log_in(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let error = response.json::<ErrorResponse>().await.unwrap().error;
+    assert!(error.contains("bounced or were marked as spam"));
+    assert!(app
+        .sent_with_subject_containing("login code")
+        .await
+        .is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn spam_complaint_blocks_2fa_login() {
+    let mut app = TestApp::new().await;
+    let email = sign_up_with_2fa(&app).await;
+
+    app.post_postmark_webhook(
+        &json!({ "RecordType": "SpamComplaint", "Type": "SpamComplaint", "Email": email }),
+        CREDENTIALS,
+    )
+    .await;
+
+    assert_eq!(log_in(&app, &email).await.status().as_u16(), 403);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn soft_bounce_still_sends_codes() {
+    let mut app = TestApp::new().await;
+    let email = sign_up_with_2fa(&app).await;
+
+    app.post_postmark_webhook(&bounce(&email, "SoftBounce", false), CREDENTIALS)
+        .await;
+
+    assert_eq!(log_in(&app, &email).await.status().as_u16(), 206);
+    assert_eq!(
+        app.sent_with_subject_containing("login code").await.len(),
+        1
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn delivery_lifts_a_suppression() {
+    let mut app = TestApp::new().await;
+    let email = sign_up_with_2fa(&app).await;
+    app.post_postmark_webhook(&bounce(&email, "HardBounce", true), CREDENTIALS)
+        .await;
+
+    // e.g. after the address was reactivated in Postmark
+    app.post_postmark_webhook(
+        &json!({ "RecordType": "Delivery", "Recipient": email }),
+        CREDENTIALS,
+    )
+    .await;
+
+    assert_eq!(log_in(&app, &email).await.status().as_u16(), 206);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn delivery_does_not_lift_a_spam_complaint() {
+    let mut app = TestApp::new().await;
+    let email = sign_up_with_2fa(&app).await;
+    app.post_postmark_webhook(
+        &json!({ "RecordType": "SpamComplaint", "Type": "SpamComplaint", "Email": email }),
+        CREDENTIALS,
+    )
+    .await;
+
+    let response = app
+        .post_postmark_webhook(
+            &json!({ "RecordType": "Delivery", "Recipient": email }),
+            CREDENTIALS,
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(log_in(&app, &email).await.status().as_u16(), 403);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn events_for_unknown_addresses_are_accepted() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_postmark_webhook(
+            &bounce(&get_random_email(), "HardBounce", true),
+            CREDENTIALS,
+        )
+        .await;
+    let open = app
+        .post_postmark_webhook(&json!({ "RecordType": "Open" }), CREDENTIALS)
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(open.status().as_u16(), 200);
+    app.clean_up().await;
+}
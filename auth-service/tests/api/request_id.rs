@@ -0,0 +1,108 @@
+use auth_service::ErrorResponse;
+use serde_json::json;
+
+use crate::helpers::TestApp;
+
+fn request_id(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get("x-request-id")
+        .expect("response should carry a request id")
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_generate_a_request_id_if_none_is_sent() {
+    let mut app = TestApp::new().await;
+
+    let first = app.get_healthz().await;
+    let second = app.get_healthz().await;
+
+    let first = request_id(&first);
+    assert!(uuid::Uuid::parse_str(&first).is_ok());
+    assert_ne!(first, request_id(&second));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_echo_a_valid_incoming_request_id() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/healthz", &app.address))
+        .header("X-Request-Id", "upstream-1234")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(request_id(&response), "upstream-1234");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_replace_an_invalid_incoming_request_id() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/healthz", &app.address))
+        .header("X-Request-Id", "<script>alert(1)</script>")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(uuid::Uuid::parse_str(&request_id(&response)).is_ok());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_include_the_request_id_in_error_bodies() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/signup", &app.address))
+        .header("X-Request-Id", "support-ticket-42")
+        .json(&json!({
+            "email": "not-an-email",
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(request_id(&response), "support-ticket-42");
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.request_id.as_deref(), Some("support-ticket-42"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_expose_the_request_id_to_browsers() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/healthz", &app.address))
+        .header("Origin", "http://localhost:8000")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let exposed = response
+        .headers()
+        .get("access-control-expose-headers")
+        .expect("request id should be exposed")
+        .to_str()
+        .unwrap();
+    assert!(exposed.contains("x-request-id"));
+    app.clean_up().await;
+}
@@ -0,0 +1,257 @@
+use auth_service::domain::data_stores::role_store::RoleDefinition;
+use auth_service::domain::Grants;
+use auth_service::routes::{AuditEventsResponse, RolesResponse, VerifyTokenResponse};
+use reqwest::Method;
+use secrecy::ExposeSecret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn admin_token(app: &TestApp) -> String {
+    app.settings
+        .admin
+        .api_token
+        .as_ref()
+        .expect("test settings have an admin token")
+        .expose_secret()
+        .clone()
+}
+
+async fn sign_up(app: &TestApp) -> String {
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    email
+}
+
+// Logs in, keeping the auth cookie for later requests, and returns the token
+async fn log_in(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == app.settings.auth.cookie.name)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    token
+}
+
+async fn grant(app: &TestApp, email: &str, role: &str) -> reqwest::Response {
+    app.admin_request(
+        Method::PUT,
+        &format!("/admin/users/{}/roles/{}", email, role),
+        Some(&admin_token(app)),
+    )
+    .await
+}
+
+async fn verify(app: &TestApp, token: &str) -> reqwest::Response {
+    app.post_verify_token(&json!({ "token": token })).await
+}
+
+#[tokio::test]
+async fn built_in_roles_are_listed() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .admin_request(Method::GET, "/admin/roles", Some(&admin_token(&app)))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let roles = response.json::<RolesResponse>().await.unwrap().roles;
+    assert_eq!(
+        roles,
+        vec![RoleDefinition {
+            name: "admin".to_owned(),
+            permissions: vec![
+                "audit_events:read".to_owned(),
+                "roles:read".to_owned(),
+                "roles:write".to_owned(),
+                "users:read".to_owned(),
+                "users:write".to_owned(),
+            ],
+        }]
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn granted_roles_are_carried_in_the_token() {
+    let mut app = TestApp::new().await;
+    let email = sign_up(&app).await;
+
+    assert_eq!(grant(&app, &email, "admin").await.status().as_u16(), 204);
+    let token = log_in(&app, &email).await;
+
+    let response = verify(&app, &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let claims = response.json::<VerifyTokenResponse>().await.unwrap();
+    assert_eq!(claims.email, email);
+    assert_eq!(claims.roles, vec!["admin"]);
+    assert!(claims.permissions.contains(&"roles:write".to_owned()));
+
+    let response = app
+        .admin_request(
+            Method::GET,
+            &format!("/admin/users/{}/roles", email),
+            Some(&admin_token(&app)),
+        )
+        .await;
+    let grants = response.json::<Grants>().await.unwrap();
+    assert_eq!(grants.roles, claims.roles);
+    assert_eq!(grants.permissions, claims.permissions);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn users_with_the_permission_can_use_admin_routes() {
+    let mut app = TestApp::new().await;
+    let email = sign_up(&app).await;
+    grant(&app, &email, "admin").await;
+    let token = log_in(&app, &email).await;
+
+    // By the bearer token or the auth cookie alike
+    let by_bearer = app
+        .admin_request(Method::GET, "/admin/roles", Some(&token))
+        .await;
+    let by_cookie = app.get_audit_events("limit=1", None).await;
+
+    assert_eq!(by_bearer.status().as_u16(), 200);
+    assert_eq!(by_cookie.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn role_changes_are_audited_as_the_caller() {
+    let mut app = TestApp::new().await;
+    let admin = sign_up(&app).await;
+    grant(&app, &admin, "admin").await;
+    let token = log_in(&app, &admin).await;
+    let grantee = sign_up(&app).await;
+
+    let response = app
+        .admin_request(
+            Method::PUT,
+            &format!("/admin/users/{}/roles/admin", grantee),
+            Some(&token),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let events = app
+        .get_audit_events(
+            &format!("kind=role_granted&subject={}", grantee),
+            Some(&admin_token(&app)),
+        )
+        .await
+        .json::<AuditEventsResponse>()
+        .await
+        .unwrap()
+        .events;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].actor.as_deref(), Some(admin.as_str()));
+    assert_eq!(events[0].subject.as_deref(), Some(grantee.as_str()));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn users_without_the_permission_are_forbidden() {
+    let mut app = TestApp::new().await;
+    let email = sign_up(&app).await;
+    let token = log_in(&app, &email).await;
+
+    let claims = verify(&app, &token)
+        .await
+        .json::<VerifyTokenResponse>()
+        .await
+        .unwrap();
+    assert!(claims.roles.is_empty());
+
+    let response = app
+        .admin_request(
+            Method::PUT,
+            &format!("/admin/users/{}/roles/admin", email),
+            None,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        app.get_audit_events("limit=1", None)
+            .await
+            .status()
+            .as_u16(),
+        403
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn revoking_a_role_ends_the_users_sessions() {
+    let mut app = TestApp::new().await;
+    let email = sign_up(&app).await;
+    grant(&app, &email, "admin").await;
+    let token = log_in(&app, &email).await;
+
+    let response = app
+        .admin_request(
+            Method::DELETE,
+            &format!("/admin/users/{}/roles/admin", email),
+            Some(&admin_token(&app)),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    // The old token still says admin, so it must stop working
+    assert_eq!(verify(&app, &token).await.status().as_u16(), 401);
+
+    // `iat` has second precision, so a login in the same second would count
+    // as revoked too
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let token = log_in(&app, &email).await;
+    let claims = verify(&app, &token)
+        .await
+        .json::<VerifyTokenResponse>()
+        .await
+        .unwrap();
+    assert!(claims.roles.is_empty());
+    assert!(claims.permissions.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn unknown_users_and_roles_are_not_found() {
+    let mut app = TestApp::new().await;
+    let email = sign_up(&app).await;
+
+    let unknown_role = grant(&app, &email, "superuser").await;
+    let unknown_user = grant(&app, &get_random_email(), "admin").await;
+    let invalid_email = grant(&app, "not-an-email", "admin").await;
+
+    assert_eq!(unknown_role.status().as_u16(), 404);
+    assert_eq!(unknown_user.status().as_u16(), 404);
+    assert_eq!(invalid_email.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn role_admin_requires_credentials() {
+    let mut app = TestApp::new().await;
+
+    let missing = app.admin_request(Method::GET, "/admin/roles", None).await;
+    let wrong = app
+        .admin_request(Method::GET, "/admin/roles", Some("wrong-token"))
+        .await;
+
+    assert_eq!(missing.status().as_u16(), 400);
+    assert_eq!(wrong.status().as_u16(), 401);
+    app.clean_up().await;
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use auth_service::domain::data_stores::UserStore;
+use auth_service::domain::{Email, EmailStatus, Password, TwoFAChannel, User};
+use auth_service::routes::LoginResponse;
+use auth_service::services::data_stores::hashmap_user_store::HashMapUserStore;
+use secrecy::Secret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PHONE_NUMBER: &str = "+4915112345678";
+
+async fn log_in(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&json!({ "email": email, "password": "password123" }))
+        .await
+}
+
+#[tokio::test]
+async fn login_code_is_texted_to_sms_users() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": true,
+            "twoFAChannel": "sms",
+            "phoneNumber": PHONE_NUMBER,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = log_in(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let LoginResponse::TwoFactorAuth(two_fa) = response.json::<LoginResponse>().await.unwrap()
+    else {
+        panic!("expected a 2FA response");
+    };
+
+    let texts = app.sms_client.sent_to(PHONE_NUMBER).await;
+    assert_eq!(texts.len(), 1);
+    assert!(app
+        .sent_with_subject_containing("login code")
+        .await
+        .is_empty());
+    let code = texts[0]
+        .body
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|word| word.len() == 6)
+        .expect("the text should contain the code");
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": two_fa.login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn email_users_with_a_phone_number_still_get_emails() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    app.post_signup(&json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true,
+        "phoneNumber": PHONE_NUMBER,
+    }))
+    .await;
+
+    assert_eq!(log_in(&app, &email).await.status().as_u16(), 206);
+
+    assert_eq!(
+        app.sent_with_subject_containing("login code").await.len(),
+        1
+    );
+    assert!(app.sms_client.sent_to(PHONE_NUMBER).await.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn sms_signup_needs_a_valid_number_and_2fa() {
+    let mut app = TestApp::new().await;
+
+    let invalid_inputs = [
+        json!({ "requires2FA": true, "twoFAChannel": "sms" }),
+        json!({ "requires2FA": true, "twoFAChannel": "sms", "phoneNumber": "0151 12345678" }),
+        json!({ "requires2FA": false, "twoFAChannel": "sms", "phoneNumber": PHONE_NUMBER }),
+        json!({ "requires2FA": true, "twoFAChannel": "fax", "phoneNumber": PHONE_NUMBER }),
+    ];
+
+    for mut input in invalid_inputs {
+        input["email"] = json!(get_random_email());
+        input["password"] = json!("password123");
+        let response = app.post_signup(&input).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", input);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn sms_users_without_a_number_fall_back_to_a_suppressed_email() {
+    // Signup won't take SMS without a number, so the account is stored as
+    // is, e.g. from before numbers were required
+    let email = get_random_email();
+    let user = User {
+        email_status: EmailStatus::HardBounced,
+        two_fa_channel: TwoFAChannel::Sms,
+        ..User::new(
+            Email::parse(email.clone()).unwrap(),
+            Password::parse(Secret::new("password123".to_owned())).unwrap(),
+            true,
+        )
+    };
+    let user_store = HashMapUserStore::default();
+    user_store.add_user(user).await.unwrap();
+    let mut app = TestApp::new_with_user_store(Arc::new(user_store)).await;
+
+    let response = log_in(&app, &email).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(app.sms_client.sent_to(PHONE_NUMBER).await.is_empty());
+    assert!(app
+        .sent_with_subject_containing("login code")
+        .await
+        .is_empty());
+    app.clean_up().await;
+}
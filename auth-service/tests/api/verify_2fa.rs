@@ -2,7 +2,8 @@ use auth_service::{
     domain::{
         data_stores::{LoginAttemptId, TwoFACode},
         Email,
-    }, routes::LoginResponse, utils::constants::JWT_COOKIE_NAME
+    },
+    routes::LoginResponse,
 };
 use secrecy::ExposeSecret;
 use serde_json::json;
@@ -117,7 +118,7 @@ async fn should_return_401_if_old_code() {
     let _ = app.post_login(&login_request).await;
 
     let email = Email::parse(random_email.clone()).unwrap();
-    let first_code = app.two_fa_store.read().await.get_code(&email).await.unwrap().1;
+    let first_code = app.two_fa_store.get_code(&email).await.unwrap().1;
 
     // second login attempt, the code we just grabbed shoudl be invalidated
     let login_res = app.post_login(&login_request).await;
@@ -174,7 +175,7 @@ async fn should_return_200_if_correct_code() {
         .await
         .expect("Could not deserialize response body to LoginResponse");
 
-    let code = app.two_fa_store.read().await.get_code(&email).await.unwrap().1;
+    let code = app.two_fa_store.get_code(&email).await.unwrap().1;
     match login_response_json {
         LoginResponse::TwoFactorAuth(two_fa_respons) => {
             // let code = app.two_fa_store.read().unwrap().get_code(&email).unwrap();
@@ -191,7 +192,7 @@ async fn should_return_200_if_correct_code() {
             //make sure cookie is set properly
             let auth_cookie = verify_res
                 .cookies()
-                .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+                .find(|cookie| cookie.name() == app.settings.auth.cookie.name)
                 .expect("No auth cookie found");
 
             assert!(!auth_cookie.value().is_empty());
@@ -231,7 +232,7 @@ async fn should_return_401_if_same_code_twice() {
         .await
         .expect("Could not deserialize response body to LoginResponse");
 
-    let code = app.two_fa_store.read().await.get_code(&email).await.unwrap().1;
+    let code = app.two_fa_store.get_code(&email).await.unwrap().1;
     match login_response_json {
         LoginResponse::TwoFactorAuth(two_fa_respons) => {
             // let code = app.two_fa_store.read().unwrap().get_code(&email).unwrap();
@@ -248,7 +249,7 @@ async fn should_return_401_if_same_code_twice() {
             //make sure cookie is set properly
             let auth_cookie = verify_res
                 .cookies()
-                .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+                .find(|cookie| cookie.name() == app.settings.auth.cookie.name)
                 .expect("No auth cookie found");
 
             assert!(!auth_cookie.value().is_empty());
@@ -1,4 +1,7 @@
-use auth_service::{domain::email::Email, utils::auth::generate_auth_token};
+use auth_service::{
+    domain::{email::Email, Grants},
+    utils::auth::generate_auth_token,
+};
 use secrecy::Secret;
 use serde_json::json;
 
@@ -18,11 +21,14 @@ async fn should_return_200_valid_token() {
     let mut app = TestApp::new().await;
 
     let email = Email::parse(get_random_email()).expect("email should be parseable");
-    let valid_token = generate_auth_token(&email).expect("should generate token");
+    let valid_token = generate_auth_token(&email, &Grants::default(), None, &app.settings.auth)
+        .expect("should generate token");
 
-    let response = app.post_verify_token(&json!({
-        "token": valid_token,
-    })).await;
+    let response = app
+        .post_verify_token(&json!({
+            "token": valid_token,
+        }))
+        .await;
 
     assert_eq!(response.status().as_u16(), 200);
     app.clean_up().await;
@@ -32,9 +38,11 @@ async fn should_return_200_valid_token() {
 async fn should_return_401_if_invalid_token() {
     let mut app = TestApp::new().await;
 
-    let response = app.post_verify_token(&json!({
-        "token": "woeifjweoijf",
-    })).await;
+    let response = app
+        .post_verify_token(&json!({
+            "token": "woeifjweoijf",
+        }))
+        .await;
 
     assert_eq!(response.status().as_u16(), 401);
     app.clean_up().await;
@@ -45,17 +53,23 @@ async fn should_return_401_if_banned_token() {
     let mut app = TestApp::new().await;
 
     let email = Email::parse(get_random_email()).expect("email should be parseable");
-    let valid_token = generate_auth_token(&email).expect("should generate token");
+    let valid_token = generate_auth_token(&email, &Grants::default(), None, &app.settings.auth)
+        .expect("should generate token");
     {
-        let mut banned_store = app.banned_token_store.write().await;
-        match banned_store.add_token(Secret::new(valid_token.clone())).await {
-            Ok(()) => {},
-            Err(_) => panic!("error adding token to banned token store")
+        match app
+            .banned_token_store
+            .add_token(Secret::new(valid_token.clone()))
+            .await
+        {
+            Ok(()) => {}
+            Err(_) => panic!("error adding token to banned token store"),
         };
     }
-    let response = app.post_verify_token(&json!({
-        "token": valid_token,
-    })).await;
+    let response = app
+        .post_verify_token(&json!({
+            "token": valid_token,
+        }))
+        .await;
 
     assert_eq!(response.status().as_u16(), 401);
     app.clean_up().await;
@@ -0,0 +1,198 @@
+use auth_service::app_state::AuditLogType;
+use auth_service::domain::data_stores::audit_log::{
+    AuditEvent, AuditEventFilter, AuditEventKind, AuditOutcome,
+};
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+
+use crate::helpers::get_random_email;
+
+// Postgres keeps timestamps to the microsecond, so start from one it can
+// round-trip exactly
+fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(6)
+}
+
+fn event(actor: &str, kind: AuditEventKind, outcome: AuditOutcome) -> AuditEvent {
+    AuditEvent {
+        kind,
+        outcome,
+        reason: None,
+        actor: Some(actor.to_owned()),
+        subject: None,
+        ip: Some("127.0.0.1".to_owned()),
+        user_agent: Some("conformance-test".to_owned()),
+        request_id: Some("request-1".to_owned()),
+        occurred_at: now(),
+    }
+}
+
+fn for_actor(actor: &str) -> AuditEventFilter {
+    AuditEventFilter {
+        actor: Some(actor.to_owned()),
+        ..Default::default()
+    }
+}
+
+async fn recorded_event_is_returned(log: AuditLogType) {
+    let actor = get_random_email();
+    let event = AuditEvent {
+        reason: Some("bad_password".to_owned()),
+        ..event(&actor, AuditEventKind::Login, AuditOutcome::Failure)
+    };
+    log.record(event.clone()).await.unwrap();
+
+    assert_eq!(log.query(&for_actor(&actor)).await.unwrap(), vec![event]);
+}
+
+async fn events_are_returned_newest_first(log: AuditLogType) {
+    let actor = get_random_email();
+    let started = now();
+    for (offset, kind) in [
+        AuditEventKind::Signup,
+        AuditEventKind::Login,
+        AuditEventKind::Logout,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let event = AuditEvent {
+            occurred_at: started + Duration::seconds(offset as i64),
+            ..event(&actor, kind, AuditOutcome::Success)
+        };
+        log.record(event).await.unwrap();
+    }
+
+    let kinds: Vec<_> = log
+        .query(&for_actor(&actor))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|event| event.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            AuditEventKind::Logout,
+            AuditEventKind::Login,
+            AuditEventKind::Signup
+        ]
+    );
+}
+
+async fn query_filters_by_actor_kind_and_outcome(log: AuditLogType) {
+    let actor = get_random_email();
+    let someone_else = get_random_email();
+    log.record(event(&actor, AuditEventKind::Login, AuditOutcome::Failure))
+        .await
+        .unwrap();
+    log.record(event(&actor, AuditEventKind::Login, AuditOutcome::Success))
+        .await
+        .unwrap();
+    log.record(event(&actor, AuditEventKind::Logout, AuditOutcome::Success))
+        .await
+        .unwrap();
+    log.record(event(
+        &someone_else,
+        AuditEventKind::Login,
+        AuditOutcome::Success,
+    ))
+    .await
+    .unwrap();
+
+    let filter = AuditEventFilter {
+        kinds: vec![AuditEventKind::Login],
+        outcome: Some(AuditOutcome::Success),
+        ..for_actor(&actor)
+    };
+    let events = log.query(&filter).await.unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].actor.as_deref(), Some(actor.as_str()));
+    assert_eq!(events[0].kind, AuditEventKind::Login);
+    assert_eq!(events[0].outcome, AuditOutcome::Success);
+}
+
+async fn query_filters_by_subject(log: AuditLogType) {
+    let admin = get_random_email();
+    let subject = get_random_email();
+    let granted = AuditEvent {
+        subject: Some(subject.clone()),
+        ..event(&admin, AuditEventKind::RoleGranted, AuditOutcome::Success)
+    };
+    log.record(granted.clone()).await.unwrap();
+    log.record(event(&admin, AuditEventKind::Login, AuditOutcome::Success))
+        .await
+        .unwrap();
+
+    let filter = AuditEventFilter {
+        subject: Some(subject),
+        ..Default::default()
+    };
+
+    assert_eq!(log.query(&filter).await.unwrap(), vec![granted]);
+}
+
+async fn query_filters_by_time_range(log: AuditLogType) {
+    let actor = get_random_email();
+    let started = now();
+    for offset in 0..3 {
+        let event = AuditEvent {
+            occurred_at: started + Duration::minutes(offset),
+            ..event(&actor, AuditEventKind::Login, AuditOutcome::Success)
+        };
+        log.record(event).await.unwrap();
+    }
+
+    // `since` is inclusive, `until` exclusive
+    let filter = AuditEventFilter {
+        since: Some(started + Duration::minutes(1)),
+        until: Some(started + Duration::minutes(2)),
+        ..for_actor(&actor)
+    };
+    let events = log.query(&filter).await.unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].occurred_at, started + Duration::minutes(1));
+}
+
+async fn query_honours_the_limit(log: AuditLogType) {
+    let actor = get_random_email();
+    for _ in 0..5 {
+        log.record(event(&actor, AuditEventKind::Login, AuditOutcome::Failure))
+            .await
+            .unwrap();
+    }
+
+    let filter = AuditEventFilter {
+        limit: 2,
+        ..for_actor(&actor)
+    };
+
+    assert_eq!(log.query(&filter).await.unwrap().len(), 2);
+}
+
+async fn health_check_succeeds(log: AuditLogType) {
+    log.health_check().await.unwrap();
+}
+
+macro_rules! audit_log_conformance {
+    ($($backend:ident),+ $(,)?) => {
+        $(
+            mod $backend {
+                conformance_cases!(crate::helpers::$backend;
+                    recorded_event_is_returned(),
+                    events_are_returned_newest_first(),
+                    query_filters_by_actor_kind_and_outcome(),
+                    query_filters_by_subject(),
+                    query_filters_by_time_range(),
+                    query_honours_the_limit(),
+                    health_check_succeeds(),
+                );
+            }
+        )+
+    };
+}
+
+audit_log_conformance!(vec_audit_log);
+#[cfg(feature = "postgres")]
+audit_log_conformance!(postgres_audit_log);
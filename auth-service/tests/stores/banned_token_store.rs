@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use auth_service::app_state::BannedTokenStoreType;
+use auth_service::domain::Email;
+use chrono::{SubsecRound, Utc};
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::helpers::{get_random_email, SHORT_TTL};
+
+fn random_token() -> Secret<String> {
+    Secret::new(Uuid::new_v4().to_string())
+}
+
+async fn added_token_is_contained(store: BannedTokenStoreType) {
+    let token = random_token();
+    store.add_token(token.clone()).await.unwrap();
+
+    assert!(store.contains_token(&token).await.unwrap());
+}
+
+async fn unknown_token_is_not_contained(store: BannedTokenStoreType) {
+    let res = store.contains_token(&random_token()).await;
+    assert!(!res.unwrap());
+}
+
+async fn adding_token_twice_succeeds(store: BannedTokenStoreType) {
+    let token = random_token();
+    store.add_token(token.clone()).await.unwrap();
+    store.add_token(token.clone()).await.unwrap();
+
+    assert!(store.contains_token(&token).await.unwrap());
+}
+
+async fn token_expires_after_ttl(store: BannedTokenStoreType) {
+    let token = random_token();
+    store.add_token(token.clone()).await.unwrap();
+
+    tokio::time::sleep(SHORT_TTL + Duration::from_secs(1)).await;
+
+    assert!(!store.contains_token(&token).await.unwrap());
+}
+
+async fn concurrent_adds_are_all_contained(store: BannedTokenStoreType) {
+    let tokens: Vec<_> = (0..16).map(|_| random_token()).collect();
+    let handles: Vec<_> = tokens
+        .iter()
+        .map(|token| {
+            let store = store.clone();
+            let token = token.clone();
+            tokio::spawn(async move { store.add_token(token).await })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.await.unwrap().unwrap();
+    }
+    for token in tokens {
+        assert!(store.contains_token(&token).await.unwrap());
+    }
+}
+
+async fn revoked_sessions_are_reported(store: BannedTokenStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+    let other = Email::parse(get_random_email()).unwrap();
+    // Redis keeps the revocation time to the millisecond
+    let revoked_at = Utc::now().trunc_subsecs(3);
+
+    store.revoke_sessions(&email, revoked_at).await.unwrap();
+
+    assert_eq!(
+        store.sessions_revoked_at(&email).await.unwrap(),
+        Some(revoked_at)
+    );
+    assert_eq!(store.sessions_revoked_at(&other).await.unwrap(), None);
+}
+
+async fn revoking_again_moves_revocation_time(store: BannedTokenStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+    let first = Utc::now().trunc_subsecs(3);
+    let second = first + chrono::Duration::seconds(5);
+
+    store.revoke_sessions(&email, first).await.unwrap();
+    store.revoke_sessions(&email, second).await.unwrap();
+
+    assert_eq!(
+        store.sessions_revoked_at(&email).await.unwrap(),
+        Some(second)
+    );
+}
+
+async fn revocation_expires_after_ttl(store: BannedTokenStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+    store.revoke_sessions(&email, Utc::now()).await.unwrap();
+
+    tokio::time::sleep(SHORT_TTL + Duration::from_secs(1)).await;
+
+    assert_eq!(store.sessions_revoked_at(&email).await.unwrap(), None);
+}
+
+async fn health_check_succeeds(store: BannedTokenStoreType) {
+    store.health_check().await.unwrap();
+}
+
+macro_rules! banned_token_store_conformance {
+    ($($backend:ident),+ $(,)?) => {
+        $(
+            mod $backend {
+                use crate::helpers::{LONG_TTL, SHORT_TTL};
+
+                conformance_cases!(crate::helpers::$backend;
+                    added_token_is_contained(LONG_TTL),
+                    unknown_token_is_not_contained(LONG_TTL),
+                    adding_token_twice_succeeds(LONG_TTL),
+                    token_expires_after_ttl(SHORT_TTL),
+                    concurrent_adds_are_all_contained(LONG_TTL),
+                    revoked_sessions_are_reported(LONG_TTL),
+                    revoking_again_moves_revocation_time(LONG_TTL),
+                    revocation_expires_after_ttl(SHORT_TTL),
+                    health_check_succeeds(LONG_TTL),
+                );
+            }
+        )+
+    };
+}
+
+banned_token_store_conformance!(hashset_banned_token_store, redis_banned_token_store);
+#[cfg(feature = "postgres")]
+banned_token_store_conformance!(postgres_banned_token_store);
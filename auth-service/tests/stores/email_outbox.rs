@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use auth_service::app_state::EmailOutboxType;
+use auth_service::domain::data_stores::email_outbox::EmailOutboxError;
+use auth_service::domain::{Email, EmailMessage};
+
+use crate::helpers::get_random_email;
+
+// Long enough that nothing claimed becomes due again while a case runs
+const LEASE: Duration = Duration::from_secs(600);
+
+fn message(subject: &str) -> EmailMessage {
+    EmailMessage {
+        subject: subject.to_owned(),
+        html_body: format!("<p>{}</p>", subject),
+        text_body: subject.to_owned(),
+    }
+}
+
+async fn enqueue(store: &EmailOutboxType, subject: &str) -> Email {
+    let recipient = Email::parse(get_random_email()).unwrap();
+    store
+        .enqueue(recipient.clone(), message(subject))
+        .await
+        .unwrap();
+    recipient
+}
+
+async fn claim_returns_enqueued_emails_in_order(store: EmailOutboxType) {
+    let first = enqueue(&store, "first").await;
+    let second = enqueue(&store, "second").await;
+
+    let claimed = store.claim_due(10, LEASE).await.unwrap();
+
+    assert_eq!(claimed.len(), 2);
+    assert_eq!(claimed[0].recipient, first);
+    assert_eq!(claimed[0].message, message("first"));
+    assert_eq!(claimed[0].attempts, 1);
+    assert_eq!(claimed[0].last_error, None);
+    assert_eq!(claimed[1].recipient, second);
+    assert_eq!(store.pending_count().await.unwrap(), 2);
+}
+
+async fn claim_respects_limit(store: EmailOutboxType) {
+    for subject in ["one", "two", "three"] {
+        enqueue(&store, subject).await;
+    }
+
+    assert_eq!(store.claim_due(2, LEASE).await.unwrap().len(), 2);
+    assert_eq!(store.claim_due(2, LEASE).await.unwrap().len(), 1);
+}
+
+async fn claimed_emails_are_hidden_until_lease_expires(store: EmailOutboxType) {
+    enqueue(&store, "leased").await;
+
+    let claimed = store
+        .claim_due(10, Duration::from_millis(200))
+        .await
+        .unwrap();
+    assert_eq!(claimed.len(), 1);
+    assert!(store.claim_due(10, LEASE).await.unwrap().is_empty());
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let reclaimed = store.claim_due(10, LEASE).await.unwrap();
+    assert_eq!(reclaimed.len(), 1);
+    assert_eq!(reclaimed[0].id, claimed[0].id);
+    assert_eq!(reclaimed[0].attempts, 2);
+}
+
+async fn sent_emails_leave_the_outbox(store: EmailOutboxType) {
+    enqueue(&store, "sent").await;
+    let claimed = store.claim_due(10, LEASE).await.unwrap();
+
+    store.mark_sent(claimed[0].id).await.unwrap();
+
+    assert_eq!(store.pending_count().await.unwrap(), 0);
+    assert!(matches!(
+        store.mark_sent(claimed[0].id).await,
+        Err(EmailOutboxError::EmailNotFound)
+    ));
+}
+
+async fn retried_emails_are_due_after_delay(store: EmailOutboxType) {
+    enqueue(&store, "retried").await;
+    let claimed = store.claim_due(10, LEASE).await.unwrap();
+
+    store
+        .retry_later(
+            claimed[0].id,
+            Duration::from_millis(200),
+            "503 from provider",
+        )
+        .await
+        .unwrap();
+
+    assert!(store.claim_due(10, LEASE).await.unwrap().is_empty());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let retried = store.claim_due(10, LEASE).await.unwrap();
+    assert_eq!(retried.len(), 1);
+    assert_eq!(retried[0].attempts, 2);
+    assert_eq!(retried[0].last_error.as_deref(), Some("503 from provider"));
+}
+
+async fn dead_letters_are_kept_but_never_claimed(store: EmailOutboxType) {
+    let recipient = enqueue(&store, "dead").await;
+    let claimed = store.claim_due(10, LEASE).await.unwrap();
+
+    store
+        .dead_letter(claimed[0].id, "mailbox does not exist")
+        .await
+        .unwrap();
+
+    assert_eq!(store.pending_count().await.unwrap(), 0);
+    assert!(store
+        .claim_due(10, Duration::ZERO)
+        .await
+        .unwrap()
+        .is_empty());
+    let dead_letters = store.dead_letters().await.unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].recipient, recipient);
+    assert_eq!(dead_letters[0].message, message("dead"));
+    assert_eq!(dead_letters[0].attempts, 1);
+    assert_eq!(
+        dead_letters[0].last_error.as_deref(),
+        Some("mailbox does not exist")
+    );
+}
+
+async fn dead_letters_are_purged_after_retention(store: EmailOutboxType) {
+    enqueue(&store, "dead").await;
+    let claimed = store.claim_due(10, LEASE).await.unwrap();
+    store.dead_letter(claimed[0].id, "bounced").await.unwrap();
+    enqueue(&store, "pending").await;
+
+    assert_eq!(store.purge_dead_letters(LEASE).await.unwrap(), 0);
+    assert_eq!(store.dead_letters().await.unwrap().len(), 1);
+
+    assert_eq!(store.purge_dead_letters(Duration::ZERO).await.unwrap(), 1);
+    assert!(store.dead_letters().await.unwrap().is_empty());
+    // Emails still waiting for delivery are never purged
+    assert_eq!(store.pending_count().await.unwrap(), 1);
+}
+
+async fn unknown_ids_are_not_found(store: EmailOutboxType) {
+    assert!(matches!(
+        store.retry_later(42, LEASE, "error").await,
+        Err(EmailOutboxError::EmailNotFound)
+    ));
+    assert!(matches!(
+        store.dead_letter(42, "error").await,
+        Err(EmailOutboxError::EmailNotFound)
+    ));
+}
+
+async fn health_check_succeeds(store: EmailOutboxType) {
+    store.health_check().await.unwrap();
+}
+
+macro_rules! email_outbox_conformance {
+    ($($backend:ident),+ $(,)?) => {
+        $(
+            mod $backend {
+                conformance_cases!(crate::helpers::$backend;
+                    claim_returns_enqueued_emails_in_order(),
+                    claim_respects_limit(),
+                    claimed_emails_are_hidden_until_lease_expires(),
+                    sent_emails_leave_the_outbox(),
+                    retried_emails_are_due_after_delay(),
+                    dead_letters_are_kept_but_never_claimed(),
+                    dead_letters_are_purged_after_retention(),
+                    unknown_ids_are_not_found(),
+                    health_check_succeeds(),
+                );
+            }
+        )+
+    };
+}
+
+email_outbox_conformance!(hashmap_email_outbox);
+#[cfg(feature = "postgres")]
+email_outbox_conformance!(postgres_email_outbox);
@@ -0,0 +1,336 @@
+use std::path::PathBuf;
+#[cfg(feature = "postgres")]
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use auth_service::app_state::{
+    AuditLogType, BannedTokenStoreType, EmailOutboxType, KnownDeviceStoreType,
+    OrganizationStoreType, RoleStoreType, TwoFACodeStoreType, UserStoreType,
+};
+#[cfg(feature = "postgres")]
+use auth_service::get_postgres_pool;
+use auth_service::get_redis_connection_manager;
+#[cfg(feature = "sqlite")]
+use auth_service::get_sqlite_pool;
+use auth_service::services::data_stores::hashmap_email_outbox::HashMapEmailOutbox;
+use auth_service::services::data_stores::hashmap_known_device_store::HashMapKnownDeviceStore;
+use auth_service::services::data_stores::hashmap_organization_store::HashMapOrganizationStore;
+use auth_service::services::data_stores::hashmap_role_store::HashMapRoleStore;
+use auth_service::services::data_stores::hashmap_two_fa_code_store::HashmapTwoFACodeStore;
+use auth_service::services::data_stores::hashmap_user_store::HashMapUserStore;
+use auth_service::services::data_stores::hashset_banned_token_store::HashsetBannedTokenStore;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_audit_log::PostgresAuditLog;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_banned_token_store::PostgresBannedTokenStore;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_email_outbox::PostgresEmailOutbox;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_known_device_store::PostgresKnownDeviceStore;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_organization_store::PostgresOrganizationStore;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_role_store::PostgresRoleStore;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_two_fa_code_store::PostgresTwoFACodeStore;
+#[cfg(feature = "postgres")]
+use auth_service::services::data_stores::postgres_user_store::PostgresUserStore;
+use auth_service::services::data_stores::redis_banned_token_store::RedisBannedTokenStore;
+use auth_service::services::data_stores::redis_two_fa_code_store::RedisTwoFACodeStore;
+#[cfg(feature = "sqlite")]
+use auth_service::services::data_stores::sqlite_user_store::SqliteUserStore;
+use auth_service::services::data_stores::vec_audit_log::VecAuditLog;
+use auth_service::settings::Settings;
+use redis::aio::ConnectionManager;
+#[cfg(feature = "postgres")]
+use secrecy::ExposeSecret;
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+use secrecy::Secret;
+#[cfg(feature = "postgres")]
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+#[cfg(feature = "postgres")]
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use uuid::Uuid;
+
+// Generates one `#[tokio::test]` per case. Every case is an async fn in the
+// enclosing suite module taking the store under test; the arguments in
+// parentheses are forwarded to the backend's setup function.
+macro_rules! conformance_cases {
+    ($setup:path; $($case:ident($($arg:expr),*)),+ $(,)?) => {
+        $(
+            #[tokio::test]
+            async fn $case() {
+                let mut backend = $setup($($arg),*).await;
+                super::$case(backend.store.clone()).await;
+                backend.clean_up().await;
+            }
+        )+
+    };
+}
+
+// Whatever a backend leaves behind that has to be removed after a test
+enum Teardown {
+    #[cfg(feature = "postgres")]
+    PostgresDatabase(String),
+    #[allow(dead_code)]
+    File(PathBuf),
+}
+
+// A store under test, plus whatever it needs torn down afterwards
+pub struct TestStore<S> {
+    pub store: S,
+    teardown: Option<Teardown>,
+}
+
+impl<S> TestStore<S> {
+    fn in_memory(store: S) -> Self {
+        Self {
+            store,
+            teardown: None,
+        }
+    }
+
+    pub async fn clean_up(&mut self) {
+        match self.teardown.take() {
+            #[cfg(feature = "postgres")]
+            Some(Teardown::PostgresDatabase(db_name)) => delete_database(&db_name).await,
+            Some(Teardown::File(path)) => {
+                let _ = std::fs::remove_file(path);
+            }
+            None => {}
+        }
+    }
+}
+
+pub async fn hashmap_user_store() -> TestStore<UserStoreType> {
+    TestStore::in_memory(Arc::new(HashMapUserStore::default()))
+}
+
+// A freshly migrated database of its own, dropped again on clean up
+#[cfg(feature = "postgres")]
+pub async fn test_database() -> TestStore<PgPool> {
+    let db_name = Uuid::new_v4().to_string();
+    let pg_pool = configure_postgresql(&db_name).await;
+    TestStore {
+        store: pg_pool,
+        teardown: Some(Teardown::PostgresDatabase(db_name)),
+    }
+}
+
+#[cfg(feature = "postgres")]
+pub async fn postgres_user_store() -> TestStore<UserStoreType> {
+    let db = test_database().await;
+    TestStore {
+        store: Arc::new(PostgresUserStore::new(db.store)),
+        teardown: db.teardown,
+    }
+}
+
+// Uses a real file rather than `sqlite::memory:` so every pooled connection
+// sees the same database, the same way a deployment would
+#[cfg(feature = "sqlite")]
+pub async fn sqlite_user_store() -> TestStore<UserStoreType> {
+    let path = std::env::temp_dir().join(format!("auth-service-{}.db", Uuid::new_v4()));
+    let url = Secret::new(format!("sqlite://{}", path.display()));
+    let sqlite_pool = get_sqlite_pool(&url)
+        .await
+        .expect("Failed to create SQLite connection pool!");
+
+    sqlx::migrate!("./migrations_sqlite")
+        .run(&sqlite_pool)
+        .await
+        .expect("Failed to migrate the database");
+
+    TestStore {
+        store: Arc::new(SqliteUserStore::new(sqlite_pool)),
+        teardown: Some(Teardown::File(path)),
+    }
+}
+
+pub async fn hashset_banned_token_store(ttl: Duration) -> TestStore<BannedTokenStoreType> {
+    TestStore::in_memory(Arc::new(HashsetBannedTokenStore::default().with_ttl(ttl)))
+}
+
+pub async fn redis_banned_token_store(ttl: Duration) -> TestStore<BannedTokenStoreType> {
+    let conn = configure_redis().await;
+    TestStore::in_memory(Arc::new(RedisBannedTokenStore::new(conn).with_ttl(ttl)))
+}
+
+#[cfg(feature = "postgres")]
+pub async fn postgres_banned_token_store(ttl: Duration) -> TestStore<BannedTokenStoreType> {
+    let db = test_database().await;
+    TestStore {
+        store: Arc::new(PostgresBannedTokenStore::new(db.store).with_ttl(ttl)),
+        teardown: db.teardown,
+    }
+}
+
+pub async fn hashmap_two_fa_code_store(ttl: Duration) -> TestStore<TwoFACodeStoreType> {
+    TestStore::in_memory(Arc::new(HashmapTwoFACodeStore::default().with_ttl(ttl)))
+}
+
+pub async fn redis_two_fa_code_store(ttl: Duration) -> TestStore<TwoFACodeStoreType> {
+    let conn = configure_redis().await;
+    TestStore::in_memory(Arc::new(RedisTwoFACodeStore::new(conn).with_ttl(ttl)))
+}
+
+#[cfg(feature = "postgres")]
+pub async fn postgres_two_fa_code_store(ttl: Duration) -> TestStore<TwoFACodeStoreType> {
+    let db = test_database().await;
+    TestStore {
+        store: Arc::new(PostgresTwoFACodeStore::new(db.store).with_ttl(ttl)),
+        teardown: db.teardown,
+    }
+}
+
+pub async fn vec_audit_log() -> TestStore<AuditLogType> {
+    TestStore::in_memory(Arc::new(VecAuditLog::default()))
+}
+
+#[cfg(feature = "postgres")]
+pub async fn postgres_audit_log() -> TestStore<AuditLogType> {
+    let db = test_database().await;
+    TestStore {
+        store: Arc::new(PostgresAuditLog::new(db.store)),
+        teardown: db.teardown,
+    }
+}
+
+pub async fn hashmap_known_device_store() -> TestStore<KnownDeviceStoreType> {
+    TestStore::in_memory(Arc::new(HashMapKnownDeviceStore::default()))
+}
+
+#[cfg(feature = "postgres")]
+pub async fn postgres_known_device_store() -> TestStore<KnownDeviceStoreType> {
+    let db = test_database().await;
+    TestStore {
+        store: Arc::new(PostgresKnownDeviceStore::new(db.store)),
+        teardown: db.teardown,
+    }
+}
+
+pub async fn hashmap_role_store() -> TestStore<RoleStoreType> {
+    TestStore::in_memory(Arc::new(HashMapRoleStore::default()))
+}
+
+#[cfg(feature = "postgres")]
+pub async fn postgres_role_store() -> TestStore<RoleStoreType> {
+    let db = test_database().await;
+    TestStore {
+        store: Arc::new(PostgresRoleStore::new(db.store)),
+        teardown: db.teardown,
+    }
+}
+
+pub async fn hashmap_organization_store() -> TestStore<OrganizationStoreType> {
+    TestStore::in_memory(Arc::new(HashMapOrganizationStore::default()))
+}
+
+#[cfg(feature = "postgres")]
+pub async fn postgres_organization_store() -> TestStore<OrganizationStoreType> {
+    let db = test_database().await;
+    TestStore {
+        store: Arc::new(PostgresOrganizationStore::new(db.store)),
+        teardown: db.teardown,
+    }
+}
+
+pub async fn hashmap_email_outbox() -> TestStore<EmailOutboxType> {
+    TestStore::in_memory(Arc::new(HashMapEmailOutbox::default()))
+}
+
+#[cfg(feature = "postgres")]
+pub async fn postgres_email_outbox() -> TestStore<EmailOutboxType> {
+    let db = test_database().await;
+    TestStore {
+        store: Arc::new(PostgresEmailOutbox::new(db.store)),
+        teardown: db.teardown,
+    }
+}
+
+// TTL long enough that nothing expires while a case runs
+pub const LONG_TTL: Duration = Duration::from_secs(600);
+// Shortest TTL every backend can honour (Redis expiry is in whole seconds)
+pub const SHORT_TTL: Duration = Duration::from_secs(1);
+
+pub fn get_random_email() -> String {
+    format!("{}@example.com", Uuid::new_v4())
+}
+
+#[cfg(feature = "postgres")]
+async fn configure_postgresql(db_name: &str) -> PgPool {
+    let postgresql_conn_url = settings().database.url;
+
+    // Create the database and run migrations against it
+    let connection = PgPoolOptions::new()
+        .connect(postgresql_conn_url.expose_secret())
+        .await
+        .expect("Failed to create Postgres connection pool.");
+
+    connection
+        .execute(format!(r#"CREATE DATABASE "{}";"#, db_name).as_str())
+        .await
+        .expect("Failed to create database.");
+
+    let postgresql_conn_url_with_db = Secret::new(format!(
+        "{}/{}",
+        postgresql_conn_url.expose_secret(),
+        db_name
+    ));
+
+    let pg_pool = get_postgres_pool(&postgresql_conn_url_with_db)
+        .await
+        .expect("Failed to create Postgres connection pool!");
+
+    sqlx::migrate!()
+        .run(&pg_pool)
+        .await
+        .expect("Failed to migrate the database");
+
+    pg_pool
+}
+
+#[cfg(feature = "postgres")]
+async fn delete_database(db_name: &str) {
+    let postgresql_conn_url: Secret<String> = settings().database.url;
+
+    let connection_options = PgConnectOptions::from_str(postgresql_conn_url.expose_secret())
+        .expect("Failed to parse PostgreSQL connection string");
+
+    let mut connection = PgConnection::connect_with(&connection_options)
+        .await
+        .expect("Failed to connect to Postgres");
+
+    // Kill any active connections to the database
+    connection
+        .execute(
+            format!(
+                r#"
+                SELECT pg_terminate_backend(pg_stat_activity.pid)
+                FROM pg_stat_activity
+                WHERE pg_stat_activity.datname = '{}'
+                  AND pid <> pg_backend_pid();
+        "#,
+                db_name
+            )
+            .as_str(),
+        )
+        .await
+        .expect("Failed to drop the database.");
+
+    connection
+        .execute(format!(r#"DROP DATABASE "{}";"#, db_name).as_str())
+        .await
+        .expect("Failed to drop the database.");
+}
+
+fn settings() -> Settings {
+    Settings::load_for("test").expect("Failed to load test settings")
+}
+
+async fn configure_redis() -> ConnectionManager {
+    get_redis_connection_manager(settings().redis.host_name)
+        .await
+        .expect("Failed to get Redis connection")
+}
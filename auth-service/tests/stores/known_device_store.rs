@@ -0,0 +1,97 @@
+use auth_service::app_state::KnownDeviceStoreType;
+use auth_service::domain::data_stores::known_device_store::{Device, DeviceStatus};
+use auth_service::domain::Email;
+
+use crate::helpers::get_random_email;
+
+fn device(ip: &str, user_agent: &str) -> Device {
+    Device {
+        ip: Some(ip.to_owned()),
+        user_agent: Some(user_agent.to_owned()),
+    }
+}
+
+async fn first_device_is_reported_as_first(store: KnownDeviceStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+
+    let status = store
+        .remember_device(&email, &device("203.0.113.7", "Firefox"))
+        .await
+        .unwrap();
+    assert_eq!(status, DeviceStatus::First);
+}
+
+async fn remembered_device_is_known(store: KnownDeviceStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+    let laptop = device("203.0.113.7", "Firefox");
+    store.remember_device(&email, &laptop).await.unwrap();
+
+    let status = store.remember_device(&email, &laptop).await.unwrap();
+    assert_eq!(status, DeviceStatus::Known);
+}
+
+async fn different_ip_or_user_agent_is_new(store: KnownDeviceStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+    store
+        .remember_device(&email, &device("203.0.113.7", "Firefox"))
+        .await
+        .unwrap();
+
+    for other in [
+        device("198.51.100.1", "Firefox"),
+        device("203.0.113.7", "Safari"),
+    ] {
+        let status = store.remember_device(&email, &other).await.unwrap();
+        assert_eq!(status, DeviceStatus::New);
+        // ...but only the first time it is seen
+        let status = store.remember_device(&email, &other).await.unwrap();
+        assert_eq!(status, DeviceStatus::Known);
+    }
+}
+
+async fn missing_client_info_is_a_device_too(store: KnownDeviceStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+    let unknown = Device {
+        ip: None,
+        user_agent: None,
+    };
+    store.remember_device(&email, &unknown).await.unwrap();
+
+    let status = store.remember_device(&email, &unknown).await.unwrap();
+    assert_eq!(status, DeviceStatus::Known);
+}
+
+async fn devices_are_tracked_per_user(store: KnownDeviceStoreType) {
+    let laptop = device("203.0.113.7", "Firefox");
+    let email = Email::parse(get_random_email()).unwrap();
+    let other = Email::parse(get_random_email()).unwrap();
+    store.remember_device(&email, &laptop).await.unwrap();
+
+    let status = store.remember_device(&other, &laptop).await.unwrap();
+    assert_eq!(status, DeviceStatus::First);
+}
+
+async fn health_check_succeeds(store: KnownDeviceStoreType) {
+    store.health_check().await.unwrap();
+}
+
+macro_rules! known_device_store_conformance {
+    ($($backend:ident),+ $(,)?) => {
+        $(
+            mod $backend {
+                conformance_cases!(crate::helpers::$backend;
+                    first_device_is_reported_as_first(),
+                    remembered_device_is_known(),
+                    different_ip_or_user_agent_is_new(),
+                    missing_client_info_is_a_device_too(),
+                    devices_are_tracked_per_user(),
+                    health_check_succeeds(),
+                );
+            }
+        )+
+    };
+}
+
+known_device_store_conformance!(hashmap_known_device_store);
+#[cfg(feature = "postgres")]
+known_device_store_conformance!(postgres_known_device_store);
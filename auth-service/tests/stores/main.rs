@@ -0,0 +1,18 @@
+// Conformance suite every store backend is run against.
+//
+// Each `*_store` module holds backend-agnostic test cases written purely in
+// terms of the store trait, followed by one `*_conformance!` invocation per
+// backend. To cover a new backend, add a setup function to `helpers` and a
+// line to the invocation at the bottom of the relevant module.
+#[macro_use]
+mod helpers;
+mod audit_log;
+mod banned_token_store;
+mod email_outbox;
+mod known_device_store;
+mod organization_store;
+#[cfg(feature = "postgres")]
+mod postgres_expiry;
+mod role_store;
+mod two_fa_code_store;
+mod user_store;
@@ -0,0 +1,341 @@
+use auth_service::app_state::OrganizationStoreType;
+use auth_service::domain::data_stores::organization_store::OrganizationStoreError;
+use auth_service::domain::{Email, Invitation, OrgRole, OrganizationId};
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+use secrecy::ExposeSecret;
+use uuid::Uuid;
+
+use crate::helpers::get_random_email;
+
+fn random_email() -> Email {
+    Email::parse(get_random_email()).unwrap()
+}
+
+fn invitation(
+    organization_id: OrganizationId,
+    email: &Email,
+    role: OrgRole,
+    expires_at: DateTime<Utc>,
+) -> Invitation {
+    Invitation {
+        id: Uuid::new_v4(),
+        organization_id,
+        email: email.as_ref().expose_secret().to_owned(),
+        role,
+        invited_by: get_random_email(),
+        expires_at,
+    }
+}
+
+// Truncated to what Postgres keeps, so invitations read back unchanged
+fn in_a_day() -> DateTime<Utc> {
+    (Utc::now() + Duration::days(1)).trunc_subsecs(6)
+}
+
+async fn creator_owns_the_organization(store: OrganizationStoreType) {
+    let owner = random_email();
+
+    let organization = store.create_organization("Initech", &owner).await.unwrap();
+
+    assert_eq!(
+        store.get_organization(&organization.id).await.unwrap(),
+        organization
+    );
+    assert_eq!(
+        store.role(&organization.id, &owner).await.unwrap(),
+        OrgRole::Owner
+    );
+    let members = store.members(&organization.id).await.unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].email, owner.as_ref().expose_secret().as_str());
+}
+
+async fn memberships_are_in_the_order_joined(store: OrganizationStoreType) {
+    let email = random_email();
+
+    let first = store.create_organization("Initech", &email).await.unwrap();
+    let second = store.create_organization("Initrode", &email).await.unwrap();
+    store
+        .create_organization("Other", &random_email())
+        .await
+        .unwrap();
+
+    let memberships = store.memberships(&email).await.unwrap();
+    let ids: Vec<OrganizationId> = memberships
+        .iter()
+        .map(|membership| membership.organization_id)
+        .collect();
+    assert_eq!(ids, vec![first.id, second.id]);
+    assert_eq!(memberships[1].organization_name, "Initrode");
+    assert!(store.memberships(&random_email()).await.unwrap().is_empty());
+}
+
+async fn unknown_organization_is_not_found(store: OrganizationStoreType) {
+    let id = OrganizationId::new();
+    let email = random_email();
+
+    let got = store.get_organization(&id).await;
+    let members = store.members(&id).await;
+    let invited = store
+        .add_invitation(invitation(id, &email, OrgRole::Member, in_a_day()))
+        .await;
+
+    assert!(matches!(
+        got,
+        Err(OrganizationStoreError::OrganizationNotFound)
+    ));
+    assert!(matches!(
+        members,
+        Err(OrganizationStoreError::OrganizationNotFound)
+    ));
+    assert!(matches!(
+        invited,
+        Err(OrganizationStoreError::OrganizationNotFound)
+    ));
+}
+
+async fn accepting_an_invitation_uses_it_up(store: OrganizationStoreType) {
+    let organization = store
+        .create_organization("Initech", &random_email())
+        .await
+        .unwrap();
+    let invitee = random_email();
+    let invitation = invitation(organization.id, &invitee, OrgRole::Admin, in_a_day());
+    store.add_invitation(invitation.clone()).await.unwrap();
+    assert_eq!(
+        store.get_invitation(&invitation.id).await.unwrap(),
+        invitation
+    );
+    assert_eq!(
+        store.invitations(&organization.id).await.unwrap(),
+        vec![invitation.clone()]
+    );
+
+    let membership = store
+        .accept_invitation(&invitation.id, &invitee)
+        .await
+        .unwrap();
+
+    assert_eq!(membership.organization_id, organization.id);
+    assert_eq!(membership.organization_name, "Initech");
+    assert_eq!(membership.role, OrgRole::Admin);
+    assert_eq!(store.memberships(&invitee).await.unwrap(), vec![membership]);
+    let again = store.accept_invitation(&invitation.id, &invitee).await;
+    assert!(matches!(
+        again,
+        Err(OrganizationStoreError::InvitationNotFound)
+    ));
+    assert!(store
+        .invitations(&organization.id)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+async fn accepting_keeps_an_existing_role(store: OrganizationStoreType) {
+    let owner = random_email();
+    let organization = store.create_organization("Initech", &owner).await.unwrap();
+    let invitation = invitation(organization.id, &owner, OrgRole::Member, in_a_day());
+    store.add_invitation(invitation.clone()).await.unwrap();
+
+    let membership = store
+        .accept_invitation(&invitation.id, &owner)
+        .await
+        .unwrap();
+
+    assert_eq!(membership.role, OrgRole::Owner);
+    assert_eq!(store.members(&organization.id).await.unwrap().len(), 1);
+}
+
+async fn expired_invitations_cannot_be_used(store: OrganizationStoreType) {
+    let organization = store
+        .create_organization("Initech", &random_email())
+        .await
+        .unwrap();
+    let invitee = random_email();
+    let expired = invitation(
+        organization.id,
+        &invitee,
+        OrgRole::Member,
+        Utc::now() - Duration::minutes(1),
+    );
+    store.add_invitation(expired.clone()).await.unwrap();
+
+    let got = store.get_invitation(&expired.id).await;
+    let accepted = store.accept_invitation(&expired.id, &invitee).await;
+
+    assert!(matches!(
+        got,
+        Err(OrganizationStoreError::InvitationNotFound)
+    ));
+    assert!(matches!(
+        accepted,
+        Err(OrganizationStoreError::InvitationNotFound)
+    ));
+    assert!(store
+        .invitations(&organization.id)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(store.memberships(&invitee).await.unwrap().is_empty());
+}
+
+async fn organizations_do_not_see_each_other(store: OrganizationStoreType) {
+    let first_owner = random_email();
+    let second_owner = random_email();
+    let first = store
+        .create_organization("Initech", &first_owner)
+        .await
+        .unwrap();
+    let second = store
+        .create_organization("Initrode", &second_owner)
+        .await
+        .unwrap();
+    store
+        .add_invitation(invitation(
+            first.id,
+            &random_email(),
+            OrgRole::Member,
+            in_a_day(),
+        ))
+        .await
+        .unwrap();
+
+    assert!(store.invitations(&second.id).await.unwrap().is_empty());
+    let members = store.members(&second.id).await.unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(
+        members[0].email,
+        second_owner.as_ref().expose_secret().as_str()
+    );
+    let role = store.role(&second.id, &first_owner).await;
+    assert!(matches!(role, Err(OrganizationStoreError::NotMember)));
+}
+
+async fn removed_member_is_no_longer_one(store: OrganizationStoreType) {
+    let organization = store
+        .create_organization("Initech", &random_email())
+        .await
+        .unwrap();
+    let member = random_email();
+    let invitation = invitation(organization.id, &member, OrgRole::Member, in_a_day());
+    store.add_invitation(invitation.clone()).await.unwrap();
+    store
+        .accept_invitation(&invitation.id, &member)
+        .await
+        .unwrap();
+
+    store
+        .remove_member(&organization.id, &member)
+        .await
+        .unwrap();
+
+    let role = store.role(&organization.id, &member).await;
+    let removed = store.remove_member(&organization.id, &member).await;
+    assert!(matches!(role, Err(OrganizationStoreError::NotMember)));
+    assert!(matches!(removed, Err(OrganizationStoreError::NotMember)));
+    assert!(store.memberships(&member).await.unwrap().is_empty());
+    assert_eq!(store.members(&organization.id).await.unwrap().len(), 1);
+}
+
+async fn the_last_owner_cannot_be_removed(store: OrganizationStoreType) {
+    let owner = random_email();
+    let organization = store.create_organization("Initech", &owner).await.unwrap();
+    let second_owner = random_email();
+    let invitation = invitation(organization.id, &second_owner, OrgRole::Owner, in_a_day());
+    store.add_invitation(invitation.clone()).await.unwrap();
+    store
+        .accept_invitation(&invitation.id, &second_owner)
+        .await
+        .unwrap();
+
+    store
+        .remove_member(&organization.id, &second_owner)
+        .await
+        .unwrap();
+    let removed = store.remove_member(&organization.id, &owner).await;
+
+    assert!(matches!(removed, Err(OrganizationStoreError::LastOwner)));
+    assert_eq!(
+        store.role(&organization.id, &owner).await.unwrap(),
+        OrgRole::Owner
+    );
+}
+
+async fn accepting_adds_the_given_account(store: OrganizationStoreType) {
+    let organization = store
+        .create_organization("Initech", &random_email())
+        .await
+        .unwrap();
+    let account = random_email();
+    let shouted = Email::parse(account.as_ref().expose_secret().to_uppercase()).unwrap();
+    let invitation = invitation(organization.id, &shouted, OrgRole::Member, in_a_day());
+    store.add_invitation(invitation.clone()).await.unwrap();
+
+    store
+        .accept_invitation(&invitation.id, &account)
+        .await
+        .unwrap();
+
+    assert_eq!(store.memberships(&account).await.unwrap().len(), 1);
+    assert!(store.memberships(&shouted).await.unwrap().is_empty());
+}
+
+async fn removed_invitation_cannot_be_used(store: OrganizationStoreType) {
+    let organization = store
+        .create_organization("Initech", &random_email())
+        .await
+        .unwrap();
+    let invitee = random_email();
+    let invitation = invitation(organization.id, &invitee, OrgRole::Member, in_a_day());
+    store.add_invitation(invitation.clone()).await.unwrap();
+
+    store.remove_invitation(&invitation.id).await.unwrap();
+
+    let accepted = store.accept_invitation(&invitation.id, &invitee).await;
+    let removed = store.remove_invitation(&invitation.id).await;
+    assert!(matches!(
+        accepted,
+        Err(OrganizationStoreError::InvitationNotFound)
+    ));
+    assert!(matches!(
+        removed,
+        Err(OrganizationStoreError::InvitationNotFound)
+    ));
+    assert!(store
+        .invitations(&organization.id)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+async fn health_check_succeeds(store: OrganizationStoreType) {
+    store.health_check().await.unwrap();
+}
+
+macro_rules! organization_store_conformance {
+    ($($backend:ident),+ $(,)?) => {
+        $(
+            mod $backend {
+                conformance_cases!(crate::helpers::$backend;
+                    creator_owns_the_organization(),
+                    memberships_are_in_the_order_joined(),
+                    unknown_organization_is_not_found(),
+                    accepting_an_invitation_uses_it_up(),
+                    accepting_keeps_an_existing_role(),
+                    expired_invitations_cannot_be_used(),
+                    organizations_do_not_see_each_other(),
+                    removed_member_is_no_longer_one(),
+                    the_last_owner_cannot_be_removed(),
+                    accepting_adds_the_given_account(),
+                    removed_invitation_cannot_be_used(),
+                    health_check_succeeds(),
+                );
+            }
+        )+
+    };
+}
+
+organization_store_conformance!(hashmap_organization_store);
+#[cfg(feature = "postgres")]
+organization_store_conformance!(postgres_organization_store);
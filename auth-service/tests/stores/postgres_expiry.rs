@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use auth_service::domain::data_stores::banned_token_store::BannedTokenStore;
+use auth_service::domain::data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore};
+use auth_service::domain::Email;
+use auth_service::services::data_stores::postgres_banned_token_store::{
+    PostgresBannedTokenStore, BANNED_TOKENS_TABLE,
+};
+use auth_service::services::data_stores::postgres_expiry::delete_expired_rows;
+use auth_service::services::data_stores::postgres_two_fa_code_store::{
+    PostgresTwoFACodeStore, TWO_FA_CODES_TABLE,
+};
+use secrecy::Secret;
+
+use crate::helpers::{get_random_email, test_database, LONG_TTL, SHORT_TTL};
+
+#[tokio::test]
+async fn cleanup_only_removes_expired_banned_tokens() {
+    let mut db = test_database().await;
+    let expiring = PostgresBannedTokenStore::new(db.store.clone()).with_ttl(SHORT_TTL);
+    let lasting = PostgresBannedTokenStore::new(db.store.clone()).with_ttl(LONG_TTL);
+
+    expiring
+        .add_token(Secret::new("expiring".to_owned()))
+        .await
+        .unwrap();
+    lasting
+        .add_token(Secret::new("lasting".to_owned()))
+        .await
+        .unwrap();
+    tokio::time::sleep(SHORT_TTL + Duration::from_secs(1)).await;
+
+    let deleted = delete_expired_rows(&db.store, BANNED_TOKENS_TABLE)
+        .await
+        .unwrap();
+    assert_eq!(deleted, 1);
+    assert!(lasting
+        .contains_token(&Secret::new("lasting".to_owned()))
+        .await
+        .unwrap());
+
+    db.clean_up().await;
+}
+
+#[tokio::test]
+async fn cleanup_only_removes_expired_two_fa_codes() {
+    let mut db = test_database().await;
+    let expiring = PostgresTwoFACodeStore::new(db.store.clone()).with_ttl(SHORT_TTL);
+    let lasting = PostgresTwoFACodeStore::new(db.store.clone()).with_ttl(LONG_TTL);
+    let lasting_email = Email::parse(get_random_email()).unwrap();
+
+    expiring
+        .add_code(
+            Email::parse(get_random_email()).unwrap(),
+            LoginAttemptId::default(),
+            TwoFACode::default(),
+        )
+        .await
+        .unwrap();
+    lasting
+        .add_code(
+            lasting_email.clone(),
+            LoginAttemptId::default(),
+            TwoFACode::default(),
+        )
+        .await
+        .unwrap();
+    tokio::time::sleep(SHORT_TTL + Duration::from_secs(1)).await;
+
+    let deleted = delete_expired_rows(&db.store, TWO_FA_CODES_TABLE)
+        .await
+        .unwrap();
+    assert_eq!(deleted, 1);
+    assert!(lasting.get_code(&lasting_email).await.is_ok());
+
+    db.clean_up().await;
+}
@@ -0,0 +1,99 @@
+use auth_service::app_state::RoleStoreType;
+use auth_service::domain::data_stores::role_store::RoleStoreError;
+use auth_service::domain::{Email, Grants, BUILT_IN_ROLES};
+
+use crate::helpers::get_random_email;
+
+async fn built_in_roles_exist(store: RoleStoreType) {
+    let roles = store.roles().await.unwrap();
+
+    for (name, permissions) in BUILT_IN_ROLES {
+        let role = roles
+            .iter()
+            .find(|role| role.name == *name)
+            .expect("built-in role is missing");
+        assert_eq!(role.permissions, *permissions);
+    }
+}
+
+async fn users_start_without_grants(store: RoleStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+
+    assert_eq!(store.grants(&email).await.unwrap(), Grants::default());
+}
+
+async fn granted_role_adds_its_permissions(store: RoleStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+
+    store.grant_role(&email, "admin").await.unwrap();
+
+    let grants = store.grants(&email).await.unwrap();
+    assert_eq!(grants.roles, vec!["admin"]);
+    assert_eq!(
+        grants.permissions,
+        vec![
+            "audit_events:read",
+            "roles:read",
+            "roles:write",
+            "users:read",
+            "users:write",
+        ]
+    );
+}
+
+async fn granting_and_revoking_are_idempotent(store: RoleStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+
+    store.grant_role(&email, "admin").await.unwrap();
+    store.grant_role(&email, "admin").await.unwrap();
+    assert_eq!(store.grants(&email).await.unwrap().roles, vec!["admin"]);
+
+    store.revoke_role(&email, "admin").await.unwrap();
+    store.revoke_role(&email, "admin").await.unwrap();
+    assert_eq!(store.grants(&email).await.unwrap(), Grants::default());
+}
+
+async fn unknown_role_is_rejected(store: RoleStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+
+    let granted = store.grant_role(&email, "superuser").await;
+    let revoked = store.revoke_role(&email, "superuser").await;
+
+    assert!(matches!(granted, Err(RoleStoreError::UnknownRole)));
+    assert!(matches!(revoked, Err(RoleStoreError::UnknownRole)));
+}
+
+async fn grants_are_per_user(store: RoleStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+    let other = Email::parse(get_random_email()).unwrap();
+
+    store.grant_role(&email, "admin").await.unwrap();
+
+    assert_eq!(store.grants(&other).await.unwrap(), Grants::default());
+}
+
+async fn health_check_succeeds(store: RoleStoreType) {
+    store.health_check().await.unwrap();
+}
+
+macro_rules! role_store_conformance {
+    ($($backend:ident),+ $(,)?) => {
+        $(
+            mod $backend {
+                conformance_cases!(crate::helpers::$backend;
+                    built_in_roles_exist(),
+                    users_start_without_grants(),
+                    granted_role_adds_its_permissions(),
+                    granting_and_revoking_are_idempotent(),
+                    unknown_role_is_rejected(),
+                    grants_are_per_user(),
+                    health_check_succeeds(),
+                );
+            }
+        )+
+    };
+}
+
+role_store_conformance!(hashmap_role_store);
+#[cfg(feature = "postgres")]
+role_store_conformance!(postgres_role_store);
@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use auth_service::app_state::TwoFACodeStoreType;
+use auth_service::domain::data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStoreError};
+use auth_service::domain::Email;
+
+use crate::helpers::{get_random_email, SHORT_TTL};
+
+fn random_email() -> Email {
+    Email::parse(get_random_email()).unwrap()
+}
+
+async fn added_code_can_be_retrieved(store: TwoFACodeStoreType) {
+    let email = random_email();
+    let id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+    store
+        .add_code(email.clone(), id.clone(), code.clone())
+        .await
+        .unwrap();
+
+    let res = store.get_code(&email).await;
+    assert_eq!(res.unwrap(), (id, code));
+}
+
+async fn missing_code_is_not_found(store: TwoFACodeStoreType) {
+    let res = store.get_code(&random_email()).await;
+    assert_eq!(
+        res.unwrap_err(),
+        TwoFACodeStoreError::LoginAttemptIdNotFound
+    );
+}
+
+async fn adding_code_again_replaces_previous(store: TwoFACodeStoreType) {
+    let email = random_email();
+    store
+        .add_code(
+            email.clone(),
+            LoginAttemptId::default(),
+            TwoFACode::default(),
+        )
+        .await
+        .unwrap();
+
+    let id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+    store
+        .add_code(email.clone(), id.clone(), code.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(store.get_code(&email).await.unwrap(), (id, code));
+}
+
+async fn removed_code_is_not_found(store: TwoFACodeStoreType) {
+    let email = random_email();
+    store
+        .add_code(
+            email.clone(),
+            LoginAttemptId::default(),
+            TwoFACode::default(),
+        )
+        .await
+        .unwrap();
+    store.remove_code(&email).await.unwrap();
+
+    let res = store.get_code(&email).await;
+    assert_eq!(
+        res.unwrap_err(),
+        TwoFACodeStoreError::LoginAttemptIdNotFound
+    );
+}
+
+async fn removing_missing_code_succeeds(store: TwoFACodeStoreType) {
+    let res = store.remove_code(&random_email()).await;
+    assert_eq!(res, Ok(()));
+}
+
+async fn code_expires_after_ttl(store: TwoFACodeStoreType) {
+    let email = random_email();
+    store
+        .add_code(
+            email.clone(),
+            LoginAttemptId::default(),
+            TwoFACode::default(),
+        )
+        .await
+        .unwrap();
+
+    tokio::time::sleep(SHORT_TTL + Duration::from_secs(1)).await;
+
+    let res = store.get_code(&email).await;
+    assert_eq!(
+        res.unwrap_err(),
+        TwoFACodeStoreError::LoginAttemptIdNotFound
+    );
+}
+
+async fn concurrent_adds_for_distinct_emails_are_isolated(store: TwoFACodeStoreType) {
+    let entries: Vec<_> = (0..16)
+        .map(|_| {
+            (
+                random_email(),
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+        })
+        .collect();
+    let handles: Vec<_> = entries
+        .iter()
+        .cloned()
+        .map(|(email, id, code)| {
+            let store = store.clone();
+            tokio::spawn(async move { store.add_code(email, id, code).await })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.await.unwrap().unwrap();
+    }
+    for (email, id, code) in entries {
+        assert_eq!(store.get_code(&email).await.unwrap(), (id, code));
+    }
+}
+
+async fn health_check_succeeds(store: TwoFACodeStoreType) {
+    store.health_check().await.unwrap();
+}
+
+macro_rules! two_fa_code_store_conformance {
+    ($($backend:ident),+ $(,)?) => {
+        $(
+            mod $backend {
+                use crate::helpers::{LONG_TTL, SHORT_TTL};
+
+                conformance_cases!(crate::helpers::$backend;
+                    added_code_can_be_retrieved(LONG_TTL),
+                    missing_code_is_not_found(LONG_TTL),
+                    adding_code_again_replaces_previous(LONG_TTL),
+                    removed_code_is_not_found(LONG_TTL),
+                    removing_missing_code_succeeds(LONG_TTL),
+                    code_expires_after_ttl(SHORT_TTL),
+                    concurrent_adds_for_distinct_emails_are_isolated(LONG_TTL),
+                    health_check_succeeds(LONG_TTL),
+                );
+            }
+        )+
+    };
+}
+
+two_fa_code_store_conformance!(hashmap_two_fa_code_store, redis_two_fa_code_store);
+#[cfg(feature = "postgres")]
+two_fa_code_store_conformance!(postgres_two_fa_code_store);
@@ -0,0 +1,376 @@
+use auth_service::app_state::UserStoreType;
+use auth_service::domain::data_stores::{UserPage, UserQuery, UserStoreError};
+use auth_service::domain::{Email, EmailStatus, Locale, Password, PhoneNumber, TwoFAChannel, User};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::helpers::get_random_email;
+
+fn user(email: &str, requires_2fa: bool) -> User {
+    User::new(
+        Email::parse(email.to_owned()).unwrap(),
+        Password::parse(Secret::new("password123".to_owned())).unwrap(),
+        requires_2fa,
+    )
+}
+
+fn emails(page: &UserPage) -> Vec<&str> {
+    page.users.iter().map(|user| user.email.as_str()).collect()
+}
+
+async fn add_user_succeeds(store: UserStoreType) {
+    let res = store.add_user(user(&get_random_email(), false)).await;
+    assert_eq!(res, Ok(()));
+}
+
+async fn add_duplicate_user_fails(store: UserStoreType) {
+    let email = get_random_email();
+    store.add_user(user(&email, false)).await.unwrap();
+
+    let res = store.add_user(user(&email, true)).await;
+    assert_eq!(res, Err(UserStoreError::UserAlreadyExists));
+}
+
+async fn get_user_returns_added_user(store: UserStoreType) {
+    let email = get_random_email();
+    store.add_user(user(&email, true)).await.unwrap();
+
+    // Backends may store a hash instead of the password, so only compare
+    // the fields every backend is expected to round-trip verbatim
+    let found = store
+        .get_user(&Email::parse(email.clone()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(found.email, Email::parse(email).unwrap());
+    assert!(found.requires_2fa);
+    assert_eq!(found.locale, Locale::default());
+    assert_eq!(found.email_status, EmailStatus::Deliverable);
+    assert_eq!(found.phone_number, None);
+    assert_eq!(found.two_fa_channel, TwoFAChannel::Email);
+}
+
+async fn get_user_returns_locale(store: UserStoreType) {
+    let email = get_random_email();
+    store
+        .add_user(user(&email, false).with_locale(Locale::De))
+        .await
+        .unwrap();
+
+    let found = store.get_user(&Email::parse(email).unwrap()).await.unwrap();
+    assert_eq!(found.locale, Locale::De);
+}
+
+async fn get_user_returns_sms_2fa(store: UserStoreType) {
+    let phone_number = PhoneNumber::parse("+4915112345678".to_owned()).unwrap();
+    let added = user(&get_random_email(), true).with_sms_2fa(phone_number.clone());
+    store.add_user(added.clone()).await.unwrap();
+
+    let found = store.get_user(&added.email).await.unwrap();
+    assert_eq!(found.phone_number, Some(phone_number));
+    assert_eq!(found.two_fa_channel, TwoFAChannel::Sms);
+}
+
+async fn set_email_status_is_returned_by_get_user(store: UserStoreType) {
+    let added = user(&get_random_email(), true);
+    store.add_user(added.clone()).await.unwrap();
+
+    store
+        .set_email_status(&added.email, EmailStatus::HardBounced)
+        .await
+        .unwrap();
+
+    let found = store.get_user(&added.email).await.unwrap();
+    assert_eq!(found.email_status, EmailStatus::HardBounced);
+}
+
+async fn set_email_status_of_missing_user_fails(store: UserStoreType) {
+    let missing = user(&get_random_email(), false);
+
+    let res = store
+        .set_email_status(&missing.email, EmailStatus::SpamComplaint)
+        .await;
+    assert_eq!(res, Err(UserStoreError::UserNotFound));
+}
+
+async fn get_missing_user_fails(store: UserStoreType) {
+    let email = Email::parse(get_random_email()).unwrap();
+    let res = store.get_user(&email).await;
+    assert_eq!(res, Err(UserStoreError::UserNotFound));
+}
+
+async fn validate_user_accepts_correct_password(store: UserStoreType) {
+    let added = user(&get_random_email(), false);
+    store.add_user(added.clone()).await.unwrap();
+
+    let res = store.validate_user(&added.email, &added.password).await;
+    assert_eq!(res, Ok(()));
+}
+
+async fn validate_user_rejects_wrong_password(store: UserStoreType) {
+    let added = user(&get_random_email(), false);
+    store.add_user(added.clone()).await.unwrap();
+
+    let wrong = Password::parse(Secret::new("not-the-password".to_owned())).unwrap();
+    let res = store.validate_user(&added.email, &wrong).await;
+    assert_eq!(res, Err(UserStoreError::InvalidCredentials));
+}
+
+async fn validate_missing_user_fails(store: UserStoreType) {
+    let missing = user(&get_random_email(), false);
+    let res = store.validate_user(&missing.email, &missing.password).await;
+    assert_eq!(res, Err(UserStoreError::UserNotFound));
+}
+
+async fn required_reset_locks_correct_password(store: UserStoreType) {
+    let added = user(&get_random_email(), false);
+    store.add_user(added.clone()).await.unwrap();
+
+    store.require_password_reset(&added.email).await.unwrap();
+
+    let res = store.validate_user(&added.email, &added.password).await;
+    assert_eq!(res, Err(UserStoreError::PasswordResetRequired));
+    // A wrong password is still just wrong
+    let wrong = Password::parse(Secret::new("not-the-password".to_owned())).unwrap();
+    let res = store.validate_user(&added.email, &wrong).await;
+    assert_eq!(res, Err(UserStoreError::InvalidCredentials));
+}
+
+async fn reset_password_replaces_password_and_unlocks(store: UserStoreType) {
+    let added = user(&get_random_email(), false);
+    store.add_user(added.clone()).await.unwrap();
+    store.require_password_reset(&added.email).await.unwrap();
+
+    let new_password = Password::parse(Secret::new("new-password123".to_owned())).unwrap();
+    store
+        .reset_password(&added.email, new_password.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.validate_user(&added.email, &new_password).await,
+        Ok(())
+    );
+    assert_eq!(
+        store.validate_user(&added.email, &added.password).await,
+        Err(UserStoreError::InvalidCredentials)
+    );
+}
+
+async fn reset_password_needs_a_locked_account(store: UserStoreType) {
+    let added = user(&get_random_email(), false);
+    store.add_user(added.clone()).await.unwrap();
+
+    let new_password = Password::parse(Secret::new("new-password123".to_owned())).unwrap();
+    let res = store.reset_password(&added.email, new_password).await;
+
+    assert_eq!(res, Err(UserStoreError::PasswordResetNotRequired));
+    assert_eq!(
+        store.validate_user(&added.email, &added.password).await,
+        Ok(())
+    );
+}
+
+async fn password_reset_of_missing_user_fails(store: UserStoreType) {
+    let missing = user(&get_random_email(), false);
+
+    let res = store.require_password_reset(&missing.email).await;
+    assert_eq!(res, Err(UserStoreError::UserNotFound));
+    let res = store.reset_password(&missing.email, missing.password).await;
+    assert_eq!(res, Err(UserStoreError::UserNotFound));
+}
+
+async fn disabled_account_locks_correct_password(store: UserStoreType) {
+    let added = user(&get_random_email(), false);
+    store.add_user(added.clone()).await.unwrap();
+
+    store.set_disabled(&added.email, true).await.unwrap();
+
+    let res = store.validate_user(&added.email, &added.password).await;
+    assert_eq!(res, Err(UserStoreError::AccountDisabled));
+    let wrong = Password::parse(Secret::new("not-the-password".to_owned())).unwrap();
+    let res = store.validate_user(&added.email, &wrong).await;
+    assert_eq!(res, Err(UserStoreError::InvalidCredentials));
+
+    store.set_disabled(&added.email, false).await.unwrap();
+    assert_eq!(
+        store.validate_user(&added.email, &added.password).await,
+        Ok(())
+    );
+}
+
+async fn user_summary_reflects_account_state(store: UserStoreType) {
+    let added = user(&get_random_email(), false).with_locale(Locale::De);
+    store.add_user(added.clone()).await.unwrap();
+
+    store.set_disabled(&added.email, true).await.unwrap();
+    store.require_password_reset(&added.email).await.unwrap();
+    store.set_requires_2fa(&added.email, true).await.unwrap();
+
+    let summary = store.user_summary(&added.email).await.unwrap();
+    assert_eq!(summary.email, added.email.as_ref().expose_secret().as_str());
+    assert!(summary.requires_2fa);
+    assert_eq!(summary.locale, Locale::De);
+    assert_eq!(summary.email_status, EmailStatus::Deliverable);
+    assert_eq!(summary.two_fa_channel, TwoFAChannel::Email);
+    assert!(summary.disabled);
+    assert!(summary.password_reset_required);
+    assert!(store.get_user(&added.email).await.unwrap().requires_2fa);
+}
+
+async fn list_users_pages_in_email_order(store: UserStoreType) {
+    for email in ["carol@example.com", "alice@example.com", "bob@example.com"] {
+        store.add_user(user(email, false)).await.unwrap();
+    }
+
+    let first = store
+        .list_users(&UserQuery {
+            limit: 2,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let second = store
+        .list_users(&UserQuery {
+            offset: 2,
+            limit: 2,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(emails(&first), ["alice@example.com", "bob@example.com"]);
+    assert_eq!(emails(&second), ["carol@example.com"]);
+    assert_eq!(first.total, 3);
+    assert_eq!(second.total, 3);
+    let past_the_end = store
+        .list_users(&UserQuery {
+            offset: usize::MAX,
+            limit: usize::MAX,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(emails(&past_the_end).is_empty());
+    assert_eq!(past_the_end.total, 3);
+}
+
+async fn list_users_searches_emails_ignoring_case(store: UserStoreType) {
+    for email in ["alice@example.com", "bob@initech.com", "carol@initech.com"] {
+        store.add_user(user(email, false)).await.unwrap();
+    }
+
+    let page = store
+        .list_users(&UserQuery {
+            search: Some("INITECH".to_owned()),
+            limit: 1,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(emails(&page), ["bob@initech.com"]);
+    assert_eq!(page.total, 2);
+    let none = store
+        .list_users(&UserQuery {
+            search: Some("initrode".to_owned()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(emails(&none).is_empty());
+    assert_eq!(none.total, 0);
+}
+
+async fn account_changes_of_missing_user_fail(store: UserStoreType) {
+    let missing = user(&get_random_email(), false);
+
+    let res = store.set_disabled(&missing.email, true).await;
+    assert_eq!(res, Err(UserStoreError::UserNotFound));
+    let res = store.set_requires_2fa(&missing.email, true).await;
+    assert_eq!(res, Err(UserStoreError::UserNotFound));
+    let res = store.user_summary(&missing.email).await;
+    assert!(matches!(res, Err(UserStoreError::UserNotFound)));
+}
+
+async fn concurrent_adds_of_distinct_users_all_succeed(store: UserStoreType) {
+    let emails: Vec<String> = (0..8).map(|_| get_random_email()).collect();
+    let handles: Vec<_> = emails
+        .iter()
+        .map(|email| {
+            let store = store.clone();
+            let added = user(email, false);
+            tokio::spawn(async move { store.add_user(added).await })
+        })
+        .collect();
+
+    for handle in handles {
+        assert_eq!(handle.await.unwrap(), Ok(()));
+    }
+    for email in emails {
+        let email = Email::parse(email).unwrap();
+        assert!(store.get_user(&email).await.is_ok());
+    }
+}
+
+async fn concurrent_adds_of_same_user_only_one_succeeds(store: UserStoreType) {
+    let email = get_random_email();
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let store = store.clone();
+            let added = user(&email, false);
+            tokio::spawn(async move { store.add_user(added).await })
+        })
+        .collect();
+
+    let mut successes = 0;
+    for handle in handles {
+        match handle.await.unwrap() {
+            Ok(()) => successes += 1,
+            Err(e) => assert_eq!(e, UserStoreError::UserAlreadyExists),
+        }
+    }
+    assert_eq!(successes, 1);
+}
+
+async fn health_check_succeeds(store: UserStoreType) {
+    store.health_check().await.unwrap();
+}
+
+macro_rules! user_store_conformance {
+    ($($backend:ident),+ $(,)?) => {
+        $(
+            mod $backend {
+                conformance_cases!(crate::helpers::$backend;
+                    add_user_succeeds(),
+                    add_duplicate_user_fails(),
+                    get_user_returns_added_user(),
+                    get_user_returns_locale(),
+                    get_user_returns_sms_2fa(),
+                    set_email_status_is_returned_by_get_user(),
+                    set_email_status_of_missing_user_fails(),
+                    get_missing_user_fails(),
+                    validate_user_accepts_correct_password(),
+                    validate_user_rejects_wrong_password(),
+                    validate_missing_user_fails(),
+                    required_reset_locks_correct_password(),
+                    reset_password_replaces_password_and_unlocks(),
+                    reset_password_needs_a_locked_account(),
+                    password_reset_of_missing_user_fails(),
+                    disabled_account_locks_correct_password(),
+                    user_summary_reflects_account_state(),
+                    list_users_pages_in_email_order(),
+                    list_users_searches_emails_ignoring_case(),
+                    account_changes_of_missing_user_fail(),
+                    concurrent_adds_of_distinct_users_all_succeed(),
+                    concurrent_adds_of_same_user_only_one_succeeds(),
+                    health_check_succeeds(),
+                );
+            }
+        )+
+    };
+}
+
+user_store_conformance!(hashmap_user_store);
+#[cfg(feature = "postgres")]
+user_store_conformance!(postgres_user_store);
+#[cfg(feature = "sqlite")]
+user_store_conformance!(sqlite_user_store);